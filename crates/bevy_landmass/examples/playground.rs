@@ -163,6 +163,8 @@ fn setup(
             cost: 4.0,
             kind: 0,
             bidirectional: false,
+            capacity: None,
+            busy_cost: None,
           },
           ArchipelagoRef3d::new(archipelago_entity),
         ));
@@ -180,6 +182,8 @@ fn setup(
             cost: 2.0,
             kind: 0,
             bidirectional: true,
+            capacity: None,
+            busy_cost: None,
           },
           ArchipelagoRef3d::new(archipelago_entity),
         ));
@@ -15,7 +15,7 @@ use crate::{
   AgentState, Archipelago, TargetReachedCondition, Velocity,
   coords::{CoordinateSystem, ThreeD, TwoD},
 };
-use crate::{ArchipelagoRef, PermittedAnimationLinks};
+use crate::{ArchipelagoRef, PermittedAnimationLinks, PermittedTypeIndices};
 
 /// A bundle to create agents. This omits the GlobalTransform component, since
 /// this is commonly added in other bundles (which is redundant and can override
@@ -35,7 +35,7 @@ pub type Agent3dBundle = AgentBundle<ThreeD>;
 
 /// A marker component to create all required components for an agent.
 #[derive(Component)]
-#[require(Transform, Velocity<CS>, AgentTarget<CS>, AgentState, AgentDesiredVelocity<CS>)]
+#[require(Transform, Velocity<CS>, AgentTarget<CS>, AgentState, AgentDesiredVelocity<CS>, AgentPlatformVelocity<CS>)]
 pub struct Agent<CS: CoordinateSystem>(PhantomData<CS>);
 
 pub type Agent2d = Agent<TwoD>;
@@ -186,6 +186,37 @@ impl<CS: CoordinateSystem> AgentDesiredVelocity<CS> {
   }
 }
 
+/// The current velocity of the island that the agent is standing on (e.g., an
+/// elevator or a boat). [`AgentDesiredVelocity`] is relative to the island, so
+/// add this to it to have the agent ride the island. This is set by `landmass`
+/// (during [`crate::LandmassSystemSet::Output`]).
+#[derive(Component)]
+pub struct AgentPlatformVelocity<CS: CoordinateSystem>(CS::Coordinate);
+
+pub type AgentPlatformVelocity2d = AgentPlatformVelocity<TwoD>;
+pub type AgentPlatformVelocity3d = AgentPlatformVelocity<ThreeD>;
+
+impl<CS: CoordinateSystem> Default for AgentPlatformVelocity<CS> {
+  fn default() -> Self {
+    Self(Default::default())
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for AgentPlatformVelocity<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("AgentPlatformVelocity").field(&self.0).finish()
+  }
+}
+
+impl<CS: CoordinateSystem> AgentPlatformVelocity<CS> {
+  /// The velocity of the island under the agent.
+  pub fn velocity(&self) -> CS::Coordinate {
+    self.0.clone()
+  }
+}
+
 /// An animation link that an agent has reached (in order to use it).
 #[derive(Component)]
 pub struct ReachedAnimationLink<CS: CoordinateSystem> {
@@ -221,12 +252,37 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
 #[derive(Component, Default, Clone, Copy, Debug)]
 pub struct PauseAgent;
 
+/// A marker component to indicate that an agent should move to the closest
+/// point it can reach when its target is unreachable. Without this, the agent
+/// stands still instead.
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct AllowPartialPaths;
+
 /// A marker component to indicate that an agent is currently using an animation
 /// link and should behave as though it is paused (see [`PauseAgent`] for
 /// details).
 #[derive(Component, Default, Clone, Copy, Debug)]
 pub struct UsingAnimationLink;
 
+/// The avoidance layers that an agent, character or avoidance obstacle is in,
+/// as a bitmask. Agents only avoid entities in the layers of their
+/// [`AvoidanceMask`]. If not present, the entity is only in the first layer
+/// (`1`).
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AvoidanceLayers(pub u32);
+
+/// The avoidance layers that an agent avoids, as a bitmask. If not present,
+/// the agent avoids every layer.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AvoidanceMask(pub u32);
+
+/// How much an agent is prioritized during avoidance. Must be positive. An
+/// agent with twice the priority of another leaves twice as much of the
+/// avoidance between them to the other agent. Priorities below `0.001` are
+/// treated as `0.001`. If not present, the priority is `1.0`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AvoidancePriority(pub f32);
+
 #[cfg(feature = "debug-avoidance")]
 /// If inserted on an agent, it will record avoidance data that can later be
 /// visualized with [`crate::debug::draw_avoidance_data`].
@@ -301,9 +357,16 @@ pub(crate) fn sync_agent_input_state<CS: CoordinateSystem>(
       Option<&TargetReachedCondition>,
       Option<&AnimationLinkReachedDistance>,
       Option<&PermittedAnimationLinks>,
+      Option<&PermittedTypeIndices>,
       Option<Ref<AgentTypeIndexCostOverrides>>,
+      Has<AllowPartialPaths>,
       Has<PauseAgent>,
       Has<UsingAnimationLink>,
+      (
+        Option<&AvoidanceLayers>,
+        Option<&AvoidanceMask>,
+        Option<&AvoidancePriority>,
+      ),
       HasKeepAvoidanceData,
     ),
     With<Transform>,
@@ -320,9 +383,12 @@ pub(crate) fn sync_agent_input_state<CS: CoordinateSystem>(
     target_reached_condition,
     animation_link_reached_distance,
     permitted_animation_links,
+    permitted_type_indices,
     type_index_cost_overrides,
+    has_allow_partial_paths,
     has_pause_agent,
     has_using_animation_link,
+    (avoidance_layers, avoidance_mask, avoidance_priority),
     keep_avoidance_data,
   ) in agent_query.iter()
   {
@@ -359,6 +425,16 @@ pub(crate) fn sync_agent_input_state<CS: CoordinateSystem>(
     landmass_agent.permitted_animation_links = permitted_animation_links
       .map(PermittedAnimationLinks::to_landmass)
       .unwrap_or(landmass::PermittedAnimationLinks::All);
+    landmass_agent.permitted_type_indices = permitted_type_indices
+      .map(PermittedTypeIndices::to_landmass)
+      .unwrap_or(landmass::PermittedTypeIndices::All);
+    landmass_agent.allow_partial_paths = has_allow_partial_paths;
+    landmass_agent.avoidance_layers =
+      avoidance_layers.map_or(1, |layers| layers.0);
+    landmass_agent.avoidance_mask =
+      avoidance_mask.map_or(u32::MAX, |mask| mask.0);
+    landmass_agent.avoidance_priority =
+      avoidance_priority.map_or(1.0, |priority| priority.0);
     match type_index_cost_overrides {
       None => {
         for (type_index, _) in
@@ -463,6 +539,34 @@ pub(crate) fn sync_desired_velocity<CS: CoordinateSystem>(
   }
 }
 
+/// Copies the agent platform velocity from `landmass` agents to their Bevy
+/// equivalent.
+pub(crate) fn sync_platform_velocity<CS: CoordinateSystem>(
+  mut agent_query: Query<
+    (Entity, &ArchipelagoRef<CS>, &mut AgentPlatformVelocity<CS>),
+    With<AgentSettings>,
+  >,
+  archipelago_query: Query<&Archipelago<CS>>,
+) {
+  for (
+    agent_entity,
+    &ArchipelagoRef { entity: arch_entity, .. },
+    mut platform_velocity,
+  ) in agent_query.iter_mut()
+  {
+    let archipelago = match archipelago_query.get(arch_entity).ok() {
+      None => continue,
+      Some(arch) => arch,
+    };
+
+    platform_velocity.0 = archipelago
+      .get_agent(agent_entity)
+      .expect("the agent is in the archipelago")
+      .get_platform_velocity()
+      .clone();
+  }
+}
+
 impl<CS: CoordinateSystem> ReachedAnimationLink<CS> {
   /// Converts the `landmass` representation of the reached animation link, to
   /// the `bevy_landmass` version.
@@ -0,0 +1,140 @@
+use bevy_ecs::{
+  bundle::Bundle, component::Component, entity::Entity, query::With,
+  system::Query,
+};
+use bevy_platform::collections::{HashMap, HashSet};
+use bevy_transform::{components::Transform, helper::TransformHelper};
+
+use crate::{
+  Archipelago, ArchipelagoRef, AvoidanceLayers, ObstacleShape, Velocity,
+  coords::{CoordinateSystem, ThreeD, TwoD},
+  uniform_scale,
+};
+
+/// A bundle to create avoidance obstacles. The GlobalTransform component is
+/// omitted, since this is commonly added in other bundles (which is redundant
+/// and can override previous bundles).
+#[derive(Bundle)]
+pub struct AvoidanceObstacleBundle<CS: CoordinateSystem> {
+  /// The avoidance obstacle itself.
+  pub avoidance_obstacle: AvoidanceObstacle<CS>,
+  /// A reference pointing to the Archipelago to associate this entity with.
+  pub archipelago_ref: ArchipelagoRef<CS>,
+}
+
+pub type AvoidanceObstacle2dBundle = AvoidanceObstacleBundle<TwoD>;
+pub type AvoidanceObstacle3dBundle = AvoidanceObstacleBundle<ThreeD>;
+
+/// An obstacle that agents avoid locally, without changing their paths. The
+/// obstacle follows the transform of its entity, and moves with its
+/// [`Velocity`]. Only uniform scales are supported, and the scale must be
+/// positive.
+#[derive(Component)]
+#[require(Transform, Velocity<CS>)]
+pub struct AvoidanceObstacle<CS: CoordinateSystem> {
+  /// The shape of the obstacle, relative to the entity's transform.
+  pub shape: ObstacleShape<CS>,
+}
+
+pub type AvoidanceObstacle2d = AvoidanceObstacle<TwoD>;
+pub type AvoidanceObstacle3d = AvoidanceObstacle<ThreeD>;
+
+/// Ensures that the avoidance obstacles (and their transforms) are up to date.
+pub(crate) fn sync_avoidance_obstacles_to_archipelago<CS: CoordinateSystem>(
+  mut archipelagos: Query<(Entity, &mut Archipelago<CS>)>,
+  avoidance_obstacles: Query<
+    (
+      Entity,
+      &AvoidanceObstacle<CS>,
+      &ArchipelagoRef<CS>,
+      Option<&Velocity<CS>>,
+      Option<&AvoidanceLayers>,
+    ),
+    With<Transform>,
+  >,
+  transform_helper: TransformHelper,
+) {
+  let mut archipelago_to_avoidance_obstacles =
+    HashMap::<_, HashSet<_>>::default();
+  for (
+    avoidance_obstacle_entity,
+    avoidance_obstacle,
+    archipelago_ref,
+    velocity,
+    avoidance_layers,
+  ) in avoidance_obstacles.iter()
+  {
+    let mut archipelago = match archipelagos.get_mut(archipelago_ref.entity) {
+      Err(_) => continue,
+      Ok((_, arch)) => arch,
+    };
+
+    let Ok(avoidance_obstacle_transform) =
+      transform_helper.compute_global_transform(avoidance_obstacle_entity)
+    else {
+      continue;
+    };
+
+    archipelago_to_avoidance_obstacles
+      .entry(archipelago_ref.entity)
+      .or_default()
+      .insert(avoidance_obstacle_entity);
+
+    let avoidance_obstacle_transform =
+      avoidance_obstacle_transform.compute_transform();
+    let landmass_avoidance_obstacle = landmass::AvoidanceObstacle {
+      shape: avoidance_obstacle.shape.clone(),
+      transform: landmass::Transform {
+        translation: CS::from_bevy_position(
+          avoidance_obstacle_transform.translation,
+        ),
+        rotation: CS::from_bevy_rotation(
+          &avoidance_obstacle_transform.rotation,
+        ),
+        scale: uniform_scale(
+          avoidance_obstacle_entity,
+          avoidance_obstacle_transform.scale,
+        ),
+      },
+      velocity: if let Some(Velocity { velocity }) = velocity {
+        velocity.clone()
+      } else {
+        CS::Coordinate::default()
+      },
+      avoidance_layers: avoidance_layers.map_or(1, |layers| layers.0),
+    };
+
+    let archipelago = archipelago.as_mut();
+    match archipelago.avoidance_obstacles.get(&avoidance_obstacle_entity) {
+      None => {
+        let avoidance_obstacle_id = archipelago
+          .archipelago
+          .add_avoidance_obstacle(landmass_avoidance_obstacle);
+        archipelago
+          .avoidance_obstacles
+          .insert(avoidance_obstacle_entity, avoidance_obstacle_id);
+      }
+      Some(&avoidance_obstacle_id) => {
+        *archipelago
+          .archipelago
+          .get_avoidance_obstacle_mut(avoidance_obstacle_id)
+          .unwrap() = landmass_avoidance_obstacle;
+      }
+    }
+  }
+
+  for (entity, mut archipelago) in archipelagos.iter_mut() {
+    let avoidance_obstacles = archipelago_to_avoidance_obstacles.get(&entity);
+    let archipelago = archipelago.as_mut();
+    archipelago.avoidance_obstacles.retain(|entity, id| {
+      if avoidance_obstacles
+        .map(|avoidance_obstacles| avoidance_obstacles.contains(entity))
+        .unwrap_or(false)
+      {
+        return true;
+      }
+      archipelago.archipelago.remove_avoidance_obstacle(*id);
+      false
+    });
+  }
+}
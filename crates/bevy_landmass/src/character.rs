@@ -8,7 +8,7 @@ use bevy_platform::collections::HashMap;
 use bevy_transform::{components::Transform, helper::TransformHelper};
 
 use crate::{
-  Archipelago, ArchipelagoRef,
+  Archipelago, ArchipelagoRef, AvoidanceLayers, ObstacleShape,
   coords::{CoordinateSystem, ThreeD, TwoD},
 };
 
@@ -47,6 +47,18 @@ pub struct CharacterSettings {
   pub radius: f32,
 }
 
+/// The shape of a character that is badly approximated by a circle (e.g., a
+/// vehicle). The shape follows the rotation of the character's entity. If
+/// present, [`CharacterSettings::radius`] is unused.
+#[derive(Component)]
+pub struct CharacterFootprint<CS: CoordinateSystem> {
+  /// The shape of the character, relative to the entity's transform.
+  pub shape: ObstacleShape<CS>,
+}
+
+pub type CharacterFootprint2d = CharacterFootprint<TwoD>;
+pub type CharacterFootprint3d = CharacterFootprint<ThreeD>;
+
 /// The current velocity of the agent/character. This must be set to match
 /// whatever speed the agent/character is going.
 #[derive(Component)]
@@ -108,6 +120,7 @@ pub(crate) fn add_characters_to_archipelago<CS: CoordinateSystem>(
           position: Default::default(),
           velocity: Default::default(),
           radius: new_character.radius,
+          ..Default::default()
         });
       archipelago.characters.insert(new_character_entity, character_id);
     }
@@ -117,7 +130,14 @@ pub(crate) fn add_characters_to_archipelago<CS: CoordinateSystem>(
 /// Copies Bevy character states to their associated landmass character.
 pub(crate) fn sync_character_state<CS: CoordinateSystem>(
   characters: Query<
-    (Entity, &CharacterSettings, &ArchipelagoRef<CS>, Option<&Velocity<CS>>),
+    (
+      Entity,
+      &CharacterSettings,
+      &ArchipelagoRef<CS>,
+      Option<&Velocity<CS>>,
+      Option<&AvoidanceLayers>,
+      Option<&CharacterFootprint<CS>>,
+    ),
     With<Transform>,
   >,
   transform_helper: TransformHelper,
@@ -128,6 +148,8 @@ pub(crate) fn sync_character_state<CS: CoordinateSystem>(
     character,
     &ArchipelagoRef { entity: arch_entity, .. },
     velocity,
+    avoidance_layers,
+    footprint,
   ) in characters.iter()
   {
     let Ok(mut archipelago) = archipelagos.get_mut(arch_entity) else {
@@ -152,5 +174,12 @@ pub(crate) fn sync_character_state<CS: CoordinateSystem>(
       CS::Coordinate::default()
     };
     landmass_character.radius = character.radius;
+    landmass_character.avoidance_layers =
+      avoidance_layers.map_or(1, |layers| layers.0);
+    landmass_character.footprint =
+      footprint.map(|footprint| landmass::CharacterFootprint {
+        shape: footprint.shape.clone(),
+        rotation: CS::from_bevy_rotation(&transform.rotation()),
+      });
   }
 }
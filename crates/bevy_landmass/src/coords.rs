@@ -9,6 +9,7 @@ pub trait CoordinateSystem:
   LandmassCoordinateSystem<
     Coordinate: Default + Send + Sync + PartialEq,
     SampleDistance: Send + Sync,
+    Rotation: PartialEq,
   > + TypePath
   + Send
   + Sync
@@ -19,9 +20,9 @@ pub trait CoordinateSystem:
   /// Converts a position in Bevy into this system's coordinate.
   fn from_bevy_position(v: bevy_math::Vec3) -> Self::Coordinate;
 
-  /// Converts a [`bevy_math::Quat`] into the corresponding rotation (in
-  /// radians) in this system.
-  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> f32;
+  /// Converts a [`bevy_math::Quat`] into the corresponding rotation in this
+  /// system.
+  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> Self::Rotation;
 
   /// Converts this system's coordinate into a world position.
   fn to_world_position(c: &Self::Coordinate) -> bevy_math::Vec3;
@@ -34,6 +35,7 @@ pub struct ThreeD;
 impl LandmassCoordinateSystem for ThreeD {
   type Coordinate = bevy_math::Vec3;
   type SampleDistance = PointSampleDistance3d;
+  type Rotation = bevy_math::Quat;
 
   const FLIP_POLYGONS: bool = true;
 
@@ -44,6 +46,12 @@ impl LandmassCoordinateSystem for ThreeD {
   fn from_landmass(v: &landmass::Vec3) -> Self::Coordinate {
     bevy_math::Vec3::new(v.x, v.z, -v.y)
   }
+
+  fn to_landmass_rotation(rotation: &Self::Rotation) -> landmass::Quat {
+    // The axes are remapped the same way as `to_landmass`, so the vector part
+    // of the quaternion is remapped identically.
+    landmass::Quat::from_xyzw(rotation.x, -rotation.z, rotation.y, rotation.w)
+  }
 }
 
 impl CoordinateSystem for ThreeD {
@@ -55,8 +63,8 @@ impl CoordinateSystem for ThreeD {
     v
   }
 
-  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> f32 {
-    rotation.to_euler(bevy_math::EulerRot::YXZ).0
+  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> Self::Rotation {
+    *rotation
   }
 
   fn to_world_position(c: &Self::Coordinate) -> bevy_math::Vec3 {
@@ -71,6 +79,8 @@ pub struct TwoD;
 impl LandmassCoordinateSystem for TwoD {
   type Coordinate = bevy_math::Vec2;
   type SampleDistance = f32;
+  /// The rotation (in radians) around the Z axis.
+  type Rotation = f32;
 
   const FLIP_POLYGONS: bool = false;
 
@@ -81,6 +91,10 @@ impl LandmassCoordinateSystem for TwoD {
   fn from_landmass(v: &landmass::Vec3) -> Self::Coordinate {
     bevy_math::Vec2::new(v.x, v.y)
   }
+
+  fn to_landmass_rotation(rotation: &Self::Rotation) -> landmass::Quat {
+    landmass::Quat::from_rotation_z(*rotation)
+  }
 }
 
 impl CoordinateSystem for TwoD {
@@ -92,7 +106,7 @@ impl CoordinateSystem for TwoD {
     v.xy()
   }
 
-  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> f32 {
+  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> Self::Rotation {
     rotation.to_euler(bevy_math::EulerRot::ZXY).0
   }
 
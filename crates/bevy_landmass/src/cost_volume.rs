@@ -0,0 +1,126 @@
+use bevy_ecs::{
+  bundle::Bundle, component::Component, entity::Entity, query::With,
+  system::Query,
+};
+use bevy_platform::collections::{HashMap, HashSet};
+use bevy_transform::{components::Transform, helper::TransformHelper};
+
+use crate::{
+  Archipelago, ArchipelagoRef, CostVolumeEffect, ObstacleShape,
+  coords::{CoordinateSystem, ThreeD, TwoD},
+  uniform_scale,
+};
+
+/// A bundle to create cost volumes. The GlobalTransform component is omitted,
+/// since this is commonly added in other bundles (which is redundant and can
+/// override previous bundles).
+#[derive(Bundle)]
+pub struct CostVolumeBundle<CS: CoordinateSystem> {
+  /// The cost volume itself.
+  pub cost_volume: CostVolume<CS>,
+  /// A reference pointing to the Archipelago to associate this entity with.
+  pub archipelago_ref: ArchipelagoRef<CS>,
+}
+
+pub type CostVolume2dBundle = CostVolumeBundle<TwoD>;
+pub type CostVolume3dBundle = CostVolumeBundle<ThreeD>;
+
+/// A volume that changes the cost of the nodes it overlaps. The volume follows
+/// the transform of its entity. Volumes with a non-positive cost multiplier
+/// are ignored. Only uniform scales are supported, and the scale must be
+/// positive.
+#[derive(Component)]
+#[require(Transform)]
+pub struct CostVolume<CS: CoordinateSystem> {
+  /// The shape of the volume, relative to the entity's transform.
+  pub shape: ObstacleShape<CS>,
+  /// The vertical extent of the volume, starting at the entity's position.
+  /// Only nodes that overlap this vertical range are affected.
+  pub height: f32,
+  /// How the volume changes the cost of the nodes it overlaps.
+  pub effect: CostVolumeEffect,
+}
+
+pub type CostVolume2d = CostVolume<TwoD>;
+pub type CostVolume3d = CostVolume<ThreeD>;
+
+/// Ensures that the cost volumes (and their transforms) are up to date.
+pub(crate) fn sync_cost_volumes_to_archipelago<CS: CoordinateSystem>(
+  mut archipelagos: Query<(Entity, &mut Archipelago<CS>)>,
+  cost_volumes: Query<
+    (Entity, &CostVolume<CS>, &ArchipelagoRef<CS>),
+    With<Transform>,
+  >,
+  transform_helper: TransformHelper,
+) {
+  let mut archipelago_to_cost_volumes = HashMap::<_, HashSet<_>>::default();
+  for (cost_volume_entity, cost_volume, archipelago_ref) in cost_volumes.iter()
+  {
+    let mut archipelago = match archipelagos.get_mut(archipelago_ref.entity) {
+      Err(_) => continue,
+      Ok((_, arch)) => arch,
+    };
+
+    let Ok(cost_volume_transform) =
+      transform_helper.compute_global_transform(cost_volume_entity)
+    else {
+      continue;
+    };
+
+    let cost_volume_transform = cost_volume_transform.compute_transform();
+    let landmass_cost_volume = landmass::CostVolume {
+      shape: cost_volume.shape.clone(),
+      transform: landmass::Transform {
+        translation: CS::from_bevy_position(cost_volume_transform.translation),
+        rotation: CS::from_bevy_rotation(&cost_volume_transform.rotation),
+        scale: uniform_scale(cost_volume_entity, cost_volume_transform.scale),
+      },
+      height: cost_volume.height,
+      effect: cost_volume.effect,
+    };
+
+    let archipelago = archipelago.as_mut();
+    if let Some(&cost_volume_id) =
+      archipelago.cost_volumes.get(&cost_volume_entity)
+    {
+      if archipelago.archipelago.get_cost_volume(cost_volume_id)
+        == Some(&landmass_cost_volume)
+      {
+        archipelago_to_cost_volumes
+          .entry(archipelago_ref.entity)
+          .or_default()
+          .insert(cost_volume_entity);
+        continue;
+      }
+      // Cost volumes can't be mutated, so replace the stale version.
+      archipelago.archipelago.remove_cost_volume(cost_volume_id);
+      archipelago.cost_volumes.remove(&cost_volume_entity);
+    }
+
+    let Ok(cost_volume_id) =
+      archipelago.archipelago.add_cost_volume(landmass_cost_volume)
+    else {
+      continue;
+    };
+    archipelago.cost_volumes.insert(cost_volume_entity, cost_volume_id);
+    archipelago_to_cost_volumes
+      .entry(archipelago_ref.entity)
+      .or_default()
+      .insert(cost_volume_entity);
+  }
+
+  for (entity, mut archipelago) in archipelagos.iter_mut() {
+    let cost_volumes = archipelago_to_cost_volumes.get(&entity);
+    let archipelago = archipelago.as_mut();
+    archipelago.cost_volumes.retain(|entity, id| {
+      if cost_volumes
+        .map(|cost_volumes| cost_volumes.contains(entity))
+        .unwrap_or(false)
+      {
+        return true;
+      }
+      archipelago.archipelago.remove_cost_volume(*id);
+      false
+    });
+  }
+}
@@ -5,15 +5,16 @@ use bevy_ecs::{
   bundle::Bundle,
   component::Component,
   entity::Entity,
-  query::With,
+  query::{Has, With},
   system::{Query, Res},
 };
 use bevy_platform::collections::{HashMap, HashSet};
 use bevy_transform::{components::Transform, helper::TransformHelper};
 
 use crate::{
-  Archipelago, ArchipelagoRef, NavMesh, NavMeshHandle,
+  Archipelago, ArchipelagoRef, NavMesh, NavMeshHandle, NavigationDisabled,
   coords::{CoordinateSystem, ThreeD, TwoD},
+  uniform_scale,
 };
 
 /// A bundle to create islands. The GlobalTransform component is omitted, since
@@ -32,7 +33,8 @@ pub struct IslandBundle<CS: CoordinateSystem> {
 pub type Island2dBundle = IslandBundle<TwoD>;
 pub type Island3dBundle = IslandBundle<ThreeD>;
 
-/// A marker component that an entity is an island.
+/// A marker component that an entity is an island. Only uniform scales are
+/// supported, and the scale must be positive.
 #[derive(Component)]
 #[require(Transform)]
 pub struct Island;
@@ -41,14 +43,16 @@ pub struct Island;
 pub(crate) fn sync_islands_to_archipelago<CS: CoordinateSystem>(
   mut archipelagos: Query<(Entity, &mut Archipelago<CS>)>,
   islands: Query<
-    (Entity, &NavMeshHandle<CS>, &ArchipelagoRef<CS>),
+    (Entity, &NavMeshHandle<CS>, &ArchipelagoRef<CS>, Has<NavigationDisabled>),
     (With<Island>, With<Transform>),
   >,
   transform_helper: TransformHelper,
   nav_meshes: Res<Assets<NavMesh<CS>>>,
 ) {
   let mut archipelago_to_islands = HashMap::<_, HashSet<_>>::default();
-  for (island_entity, island_nav_mesh, archipelago_ref) in islands.iter() {
+  for (island_entity, island_nav_mesh, archipelago_ref, disabled) in
+    islands.iter()
+  {
     let mut archipelago = match archipelagos.get_mut(archipelago_ref.entity) {
       Err(_) => continue,
       Ok((_, arch)) => arch,
@@ -73,15 +77,17 @@ pub(crate) fn sync_islands_to_archipelago<CS: CoordinateSystem>(
     let landmass_transform = landmass::Transform {
       translation: CS::from_bevy_position(island_transform.translation),
       rotation: CS::from_bevy_rotation(&island_transform.rotation),
+      scale: uniform_scale(island_entity, island_transform.scale),
     };
 
     match archipelago.get_island_mut(island_entity) {
       None => {
-        let island_id =
-          archipelago.archipelago.add_island(landmass::Island::new(
-            landmass_transform,
-            island_nav_mesh.nav_mesh.clone(),
-          ));
+        let mut island = landmass::Island::new(
+          landmass_transform,
+          island_nav_mesh.nav_mesh.clone(),
+        );
+        island.set_enabled(!disabled);
+        let island_id = archipelago.archipelago.add_island(island);
         archipelago.islands.insert(island_entity, island_id);
         archipelago.reverse_islands.insert(island_id, island_entity);
       }
@@ -92,6 +98,9 @@ pub(crate) fn sync_islands_to_archipelago<CS: CoordinateSystem>(
         if !Arc::ptr_eq(&island.get_nav_mesh(), &island_nav_mesh.nav_mesh) {
           island.set_nav_mesh(island_nav_mesh.nav_mesh.clone());
         }
+        if island.is_enabled() == disabled {
+          island.set_enabled(!disabled);
+        }
       }
     };
   }
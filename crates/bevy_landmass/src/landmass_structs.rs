@@ -19,14 +19,24 @@ pub enum AgentState {
   ReachedAnimationLink,
   /// The agent is currently using an animation link.
   UsingAnimationLink,
+  /// The agent has reached an animation link along its path to the target,
+  /// but the link is full. The agent waits near the start of the link until
+  /// there is room.
+  WaitingForAnimationLink,
   /// The agent has a path and is moving towards their target.
   Moving,
+  /// The agent is still searching for a path to their target.
+  Pathing,
   /// The agent is not on a nav mesh.
   AgentNotOnNavMesh,
   /// The target is not on a nav mesh.
   TargetNotOnNavMesh,
   /// The agent has a target but cannot find a path to it.
   NoPath,
+  /// The agent cannot find a path to its target, so it is moving to (or has
+  /// reached) the closest point to the target that it can reach. This only
+  /// happens if the agent has [`crate::AllowPartialPaths`].
+  MovingToClosestPoint,
   /// The agent is paused.
   Paused,
 }
@@ -39,10 +49,15 @@ impl AgentState {
       landmass::AgentState::ReachedTarget => Self::ReachedTarget,
       landmass::AgentState::ReachedAnimationLink => Self::ReachedAnimationLink,
       landmass::AgentState::UsingAnimationLink => Self::UsingAnimationLink,
+      landmass::AgentState::WaitingForAnimationLink => {
+        Self::WaitingForAnimationLink
+      }
       landmass::AgentState::Moving => Self::Moving,
+      landmass::AgentState::Pathing => Self::Pathing,
       landmass::AgentState::AgentNotOnNavMesh => Self::AgentNotOnNavMesh,
       landmass::AgentState::TargetNotOnNavMesh => Self::TargetNotOnNavMesh,
       landmass::AgentState::NoPath => Self::NoPath,
+      landmass::AgentState::MovingToClosestPoint => Self::MovingToClosestPoint,
       landmass::AgentState::Paused => Self::Paused,
     }
   }
@@ -113,3 +128,31 @@ impl PermittedAnimationLinks {
     }
   }
 }
+
+/// Defines the list of type indices that an agent is allowed to walk on.
+#[derive(Component, Clone, Default, Debug)]
+pub enum PermittedTypeIndices {
+  /// Every type index is permitted.
+  #[default]
+  All,
+  /// Only type indices in this set are permitted.
+  Include(Arc<HashSet<usize>>),
+  /// Every type index except those in this set is permitted.
+  Exclude(Arc<HashSet<usize>>),
+}
+
+impl PermittedTypeIndices {
+  /// Convert the `bevy_landmass` version of the permitted type indices to the
+  /// `landmass` version.
+  pub(crate) fn to_landmass(&self) -> landmass::PermittedTypeIndices {
+    match self {
+      PermittedTypeIndices::All => landmass::PermittedTypeIndices::All,
+      PermittedTypeIndices::Include(type_indices) => {
+        landmass::PermittedTypeIndices::Include(type_indices.clone())
+      }
+      PermittedTypeIndices::Exclude(type_indices) => {
+        landmass::PermittedTypeIndices::Exclude(type_indices.clone())
+      }
+    }
+  }
+}
@@ -11,30 +11,46 @@ use bevy_ecs::{
   schedule::{IntoScheduleConfigs, ScheduleLabel, SystemSet},
   system::{Query, Res},
 };
+use bevy_log::warn_once;
+use bevy_math::Vec3;
 use bevy_platform::collections::HashMap;
 use bevy_reflect::TypePath;
 use bevy_time::Time;
 use coords::{CoordinateSystem, ThreeD, TwoD};
-use landmass::{AgentId, AnimationLinkId, CharacterId, IslandId};
+use landmass::{
+  AgentId, AnimationLinkId, AvoidanceObstacleId, CharacterId, CostVolumeId,
+  IslandId, ObstacleId,
+};
+use rand::Rng;
 
 mod agent;
+mod avoidance_obstacle;
 mod character;
+mod cost_volume;
 mod island;
 mod landmass_structs;
 mod link;
+mod obstacle;
 
 pub use landmass::{
-  ArchipelagoOptions, FindPathError, FromAgentRadius, HeightNavigationMesh,
-  HeightPolygon, NavigationMesh, PathStep, PointSampleDistance3d,
-  SamplePointError, SetTypeIndexCostError, ValidNavigationMesh,
+  AddCostVolumeError, ArchipelagoOptions, AvoidanceAgent, AvoidanceBackend,
+  AvoidanceCharacter, AvoidanceContext, AvoidanceFootprint, AvoidanceNeighbour,
+  AvoidanceVelocities, BorderObstacle, CostVolumeEffect, DodgyAvoidance,
+  FindPathError, FromAgentRadius, HeightNavigationMesh, HeightPolygon,
+  NavigationMesh, NearestWall, NoAvoidance, ObstacleShape, PartialPath,
+  PathStep, PointSampleDistance3d, RandomPointError, SamplePointError,
+  SeparationAvoidance, SetTypeIndexCostError, ValidNavigationMesh,
   ValidationError,
 };
 
 pub use agent::*;
+pub use avoidance_obstacle::*;
 pub use character::*;
+pub use cost_volume::*;
 pub use island::*;
 pub use landmass_structs::*;
 pub use link::*;
+pub use obstacle::*;
 
 pub mod coords;
 pub mod debug;
@@ -47,6 +63,8 @@ pub mod prelude {
   pub use crate::Agent3dBundle;
   pub use crate::AgentDesiredVelocity2d;
   pub use crate::AgentDesiredVelocity3d;
+  pub use crate::AgentPlatformVelocity2d;
+  pub use crate::AgentPlatformVelocity3d;
   pub use crate::AgentSettings;
   pub use crate::AgentState;
   pub use crate::AgentTarget2d;
@@ -60,9 +78,18 @@ pub mod prelude {
   pub use crate::ArchipelagoOptions;
   pub use crate::ArchipelagoRef2d;
   pub use crate::ArchipelagoRef3d;
+  pub use crate::AvoidanceObstacle2d;
+  pub use crate::AvoidanceObstacle2dBundle;
+  pub use crate::AvoidanceObstacle3d;
+  pub use crate::AvoidanceObstacle3dBundle;
   pub use crate::Character2dBundle;
   pub use crate::Character3dBundle;
   pub use crate::CharacterSettings;
+  pub use crate::CostVolume2d;
+  pub use crate::CostVolume2dBundle;
+  pub use crate::CostVolume3d;
+  pub use crate::CostVolume3dBundle;
+  pub use crate::CostVolumeEffect;
   pub use crate::FromAgentRadius;
   pub use crate::HeightNavigationMesh2d;
   pub use crate::HeightNavigationMesh3d;
@@ -76,8 +103,14 @@ pub mod prelude {
   pub use crate::NavMesh3d;
   pub use crate::NavMeshHandle2d;
   pub use crate::NavMeshHandle3d;
+  pub use crate::NavigationDisabled;
   pub use crate::NavigationMesh2d;
   pub use crate::NavigationMesh3d;
+  pub use crate::Obstacle2d;
+  pub use crate::Obstacle2dBundle;
+  pub use crate::Obstacle3d;
+  pub use crate::Obstacle3dBundle;
+  pub use crate::ObstacleShape;
   pub use crate::ReachedAnimationLink2d;
   pub use crate::ReachedAnimationLink3d;
   pub use crate::ValidNavigationMesh2d;
@@ -133,6 +166,9 @@ impl<CS: CoordinateSystem> Plugin for LandmassPlugin<CS> {
         sync_islands_to_archipelago::<CS>,
         add_characters_to_archipelago::<CS>,
         update_animation_links_to_archipelagos::<CS>,
+        sync_obstacles_to_archipelago::<CS>,
+        sync_avoidance_obstacles_to_archipelago::<CS>,
+        sync_cost_volumes_to_archipelago::<CS>,
       )
         .in_set(LandmassSystems::SyncExistence),
     );
@@ -150,6 +186,7 @@ impl<CS: CoordinateSystem> Plugin for LandmassPlugin<CS> {
       (
         sync_agent_state::<CS>,
         sync_desired_velocity::<CS>,
+        sync_platform_velocity::<CS>,
         sync_agent_reached_animation_link::<CS>,
       )
         .in_set(LandmassSystems::Output),
@@ -196,6 +233,15 @@ pub struct Archipelago<CS: CoordinateSystem> {
   /// [`Self::archipelago`]. This is just the reverse of
   /// [`Self::animation_links`].
   reverse_animation_links: HashMap<AnimationLinkId, Entity>,
+  /// A map from the Bevy entity to its associated obstacle ID in
+  /// [`Self::archipelago`].
+  obstacles: HashMap<Entity, ObstacleId>,
+  /// A map from the Bevy entity to its associated avoidance obstacle ID in
+  /// [`Self::archipelago`].
+  avoidance_obstacles: HashMap<Entity, AvoidanceObstacleId>,
+  /// A map from the Bevy entity to its associated cost volume ID in
+  /// [`Self::archipelago`].
+  cost_volumes: HashMap<Entity, CostVolumeId>,
   /// A map from the Bevy entity to its associated agent ID in
   /// [`Self::archipelago`].
   agents: HashMap<Entity, AgentId>,
@@ -219,6 +265,9 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       reverse_islands: HashMap::new(),
       animation_links: HashMap::new(),
       reverse_animation_links: HashMap::new(),
+      obstacles: HashMap::new(),
+      avoidance_obstacles: HashMap::new(),
+      cost_volumes: HashMap::new(),
       agents: HashMap::new(),
       reverse_agents: HashMap::new(),
       characters: HashMap::new(),
@@ -235,6 +284,15 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     &mut self.archipelago.archipelago_options
   }
 
+  /// Sets the backend used for local avoidance. By default, this is
+  /// [`DodgyAvoidance`].
+  pub fn set_avoidance_backend(
+    &mut self,
+    avoidance_backend: impl AvoidanceBackend<CS> + 'static,
+  ) {
+    self.archipelago.set_avoidance_backend(avoidance_backend);
+  }
+
   /// Sets the cost of `type_index` to `cost`. The cost is a multiplier on the
   /// distance travelled along this node (essentially the cost per meter).
   /// Agents will prefer to travel along low-cost terrain.
@@ -259,14 +317,19 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
   }
 
   /// Finds the nearest point on the navigation meshes to (and within
-  /// `distance_to_node` of) `point`.
+  /// `distance_to_node` of) `point`. Only nodes whose type index is in
+  /// `permitted_type_indices` are considered.
   pub fn sample_point(
     &self,
     point: CS::Coordinate,
     point_sample_distance: &CS::SampleDistance,
+    permitted_type_indices: PermittedTypeIndices,
   ) -> Result<SampledPoint<'_, CS>, SamplePointError> {
-    let sampled_point =
-      self.archipelago.sample_point(point, point_sample_distance)?;
+    let sampled_point = self.archipelago.sample_point(
+      point,
+      point_sample_distance,
+      permitted_type_indices.to_landmass(),
+    )?;
     Ok(SampledPoint {
       island: *self
         .reverse_islands
@@ -280,22 +343,193 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
   /// meshes. Only [`SampledPoint`]s from this archipelago are supported. This
   /// should only be used for querying (e.g., finding the walking distance to an
   /// object), not for controlling movement. For controlling movement, use
-  /// agents.
+  /// agents. Nodes whose type index is not in `permitted_type_indices` are
+  /// never entered.
   pub fn find_path(
     &self,
     start_point: &SampledPoint<'_, CS>,
     end_point: &SampledPoint<'_, CS>,
     override_type_index_costs: &std::collections::HashMap<usize, f32>,
     permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
   ) -> Result<Vec<PathStep<CS>>, FindPathError> {
     self.archipelago.find_path(
       &start_point.sampled_point,
       &end_point.sampled_point,
       override_type_index_costs,
       permitted_animation_links.to_landmass(),
+      permitted_type_indices.to_landmass(),
     )
   }
 
+  /// Same as [`Self::find_path`], except if `end_point` is unreachable, the
+  /// returned path leads to the reachable point closest to `end_point`
+  /// instead, and is marked as [`PartialPath::is_partial`].
+  pub fn find_partial_path(
+    &self,
+    start_point: &SampledPoint<'_, CS>,
+    end_point: &SampledPoint<'_, CS>,
+    override_type_index_costs: &std::collections::HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+  ) -> Result<PartialPath<CS>, FindPathError> {
+    self.archipelago.find_partial_path(
+      &start_point.sampled_point,
+      &end_point.sampled_point,
+      override_type_index_costs,
+      permitted_animation_links.to_landmass(),
+      permitted_type_indices.to_landmass(),
+    )
+  }
+
+  /// Finds every node that can be reached from `start_point` with a path cost
+  /// of at most `max_cost`, along with the cost to reach each of them. The
+  /// cost of a node is the cost of the path to where it enters the node (or 0
+  /// for the node containing `start_point`). The nodes are sorted by cost.
+  /// Nodes whose type index is not in `permitted_type_indices` are never
+  /// entered. Only [`SampledPoint`]s from this archipelago are supported.
+  pub fn find_reachable_nodes<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    max_cost: f32,
+    override_type_index_costs: &std::collections::HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+  ) -> Result<Vec<ReachableNode<'a, CS>>, FindPathError> {
+    let reachable_nodes = self.archipelago.find_reachable_nodes(
+      &start_point.sampled_point,
+      max_cost,
+      override_type_index_costs,
+      permitted_animation_links.to_landmass(),
+      permitted_type_indices.to_landmass(),
+    )?;
+    Ok(
+      reachable_nodes
+        .into_iter()
+        .map(|node| ReachableNode {
+          center: SampledPoint {
+            island: *self
+              .reverse_islands
+              .get(&node.center.island())
+              .expect("The island hasn't been removed from the archipelago."),
+            sampled_point: node.center,
+          },
+          cost: node.cost,
+        })
+        .collect(),
+    )
+  }
+
+  /// Finds the cost of the cheapest path from `start_point` to each of
+  /// `end_points`. This runs a single search for all the end points, so it is
+  /// much cheaper than calling [`Self::find_path`] for each of them. The
+  /// result is in the same order as `end_points`, and is None for end points
+  /// that are unreachable. Only [`SampledPoint`]s from this archipelago are
+  /// supported.
+  pub fn find_path_costs<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    end_points: &[SampledPoint<'a, CS>],
+    override_type_index_costs: &std::collections::HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+  ) -> Result<Vec<Option<f32>>, FindPathError> {
+    let end_points = end_points
+      .iter()
+      .map(|end_point| end_point.sampled_point.clone())
+      .collect::<Vec<_>>();
+    self.archipelago.find_path_costs(
+      &start_point.sampled_point,
+      &end_points,
+      override_type_index_costs,
+      permitted_animation_links.to_landmass(),
+      permitted_type_indices.to_landmass(),
+    )
+  }
+
+  /// Casts a ray along the navigation meshes from `start_point` towards
+  /// `end_point`, to check whether an agent could walk in a straight line
+  /// between them. The ray is only checked horizontally. Only
+  /// [`SampledPoint`]s from this archipelago are supported.
+  pub fn raycast<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    end_point: CS::Coordinate,
+  ) -> RaycastResult<'a, CS> {
+    match self.archipelago.raycast(&start_point.sampled_point, end_point) {
+      landmass::RaycastResult::Reached => RaycastResult::Reached,
+      landmass::RaycastResult::Hit { point, normal } => RaycastResult::Hit {
+        point: SampledPoint {
+          island: *self
+            .reverse_islands
+            .get(&point.island())
+            .expect("The island hasn't been removed from the archipelago."),
+          sampled_point: point,
+        },
+        normal,
+      },
+    }
+  }
+
+  /// Finds the nearest point on the boundary of the navigation meshes (a
+  /// "wall") to `point`, within `max_radius`. Seams between islands connected
+  /// by boundary links are not walls. Returns None if there is no wall within
+  /// `max_radius`. Only [`SampledPoint`]s from this archipelago are supported.
+  pub fn find_distance_to_wall(
+    &self,
+    point: &SampledPoint<'_, CS>,
+    max_radius: f32,
+  ) -> Option<NearestWall<CS>> {
+    self.archipelago.find_distance_to_wall(&point.sampled_point, max_radius)
+  }
+
+  /// Picks a uniformly random point on the navigation meshes. Only nodes whose
+  /// type index is in `permitted_type_indices` are considered.
+  pub fn random_point(
+    &self,
+    permitted_type_indices: PermittedTypeIndices,
+    rng: &mut impl Rng,
+  ) -> Result<SampledPoint<'_, CS>, RandomPointError> {
+    let sampled_point = self
+      .archipelago
+      .random_point(permitted_type_indices.to_landmass(), rng)?;
+    Ok(SampledPoint {
+      island: *self
+        .reverse_islands
+        .get(&sampled_point.island())
+        .expect("The island hasn't been removed from the archipelago."),
+      sampled_point,
+    })
+  }
+
+  /// Picks a uniformly random point within `radius` of `start_point` that can
+  /// be reached from `start_point`. Only nodes whose type index is in
+  /// `permitted_type_indices` are considered. Only [`SampledPoint`]s from this
+  /// archipelago are supported.
+  pub fn random_point_in_radius<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    radius: f32,
+    permitted_type_indices: PermittedTypeIndices,
+    permitted_animation_links: PermittedAnimationLinks,
+    rng: &mut impl Rng,
+  ) -> Result<SampledPoint<'a, CS>, RandomPointError> {
+    let sampled_point = self.archipelago.random_point_in_radius(
+      &start_point.sampled_point,
+      radius,
+      permitted_type_indices.to_landmass(),
+      permitted_animation_links.to_landmass(),
+      rng,
+    )?;
+    Ok(SampledPoint {
+      island: *self
+        .reverse_islands
+        .get(&sampled_point.island())
+        .expect("The island hasn't been removed from the archipelago."),
+      sampled_point,
+    })
+  }
+
   /// Gets an agent.
   fn get_agent(&self, entity: Entity) -> Option<&landmass::Agent<CS>> {
     self
@@ -392,6 +626,13 @@ impl<CS: CoordinateSystem> ArchipelagoRef<CS> {
   }
 }
 
+/// A marker component to disable an island or an animation link without
+/// removing it. Disabled islands and animation links are treated as absent for
+/// pathfinding and sampling, but keep their links, so toggling this (e.g., for
+/// doors or drawbridges) is cheaper than despawning and respawning them.
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct NavigationDisabled;
+
 ///[`NavigationMesh`] using 2D Bevy coordinates.
 /// That means that the mesh expects vertices to be on the XY plane.
 ///
@@ -467,6 +708,64 @@ impl<CS: CoordinateSystem> SampledPoint<'_, CS> {
   }
 }
 
+/// A node that can be reached within a cost budget. See
+/// [`Archipelago::find_reachable_nodes`].
+pub struct ReachableNode<'archipelago, CS: CoordinateSystem> {
+  /// The center of the node.
+  pub center: SampledPoint<'archipelago, CS>,
+  /// The cost of the cheapest path to reach the node.
+  pub cost: f32,
+}
+
+// Manual Clone impl for `ReachableNode` to avoid the Clone bound on CS.
+impl<CS: CoordinateSystem> Clone for ReachableNode<'_, CS> {
+  fn clone(&self) -> Self {
+    Self { center: self.center.clone(), cost: self.cost }
+  }
+}
+
+/// The result of casting a ray across the navigation meshes. See
+/// [`Archipelago::raycast`].
+pub enum RaycastResult<'archipelago, CS: CoordinateSystem> {
+  /// The ray reached the end point without leaving the navigation meshes.
+  Reached,
+  /// The ray left the navigation meshes before reaching the end point.
+  Hit {
+    /// The point where the ray left the navigation meshes. This is on the last
+    /// node the ray crossed.
+    point: SampledPoint<'archipelago, CS>,
+    /// The normal of the edge that was hit, pointing away from the navigation
+    /// meshes.
+    normal: CS::Coordinate,
+  },
+}
+
+// Manual Clone impl for `RaycastResult` to avoid the Clone bound on CS.
+impl<CS: CoordinateSystem> Clone for RaycastResult<'_, CS> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Reached => Self::Reached,
+      Self::Hit { point, normal } => {
+        Self::Hit { point: point.clone(), normal: normal.clone() }
+      }
+    }
+  }
+}
+
+/// Returns the uniform scale of `entity` with the global `scale`. Only uniform
+/// scales are supported, so a non-uniform scale warns and uses its X
+/// component.
+pub(crate) fn uniform_scale(entity: Entity, scale: Vec3) -> f32 {
+  // Decomposing a global transform can introduce some error, so allow a small
+  // relative difference between the components.
+  if (scale - scale.x).abs().max_element() > scale.x.abs() * 1e-4 {
+    warn_once!(
+      "Entity {entity} has a non-uniform scale {scale}. Only uniform scales are supported, so only the X component is used."
+    );
+  }
+  scale.x
+}
+
 #[cfg(test)]
 #[path = "lib_test.rs"]
 mod test;
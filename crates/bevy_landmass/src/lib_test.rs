@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, f32::consts::SQRT_2, sync::Arc};
 
 use bevy::MinimalPlugins;
 use bevy_app::{App, Plugin};
@@ -20,8 +20,9 @@ use crate::{
   ArchipelagoRef3d, Character3dBundle, CharacterSettings, FromAgentRadius,
   Island, Island2dBundle, Island3dBundle, Landmass2dPlugin, Landmass3dPlugin,
   NavMesh2d, NavMesh3d, NavMeshHandle, NavigationMesh, NavigationMesh3d,
-  PauseAgent, PermittedAnimationLinks, ReachedAnimationLink2d,
-  SamplePointError, UsingAnimationLink, Velocity3d,
+  Obstacle2d, Obstacle2dBundle, ObstacleShape, PauseAgent,
+  PermittedAnimationLinks, PermittedTypeIndices, RaycastResult,
+  ReachedAnimationLink2d, SamplePointError, UsingAnimationLink, Velocity3d,
 };
 
 #[test]
@@ -96,13 +97,15 @@ fn computes_path_for_agent_and_updates_desired_velocity() {
     *app.world().get::<AgentState>(agent_id).expect("current state was added"),
     AgentState::Moving,
   );
-  assert_eq!(
+  // The agent keeps its radius away from the corner.
+  let waypoint = Vec3::new(4.0 + 0.5 / SQRT_2, 1.0, 3.0 - 0.5 / SQRT_2);
+  assert!(
     app
       .world()
       .get::<AgentDesiredVelocity3d>(agent_id)
       .expect("desired velocity was added")
-      .velocity(),
-    Vec3::new(1.5, 0.0, 0.5).normalize(),
+      .velocity()
+      .abs_diff_eq((waypoint - Vec3::new(2.5, 1.0, 2.5)).normalize(), 1e-6)
   );
 }
 
@@ -470,6 +473,8 @@ fn adds_and_removes_animation_links() {
         kind: 0,
         cost: 1.0,
         bidirectional: false,
+        capacity: None,
+        busy_cost: None,
       },
       archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
     })
@@ -502,6 +507,77 @@ fn adds_and_removes_animation_links() {
   );
 }
 
+#[googletest::test]
+fn adds_moves_and_removes_obstacles() {
+  let mut app = create_test_app_2d();
+
+  let archipelago_entity = app
+    .world_mut()
+    .spawn(Archipelago2d::new(ArchipelagoOptions::from_agent_radius(0.5)))
+    .id();
+
+  let obstacle_entity = app
+    .world_mut()
+    .spawn((
+      Transform::from_translation(Vec3::new(1.0, 2.0, 0.0)),
+      Obstacle2dBundle {
+        obstacle: Obstacle2d {
+          shape: ObstacleShape::Circle { radius: 0.5 },
+          height: 1.0,
+        },
+        archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
+      },
+    ))
+    .id();
+  app.update();
+
+  let archipelago =
+    app.world().entity(archipelago_entity).get::<Archipelago2d>().unwrap();
+  expect_that!(
+    archipelago.obstacles.keys().copied().collect::<Vec<_>>(),
+    unordered_elements_are!(&obstacle_entity)
+  );
+  let obstacle_id = *archipelago.obstacles.get(&obstacle_entity).unwrap();
+  expect_eq!(
+    archipelago.archipelago.get_obstacle(obstacle_id).unwrap().transform,
+    landmass::Transform {
+      translation: Vec2::new(1.0, 2.0),
+      rotation: 0.0,
+      scale: 1.0
+    }
+  );
+
+  app
+    .world_mut()
+    .entity_mut(obstacle_entity)
+    .get_mut::<Transform>()
+    .unwrap()
+    .translation = Vec3::new(3.0, 4.0, 0.0);
+  app.update();
+
+  let archipelago =
+    app.world().entity(archipelago_entity).get::<Archipelago2d>().unwrap();
+  expect_eq!(
+    archipelago.archipelago.get_obstacle(obstacle_id).unwrap().transform,
+    landmass::Transform {
+      translation: Vec2::new(3.0, 4.0),
+      rotation: 0.0,
+      scale: 1.0
+    }
+  );
+
+  app.world_mut().despawn(obstacle_entity);
+  app.update();
+
+  let archipelago =
+    app.world().entity(archipelago_entity).get::<Archipelago2d>().unwrap();
+  expect_that!(
+    archipelago.obstacles.keys().copied().collect::<Vec<_>>(),
+    is_empty()
+  );
+  expect_eq!(archipelago.archipelago.get_obstacle_ids().len(), 0);
+}
+
 #[test]
 fn changing_agent_fields_changes_landmass_agent() {
   let mut app = create_test_app_3d();
@@ -549,6 +625,7 @@ fn changing_agent_fields_changes_landmass_agent() {
     panic!("Expected distance reached condition");
   };
   assert_eq!(dist, Some(1.0));
+  assert!(!agent_ref.allow_partial_paths);
   #[cfg(feature = "debug-avoidance")]
   assert_eq!(agent_ref.keep_avoidance_data, false);
 
@@ -558,6 +635,7 @@ fn changing_agent_fields_changes_landmass_agent() {
     Velocity3d { velocity: Vec3::new(10.0, 11.0, 12.0) },
     AgentTarget3d::Point(Vec3::new(13.0, 14.0, 15.0)),
     crate::TargetReachedCondition::VisibleAtDistance(Some(2.0)),
+    crate::AllowPartialPaths,
     #[cfg(feature = "debug-avoidance")]
     crate::KeepAvoidanceData,
   ));
@@ -582,6 +660,7 @@ fn changing_agent_fields_changes_landmass_agent() {
     panic!("Expected distance reached condition");
   };
   assert_eq!(dist, Some(2.0));
+  assert!(agent_ref.allow_partial_paths);
   #[cfg(feature = "debug-avoidance")]
   assert_eq!(agent_ref.keep_avoidance_data, true);
 }
@@ -743,7 +822,7 @@ fn type_index_costs_are_used() {
       .get::<AgentDesiredVelocity2d>(agent_id)
       .expect("desired velocity was added")
       .velocity(),
-    Vec2::new(1.5, 0.5).normalize(),
+    Vec2::new(1.0, 0.0),
   );
 }
 
@@ -855,7 +934,7 @@ fn overridden_type_index_costs_are_used() {
       .get::<AgentDesiredVelocity2d>(agent_id)
       .expect("desired velocity was added")
       .velocity(),
-    Vec2::new(1.5, 0.5).normalize(),
+    Vec2::new(1.0, 0.0),
   );
 }
 
@@ -900,7 +979,9 @@ fn sample_point_error_on_out_of_range() {
     app.world().get::<Archipelago2d>(archipelago_entity).unwrap();
 
   assert_eq!(
-    archipelago.sample_point(Vec2::new(-0.5, 0.5), &0.1).map(|p| p.point()),
+    archipelago
+      .sample_point(Vec2::new(-0.5, 0.5), &0.1, PermittedTypeIndices::All)
+      .map(|p| p.point()),
     Err(SamplePointError::OutOfRange)
   );
 }
@@ -956,7 +1037,8 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(-0.5, 0.5),
-        /* distance_to_node= */ &0.6
+        /* distance_to_node= */ &0.6,
+        PermittedTypeIndices::All,
       )
       .map(|p| (p.island(), p.point())),
     Ok((island_id, offset + Vec2::new(0.0, 0.5)))
@@ -965,7 +1047,8 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(0.5, 0.5),
-        /* distance_to_node= */ &0.6
+        /* distance_to_node= */ &0.6,
+        PermittedTypeIndices::All,
       )
       .map(|p| (p.island(), p.point())),
     Ok((island_id, offset + Vec2::new(0.5, 0.5)))
@@ -974,7 +1057,8 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(1.2, 1.2),
-        /* distance_to_node= */ &0.6
+        /* distance_to_node= */ &0.6,
+        PermittedTypeIndices::All,
       )
       .map(|p| (p.island(), p.point())),
     Ok((island_id, offset + Vec2::new(1.0, 1.0)))
@@ -1030,7 +1114,8 @@ fn samples_type_indices() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(0.5, 0.5),
-        /* distance_to_node= */ &0.1
+        /* distance_to_node= */ &0.1,
+        PermittedTypeIndices::All,
       )
       .map(|p| p.type_index()),
     Ok(0)
@@ -1039,7 +1124,8 @@ fn samples_type_indices() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(1.5, 0.5),
-        /* distance_to_node= */ &0.1
+        /* distance_to_node= */ &0.1,
+        PermittedTypeIndices::All,
       )
       .map(|p| p.type_index()),
     Ok(1)
@@ -1105,17 +1191,18 @@ fn finds_path() {
   let archipelago =
     app.world().get::<Archipelago2d>(archipelago_entity).unwrap();
   let start_point = archipelago
-    .sample_point(Vec2::new(0.5, 0.5), &1e-5)
+    .sample_point(Vec2::new(0.5, 0.5), &1e-5, PermittedTypeIndices::All)
     .expect("point is on nav mesh.");
   let end_point = archipelago
-    .sample_point(Vec2::new(2.5, 1.25), &1e-5)
+    .sample_point(Vec2::new(2.5, 1.25), &1e-5, PermittedTypeIndices::All)
     .expect("point is on nav mesh.");
   assert_eq!(
     archipelago.find_path(
       &start_point,
       &end_point,
       &HashMap::new(),
-      PermittedAnimationLinks::All
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
     ),
     Ok(vec![
       PathStep::Waypoint(Vec2::new(0.5, 0.5)),
@@ -1125,6 +1212,76 @@ fn finds_path() {
   );
 }
 
+#[test]
+fn raycast_hits_edge_of_island() {
+  let mut app = create_test_app_2d();
+
+  let archipelago_entity = app
+    .world_mut()
+    .spawn(Archipelago2d::new(ArchipelagoOptions::from_agent_radius(0.5)))
+    .id();
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let nav_mesh = app
+    .world_mut()
+    .resource_mut::<Assets<NavMesh2d>>()
+    .add(NavMesh2d { nav_mesh });
+
+  app.world_mut().spawn((Island2dBundle {
+    island: Island,
+    archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
+    nav_mesh: NavMeshHandle(nav_mesh.clone()),
+  },));
+
+  let island_2 = app
+    .world_mut()
+    .spawn((
+      Transform::from_translation(Vec3::new(1.0, 0.0, 0.0)),
+      Island2dBundle {
+        island: Island,
+        archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
+        nav_mesh: NavMeshHandle(nav_mesh.clone()),
+      },
+    ))
+    .id();
+
+  app.update();
+
+  let archipelago =
+    app.world().get::<Archipelago2d>(archipelago_entity).unwrap();
+  let start_point = archipelago
+    .sample_point(Vec2::new(0.5, 0.5), &1e-5, PermittedTypeIndices::All)
+    .expect("point is on nav mesh.");
+  assert!(matches!(
+    archipelago.raycast(&start_point, Vec2::new(1.5, 0.5)),
+    RaycastResult::Reached
+  ));
+
+  let RaycastResult::Hit { point, normal } =
+    archipelago.raycast(&start_point, Vec2::new(2.5, 0.5))
+  else {
+    panic!("Expected the ray to hit an edge.");
+  };
+  assert_eq!(point.point(), Vec2::new(2.0, 0.5));
+  assert_eq!(point.island(), island_2);
+  assert_eq!(normal, Vec2::new(1.0, 0.0));
+}
+
 #[test]
 fn island_matches_rotation_3d() {
   let mut app = create_test_app_3d();
@@ -1265,7 +1422,7 @@ fn agent_is_paused() {
   let nav_mesh = app
     .world_mut()
     .resource_mut::<Assets<NavMesh2d>>()
-    .add(NavMesh2d { nav_mesh: nav_mesh });
+    .add(NavMesh2d { nav_mesh });
 
   app.world_mut().spawn(Island2dBundle {
     archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
@@ -1378,7 +1535,7 @@ fn agent_using_animation_link() {
   let nav_mesh = app
     .world_mut()
     .resource_mut::<Assets<NavMesh2d>>()
-    .add(NavMesh2d { nav_mesh: nav_mesh });
+    .add(NavMesh2d { nav_mesh });
 
   app.world_mut().spawn(Island2dBundle {
     archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
@@ -1402,6 +1559,8 @@ fn agent_using_animation_link() {
         kind: 0,
         cost: 1.0,
         bidirectional: false,
+        capacity: None,
+        busy_cost: None,
       },
       archipelago_ref: ArchipelagoRef2d::new(archipelago_entity),
     })
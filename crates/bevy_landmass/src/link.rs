@@ -5,12 +5,12 @@ use bevy_ecs::{
   event::EntityEvent,
   lifecycle::{Discard, Remove},
   observer::On,
-  query::{Changed, With},
+  query::{Changed, Has, With},
   system::Query,
 };
 
 use crate::{
-  Archipelago, ArchipelagoRef,
+  Archipelago, ArchipelagoRef, NavigationDisabled,
   coords::{CoordinateSystem, ThreeD, TwoD},
 };
 
@@ -52,6 +52,16 @@ pub struct AnimationLink<CS: CoordinateSystem> {
   /// This is a convenience to avoid needing to create two links to go in both
   /// directions.
   pub bidirectional: bool,
+  /// The number of agents that can use this link at the same time. Agents
+  /// that reach a full link wait near the start of the link (in the
+  /// [`crate::AgentState::WaitingForAnimationLink`] state) until there is room.
+  /// If [`None`], any number of agents can use the link at once.
+  pub capacity: Option<usize>,
+  /// The cost of taking this animation link while it is full, replacing
+  /// [`Self::cost`] for new paths. Setting this higher than [`Self::cost`]
+  /// makes agents route around busy links. If [`None`], [`Self::cost`] is
+  /// always used. This is only relevant if [`Self::capacity`] is set.
+  pub busy_cost: Option<f32>,
 }
 
 pub type AnimationLink2d = AnimationLink<TwoD>;
@@ -67,6 +77,8 @@ impl<CS: CoordinateSystem> AnimationLink<CS> {
       kind: self.kind,
       cost: self.cost,
       bidirectional: self.bidirectional,
+      capacity: self.capacity,
+      busy_cost: self.busy_cost,
     }
   }
 }
@@ -119,12 +131,17 @@ fn try_remove_animation_link<CS: CoordinateSystem>(
 }
 
 /// Adds or changes animation links to match between the `bevy_landmass` version
-/// and the underlying archipelago.
+/// and the underlying archipelago. This also enables or disables links based on
+/// [`NavigationDisabled`].
 pub(crate) fn update_animation_links_to_archipelagos<CS: CoordinateSystem>(
   animation_links: Query<
     (Entity, &AnimationLink<CS>, &ArchipelagoRef<CS>),
     Changed<AnimationLink<CS>>,
   >,
+  all_animation_links: Query<
+    (Entity, &ArchipelagoRef<CS>, Has<NavigationDisabled>),
+    With<AnimationLink<CS>>,
+  >,
   mut archipelagos: Query<&mut Archipelago<CS>>,
 ) {
   for (entity, animation_link, archipelago_ref) in animation_links.iter() {
@@ -143,4 +160,19 @@ pub(crate) fn update_animation_links_to_archipelagos<CS: CoordinateSystem>(
     archipelago.animation_links.insert(entity, link_id);
     archipelago.reverse_animation_links.insert(link_id, entity);
   }
+
+  for (entity, archipelago_ref, disabled) in all_animation_links.iter() {
+    let Ok(mut archipelago) = archipelagos.get_mut(archipelago_ref.entity)
+    else {
+      continue;
+    };
+    let Some(&link_id) = archipelago.animation_links.get(&entity) else {
+      continue;
+    };
+    if archipelago.archipelago.is_animation_link_enabled(link_id)
+      == Some(disabled)
+    {
+      archipelago.archipelago.set_animation_link_enabled(link_id, !disabled);
+    }
+  }
 }
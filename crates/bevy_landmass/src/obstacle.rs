@@ -0,0 +1,114 @@
+use bevy_ecs::{
+  bundle::Bundle, component::Component, entity::Entity, query::With,
+  system::Query,
+};
+use bevy_platform::collections::{HashMap, HashSet};
+use bevy_transform::{components::Transform, helper::TransformHelper};
+
+use crate::{
+  Archipelago, ArchipelagoRef, ObstacleShape,
+  coords::{CoordinateSystem, ThreeD, TwoD},
+  uniform_scale,
+};
+
+/// A bundle to create obstacles. The GlobalTransform component is omitted,
+/// since this is commonly added in other bundles (which is redundant and can
+/// override previous bundles).
+#[derive(Bundle)]
+pub struct ObstacleBundle<CS: CoordinateSystem> {
+  /// The obstacle itself.
+  pub obstacle: Obstacle<CS>,
+  /// A reference pointing to the Archipelago to associate this entity with.
+  pub archipelago_ref: ArchipelagoRef<CS>,
+}
+
+pub type Obstacle2dBundle = ObstacleBundle<TwoD>;
+pub type Obstacle3dBundle = ObstacleBundle<ThreeD>;
+
+/// An obstacle that carves a hole into the navigation meshes it overlaps. The
+/// obstacle follows the transform of its entity. Only uniform scales are
+/// supported, and the scale must be positive.
+#[derive(Component)]
+#[require(Transform)]
+pub struct Obstacle<CS: CoordinateSystem> {
+  /// The shape of the obstacle, relative to the entity's transform.
+  pub shape: ObstacleShape<CS>,
+  /// The vertical extent of the obstacle, starting at the entity's position.
+  /// Only nodes that overlap this vertical range are carved.
+  pub height: f32,
+}
+
+pub type Obstacle2d = Obstacle<TwoD>;
+pub type Obstacle3d = Obstacle<ThreeD>;
+
+/// Ensures that the obstacles (and their transforms) are up to date.
+pub(crate) fn sync_obstacles_to_archipelago<CS: CoordinateSystem>(
+  mut archipelagos: Query<(Entity, &mut Archipelago<CS>)>,
+  obstacles: Query<
+    (Entity, &Obstacle<CS>, &ArchipelagoRef<CS>),
+    With<Transform>,
+  >,
+  transform_helper: TransformHelper,
+) {
+  let mut archipelago_to_obstacles = HashMap::<_, HashSet<_>>::default();
+  for (obstacle_entity, obstacle, archipelago_ref) in obstacles.iter() {
+    let mut archipelago = match archipelagos.get_mut(archipelago_ref.entity) {
+      Err(_) => continue,
+      Ok((_, arch)) => arch,
+    };
+
+    let Ok(obstacle_transform) =
+      transform_helper.compute_global_transform(obstacle_entity)
+    else {
+      continue;
+    };
+
+    archipelago_to_obstacles
+      .entry(archipelago_ref.entity)
+      .or_default()
+      .insert(obstacle_entity);
+
+    let obstacle_transform = obstacle_transform.compute_transform();
+    let landmass_obstacle = landmass::Obstacle {
+      shape: obstacle.shape.clone(),
+      transform: landmass::Transform {
+        translation: CS::from_bevy_position(obstacle_transform.translation),
+        rotation: CS::from_bevy_rotation(&obstacle_transform.rotation),
+        scale: uniform_scale(obstacle_entity, obstacle_transform.scale),
+      },
+      height: obstacle.height,
+    };
+
+    let archipelago = archipelago.as_mut();
+    match archipelago.obstacles.get(&obstacle_entity) {
+      None => {
+        let obstacle_id =
+          archipelago.archipelago.add_obstacle(landmass_obstacle);
+        archipelago.obstacles.insert(obstacle_entity, obstacle_id);
+      }
+      Some(&obstacle_id) => {
+        // Only mutate the obstacle if it changed, since mutating it causes it
+        // to carve the navigation meshes again.
+        if archipelago.archipelago.get_obstacle(obstacle_id)
+          != Some(&landmass_obstacle)
+        {
+          *archipelago.archipelago.get_obstacle_mut(obstacle_id).unwrap() =
+            landmass_obstacle;
+        }
+      }
+    }
+  }
+
+  for (entity, mut archipelago) in archipelagos.iter_mut() {
+    let obstacles = archipelago_to_obstacles.get(&entity);
+    let archipelago = archipelago.as_mut();
+    archipelago.obstacles.retain(|entity, id| {
+      if obstacles.map(|obstacles| obstacles.contains(entity)).unwrap_or(false)
+      {
+        return true;
+      }
+      archipelago.archipelago.remove_obstacle(*id);
+      false
+    });
+  }
+}
@@ -11,7 +11,8 @@ use crate::{
   CoordinateSystem, IslandId, NavigationData,
   link::AnimationLinkId,
   nav_data::{NodeRef, OffMeshLinkId},
-  path::{Path, PathIndex, StraightPathStep},
+  path::{Path, PathIndex, PathInvalidation, StraightPathStep},
+  pathfinding::{PathSearch, PathSearchStatus},
 };
 
 new_key_type! {
@@ -34,14 +35,26 @@ pub enum AgentState {
   ReachedAnimationLink,
   /// The agent is currently using an animation link.
   UsingAnimationLink,
+  /// The agent has reached an animation link along its path to the target,
+  /// but the link is full (see [`crate::AnimationLink::capacity`]). The agent
+  /// waits near the start of the link until there is room.
+  WaitingForAnimationLink,
   /// The agent has a path and is moving towards their target.
   Moving,
+  /// The agent is still searching for a path to their target. This only
+  /// happens when [`crate::ArchipelagoOptions::max_explored_nodes_per_update`]
+  /// is set.
+  Pathing,
   /// The agent is not on a nav mesh.
   AgentNotOnNavMesh,
   /// The target is not on a nav mesh.
   TargetNotOnNavMesh,
   /// The agent has a target but cannot find a path to it.
   NoPath,
+  /// The agent cannot find a path to its target, so it is moving to (or has
+  /// reached) the closest point to the target that it can reach. This only
+  /// happens if [`Agent::allow_partial_paths`] is true.
+  MovingToClosestPoint,
   /// The agent is paused.
   Paused,
 }
@@ -78,12 +91,35 @@ pub struct Agent<CS: CoordinateSystem> {
   /// Note, changing this at runtime may result in the agent continuing on a
   /// path that still contains a previously allowed animation link.
   pub permitted_animation_links: PermittedAnimationLinks,
+  /// The type indices that the agent is allowed to walk on. The agent never
+  /// paths through nodes with other type indices, and is only sampled onto
+  /// permitted nodes.
+  ///
+  /// Note, changing this at runtime may result in the agent continuing on a
+  /// path that still contains a previously allowed type index.
+  pub permitted_type_indices: PermittedTypeIndices,
+  /// Whether the agent should move to the closest point it can reach when its
+  /// target is unreachable. If false, the agent stands still instead.
+  pub allow_partial_paths: bool,
   /// Whether this agent is "paused". Paused agents are not considered for
   /// avoidance, and will not recompute their paths. However, their paths are
   /// still kept "consistent" - meaning that once the agent becomes unpaused,
   /// it can reuse that path if it is still valid and relevant (the agent still
   /// wants to go to the same place).
   pub paused: bool,
+  /// The avoidance layers that the agent is in, as a bitmask. Other agents
+  /// only avoid this agent if their [`Self::avoidance_mask`] shares a layer
+  /// with it.
+  pub avoidance_layers: u32,
+  /// The avoidance layers that the agent avoids, as a bitmask. For example,
+  /// allies can ignore each other by leaving their own layer out of the mask,
+  /// and a mask of 0 makes the agent ignore everyone.
+  pub avoidance_mask: u32,
+  /// How much the agent is prioritized during avoidance. Must be positive. An
+  /// agent with twice the priority of another leaves twice as much of the
+  /// avoidance between them to the other agent. This divides the agent's
+  /// avoidance responsibility. Priorities below 0.001 are treated as 0.001.
+  pub avoidance_priority: f32,
   #[cfg(feature = "debug-avoidance")]
   /// If true, avoidance debug data will be stored during update iterations.
   /// This can later be used for visualization.
@@ -93,6 +129,12 @@ pub struct Agent<CS: CoordinateSystem> {
   /// The current path of the agent. None if a path is unavailable or a new
   /// path has not been computed yet (i.e., no path).
   pub(crate) current_path: Option<Path>,
+  /// If [`Self::current_path`] is a partial path, this is the node that the
+  /// target was in when the path was found.
+  pub(crate) partial_path_target_node: Option<NodeRef>,
+  /// The in-progress search for a new path. This is only set while the agent
+  /// is in the [`AgentState::Pathing`] state.
+  pub(crate) current_path_search: Option<PathSearch>,
   /// The desired velocity of the agent to move towards its goal.
   pub(crate) current_desired_move: CS::Coordinate,
   /// The state of the agent.
@@ -100,8 +142,12 @@ pub struct Agent<CS: CoordinateSystem> {
   /// The animation link that the agent has reached. This includes the
   /// animation link, and the off mesh link being used.
   pub(crate) current_animation_link: Option<ReachedAnimationLink<CS>>,
-  /// Whether this agent is currently using an animation link.
-  pub(crate) using_animation_link: bool,
+  /// The animation link that this agent is currently using.
+  pub(crate) using_animation_link: Option<AnimationLinkId>,
+  /// The island that the agent was standing on in the last update.
+  pub(crate) current_island: Option<IslandId>,
+  /// The velocity of [`Self::current_island`] at the agent's position.
+  pub(crate) platform_velocity: CS::Coordinate,
   #[cfg(feature = "debug-avoidance")]
   /// The avoidance data from the most recent update iteration. Only populated
   /// if [`Self::keep_avoidance_data`] is true.
@@ -186,6 +232,30 @@ impl PermittedAnimationLinks {
   }
 }
 
+/// Defines the list of type indices that an agent is allowed to walk on.
+#[derive(Clone, Default, Debug)]
+pub enum PermittedTypeIndices {
+  /// Every type index is permitted.
+  #[default]
+  All,
+  /// Only type indices in this set are permitted.
+  Include(Arc<HashSet<usize>>),
+  /// Every type index except those in this set is permitted.
+  Exclude(Arc<HashSet<usize>>),
+}
+
+impl PermittedTypeIndices {
+  /// Returns whether the type index is permitted.
+  #[inline]
+  pub(crate) fn is_permitted(&self, type_index: usize) -> bool {
+    match self {
+      Self::All => true,
+      Self::Include(type_indices) => type_indices.contains(&type_index),
+      Self::Exclude(type_indices) => !type_indices.contains(&type_index),
+    }
+  }
+}
+
 impl<CS: CoordinateSystem> Agent<CS> {
   /// Creates a new agent.
   pub fn create(
@@ -205,20 +275,34 @@ impl<CS: CoordinateSystem> Agent<CS> {
       target_reached_condition: TargetReachedCondition::Distance(None),
       animation_link_reached_distance: None,
       permitted_animation_links: PermittedAnimationLinks::All,
+      permitted_type_indices: PermittedTypeIndices::All,
+      allow_partial_paths: false,
       paused: false,
+      avoidance_layers: 1,
+      avoidance_mask: u32::MAX,
+      avoidance_priority: 1.0,
       #[cfg(feature = "debug-avoidance")]
       keep_avoidance_data: false,
       override_type_index_to_cost: HashMap::new(),
       current_path: None,
+      partial_path_target_node: None,
+      current_path_search: None,
       current_desired_move: CS::from_landmass(&Vec3::ZERO),
       state: AgentState::Idle,
       current_animation_link: None,
-      using_animation_link: false,
+      using_animation_link: None,
+      current_island: None,
+      platform_velocity: CS::from_landmass(&Vec3::ZERO),
       #[cfg(feature = "debug-avoidance")]
       avoidance_data: None,
     }
   }
 
+  /// Whether this agent avoids agents or characters in `avoidance_layers`.
+  pub fn avoids_layers(&self, avoidance_layers: u32) -> bool {
+    self.avoidance_mask & avoidance_layers != 0
+  }
+
   /// Sets the type index cost for this agent to `cost`. Returns false if the
   /// cost is <= 0.0. Otherwise returns true.
   pub fn override_type_index_cost(
@@ -258,6 +342,23 @@ impl<CS: CoordinateSystem> Agent<CS> {
     &self.current_desired_move
   }
 
+  /// Returns the island that the agent is standing on. This is None if the
+  /// agent is not on a nav mesh, is paused, or is using an animation link. This
+  /// will only be updated if `update` was called on the associated
+  /// [`crate::Archipelago`].
+  pub fn current_island(&self) -> Option<IslandId> {
+    self.current_island
+  }
+
+  /// Returns the velocity of the island that the agent is standing on (at the
+  /// agent's position), due to the island moving (e.g., an elevator or a
+  /// boat). The desired velocity is relative to the island, so add this to the
+  /// desired velocity to have the agent ride the island. This will only be
+  /// updated if `update` was called on the associated [`crate::Archipelago`].
+  pub fn get_platform_velocity(&self) -> &CS::Coordinate {
+    &self.platform_velocity
+  }
+
   /// Returns the state of the agent. This will only be updated if `update` was
   /// called on the associated [`crate::Archipelago`].
   pub fn state(&self) -> AgentState {
@@ -281,10 +382,10 @@ impl<CS: CoordinateSystem> Agent<CS> {
   pub fn start_animation_link(
     &mut self,
   ) -> Result<(), NotReachedAnimationLinkError> {
-    if self.current_animation_link.is_none() {
+    let Some(animation_link) = self.current_animation_link.as_ref() else {
       return Err(NotReachedAnimationLinkError);
-    }
-    self.using_animation_link = true;
+    };
+    self.using_animation_link = Some(animation_link.link_id);
     Ok(())
   }
 
@@ -294,10 +395,10 @@ impl<CS: CoordinateSystem> Agent<CS> {
   pub fn end_animation_link(
     &mut self,
   ) -> Result<(), NotUsingAnimationLinkError> {
-    if !self.using_animation_link {
+    if self.using_animation_link.is_none() {
       return Err(NotUsingAnimationLinkError);
     }
-    self.using_animation_link = false;
+    self.using_animation_link = None;
     Ok(())
   }
 
@@ -305,7 +406,7 @@ impl<CS: CoordinateSystem> Agent<CS> {
   /// Essentially this reports whether we have called
   /// [`Self::start_animation_link`] without ending the link yet.
   pub fn is_using_animation_link(&self) -> bool {
-    self.using_animation_link
+    self.using_animation_link.is_some()
   }
 
   /// Gets the distance at which to consider to reach animation links.
@@ -348,6 +449,7 @@ impl<CS: CoordinateSystem> Agent<CS> {
           break 'result false;
         };
         next_waypoint.0 == target_waypoint.0
+          && next_point == target_waypoint.1
           && sampled_point.distance_squared(next_point) < distance * distance
       }
       TargetReachedCondition::StraightPathDistance(distance) => 'result: {
@@ -362,7 +464,9 @@ impl<CS: CoordinateSystem> Agent<CS> {
 
         // If the next waypoint is the target point, then we've already
         // computed the straight line distance and it is below the limit.
-        if next_waypoint.0 == target_waypoint.0 {
+        if next_waypoint.0 == target_waypoint.0
+          && next_waypoint.1 == StraightPathStep::Waypoint(target_waypoint.1)
+        {
           break 'result true;
         }
 
@@ -376,7 +480,7 @@ impl<CS: CoordinateSystem> Agent<CS> {
         let mut straight_line_distance = sampled_point.distance(next_point);
         let mut current_waypoint = (next_waypoint.0, next_point);
 
-        while current_waypoint.0 != target_waypoint.0
+        while current_waypoint != target_waypoint
           && straight_line_distance < distance
         {
           let next_waypoint = path.find_next_point_in_straight_path(
@@ -385,6 +489,7 @@ impl<CS: CoordinateSystem> Agent<CS> {
             current_waypoint.1,
             target_waypoint.0,
             target_waypoint.1,
+            self.radius,
           );
 
           let StraightPathStep::Waypoint(next_point) = next_waypoint.1 else {
@@ -418,19 +523,34 @@ pub(crate) enum RepathResult {
   ClearPathBadAgent,
   /// Clear the path and don't repath, since the target is not on a valid node.
   ClearPathBadTarget,
+  /// Repair part of the existing path instead of recomputing all of it.
+  /// Stores the index in the path of the agent's node.
+  RepairPath(PathIndex, PathRepair),
   /// Recompute the path.
   NeedsRepath,
 }
 
+/// A repair to part of an agent's path.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PathRepair {
+  /// The target left the path, so extend the path from its last node to the
+  /// target.
+  ExtendToTarget,
+  /// Part of the path was invalidated, so replace the nodes between these
+  /// indices (inclusive) with a new path between those nodes.
+  Replace(PathIndex, PathIndex),
+}
+
 pub(crate) fn does_agent_need_repath<CS: CoordinateSystem>(
   agent: &Agent<CS>,
   agent_node: Option<NodeRef>,
   target_node: Option<NodeRef>,
   invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
   invalidated_islands: &HashSet<IslandId>,
+  invalidated_nodes: &HashSet<NodeRef>,
 ) -> RepathResult {
   if agent.current_target.is_none() {
-    if agent.current_path.is_some() {
+    if agent.current_path.is_some() || agent.current_path_search.is_some() {
       return RepathResult::ClearPathNoTarget;
     } else {
       return RepathResult::DoNothing;
@@ -451,29 +571,148 @@ pub(crate) fn does_agent_need_repath<CS: CoordinateSystem>(
     Some(current_path) => current_path,
   };
 
-  if !current_path.is_valid(invalidated_off_mesh_links, invalidated_islands) {
-    return RepathResult::NeedsRepath;
-  }
-
   let Some(agent_node_index_in_path) =
     current_path.find_index_of_node(agent_node)
   else {
     return RepathResult::NeedsRepath;
   };
 
+  if let Some(partial_path_target_node) = agent.partial_path_target_node {
+    // Partial paths never reach the target node, so just make sure the target
+    // is still in the same node. Partial paths are never repaired, since the
+    // target may have become reachable.
+    if !current_path.is_valid(
+      invalidated_off_mesh_links,
+      invalidated_islands,
+      invalidated_nodes,
+    ) || !agent.allow_partial_paths
+      || partial_path_target_node != target_node
+    {
+      return RepathResult::NeedsRepath;
+    }
+    return RepathResult::FollowPath(
+      agent_node_index_in_path,
+      current_path.last_index(),
+    );
+  }
+
+  // The agent has already passed any invalidated nodes before it, so only the
+  // rest of the path matters.
+  let invalidated_range = match current_path.find_invalidation(
+    agent_node_index_in_path,
+    invalidated_off_mesh_links,
+    invalidated_islands,
+    invalidated_nodes,
+  ) {
+    PathInvalidation::Valid => None,
+    PathInvalidation::Between(start_index, end_index) => {
+      Some((start_index, end_index))
+    }
+    PathInvalidation::Unrepairable => return RepathResult::NeedsRepath,
+  };
+
   let Some(target_node_index_in_path) =
     current_path.find_index_of_node_rev(target_node)
   else {
-    return RepathResult::NeedsRepath;
+    if invalidated_range.is_some() {
+      return RepathResult::NeedsRepath;
+    }
+    return RepathResult::RepairPath(
+      agent_node_index_in_path,
+      PathRepair::ExtendToTarget,
+    );
   };
 
   if agent_node_index_in_path > target_node_index_in_path {
     return RepathResult::NeedsRepath;
   }
 
+  match invalidated_range {
+    // The invalidated part is after the target, so it doesn't matter.
+    Some((start_index, _)) if target_node_index_in_path <= start_index => {}
+    Some((start_index, end_index)) => {
+      if target_node_index_in_path < end_index {
+        return RepathResult::NeedsRepath;
+      }
+      return RepathResult::RepairPath(
+        agent_node_index_in_path,
+        PathRepair::Replace(start_index, end_index),
+      );
+    }
+    None => {}
+  }
+
   RepathResult::FollowPath(agent_node_index_in_path, target_node_index_in_path)
 }
 
+/// Repairs the current path of `agent` with `path_repair`, by searching for a
+/// path between the ends of the repaired part. `agent_node_index_in_path` is
+/// the index of the agent's node in the path, and `target_node` and
+/// `target_point` are where the agent's target is (in world space). The search
+/// explores at most `max_explored_nodes` nodes. Returns whether the path was
+/// repaired, and the number of explored nodes. If the path was not repaired,
+/// it is left unchanged. Otherwise, the nodes before the agent are also
+/// removed from the path, so the path doesn't keep growing.
+pub(crate) fn repair_path<CS: CoordinateSystem>(
+  agent: &mut Agent<CS>,
+  nav_data: &NavigationData<CS>,
+  agent_node_index_in_path: PathIndex,
+  path_repair: PathRepair,
+  target_node: NodeRef,
+  target_point: Vec3,
+  max_explored_nodes: u32,
+) -> (bool, u32) {
+  let path = agent.current_path.as_mut().expect("only paths are repaired");
+  let node_center = |node_ref: NodeRef| {
+    let island = nav_data.get_island(node_ref.island_id).unwrap();
+    island
+      .transform
+      .apply(island.nav_mesh.polygons[node_ref.polygon_index].center)
+  };
+  let (start_index, end_node, end_point) = match path_repair {
+    PathRepair::ExtendToTarget => {
+      (path.last_index(), target_node, target_point)
+    }
+    PathRepair::Replace(start_index, end_index) => {
+      let end_node = path.node_at(end_index);
+      (start_index, end_node, node_center(end_node))
+    }
+  };
+  let start_node = path.node_at(start_index);
+
+  let path_search = PathSearch::new(
+    nav_data,
+    start_node,
+    node_center(start_node),
+    end_node,
+    end_point,
+    &agent.override_type_index_to_cost,
+    agent.permitted_animation_links.clone(),
+    agent.permitted_type_indices.clone(),
+    agent.radius,
+    /* allow_partial_path= */ false,
+  );
+  let path_result = match path_search.resume(nav_data, max_explored_nodes) {
+    PathSearchStatus::Incomplete(path_search) => {
+      return (false, path_search.explored_nodes());
+    }
+    PathSearchStatus::Complete(path_result) => path_result,
+  };
+  let explored_nodes = path_result.stats.explored_nodes;
+  let Some(new_path) = path_result.path else {
+    return (false, explored_nodes);
+  };
+
+  match path_repair {
+    PathRepair::ExtendToTarget => path.extend(new_path),
+    PathRepair::Replace(start_index, end_index) => {
+      path.replace(start_index, end_index, new_path)
+    }
+  }
+  path.remove_before(agent_node_index_in_path);
+  (true, explored_nodes)
+}
+
 #[derive(Error, Debug, Clone, Copy)]
 #[error(
   "The agent hasn't reached an animation link, so it cannot start an animation link"
@@ -1,6 +1,6 @@
 use std::{collections::HashSet, f32::consts::PI, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{expect_that, expect_true, matchers::*};
 use slotmap::SlotMap;
 
@@ -8,7 +8,10 @@ use crate::{
   Agent, Archipelago, ArchipelagoOptions, CoordinateSystem, FromAgentRadius,
   Island, IslandId, NavigationMesh, NotReachedAnimationLinkError,
   ReachedAnimationLink, TargetReachedCondition, Transform,
-  agent::{NotUsingAnimationLinkError, RepathResult, does_agent_need_repath},
+  agent::{
+    NotUsingAnimationLinkError, PathRepair, RepathResult,
+    does_agent_need_repath,
+  },
   coords::{XY, XYZ},
   link::{AnimationLink, AnimationLinkId},
   nav_data::{KindedOffMeshLink, NodeRef, OffMeshLinkId},
@@ -77,8 +80,11 @@ fn has_reached_target_at_end_node() {
   }
   .validate()
   .expect("nav mesh is valid");
-  let transform =
-    Transform { translation: Vec3::new(2.0, 3.0, 4.0), rotation: PI * 0.85 };
+  let transform = Transform {
+    translation: Vec3::new(2.0, 3.0, 4.0),
+    rotation: Quat::from_rotation_z(PI * 0.85),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -171,8 +177,11 @@ fn long_detour_reaches_target_in_different_ways() {
   .validate()
   .expect("nav mesh is valid");
 
-  let transform =
-    Transform { translation: Vec3::new(2.0, 4.0, 3.0), rotation: PI * -0.85 };
+  let transform = Transform {
+    translation: Vec3::new(2.0, 4.0, 3.0),
+    rotation: Quat::from_rotation_z(PI * -0.85),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -432,6 +441,8 @@ fn using_animation_link_does_not_reach_target() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   archipelago.update(1.0);
@@ -606,7 +617,8 @@ fn nothing_or_clear_path_for_no_target() {
       None,
       None,
       &HashSet::new(),
-      &HashSet::new()
+      &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::DoNothing
   );
@@ -624,7 +636,8 @@ fn nothing_or_clear_path_for_no_target() {
       None,
       None,
       &HashSet::new(),
-      &HashSet::new()
+      &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::ClearPathNoTarget,
   );
@@ -652,6 +665,7 @@ fn clears_path_for_missing_nodes() {
       Some(NodeRef { island_id, polygon_index: 0 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::ClearPathBadAgent,
   );
@@ -663,6 +677,7 @@ fn clears_path_for_missing_nodes() {
       None,
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::ClearPathBadTarget,
   );
@@ -692,6 +707,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 3 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath,
   );
@@ -715,6 +731,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 1 }),
       &HashSet::new(),
       &HashSet::from([island_id]),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath
   );
@@ -727,11 +744,12 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 1 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath,
   );
 
-  // Missing target node.
+  // Missing target node, so the path is extended to the target.
   assert_eq!(
     does_agent_need_repath(
       &agent,
@@ -739,8 +757,12 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 6 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
+    ),
+    RepathResult::RepairPath(
+      PathIndex::from_corridor_index(0, 1),
+      PathRepair::ExtendToTarget
     ),
-    RepathResult::NeedsRepath,
   );
 
   // Agent and target are in the wrong order.
@@ -751,10 +773,60 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 3 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
+    ),
+    RepathResult::NeedsRepath,
+  );
+
+  // Invalidated node between the agent and the target, so only that part of
+  // the path is replaced.
+  assert_eq!(
+    does_agent_need_repath(
+      &agent,
+      Some(NodeRef { island_id, polygon_index: 3 }),
+      Some(NodeRef { island_id, polygon_index: 0 }),
+      &HashSet::new(),
+      &HashSet::new(),
+      &HashSet::from([NodeRef { island_id, polygon_index: 1 }]),
+    ),
+    RepathResult::RepairPath(
+      PathIndex::from_corridor_index(0, 1),
+      PathRepair::Replace(
+        PathIndex::from_corridor_index(0, 2),
+        PathIndex::from_corridor_index(0, 4)
+      )
+    ),
+  );
+
+  // Invalidated target node.
+  assert_eq!(
+    does_agent_need_repath(
+      &agent,
+      Some(NodeRef { island_id, polygon_index: 3 }),
+      Some(NodeRef { island_id, polygon_index: 0 }),
+      &HashSet::new(),
+      &HashSet::new(),
+      &HashSet::from([NodeRef { island_id, polygon_index: 0 }]),
     ),
     RepathResult::NeedsRepath,
   );
 
+  // Invalidated node before the agent, which the agent has already passed.
+  assert_eq!(
+    does_agent_need_repath(
+      &agent,
+      Some(NodeRef { island_id, polygon_index: 3 }),
+      Some(NodeRef { island_id, polygon_index: 1 }),
+      &HashSet::new(),
+      &HashSet::new(),
+      &HashSet::from([NodeRef { island_id, polygon_index: 2 }]),
+    ),
+    RepathResult::FollowPath(
+      PathIndex::from_corridor_index(0, 1),
+      PathIndex::from_corridor_index(0, 3)
+    ),
+  );
+
   // Following is now fine.
   assert_eq!(
     does_agent_need_repath(
@@ -764,6 +836,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       &HashSet::new(),
       // This island is not involved in the path, so the path is still valid.
       &HashSet::from([missing_island_id]),
+      &HashSet::new(),
     ),
     RepathResult::FollowPath(
       PathIndex::from_corridor_index(0, 1),
@@ -31,15 +31,15 @@ pub(crate) trait AStarProblem {
 }
 
 /// A node which represents a single path (by following the previous nodes).
-struct Node<ProblemType: AStarProblem> {
+struct Node<ActionType, StateType> {
   /// The cost of all actions taken by this path.
   cost: f32,
   /// The state that the path results in.
-  state: ProblemType::StateType,
+  state: StateType,
   /// The previous node in the path. This is stored as the index of the node
   /// and the action used to get to this state from the previous state. Only
   /// `None` for the initial state.
-  previous_node: Option<(usize, ProblemType::ActionType)>,
+  previous_node: Option<(usize, ActionType)>,
 }
 
 /// A reference to a node.
@@ -82,13 +82,12 @@ impl Ord for NodeRef {
   }
 }
 
-/// Determines the list of actions taken by `node_ref`.
-fn recover_path_from_node<ProblemType: AStarProblem>(
-  node_ref: &NodeRef,
-  nodes: &[Node<ProblemType>],
-) -> Vec<ProblemType::ActionType> {
+/// Determines the list of actions taken to reach the node at `node_index`.
+fn recover_path_from_node<ActionType: Clone, StateType>(
+  mut node_index: usize,
+  nodes: &[Node<ActionType, StateType>],
+) -> Vec<ActionType> {
   let mut path = Vec::new();
-  let mut node_index = node_ref.index;
   loop {
     let node = &nodes[node_index];
     match &node.previous_node {
@@ -119,90 +118,241 @@ pub(crate) struct PathResult<ActionType> {
   pub(crate) stats: PathStats,
   /// The path if one was found.
   pub(crate) path: Option<Vec<ActionType>>,
+  /// The path to the explored state with the lowest heuristic (i.e., the state
+  /// that is "closest" to a goal state). Only set if no path was found.
+  pub(crate) closest_path: Option<Vec<ActionType>>,
 }
 
-/// Finds a path in `problem` to get from the initial state to a goal state.
-/// Returns an `Err` if no path could be found.
-pub(crate) fn find_path<ProblemType: AStarProblem>(
-  problem: &ProblemType,
-) -> PathResult<ProblemType::ActionType> {
-  let mut stats = PathStats { explored_nodes: 0 };
+/// An in-progress A* search. This allows a search to be split across several
+/// calls to [`Search::resume`], where each call only explores a limited number
+/// of nodes. The search does not borrow the problem, so the problem can be
+/// recreated between calls. However, the problem must be equivalent on each
+/// call.
+pub(crate) struct Search<ActionType, StateType> {
+  /// Stats about the search so far.
+  stats: PathStats,
+  /// The best estimate found for each state so far.
+  best_estimates: HashMap<StateType, f32>,
+  /// All nodes that have been added to the search.
+  all_nodes: Vec<Node<ActionType, StateType>>,
+  /// The nodes that have yet to be explored.
+  open_nodes: BinaryHeap<Reverse<NodeRef>>,
+  /// The index of the explored node with the lowest heuristic, and its
+  /// heuristic.
+  closest_node: Option<(usize, f32)>,
+}
 
-  let mut best_estimates = HashMap::new();
+/// The result of exploring a problem. See [`Search::explore`].
+#[derive(Debug)]
+pub(crate) struct ExploreResult<StateType> {
+  /// The cost of the cheapest path to each explored state.
+  pub(crate) state_costs: HashMap<StateType, f32>,
+}
 
-  let mut all_nodes = Vec::<Node<ProblemType>>::new();
-  let mut open_nodes = BinaryHeap::new();
+/// The status of a [`Search`] after resuming it.
+pub(crate) enum SearchStatus<ActionType> {
+  /// The search ran out of explored nodes before completing.
+  Incomplete,
+  /// The search finished.
+  Complete(PathResult<ActionType>),
+}
 
-  fn try_add_node<ProblemType: AStarProblem>(
+impl<ActionType: Clone, StateType: Hash + Eq + Clone>
+  Search<ActionType, StateType>
+{
+  /// Starts a new search for `problem`. No nodes are explored until
+  /// [`Self::resume`] is called.
+  pub(crate) fn new<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
     problem: &ProblemType,
-    node: Node<ProblemType>,
-    all_nodes: &mut Vec<Node<ProblemType>>,
-    open_nodes: &mut BinaryHeap<Reverse<NodeRef>>,
-    best_estimates: &mut HashMap<ProblemType::StateType, f32>,
+  ) -> Self {
+    let mut search = Self {
+      stats: PathStats { explored_nodes: 0 },
+      best_estimates: HashMap::new(),
+      all_nodes: Vec::new(),
+      open_nodes: BinaryHeap::new(),
+      closest_node: None,
+    };
+    let initial_node =
+      Node { cost: 0.0, state: problem.initial_state(), previous_node: None };
+    search.try_add_node(problem, initial_node);
+    search
+  }
+
+  /// Returns the stats of the search so far.
+  pub(crate) fn stats(&self) -> &PathStats {
+    &self.stats
+  }
+
+  /// Returns every state that has been added to the search so far.
+  pub(crate) fn states(&self) -> impl Iterator<Item = &StateType> {
+    self.best_estimates.keys()
+  }
+
+  /// Adds `node` to the search if it is better than any other node with the
+  /// same state.
+  fn try_add_node<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
+    &mut self,
+    problem: &ProblemType,
+    node: Node<ActionType, StateType>,
   ) {
     let estimate = node.cost + problem.heuristic(&node.state);
     let best_estimate =
-      best_estimates.entry(node.state.clone()).or_insert(f32::INFINITY);
+      self.best_estimates.entry(node.state.clone()).or_insert(f32::INFINITY);
     if *best_estimate <= estimate {
       return;
     }
     *best_estimate = estimate;
-    open_nodes.push(Reverse(NodeRef {
+    self.open_nodes.push(Reverse(NodeRef {
       cost: node.cost,
       estimate,
-      index: all_nodes.len(),
+      index: self.all_nodes.len(),
     }));
-    all_nodes.push(node);
+    self.all_nodes.push(node);
   }
 
-  let initial_node =
-    Node { cost: 0.0, state: problem.initial_state(), previous_node: None };
-  try_add_node(
-    problem,
-    initial_node,
-    &mut all_nodes,
-    &mut open_nodes,
-    &mut best_estimates,
-  );
-
-  while let Some(Reverse(current_node_ref)) = open_nodes.pop() {
-    let current_node = &all_nodes[current_node_ref.index];
-    // If this node is not the best path to the state, skip it. This state must
-    // have already been explored ahead of this node.
-    if *best_estimates.get(&current_node.state).unwrap()
-      < current_node_ref.estimate
-    {
-      continue;
-    }
-    stats.explored_nodes += 1;
-
-    if problem.is_goal_state(&current_node.state) {
-      return PathResult {
-        stats,
-        path: Some(recover_path_from_node(&current_node_ref, &all_nodes)),
+  /// Continues the search for `problem`, exploring at most
+  /// `max_explored_nodes` nodes.
+  pub(crate) fn resume<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
+    &mut self,
+    problem: &ProblemType,
+    max_explored_nodes: u32,
+  ) -> SearchStatus<ActionType> {
+    let mut explored_nodes = 0;
+    loop {
+      if explored_nodes >= max_explored_nodes {
+        return SearchStatus::Incomplete;
+      }
+      let Some(Reverse(current_node_ref)) = self.open_nodes.pop() else {
+        break;
       };
+
+      let current_node = &self.all_nodes[current_node_ref.index];
+      // If this node is not the best path to the state, skip it. This state
+      // must have already been explored ahead of this node.
+      if *self.best_estimates.get(&current_node.state).unwrap()
+        < current_node_ref.estimate
+      {
+        continue;
+      }
+      explored_nodes += 1;
+      self.stats.explored_nodes += 1;
+
+      let heuristic = problem.heuristic(&current_node.state);
+      if self
+        .closest_node
+        .is_none_or(|(_, closest_heuristic)| heuristic < closest_heuristic)
+      {
+        self.closest_node = Some((current_node_ref.index, heuristic));
+      }
+
+      if problem.is_goal_state(&current_node.state) {
+        return SearchStatus::Complete(PathResult {
+          stats: PathStats { explored_nodes: self.stats.explored_nodes },
+          path: Some(recover_path_from_node(
+            current_node_ref.index,
+            &self.all_nodes,
+          )),
+          closest_path: None,
+        });
+      }
+
+      let current_cost = current_node.cost;
+      for (action_cost, action, state) in
+        problem.successors(&current_node.state)
+      {
+        let new_node = Node {
+          cost: current_cost + action_cost,
+          state,
+          previous_node: Some((current_node_ref.index, action)),
+        };
+
+        self.try_add_node(problem, new_node);
+      }
     }
 
-    let current_cost = current_node.cost;
-    for (action_cost, action, state) in problem.successors(&current_node.state)
-    {
-      let new_node = Node {
-        cost: current_cost + action_cost,
-        state,
-        previous_node: Some((current_node_ref.index, action)),
-      };
+    SearchStatus::Complete(PathResult {
+      stats: PathStats { explored_nodes: self.stats.explored_nodes },
+      path: None,
+      closest_path: self.closest_node.map(|(closest_node_index, _)| {
+        recover_path_from_node(closest_node_index, &self.all_nodes)
+      }),
+    })
+  }
+
+  /// Explores every state of `problem` whose estimate is at most
+  /// `max_estimate`, and records the cost of the cheapest path to each of
+  /// them. Unlike [`Self::resume`], reaching a goal state does not end the
+  /// search. Instead, goal states are not expanded, and the search ends once
+  /// `goal_states` goal states have been explored. The heuristic of `problem`
+  /// must be consistent for the costs to be the cheapest.
+  pub(crate) fn explore<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
+    mut self,
+    problem: &ProblemType,
+    max_estimate: f32,
+    goal_states: usize,
+  ) -> ExploreResult<StateType> {
+    let mut state_costs = HashMap::new();
+    let mut explored_goal_states = 0;
+    while let Some(Reverse(current_node_ref)) = self.open_nodes.pop() {
+      if current_node_ref.estimate > max_estimate {
+        break;
+      }
+
+      let current_node = &self.all_nodes[current_node_ref.index];
+      // See `Self::resume` for why this node can be skipped.
+      if *self.best_estimates.get(&current_node.state).unwrap()
+        < current_node_ref.estimate
+      {
+        continue;
+      }
+      state_costs.insert(current_node.state.clone(), current_node.cost);
+
+      if problem.is_goal_state(&current_node.state) {
+        explored_goal_states += 1;
+        if explored_goal_states == goal_states {
+          break;
+        }
+        continue;
+      }
 
-      try_add_node(
-        problem,
-        new_node,
-        &mut all_nodes,
-        &mut open_nodes,
-        &mut best_estimates,
-      );
+      let current_cost = current_node.cost;
+      for (action_cost, action, state) in
+        problem.successors(&current_node.state)
+      {
+        let new_node = Node {
+          cost: current_cost + action_cost,
+          state,
+          previous_node: Some((current_node_ref.index, action)),
+        };
+
+        self.try_add_node(problem, new_node);
+      }
     }
+
+    ExploreResult { state_costs }
   }
+}
 
-  PathResult { stats, path: None }
+/// Finds a path in `problem` to get from the initial state to a goal state.
+/// Returns an `Err` if no path could be found.
+#[cfg(test)]
+pub(crate) fn find_path<ProblemType: AStarProblem>(
+  problem: &ProblemType,
+) -> PathResult<ProblemType::ActionType> {
+  match Search::new(problem).resume(problem, u32::MAX) {
+    SearchStatus::Incomplete => {
+      unreachable!("an unlimited search always completes")
+    }
+    SearchStatus::Complete(result) => result,
+  }
 }
 
 #[cfg(test)]
@@ -1,4 +1,6 @@
-use super::{AStarProblem, find_path};
+use std::collections::HashMap;
+
+use super::{AStarProblem, Search, SearchStatus, find_path};
 
 struct AdjacencyListProblemState {
   adjacency: Vec<(f32, i32, usize)>,
@@ -216,3 +218,141 @@ fn dead_end_wrong_heuristic() {
   // was found.
   assert_eq!(path.stats.explored_nodes, 4);
 }
+
+#[test]
+fn resumed_search_matches_full_search() {
+  const WIDTH: usize = 5;
+
+  let mut problem = AdjacencyListProblem {
+    start: 0,
+    end: WIDTH * WIDTH - 1,
+    states: Vec::with_capacity(WIDTH * WIDTH),
+  };
+
+  for y in 0..WIDTH {
+    for x in 0..WIDTH {
+      let mut state = AdjacencyListProblemState {
+        heuristic: 0.0,
+        adjacency: Vec::with_capacity(2),
+      };
+
+      let state_index = problem.states.len();
+      if x < WIDTH - 1 {
+        state.adjacency.push((1.0, 2, state_index + 1));
+      }
+      if y < WIDTH - 1 {
+        state.adjacency.push((1.0, 4, state_index + WIDTH));
+      }
+
+      problem.states.push(state);
+    }
+  }
+
+  let expected_result = find_path(&problem);
+
+  let mut search = Search::new(&problem);
+  let mut resumes = 0;
+  let result = loop {
+    resumes += 1;
+    match search.resume(&problem, 3) {
+      SearchStatus::Incomplete => {
+        assert_eq!(search.stats().explored_nodes, resumes * 3);
+      }
+      SearchStatus::Complete(result) => break result,
+    }
+  };
+
+  assert_eq!(result.path, expected_result.path);
+  assert_eq!(result.stats.explored_nodes, expected_result.stats.explored_nodes);
+  // Each resume explores at most 3 nodes.
+  assert_eq!(resumes, expected_result.stats.explored_nodes.div_ceil(3));
+}
+
+#[test]
+fn no_path_finds_path_to_closest_state() {
+  let problem = AdjacencyListProblem {
+    start: 0,
+    // This state doesn't exist, so a path can't be found!
+    end: 3,
+    states: vec![
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 1, 1), (1.0, 2, 2)],
+        heuristic: 5.0,
+      },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 2.0 },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 3.0 },
+    ],
+  };
+
+  let result = find_path(&problem);
+  assert!(result.path.is_none());
+  assert_eq!(result.closest_path, Some(vec![1]));
+  assert_eq!(result.stats.explored_nodes, 3);
+}
+
+#[test]
+fn explore_finds_costs_within_max_estimate() {
+  let problem = AdjacencyListProblem {
+    start: 0,
+    end: 3,
+    states: vec![
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 1, 1), (5.0, 2, 2)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 3, 2)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(10.0, 4, 3)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 0.0 },
+    ],
+  };
+
+  let result = Search::new(&problem)
+    .explore(&problem, /* max_estimate= */ 5.0, /* goal_states= */ 1);
+  assert_eq!(result.state_costs, HashMap::from([(0, 0.0), (1, 1.0), (2, 2.0)]));
+}
+
+#[test]
+fn explore_stops_after_goal_states() {
+  struct MultiGoalProblem;
+
+  impl AStarProblem for MultiGoalProblem {
+    type ActionType = ();
+    type StateType = usize;
+
+    fn initial_state(&self) -> Self::StateType {
+      0
+    }
+
+    fn successors(
+      &self,
+      state: &Self::StateType,
+    ) -> Vec<(f32, Self::ActionType, Self::StateType)> {
+      match state {
+        0 => vec![(1.0, (), 1), (2.0, (), 2), (3.0, (), 3)],
+        _ => vec![(1.0, (), 0)],
+      }
+    }
+
+    fn heuristic(&self, _state: &Self::StateType) -> f32 {
+      0.0
+    }
+
+    fn is_goal_state(&self, state: &Self::StateType) -> bool {
+      *state != 0
+    }
+  }
+
+  let result = Search::new(&MultiGoalProblem).explore(
+    &MultiGoalProblem,
+    /* max_estimate= */ f32::INFINITY,
+    /* goal_states= */ 2,
+  );
+  // The search ends after exploring the two cheapest goal states.
+  assert_eq!(result.state_costs, HashMap::from([(0, 0.0), (1, 1.0), (2, 2.0)]));
+}
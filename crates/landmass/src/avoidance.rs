@@ -1,25 +1,354 @@
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
 use dodgy_2d::VisibilitySet;
-use glam::{Vec3, Vec3Swizzles};
+use geo::{ConvexHull, MultiPoint, Point};
+use glam::{Vec2, Vec3, Vec3Swizzles};
 use kdtree::{KdTree, distance::squared_euclidean};
 use slotmap::DenseSlotMap;
 
 use crate::{
-  Agent, AgentId, AgentState, ArchipelagoOptions, Character, CharacterId,
-  CoordinateSystem, Island, IslandId, NavigationData,
+  Agent, AgentId, AgentState, ArchipelagoOptions, AvoidanceObstacleId,
+  Character, CharacterId, CoordinateSystem, Island, IslandId, NavigationData,
+  geometry::project_point_to_line_segment,
   nav_data::{KindedOffMeshLink, ModifiedNode, NodeRef},
+  region_graph::ClusterId,
+  util::map_maybe_parallel,
 };
 
-/// Adjusts the velocity of `agents` to apply local avoidance. `delta_time` must
-/// be positive.
+/// A local avoidance algorithm. The archipelago calls the backend once per
+/// update to turn the desired velocities of its agents into the final
+/// velocities that avoid other agents, characters, and the nav mesh borders.
+pub trait AvoidanceBackend<CS: CoordinateSystem>: Send + Sync {
+  /// Computes the final velocities of the agents in `context`. Agents that are
+  /// not given a velocity in `velocities` keep their desired velocity.
+  fn apply_avoidance(
+    &mut self,
+    context: &AvoidanceContext<'_, CS>,
+    velocities: &mut AvoidanceVelocities<CS>,
+  );
+}
+
+/// The state of the archipelago that an [`AvoidanceBackend`] avoids with.
+pub struct AvoidanceContext<'a, CS: CoordinateSystem> {
+  /// The agents in the archipelago.
+  agents: &'a DenseSlotMap<AgentId, Agent<CS>>,
+  /// The sampled point and node of each agent on the nav mesh.
+  agent_id_to_agent_node: &'a HashMap<AgentId, (Vec3, NodeRef)>,
+  /// The characters in the archipelago.
+  characters: &'a DenseSlotMap<CharacterId, Character<CS>>,
+  /// The sampled point and node of each character on the nav mesh.
+  character_id_to_character_node: &'a HashMap<CharacterId, (Vec3, NodeRef)>,
+  /// The footprints of non-circular characters and avoidance obstacles.
+  footprints: &'a [Footprint],
+  /// The sampled point and walkable layer of each agent, character, and
+  /// footprint on the nav mesh.
+  neighbour_to_walkable_layer: HashMap<AvoidanceNeighbour, (Vec3, ClusterId)>,
+  /// The navigation data to find nav mesh borders in.
+  nav_data: &'a NavigationData<CS>,
+  /// The options of the archipelago.
+  archipelago_options: &'a ArchipelagoOptions<CS>,
+  /// The time since the last update. Always positive.
+  delta_time: f32,
+}
+
+/// An agent that needs avoidance.
+pub struct AvoidanceAgent<'a, CS: CoordinateSystem> {
+  /// The ID of the agent.
+  pub id: AgentId,
+  /// The agent itself. Its desired velocity is the velocity to adjust.
+  pub agent: &'a Agent<CS>,
+  /// The agent's position sampled onto the nav mesh.
+  pub position: CS::Coordinate,
+}
+
+/// A character that agents should avoid.
+pub struct AvoidanceCharacter<'a, CS: CoordinateSystem> {
+  /// The ID of the character.
+  pub id: CharacterId,
+  /// The character itself.
+  pub character: &'a Character<CS>,
+  /// The character's position sampled onto the nav mesh.
+  pub position: CS::Coordinate,
+}
+
+/// Something that agents may avoid.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AvoidanceNeighbour {
+  /// Another agent.
+  Agent(AgentId),
+  /// A character.
+  Character(CharacterId),
+  /// An avoidance obstacle.
+  Obstacle(AvoidanceObstacleId),
+}
+
+/// The footprint of a non-circular character or an avoidance obstacle that
+/// agents should avoid.
+pub struct AvoidanceFootprint<CS: CoordinateSystem> {
+  /// The character or avoidance obstacle that this is the footprint of.
+  pub neighbour: AvoidanceNeighbour,
+  /// The outline of the footprint. The vertices go counter-clockwise (in
+  /// landmass's standard coordinate system), and are all at the height of the
+  /// footprint's position sampled onto the nav mesh.
+  pub vertices: Vec<CS::Coordinate>,
+  /// The current velocity of the footprint.
+  pub velocity: CS::Coordinate,
+  /// The avoidance layers that the footprint is in.
+  pub avoidance_layers: u32,
+}
+
+/// The footprint of a non-circular character or an avoidance obstacle, in
+/// landmass's standard coordinate system.
+pub(crate) struct Footprint {
+  /// The character or avoidance obstacle that this is the footprint of.
+  neighbour: AvoidanceNeighbour,
+  /// The outline of the footprint, going counter-clockwise.
+  outline: Vec<Vec2>,
+  /// The footprint's position sampled onto the nav mesh.
+  point: Vec3,
+  /// The node that [`Self::point`] is on.
+  node: NodeRef,
+  /// The current velocity of the footprint.
+  velocity: Vec3,
+  /// The avoidance layers that the footprint is in.
+  avoidance_layers: u32,
+}
+
+impl Footprint {
+  /// Creates a footprint from the (convex) `outline` of a shape in world space.
+  /// `outline` may go either clockwise or counter-clockwise.
+  pub(crate) fn new(
+    neighbour: AvoidanceNeighbour,
+    mut outline: Vec<Vec2>,
+    (point, node): (Vec3, NodeRef),
+    velocity: Vec3,
+    avoidance_layers: u32,
+  ) -> Self {
+    let double_area = outline
+      .iter()
+      .zip(outline.iter().cycle().skip(1))
+      .map(|(a, b)| a.perp_dot(*b))
+      .sum::<f32>();
+    if double_area < 0.0 {
+      outline.reverse();
+    }
+    Self { neighbour, outline, point, node, velocity, avoidance_layers }
+  }
+
+  /// Computes the region covered by the footprint as it moves for
+  /// `time_horizon`, as a counter-clockwise convex outline. Returns the
+  /// outline and the furthest horizontal distance from [`Self::point`] to the
+  /// outline.
+  fn swept_outline(&self, time_horizon: f32) -> (Vec<Vec2>, f32) {
+    let offset = self.velocity.xy() * time_horizon;
+    let hull = MultiPoint::new(
+      self
+        .outline
+        .iter()
+        .flat_map(|&vertex| [vertex, vertex + offset])
+        .map(|vertex| Point::new(vertex.x, vertex.y))
+        .collect(),
+    )
+    .convex_hull();
+    let mut outline = hull
+      .exterior()
+      .points()
+      .map(|point| Vec2::new(point.x(), point.y()))
+      .collect::<Vec<_>>();
+    // The exterior of the hull is closed, so the first vertex is repeated.
+    outline.pop();
+    let reach = outline
+      .iter()
+      .map(|vertex| vertex.distance(self.point.xy()))
+      .fold(0.0, f32::max);
+    (outline, reach)
+  }
+
+  /// Finds the closest point on the boundary of the footprint to `point`, and
+  /// whether `point` is inside the footprint.
+  fn closest_boundary_point(&self, point: Vec2) -> (Vec2, bool) {
+    let mut closest = (Vec2::ZERO, f32::INFINITY);
+    let mut inside = true;
+    for (&start, &end) in
+      self.outline.iter().zip(self.outline.iter().cycle().skip(1))
+    {
+      inside &= (end - start).perp_dot(point - start) >= 0.0;
+      let (projected, _) = project_point_to_line_segment(
+        point.extend(0.0),
+        (start.extend(0.0), end.extend(0.0)),
+      );
+      let distance = projected.xy().distance_squared(point);
+      if distance < closest.1 {
+        closest = (projected.xy(), distance);
+      }
+    }
+    (closest.0, inside)
+  }
+}
+
+/// A chain of nav mesh border edges near an agent. The walkable side of each
+/// edge is on its left (in landmass's standard coordinate system), and all
+/// vertices are at the height of the agent.
+pub enum BorderObstacle<CS: CoordinateSystem> {
+  /// The border forms a loop. The last vertex connects back to the first.
+  Closed { vertices: Vec<CS::Coordinate> },
+  /// The border is only partially visible to the agent, so the chain ends.
+  Open { vertices: Vec<CS::Coordinate> },
+}
+
+/// The final velocities computed by an [`AvoidanceBackend`].
+pub struct AvoidanceVelocities<CS: CoordinateSystem> {
+  /// The final velocity of each agent.
+  velocities: HashMap<AgentId, CS::Coordinate>,
+  /// The debug data of each agent, for backends that use `dodgy_2d`.
+  #[cfg(feature = "debug-avoidance")]
+  debug_data: HashMap<AgentId, dodgy_2d::debug::DebugData>,
+}
+
+impl<'a, CS: CoordinateSystem> AvoidanceContext<'a, CS> {
+  /// The time since the last update. This is always positive, even if the
+  /// archipelago was updated with a delta time of zero.
+  pub fn delta_time(&self) -> f32 {
+    self.delta_time
+  }
+
+  /// The options of the archipelago.
+  pub fn archipelago_options(&self) -> &'a ArchipelagoOptions<CS> {
+    self.archipelago_options
+  }
+
+  /// The agents that need avoidance, which are the agents that are on the nav
+  /// mesh and not paused.
+  pub fn agents(&self) -> impl Iterator<Item = AvoidanceAgent<'a, CS>> + '_ {
+    self.agents.iter().filter_map(|(id, agent)| {
+      let (point, _) = self.agent_id_to_agent_node.get(&id)?;
+      Some(AvoidanceAgent { id, agent, position: CS::from_landmass(point) })
+    })
+  }
+
+  /// The circular characters that agents should avoid, which are the
+  /// characters that are on the nav mesh and have no footprint. Characters
+  /// with a footprint are in [`Self::footprints`] instead.
+  pub fn characters(
+    &self,
+  ) -> impl Iterator<Item = AvoidanceCharacter<'a, CS>> + '_ {
+    self.characters.iter().filter_map(|(id, character)| {
+      if character.footprint.is_some() {
+        return None;
+      }
+      let (point, _) = self.character_id_to_character_node.get(&id)?;
+      Some(AvoidanceCharacter {
+        id,
+        character,
+        position: CS::from_landmass(point),
+      })
+    })
+  }
+
+  /// The footprints that agents should avoid, which are the footprints of the
+  /// characters with a footprint and the avoidance obstacles that are on the
+  /// nav mesh.
+  pub fn footprints(
+    &self,
+  ) -> impl Iterator<Item = AvoidanceFootprint<CS>> + '_ {
+    self.footprints.iter().map(|footprint| AvoidanceFootprint {
+      neighbour: footprint.neighbour,
+      vertices: footprint
+        .outline
+        .iter()
+        .map(|vertex| CS::from_landmass(&vertex.extend(footprint.point.z)))
+        .collect(),
+      velocity: CS::from_landmass(&footprint.velocity),
+      avoidance_layers: footprint.avoidance_layers,
+    })
+  }
+
+  /// Whether `agent_id` and `neighbour` share a walkable layer, meaning they
+  /// are within [`ArchipelagoOptions::avoidance_vertical_tolerance`] of each
+  /// other vertically, and they are on nodes connected by walkable ground
+  /// (the nav mesh or boundary links between islands, but not animation
+  /// links). Agents should only avoid neighbours that share a walkable layer.
+  /// Always true if the tolerance is [`None`] (and both are on the nav mesh).
+  pub fn shares_walkable_layer(
+    &self,
+    agent_id: AgentId,
+    neighbour: AvoidanceNeighbour,
+  ) -> bool {
+    let Some(&(agent_point, agent_walkable_layer)) = self
+      .neighbour_to_walkable_layer
+      .get(&AvoidanceNeighbour::Agent(agent_id))
+    else {
+      return false;
+    };
+    let Some(&(neighbour_point, neighbour_walkable_layer)) =
+      self.neighbour_to_walkable_layer.get(&neighbour)
+    else {
+      return false;
+    };
+    let Some(vertical_tolerance) =
+      self.archipelago_options.avoidance_vertical_tolerance
+    else {
+      return true;
+    };
+    (agent_point.z - neighbour_point.z).abs() <= vertical_tolerance
+      && agent_walkable_layer == neighbour_walkable_layer
+  }
+
+  /// Finds the nav mesh borders that are visible from `agent_id` within
+  /// `distance`. Returns an empty list if the agent does not need avoidance.
+  pub fn nav_mesh_borders(
+    &self,
+    agent_id: AgentId,
+    distance: f32,
+  ) -> Vec<BorderObstacle<CS>> {
+    let Some(&agent_node) = self.agent_id_to_agent_node.get(&agent_id) else {
+      return vec![];
+    };
+    let height = agent_node.0.z;
+    let to_coordinates = |vertices: Vec<dodgy_2d::Vec2>| {
+      vertices
+        .into_iter()
+        .map(|vertex| CS::from_landmass(&Vec3::new(vertex.x, vertex.y, height)))
+        .collect()
+    };
+    nav_mesh_borders_to_dodgy_obstacles(agent_node, self.nav_data, distance)
+      .into_iter()
+      .map(|obstacle| match obstacle {
+        dodgy_2d::Obstacle::Closed { vertices } => {
+          BorderObstacle::Closed { vertices: to_coordinates(vertices) }
+        }
+        dodgy_2d::Obstacle::Open { vertices } => {
+          BorderObstacle::Open { vertices: to_coordinates(vertices) }
+        }
+      })
+      .collect()
+  }
+}
+
+impl<CS: CoordinateSystem> AvoidanceVelocities<CS> {
+  /// Sets the final velocity of `agent_id` to `velocity`.
+  pub fn set(&mut self, agent_id: AgentId, velocity: CS::Coordinate) {
+    self.velocities.insert(agent_id, velocity);
+  }
+
+  /// Gets the final velocity of `agent_id`, if it has been set.
+  pub fn get(&self, agent_id: AgentId) -> Option<&CS::Coordinate> {
+    self.velocities.get(&agent_id)
+  }
+}
+
+/// Adjusts the velocity of `agents` to apply local avoidance using
+/// `avoidance_backend`.
+#[expect(clippy::too_many_arguments)]
 pub(crate) fn apply_avoidance_to_agents<CS: CoordinateSystem>(
+  avoidance_backend: &mut dyn AvoidanceBackend<CS>,
   agents: &mut DenseSlotMap<AgentId, Agent<CS>>,
   agent_id_to_agent_node: &HashMap<AgentId, (Vec3, NodeRef)>,
   characters: &DenseSlotMap<CharacterId, Character<CS>>,
-  character_id_to_nav_mesh_point: &HashMap<CharacterId, Vec3>,
+  character_id_to_character_node: &HashMap<CharacterId, (Vec3, NodeRef)>,
+  footprints: &[Footprint],
   nav_data: &NavigationData<CS>,
-  agent_options: &ArchipelagoOptions<CS>,
+  archipelago_options: &ArchipelagoOptions<CS>,
   mut delta_time: f32,
 ) {
   if delta_time == 0.0 {
@@ -28,153 +357,463 @@ pub(crate) fn apply_avoidance_to_agents<CS: CoordinateSystem>(
     delta_time = 1.0;
   }
 
-  let mut agent_id_to_dodgy_agent = HashMap::new();
-  let mut agent_kdtree = KdTree::new(/* dimensions= */ 3);
-  let mut agent_max_radius = 0.0f32;
+  // Find the walkable layer of everything once, so checking whether an agent
+  // and a neighbour share a walkable layer is just a comparison.
+  let walkable_layer = |&(point, node): &(Vec3, NodeRef)| {
+    (point, nav_data.node_to_walkable_layer(node))
+  };
+  let neighbour_to_walkable_layer = agent_id_to_agent_node
+    .iter()
+    .map(|(&agent_id, agent_node)| {
+      (AvoidanceNeighbour::Agent(agent_id), walkable_layer(agent_node))
+    })
+    .chain(character_id_to_character_node.iter().map(
+      |(&character_id, character_node)| {
+        (
+          AvoidanceNeighbour::Character(character_id),
+          walkable_layer(character_node),
+        )
+      },
+    ))
+    .chain(footprints.iter().map(|footprint| {
+      (footprint.neighbour, walkable_layer(&(footprint.point, footprint.node)))
+    }))
+    .collect();
 
-  for (agent_id, agent) in agents.iter() {
-    let agent_point = match agent_id_to_agent_node.get(&agent_id) {
-      None => continue,
-      Some(agent_point_and_node) => agent_point_and_node.0,
-    };
+  let mut velocities = AvoidanceVelocities {
+    velocities: HashMap::new(),
+    #[cfg(feature = "debug-avoidance")]
+    debug_data: HashMap::new(),
+  };
+  avoidance_backend.apply_avoidance(
+    &AvoidanceContext {
+      agents,
+      agent_id_to_agent_node,
+      characters,
+      character_id_to_character_node,
+      footprints,
+      neighbour_to_walkable_layer,
+      nav_data,
+      archipelago_options,
+      delta_time,
+    },
+    &mut velocities,
+  );
 
-    agent_id_to_dodgy_agent.insert(
-      agent_id,
-      dodgy_2d::Agent {
-        position: to_dodgy_vec2(agent_point.xy()),
-        velocity: to_dodgy_vec2(CS::to_landmass(&agent.velocity).xy()),
-        radius: agent.radius,
-        avoidance_responsibility: if agent.state == AgentState::ReachedTarget {
-          agent_options.reached_destination_avoidance_responsibility
-        } else {
-          1.0
-        },
-      },
-    );
-    agent_kdtree
-      .add([agent_point.x, agent_point.y, agent_point.z], agent_id)
-      .expect("Agent point is finite");
-    agent_max_radius = agent_max_radius.max(agent.radius);
+  for (agent_id, agent) in agents.iter_mut() {
+    if let Some(velocity) = velocities.velocities.remove(&agent_id) {
+      agent.current_desired_move = velocity;
+    }
+    #[cfg(feature = "debug-avoidance")]
+    {
+      agent.avoidance_data = velocities
+        .debug_data
+        .remove(&agent_id)
+        .filter(|_| agent.keep_avoidance_data);
+    }
   }
+}
 
-  let mut character_kdtree = KdTree::new(/* dimensions= */ 3);
-  for (character_id, character) in characters.iter() {
-    let Some(character_point) =
-      character_id_to_nav_mesh_point.get(&character_id)
-    else {
-      continue;
-    };
-    character_kdtree
-      .add(
-        [character_point.x, character_point.y, character_point.z],
+/// The smallest avoidance priority used by [`DodgyAvoidance`]. Smaller
+/// priorities (including zero, negative, and NaN priorities) are clamped to
+/// this, since they would give an infinite or inverted responsibility.
+const MIN_AVOIDANCE_PRIORITY: f32 = 1e-3;
+
+/// Avoidance using the optimal reciprocal collision avoidance (ORCA) algorithm
+/// from `dodgy_2d`. This is the default backend.
+#[derive(Default)]
+pub struct DodgyAvoidance;
+
+impl<CS: CoordinateSystem> AvoidanceBackend<CS> for DodgyAvoidance {
+  fn apply_avoidance(
+    &mut self,
+    context: &AvoidanceContext<'_, CS>,
+    velocities: &mut AvoidanceVelocities<CS>,
+  ) {
+    let agent_options = context.archipelago_options;
+
+    let mut agent_id_to_dodgy_agent = HashMap::new();
+    let mut agent_kdtree = KdTree::new(/* dimensions= */ 3);
+    let mut agent_max_radius = 0.0f32;
+
+    for (agent_id, agent) in context.agents.iter() {
+      let agent_point = match context.agent_id_to_agent_node.get(&agent_id) {
+        None => continue,
+        Some(agent_point_and_node) => agent_point_and_node.0,
+      };
+
+      let base_responsibility = if agent.state == AgentState::ReachedTarget {
+        agent_options.reached_destination_avoidance_responsibility
+      } else {
+        1.0
+      };
+      agent_id_to_dodgy_agent.insert(
+        agent_id,
         dodgy_2d::Agent {
-          position: to_dodgy_vec2(character_point.xy()),
-          velocity: to_dodgy_vec2(CS::to_landmass(&character.velocity).xy()),
-          radius: character.radius,
-          // Characters are not responsible for any avoidance since landmass has
-          // no control over them.
-          avoidance_responsibility: 0.0,
+          position: to_dodgy_vec2(agent_point.xy()),
+          // Desired velocities are relative to the island the agent stands
+          // on, so avoid in that frame too.
+          velocity: to_dodgy_vec2(
+            (CS::to_landmass(&agent.velocity)
+              - CS::to_landmass(&agent.platform_velocity))
+            .xy(),
+          ),
+          radius: agent.radius,
+          avoidance_responsibility: base_responsibility
+            / agent.avoidance_priority.max(MIN_AVOIDANCE_PRIORITY),
         },
-      )
-      .expect("Character point is finite");
-  }
+      );
+      agent_kdtree
+        .add([agent_point.x, agent_point.y, agent_point.z], agent_id)
+        .expect("Agent point is finite");
+      agent_max_radius = agent_max_radius.max(agent.radius);
+    }
 
-  let neighbourhood = agent_max_radius + agent_options.neighbourhood;
-  let neighbourhood_squared = neighbourhood * neighbourhood;
-  for (agent_id, agent) in agents.iter_mut() {
-    let agent_node = match agent_id_to_agent_node.get(&agent_id) {
-      None => continue,
-      Some(agent_node) => agent_node,
-    };
-    let agent_point = agent_node.0;
-    let nearby_agents = agent_kdtree
-      .within(
-        &[agent_point.x, agent_point.y, agent_point.z],
-        neighbourhood_squared,
-        &squared_euclidean,
-      )
-      .unwrap();
-    let nearby_characters = character_kdtree
-      .within(
-        &[agent_point.x, agent_point.y, agent_point.z],
-        neighbourhood_squared,
-        &squared_euclidean,
-      )
-      .unwrap();
-
-    let nearby_agents = nearby_agents
+    let mut character_kdtree = KdTree::new(/* dimensions= */ 3);
+    for (character_id, character) in context.characters.iter() {
+      if character.footprint.is_some() {
+        // Non-circular characters are avoided as footprints.
+        continue;
+      }
+      let Some(&(character_point, _)) =
+        context.character_id_to_character_node.get(&character_id)
+      else {
+        continue;
+      };
+      character_kdtree
+        .add(
+          [character_point.x, character_point.y, character_point.z],
+          (
+            dodgy_2d::Agent {
+              position: to_dodgy_vec2(character_point.xy()),
+              velocity: to_dodgy_vec2(
+                CS::to_landmass(&character.velocity).xy(),
+              ),
+              radius: character.radius,
+              // Characters are not responsible for any avoidance since
+              // landmass has no control over them.
+              avoidance_responsibility: 0.0,
+            },
+            character.avoidance_layers,
+            character_id,
+          ),
+        )
+        .expect("Character point is finite");
+    }
+
+    // Moving footprints are avoided by avoiding everywhere they will be within
+    // the obstacle time horizon.
+    let footprint_obstacles = context
+      .footprints
       .iter()
-      .filter_map(|&(distance_squared, neighbour_id)| {
-        if *neighbour_id == agent_id {
-          return None;
-        }
+      .map(|footprint| {
+        let (outline, reach) = footprint
+          .swept_outline(agent_options.obstacle_avoidance_time_horizon);
+        (
+          footprint,
+          dodgy_2d::Obstacle::Closed {
+            vertices: outline.into_iter().map(to_dodgy_vec2).collect(),
+          },
+          reach,
+        )
+      })
+      .collect::<Vec<_>>();
 
-        let dodgy_agent = agent_id_to_dodgy_agent.get(neighbour_id).unwrap();
+    let neighbourhood = agent_max_radius + agent_options.neighbourhood;
+    let neighbourhood_squared = neighbourhood * neighbourhood;
+    let mut agents = context.agents.iter().collect::<Vec<_>>();
+    let results = map_maybe_parallel(&mut agents, |(agent_id, agent)| {
+      let agent_node = context.agent_id_to_agent_node.get(agent_id)?;
+      let agent_point = agent_node.0;
+      let nearby_agents = agent_kdtree
+        .within(
+          &[agent_point.x, agent_point.y, agent_point.z],
+          neighbourhood_squared,
+          &squared_euclidean,
+        )
+        .unwrap();
+      let nearby_characters = character_kdtree
+        .within(
+          &[agent_point.x, agent_point.y, agent_point.z],
+          neighbourhood_squared,
+          &squared_euclidean,
+        )
+        .unwrap();
 
-        let neighbourhood = agent_options.neighbourhood + dodgy_agent.radius;
-        if distance_squared < neighbourhood * neighbourhood {
-          Some(std::borrow::Cow::Borrowed(dodgy_agent))
-        } else {
-          None
-        }
-      })
-      .chain(nearby_characters.iter().filter_map(
-        |&(distance_squared, dodgy_agent)| {
-          if distance_squared < neighbourhood_squared {
+      let nearby_agents = nearby_agents
+        .iter()
+        .filter_map(|&(distance_squared, neighbour_id)| {
+          if neighbour_id == agent_id {
+            return None;
+          }
+
+          let neighbour = context.agents.get(*neighbour_id).unwrap();
+          if !agent.avoids_layers(neighbour.avoidance_layers) {
+            return None;
+          }
+
+          let dodgy_agent = agent_id_to_dodgy_agent.get(neighbour_id).unwrap();
+
+          let neighbourhood = agent_options.neighbourhood + dodgy_agent.radius;
+          if distance_squared >= neighbourhood * neighbourhood {
+            return None;
+          }
+          if !context.shares_walkable_layer(
+            *agent_id,
+            AvoidanceNeighbour::Agent(*neighbour_id),
+          ) {
+            return None;
+          }
+          if neighbour.avoids_layers(agent.avoidance_layers) {
             Some(std::borrow::Cow::Borrowed(dodgy_agent))
           } else {
-            None
+            // The neighbour ignores this agent, so this agent must take all
+            // the responsibility for avoiding it (like for characters).
+            Some(std::borrow::Cow::Owned(dodgy_2d::Agent {
+              avoidance_responsibility: 0.0,
+              ..dodgy_agent.clone()
+            }))
           }
-        },
-      ))
-      .collect::<Vec<_>>();
+        })
+        .chain(nearby_characters.iter().filter_map(
+          |&(
+            distance_squared,
+            (dodgy_agent, avoidance_layers, character_id),
+          )| {
+            if distance_squared < neighbourhood_squared
+              && agent.avoids_layers(*avoidance_layers)
+              && context.shares_walkable_layer(
+                *agent_id,
+                AvoidanceNeighbour::Character(*character_id),
+              )
+            {
+              Some(std::borrow::Cow::Borrowed(dodgy_agent))
+            } else {
+              None
+            }
+          },
+        ))
+        .collect::<Vec<_>>();
 
-    let mut nearby_obstacles = nav_mesh_borders_to_dodgy_obstacles(
-      *agent_node,
-      nav_data,
-      agent_options.neighbourhood,
-    );
-    let nearby_obstacles = nearby_obstacles
-      .drain(..)
-      .map(std::borrow::Cow::Owned)
-      .collect::<Vec<_>>();
-    let preferred_velocity =
-      to_dodgy_vec2(CS::to_landmass(&agent.current_desired_move).xy());
-    let avoidance_options = dodgy_2d::AvoidanceOptions {
-      // Always use an avoidance margin of zero since we assume the nav mesh
-      // is the "valid" region.
-      obstacle_margin: 0.0,
-      time_horizon: agent_options.avoidance_time_horizon,
-      obstacle_time_horizon: agent_options.obstacle_avoidance_time_horizon,
-    };
+      let mut nearby_obstacles = nav_mesh_borders_to_dodgy_obstacles(
+        *agent_node,
+        context.nav_data,
+        agent_options.neighbourhood,
+      );
+      let nearby_obstacles = nearby_obstacles
+        .drain(..)
+        .map(std::borrow::Cow::Owned)
+        .chain(footprint_obstacles.iter().filter_map(
+          |(footprint, obstacle, reach)| {
+            if agent.avoids_layers(footprint.avoidance_layers)
+              && footprint.point.distance(agent_point) - reach
+                < agent_options.neighbourhood
+              && context.shares_walkable_layer(*agent_id, footprint.neighbour)
+            {
+              Some(std::borrow::Cow::Borrowed(obstacle))
+            } else {
+              None
+            }
+          },
+        ))
+        .collect::<Vec<_>>();
+      let preferred_velocity =
+        to_dodgy_vec2(CS::to_landmass(&agent.current_desired_move).xy());
+      let avoidance_options = dodgy_2d::AvoidanceOptions {
+        // Always use an avoidance margin of zero since we assume the nav mesh
+        // is the "valid" region.
+        obstacle_margin: 0.0,
+        time_horizon: agent_options.avoidance_time_horizon,
+        obstacle_time_horizon: agent_options.obstacle_avoidance_time_horizon,
+      };
 
-    let dodgy_agent = agent_id_to_dodgy_agent.get(&agent_id).unwrap();
-    #[cfg(not(feature = "debug-avoidance"))]
-    let desired_move = dodgy_agent.compute_avoiding_velocity(
-      &nearby_agents,
-      &nearby_obstacles,
-      preferred_velocity,
-      agent.max_speed,
-      delta_time,
-      &avoidance_options,
-    );
-    #[cfg(feature = "debug-avoidance")]
-    let desired_move = {
+      let dodgy_agent = agent_id_to_dodgy_agent.get(agent_id).unwrap();
+      #[cfg(not(feature = "debug-avoidance"))]
+      let desired_move = dodgy_agent.compute_avoiding_velocity(
+        &nearby_agents,
+        &nearby_obstacles,
+        preferred_velocity,
+        agent.max_speed,
+        context.delta_time,
+        &avoidance_options,
+      );
+      #[cfg(feature = "debug-avoidance")]
       let (desired_move, debug_data) = dodgy_agent
         .compute_avoiding_velocity_with_debug(
           &nearby_agents,
           &nearby_obstacles,
           preferred_velocity,
           agent.max_speed,
-          delta_time,
+          context.delta_time,
           &avoidance_options,
         );
-      agent.avoidance_data = agent.keep_avoidance_data.then_some(debug_data);
-      desired_move
-    };
 
-    agent.current_desired_move =
-      CS::from_landmass(&glam::Vec3::new(desired_move.x, desired_move.y, 0.0));
+      Some((
+        *agent_id,
+        CS::from_landmass(&Vec3::new(desired_move.x, desired_move.y, 0.0)),
+        #[cfg(feature = "debug-avoidance")]
+        debug_data,
+      ))
+    });
+
+    #[cfg(not(feature = "debug-avoidance"))]
+    for (agent_id, velocity) in results.into_iter().flatten() {
+      velocities.set(agent_id, velocity);
+    }
+    #[cfg(feature = "debug-avoidance")]
+    for (agent_id, velocity, debug_data) in results.into_iter().flatten() {
+      velocities.set(agent_id, velocity);
+      velocities.debug_data.insert(agent_id, debug_data);
+    }
+  }
+}
+
+/// No avoidance. Agents move at their desired velocity, even if that makes
+/// them run into each other.
+#[derive(Default)]
+pub struct NoAvoidance;
+
+impl<CS: CoordinateSystem> AvoidanceBackend<CS> for NoAvoidance {
+  fn apply_avoidance(
+    &mut self,
+    _context: &AvoidanceContext<'_, CS>,
+    _velocities: &mut AvoidanceVelocities<CS>,
+  ) {
+  }
+}
+
+/// Simple boids-style separation. Agents steer away from nearby agents and
+/// characters, on top of their desired velocity. This is much cheaper than
+/// [`DodgyAvoidance`], but does not predict collisions or avoid nav mesh
+/// borders. Agents only separate from the neighbours in their avoidance mask
+/// that share a walkable layer with them. Footprints are treated like
+/// neighbours, separating from their closest point.
+pub struct SeparationAvoidance {
+  /// The gap between the edges of two agents (or an agent and a character) at
+  /// which they start separating.
+  pub distance: f32,
+  /// The speed at which two touching agents separate. The speed falls off
+  /// linearly until the agents are [`Self::distance`] apart.
+  pub strength: f32,
+}
+
+impl Default for SeparationAvoidance {
+  fn default() -> Self {
+    Self { distance: 0.5, strength: 1.0 }
+  }
+}
+
+impl SeparationAvoidance {
+  /// The fraction of the separation strength to use for a gap of `gap`.
+  fn falloff(&self, gap: f32) -> f32 {
+    if self.distance > 0.0 { (1.0 - gap / self.distance).min(1.0) } else { 1.0 }
+  }
+}
+
+impl<CS: CoordinateSystem> AvoidanceBackend<CS> for SeparationAvoidance {
+  fn apply_avoidance(
+    &mut self,
+    context: &AvoidanceContext<'_, CS>,
+    velocities: &mut AvoidanceVelocities<CS>,
+  ) {
+    // Agents and characters are separated the same way, so put them all in
+    // one tree.
+    let mut kdtree = KdTree::new(/* dimensions= */ 3);
+    let mut max_radius = 0.0f32;
+    let mut add_neighbour =
+      |neighbour: AvoidanceNeighbour,
+       position: &CS::Coordinate,
+       radius: f32,
+       avoidance_layers: u32| {
+        let position = CS::to_landmass(position);
+        kdtree
+          .add(
+            [position.x, position.y, position.z],
+            (neighbour, position, radius, avoidance_layers),
+          )
+          .expect("Neighbour point is finite");
+        max_radius = max_radius.max(radius);
+      };
+    for agent in context.agents() {
+      add_neighbour(
+        AvoidanceNeighbour::Agent(agent.id),
+        &agent.position,
+        agent.agent.radius,
+        agent.agent.avoidance_layers,
+      );
+    }
+    for character in context.characters() {
+      add_neighbour(
+        AvoidanceNeighbour::Character(character.id),
+        &character.position,
+        character.character.radius,
+        character.character.avoidance_layers,
+      );
+    }
+
+    for agent in context.agents() {
+      let position = CS::to_landmass(&agent.position);
+      let search_distance = agent.agent.radius + max_radius + self.distance;
+      let mut push = Vec2::ZERO;
+      for (
+        _,
+        &(neighbour, neighbour_position, neighbour_radius, avoidance_layers),
+      ) in kdtree
+        .within(
+          &[position.x, position.y, position.z],
+          search_distance * search_distance,
+          &squared_euclidean,
+        )
+        .unwrap()
+      {
+        if neighbour == AvoidanceNeighbour::Agent(agent.id)
+          || !agent.agent.avoids_layers(avoidance_layers)
+        {
+          continue;
+        }
+        let offset = (position - neighbour_position).xy();
+        let gap = offset.length() - agent.agent.radius - neighbour_radius;
+        if gap >= self.distance
+          || !context.shares_walkable_layer(agent.id, neighbour)
+        {
+          continue;
+        }
+        // Pick an arbitrary direction for neighbours in the exact same spot.
+        let direction = offset.try_normalize().unwrap_or(Vec2::X);
+        push += direction * self.strength * self.falloff(gap);
+      }
+
+      for footprint in context.footprints {
+        if !agent.agent.avoids_layers(footprint.avoidance_layers)
+          || !context.shares_walkable_layer(agent.id, footprint.neighbour)
+        {
+          continue;
+        }
+        let (closest_point, inside) =
+          footprint.closest_boundary_point(position.xy());
+        let offset = position.xy() - closest_point;
+        let (direction, gap) = if inside {
+          (-offset, -offset.length() - agent.agent.radius)
+        } else {
+          (offset, offset.length() - agent.agent.radius)
+        };
+        if gap >= self.distance {
+          continue;
+        }
+        // Pick an arbitrary direction for agents exactly on the boundary.
+        let direction = direction.try_normalize().unwrap_or(Vec2::X);
+        push += direction * self.strength * self.falloff(gap);
+      }
+
+      let desired_move =
+        CS::to_landmass(agent.agent.get_desired_velocity()).xy();
+      let velocity =
+        (desired_move + push).clamp_length_max(agent.agent.max_speed);
+      velocities.set(
+        agent.id,
+        CS::from_landmass(&Vec3::new(velocity.x, velocity.y, 0.0)),
+      );
+    }
   }
 }
 
@@ -0,0 +1,54 @@
+use slotmap::new_key_type;
+
+use crate::{CoordinateSystem, ObstacleShape, Transform};
+
+new_key_type! {
+  /// The ID of an [`AvoidanceObstacle`].
+  pub struct AvoidanceObstacleId;
+}
+
+/// An obstacle that agents avoid locally.
+///
+/// Unlike [`crate::Obstacle`]s (which carve the navigation meshes), avoidance
+/// obstacles do not change agent paths. They are like characters with a
+/// non-circular shape, and are useful for things that move around often (e.g.,
+/// physics props or moving vehicles).
+pub struct AvoidanceObstacle<CS: CoordinateSystem> {
+  /// The shape of the obstacle, relative to `transform`.
+  pub shape: ObstacleShape<CS>,
+  /// The transform from the obstacle's frame to the Archipelago's frame.
+  pub transform: Transform<CS>,
+  /// The current velocity of the obstacle.
+  pub velocity: CS::Coordinate,
+  /// The avoidance layers that the obstacle is in, as a bitmask. Agents only
+  /// avoid the obstacle if their [`crate::Agent::avoidance_mask`] shares a
+  /// layer with it.
+  pub avoidance_layers: u32,
+}
+
+// Manual Clone impl to avoid `CS` having a Clone bound itself.
+impl<CS: CoordinateSystem> Clone for AvoidanceObstacle<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      shape: self.shape.clone(),
+      transform: self.transform.clone(),
+      velocity: self.velocity.clone(),
+      avoidance_layers: self.avoidance_layers,
+    }
+  }
+}
+
+// Manual Debug impl to avoid `CS` having a Debug bound itself.
+impl<
+  CS: CoordinateSystem<Coordinate: std::fmt::Debug, Rotation: std::fmt::Debug>,
+> std::fmt::Debug for AvoidanceObstacle<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("AvoidanceObstacle")
+      .field("shape", &self.shape)
+      .field("transform", &self.transform)
+      .field("velocity", &self.velocity)
+      .field("avoidance_layers", &self.avoidance_layers)
+      .finish()
+  }
+}
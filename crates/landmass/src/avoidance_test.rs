@@ -1,17 +1,20 @@
 use std::{collections::HashMap, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use slotmap::DenseSlotMap;
 
 use crate::{
-  Agent, AgentId, Archipelago, ArchipelagoOptions, Character, CharacterId,
-  FromAgentRadius, Island, NavigationData, NavigationMesh, Transform,
+  Agent, AgentId, Archipelago, ArchipelagoOptions, AvoidanceBackend,
+  AvoidanceContext, AvoidanceNeighbour, AvoidanceObstacle, AvoidanceObstacleId,
+  AvoidanceVelocities, BorderObstacle, Character, CharacterFootprint,
+  CharacterId, FromAgentRadius, Island, IslandId, NavigationData,
+  NavigationMesh, NoAvoidance, ObstacleShape, SeparationAvoidance, Transform,
   avoidance::apply_avoidance_to_agents,
   coords::{XY, XYZ},
   nav_data::NodeRef,
 };
 
-use super::nav_mesh_borders_to_dodgy_obstacles;
+use super::{DodgyAvoidance, Footprint, nav_mesh_borders_to_dodgy_obstacles};
 
 fn obstacle_matches(
   left: &dodgy_2d::Obstacle,
@@ -88,7 +91,11 @@ fn computes_obstacle_for_box() {
     dodgy_2d::Vec2::new(island_offset.x, island_offset.y);
 
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: island_offset, rotation: 0.0 },
+    Transform {
+      translation: island_offset,
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::new(nav_mesh),
   ));
 
@@ -143,10 +150,8 @@ fn dead_end_makes_open_obstacle() {
   .expect("Validation succeeds");
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   assert_obstacles_match!(
     nav_mesh_borders_to_dodgy_obstacles(
@@ -286,10 +291,8 @@ fn split_borders() {
   .expect("Validation succeeds");
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   assert_obstacles_match!(
     nav_mesh_borders_to_dodgy_obstacles(
@@ -345,12 +348,13 @@ fn creates_obstacles_across_boundary_link() {
   );
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  nav_data.add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
   let island_id_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
 
@@ -403,10 +407,8 @@ fn applies_no_avoidance_for_far_agents() {
   .expect("Validation succeeded.");
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let mut agents = DenseSlotMap::<AgentId, _>::with_key();
   let agent_1 = agents.insert({
@@ -461,10 +463,12 @@ fn applies_no_avoidance_for_far_agents() {
   // `agent_3` is not on a node.
 
   apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
     &mut agents,
     &agent_id_to_agent_node,
     /* characters= */ &DenseSlotMap::with_key(),
-    /* character_id_to_nav_mesh_point= */ &HashMap::new(),
+    /* character_id_to_character_node= */ &HashMap::new(),
+    /* footprints= */ &[],
     &nav_data,
     &ArchipelagoOptions {
       neighbourhood: 5.0,
@@ -504,10 +508,8 @@ fn applies_avoidance_for_two_agents() {
   .expect("Validation succeeded.");
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let mut agents = DenseSlotMap::<AgentId, _>::with_key();
   let agent_1 = agents.insert({
@@ -550,10 +552,12 @@ fn applies_avoidance_for_two_agents() {
   );
 
   apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
     &mut agents,
     &agent_id_to_agent_node,
     /* characters= */ &DenseSlotMap::with_key(),
-    /* character_id_to_nav_mesh_point= */ &HashMap::new(),
+    /* character_id_to_character_node= */ &HashMap::new(),
+    /* footprints= */ &[],
     &nav_data,
     &ArchipelagoOptions {
       neighbourhood: 15.0,
@@ -599,10 +603,8 @@ fn agent_avoids_character() {
   .expect("Validation succeeded.");
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let mut agents = DenseSlotMap::<AgentId, _>::with_key();
   let agent = agents.insert({
@@ -621,6 +623,7 @@ fn agent_avoids_character() {
     position: Vec3::new(11.0, 1.01, 0.0),
     velocity: Vec3::new(-1.0, 0.0, 0.0),
     radius: 1.0,
+    ..Default::default()
   });
 
   let mut agent_id_to_agent_node = HashMap::new();
@@ -631,15 +634,22 @@ fn agent_avoids_character() {
       NodeRef { island_id, polygon_index: 0 },
     ),
   );
-  let mut character_id_to_nav_mesh_point = HashMap::new();
-  character_id_to_nav_mesh_point
-    .insert(character, characters.get(character).unwrap().position);
+  let mut character_id_to_character_node = HashMap::new();
+  character_id_to_character_node.insert(
+    character,
+    (
+      characters.get(character).unwrap().position,
+      NodeRef { island_id, polygon_index: 0 },
+    ),
+  );
 
   apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
     &mut agents,
     &agent_id_to_agent_node,
     &characters,
-    &character_id_to_nav_mesh_point,
+    &character_id_to_character_node,
+    /* footprints= */ &[],
     &nav_data,
     &ArchipelagoOptions {
       neighbourhood: 15.0,
@@ -681,10 +691,8 @@ fn agent_speeds_up_to_avoid_character() {
   .expect("Validation succeeded.");
 
   let mut nav_data = NavigationData::<XY>::new();
-  let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let mut agents = DenseSlotMap::<AgentId, _>::with_key();
   let agent = agents.insert({
@@ -709,10 +717,12 @@ fn agent_speeds_up_to_avoid_character() {
   );
 
   apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
     &mut agents,
     &agent_id_to_agent_node,
     &DenseSlotMap::with_key(),
     &HashMap::new(),
+    /* footprints= */ &[],
     &nav_data,
     &ArchipelagoOptions {
       neighbourhood: 15.0,
@@ -733,16 +743,24 @@ fn agent_speeds_up_to_avoid_character() {
     position: Vec2::new(0.0, 5.0),
     velocity: Vec2::new(0.0, -1.0),
     radius: 0.5,
+    ..Default::default()
   });
-  let mut character_id_to_nav_mesh_point = HashMap::new();
-  character_id_to_nav_mesh_point
-    .insert(character, characters.get(character).unwrap().position.extend(0.0));
+  let mut character_id_to_character_node = HashMap::new();
+  character_id_to_character_node.insert(
+    character,
+    (
+      characters.get(character).unwrap().position.extend(0.0),
+      NodeRef { island_id, polygon_index: 0 },
+    ),
+  );
 
   apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
     &mut agents,
     &agent_id_to_agent_node,
     &characters,
-    &character_id_to_nav_mesh_point,
+    &character_id_to_character_node,
+    /* footprints= */ &[],
     &nav_data,
     &ArchipelagoOptions {
       neighbourhood: 15.0,
@@ -897,7 +915,7 @@ fn switching_nav_mesh_to_fewer_vertices_does_not_result_in_panic() {
   archipelago.add_island(Island::new(
     // This island is shifted over but is slightly misaligned to generate new
     // vertices.
-    Transform { translation: Vec2::new(1.0, 0.25), rotation: 0.0 },
+    Transform { translation: Vec2::new(1.0, 0.25), rotation: 0.0, scale: 1.0 },
     redundant_mesh,
   ));
 
@@ -923,3 +941,1190 @@ fn switching_nav_mesh_to_fewer_vertices_does_not_result_in_panic() {
 
   archipelago.update(0.01);
 }
+
+#[test]
+fn no_avoidance_keeps_desired_velocities() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(-1.0, -1.0, 0.0),
+      Vec3::new(13.0, -1.0, 0.0),
+      Vec3::new(13.0, 3.0, 0.0),
+      Vec3::new(-1.0, 3.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Validation succeeded.");
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+  let agent_1 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(1.0, 1.0, 0.0),
+      /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(1.0, 0.0, 0.0);
+    agent
+  });
+  let agent_2 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(2.0, 1.5, 0.0),
+      /* velocity= */ Vec3::new(-1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(-1.0, 0.0, 0.0);
+    agent
+  });
+
+  let agent_id_to_agent_node = HashMap::from([
+    (
+      agent_1,
+      (
+        agents.get(agent_1).unwrap().position,
+        NodeRef { island_id, polygon_index: 0 },
+      ),
+    ),
+    (
+      agent_2,
+      (
+        agents.get(agent_2).unwrap().position,
+        NodeRef { island_id, polygon_index: 0 },
+      ),
+    ),
+  ]);
+
+  apply_avoidance_to_agents(
+    &mut NoAvoidance,
+    &mut agents,
+    &agent_id_to_agent_node,
+    /* characters= */ &DenseSlotMap::with_key(),
+    /* character_id_to_character_node= */ &HashMap::new(),
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions::from_agent_radius(0.5),
+    /* delta_time= */ 0.01,
+  );
+
+  assert_eq!(
+    *agents.get(agent_1).unwrap().get_desired_velocity(),
+    Vec3::new(1.0, 0.0, 0.0)
+  );
+  assert_eq!(
+    *agents.get(agent_2).unwrap().get_desired_velocity(),
+    Vec3::new(-1.0, 0.0, 0.0)
+  );
+}
+
+#[test]
+fn separation_pushes_overlapping_agents_apart() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(-1.0, -1.0, 0.0),
+      Vec3::new(13.0, -1.0, 0.0),
+      Vec3::new(13.0, 3.0, 0.0),
+      Vec3::new(-1.0, 3.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Validation succeeded.");
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+  let agent_1 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(1.0, 1.0, 0.0),
+      /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(1.0, 0.0, 0.0);
+    agent
+  });
+  let agent_2 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(2.0, 1.5, 0.0),
+      /* velocity= */ Vec3::new(-1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(-1.0, 0.0, 0.0);
+    agent
+  });
+
+  let agent_id_to_agent_node = HashMap::from([
+    (
+      agent_1,
+      (
+        agents.get(agent_1).unwrap().position,
+        NodeRef { island_id, polygon_index: 0 },
+      ),
+    ),
+    (
+      agent_2,
+      (
+        agents.get(agent_2).unwrap().position,
+        NodeRef { island_id, polygon_index: 0 },
+      ),
+    ),
+  ]);
+
+  apply_avoidance_to_agents(
+    &mut SeparationAvoidance { distance: 1.0, strength: 1.0 },
+    &mut agents,
+    &agent_id_to_agent_node,
+    /* characters= */ &DenseSlotMap::with_key(),
+    /* character_id_to_character_node= */ &HashMap::new(),
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions::from_agent_radius(0.5),
+    /* delta_time= */ 0.01,
+  );
+
+  // The agents overlap, so they are pushed apart at full strength along the
+  // line between them.
+  let push = Vec3::new(-1.0, -0.5, 0.0).normalize();
+  let agent_1_desired_velocity =
+    *agents.get(agent_1).unwrap().get_desired_velocity();
+  assert!(
+    agent_1_desired_velocity.abs_diff_eq(Vec3::new(1.0, 0.0, 0.0) + push, 1e-5),
+    "left={agent_1_desired_velocity}, right={}",
+    Vec3::new(1.0, 0.0, 0.0) + push
+  );
+  let agent_2_desired_velocity =
+    *agents.get(agent_2).unwrap().get_desired_velocity();
+  assert!(
+    agent_2_desired_velocity
+      .abs_diff_eq(Vec3::new(-1.0, 0.0, 0.0) - push, 1e-5),
+    "left={agent_2_desired_velocity}, right={}",
+    Vec3::new(-1.0, 0.0, 0.0) - push
+  );
+}
+
+#[test]
+fn separation_ignores_far_characters_and_clamps_to_max_speed() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(-1.0, -1.0, 0.0),
+      Vec3::new(13.0, -1.0, 0.0),
+      Vec3::new(13.0, 3.0, 0.0),
+      Vec3::new(-1.0, 3.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Validation succeeded.");
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+  let agent_1 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(1.0, 1.0, 0.0),
+      /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(1.0, 0.0, 0.0);
+    agent
+  });
+  let agent_2 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(2.0, 1.5, 0.0),
+      /* velocity= */ Vec3::new(-1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(-1.0, 0.0, 0.0);
+    agent
+  });
+
+  let agent_id_to_agent_node = HashMap::from([
+    (
+      agent_1,
+      (
+        agents.get(agent_1).unwrap().position,
+        NodeRef { island_id, polygon_index: 0 },
+      ),
+    ),
+    (
+      agent_2,
+      (
+        agents.get(agent_2).unwrap().position,
+        NodeRef { island_id, polygon_index: 0 },
+      ),
+    ),
+  ]);
+  agents.get_mut(agent_2).unwrap().current_desired_move = Vec3::ZERO;
+
+  let mut characters = DenseSlotMap::<CharacterId, _>::with_key();
+  let near_character = characters.insert(Character {
+    position: Vec3::new(1.0, -1.0, 0.0),
+    velocity: Vec3::ZERO,
+    radius: 1.0,
+    ..Default::default()
+  });
+  let far_character = characters.insert(Character {
+    position: Vec3::new(11.0, 1.0, 0.0),
+    velocity: Vec3::ZERO,
+    radius: 1.0,
+    ..Default::default()
+  });
+  let node = agent_id_to_agent_node.get(&agent_1).unwrap().1;
+  let character_id_to_character_node = [near_character, far_character]
+    .into_iter()
+    .map(|character_id| {
+      (character_id, (characters.get(character_id).unwrap().position, node))
+    })
+    .collect();
+
+  apply_avoidance_to_agents(
+    &mut SeparationAvoidance { distance: 1.0, strength: 4.0 },
+    &mut agents,
+    &agent_id_to_agent_node,
+    &characters,
+    &character_id_to_character_node,
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions::from_agent_radius(0.5),
+    /* delta_time= */ 0.01,
+  );
+
+  // The first agent is pushed away from both the second agent and the near
+  // character, but never faster than its max speed.
+  let agent_1_desired_velocity =
+    *agents.get(agent_1).unwrap().get_desired_velocity();
+  let expected_velocity = (Vec3::new(1.0, 0.0, 0.0)
+    + Vec3::new(-1.0, -0.5, 0.0).normalize() * 4.0
+    + Vec3::new(0.0, 1.0, 0.0) * 4.0)
+    .clamp_length_max(2.0);
+  assert!(
+    agent_1_desired_velocity.abs_diff_eq(expected_velocity, 1e-5),
+    "left={agent_1_desired_velocity}, right={expected_velocity}"
+  );
+}
+
+/// An avoidance backend that moves each agent towards the closest vertex of
+/// its nav mesh borders.
+struct MoveToBorderVertex;
+
+impl AvoidanceBackend<XY> for MoveToBorderVertex {
+  fn apply_avoidance(
+    &mut self,
+    context: &AvoidanceContext<'_, XY>,
+    velocities: &mut AvoidanceVelocities<XY>,
+  ) {
+    for agent in context.agents() {
+      let closest_vertex = context
+        .nav_mesh_borders(agent.id, /* distance= */ 10.0)
+        .into_iter()
+        .flat_map(|obstacle| match obstacle {
+          BorderObstacle::Closed { vertices } => vertices,
+          BorderObstacle::Open { vertices } => vertices,
+        })
+        .min_by(|a, b| {
+          a.distance(agent.position).total_cmp(&b.distance(agent.position))
+        })
+        .unwrap();
+      velocities.set(agent.id, closest_vertex - agent.position);
+    }
+  }
+}
+
+#[test]
+fn archipelago_uses_avoidance_backend() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago.set_avoidance_backend(MoveToBorderVertex);
+
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(4.0, 0.0),
+      Vec2::new(4.0, 4.0),
+      Vec2::new(0.0, 4.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .unwrap();
+  archipelago.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let agent = archipelago.add_agent(Agent::create(
+    /* position= */ Vec2::new(3.0, 2.5),
+    /* velocity= */ Vec2::ZERO,
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 1.0,
+  ));
+
+  archipelago.update(0.01);
+
+  assert_eq!(
+    *archipelago.get_agent(agent).unwrap().get_desired_velocity(),
+    Vec2::new(1.0, 1.5)
+  );
+}
+
+/// Runs the default avoidance for two agents passing each other head on, after
+/// configuring each agent with `configure_agent`. Returns the avoiding
+/// velocities of both agents.
+fn avoid_head_on(
+  configure_agent: impl Fn(usize, &mut Agent<XYZ>),
+) -> [Vec3; 2] {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(-1.0, -1.0, 0.0),
+      Vec3::new(13.0, -1.0, 0.0),
+      Vec3::new(13.0, 3.0, 0.0),
+      Vec3::new(-1.0, 3.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Validation succeeded.");
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+  let agent_ids = [
+    (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+    (Vec3::new(11.0, 1.01, 0.0), Vec3::new(-1.0, 0.0, 0.0)),
+  ]
+  .into_iter()
+  .enumerate()
+  .map(|(index, (position, velocity))| {
+    let mut agent = Agent::create(
+      position, velocity, /* radius= */ 1.0,
+      /* desired_speed= */ 1.0, /* max_speed= */ 1.0,
+    );
+    agent.current_desired_move = velocity;
+    configure_agent(index, &mut agent);
+    agents.insert(agent)
+  })
+  .collect::<Vec<_>>();
+
+  let agent_id_to_agent_node = agent_ids
+    .iter()
+    .map(|&agent_id| {
+      (
+        agent_id,
+        (
+          agents.get(agent_id).unwrap().position,
+          NodeRef { island_id, polygon_index: 0 },
+        ),
+      )
+    })
+    .collect();
+
+  apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
+    &mut agents,
+    &agent_id_to_agent_node,
+    /* characters= */ &DenseSlotMap::with_key(),
+    /* character_id_to_character_node= */ &HashMap::new(),
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions {
+      neighbourhood: 15.0,
+      avoidance_time_horizon: 15.0,
+      ..ArchipelagoOptions::from_agent_radius(0.5)
+    },
+    /* delta_time= */ 0.01,
+  );
+
+  [
+    *agents.get(agent_ids[0]).unwrap().get_desired_velocity(),
+    *agents.get(agent_ids[1]).unwrap().get_desired_velocity(),
+  ]
+}
+
+#[test]
+fn agents_ignore_agents_outside_their_avoidance_mask() {
+  // The agents are both allies on layer 2, and allies don't avoid each other.
+  let velocities = avoid_head_on(|_, agent| {
+    agent.avoidance_layers = 0b10;
+    agent.avoidance_mask = 0b01;
+  });
+
+  assert_eq!(velocities, [Vec3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0)]);
+}
+
+#[test]
+fn agent_takes_full_responsibility_if_neighbour_ignores_it() {
+  // The first agent is a "ghost" that ignores everyone.
+  let [ghost_velocity, agent_velocity] = avoid_head_on(|index, agent| {
+    if index == 0 {
+      agent.avoidance_mask = 0;
+    }
+  });
+
+  // The ghost doesn't avoid at all, so the other agent needs to go around it
+  // by itself, the same as avoiding a character. See
+  // `agent_avoids_character`.
+  assert_eq!(ghost_velocity, Vec3::new(1.0, 0.0, 0.0));
+  assert!(
+    agent_velocity
+      .abs_diff_eq(Vec3::new(-(1.0f32 - 0.4 * 0.4).sqrt(), 0.4, 0.0), 0.05),
+    "left={agent_velocity}, right=Vec3(-0.9165..., 0.4, 0.0)"
+  );
+}
+
+#[test]
+fn higher_priority_agent_avoids_less() {
+  let [vip_velocity, agent_velocity] = avoid_head_on(|index, agent| {
+    if index == 0 {
+      agent.avoidance_priority = 3.0;
+    }
+  });
+
+  // The VIP has a quarter of the responsibility, so it only needs to go to
+  // (6,0.5) while the other agent goes to (6,-1.5). See
+  // `applies_avoidance_for_two_agents`.
+  assert!(
+    vip_velocity
+      .abs_diff_eq(Vec3::new((1.0f32 - 0.1 * 0.1).sqrt(), -0.1, 0.0), 0.05),
+    "left={vip_velocity}, right=Vec3(0.995..., -0.1, 0.0)"
+  );
+  assert!(
+    agent_velocity
+      .abs_diff_eq(Vec3::new(-(1.0f32 - 0.3 * 0.3).sqrt(), 0.3, 0.0), 0.05),
+    "left={agent_velocity}, right=Vec3(-0.954..., 0.3, 0.0)"
+  );
+}
+
+#[test]
+fn non_positive_priority_is_clamped() {
+  let min_priority_velocities = avoid_head_on(|index, agent| {
+    if index == 0 {
+      agent.avoidance_priority = 1e-3;
+    }
+  });
+  assert!(
+    min_priority_velocities[0].is_finite()
+      && min_priority_velocities[1].is_finite(),
+    "velocities={min_priority_velocities:?}"
+  );
+
+  for priority in [0.0, -1.0, f32::NAN] {
+    let velocities = avoid_head_on(|index, agent| {
+      if index == 0 {
+        agent.avoidance_priority = priority;
+      }
+    });
+    assert_eq!(velocities, min_priority_velocities, "priority={priority}");
+  }
+}
+
+#[test]
+fn agent_ignores_character_outside_its_avoidance_mask() {
+  let mut agents = DenseSlotMap::<AgentId, Agent<XY>>::with_key();
+  let agent = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(1.0, 1.0),
+      /* velocity= */ Vec2::new(1.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_desired_move = Vec2::new(1.0, 0.0);
+    agent.avoidance_mask = 0b01;
+    agent
+  });
+  let mut characters = DenseSlotMap::<CharacterId, _>::with_key();
+  let character = characters.insert(Character {
+    position: Vec2::new(3.0, 1.0),
+    velocity: Vec2::new(-1.0, 0.0),
+    radius: 1.0,
+    avoidance_layers: 0b10,
+    ..Default::default()
+  });
+
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id = nav_data.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec2::new(-1.0, -1.0),
+          Vec2::new(13.0, -1.0),
+          Vec2::new(13.0, 3.0),
+          Vec2::new(-1.0, 3.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("Validation succeeded."),
+    ),
+  ));
+
+  apply_avoidance_to_agents(
+    &mut SeparationAvoidance::default(),
+    &mut agents,
+    &HashMap::from([(
+      agent,
+      (Vec3::new(1.0, 1.0, 0.0), NodeRef { island_id, polygon_index: 0 }),
+    )]),
+    &characters,
+    &HashMap::from([(
+      character,
+      (Vec3::new(3.0, 1.0, 0.0), NodeRef { island_id, polygon_index: 0 }),
+    )]),
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions::from_agent_radius(0.5),
+    /* delta_time= */ 0.01,
+  );
+  assert_eq!(
+    *agents.get(agent).unwrap().get_desired_velocity(),
+    Vec2::new(1.0, 0.0)
+  );
+
+  // Once the character is on a layer the agent avoids, the agent is pushed
+  // away.
+  characters.get_mut(character).unwrap().avoidance_layers = 0b11;
+  apply_avoidance_to_agents(
+    &mut SeparationAvoidance::default(),
+    &mut agents,
+    &HashMap::from([(
+      agent,
+      (Vec3::new(1.0, 1.0, 0.0), NodeRef { island_id, polygon_index: 0 }),
+    )]),
+    &characters,
+    &HashMap::from([(
+      character,
+      (Vec3::new(3.0, 1.0, 0.0), NodeRef { island_id, polygon_index: 0 }),
+    )]),
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions::from_agent_radius(0.5),
+    /* delta_time= */ 0.01,
+  );
+  assert_eq!(
+    *agents.get(agent).unwrap().get_desired_velocity(),
+    Vec2::new(0.0, 0.0)
+  );
+}
+
+#[test]
+fn footprint_is_counter_clockwise_and_swept_by_velocity() {
+  let footprint = Footprint::new(
+    AvoidanceNeighbour::Obstacle(AvoidanceObstacleId::default()),
+    // A clockwise unit square.
+    vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(1.0, 0.0),
+    ],
+    /* node= */
+    (
+      Vec3::new(0.5, 0.5, 0.0),
+      NodeRef { island_id: IslandId::default(), polygon_index: 0 },
+    ),
+    /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+    /* avoidance_layers= */ 1,
+  );
+  assert_eq!(
+    footprint.outline,
+    [
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(0.0, 0.0),
+    ]
+  );
+
+  // The square moves right by 2 units, so it covers a 3x1 rectangle.
+  let (outline, reach) = footprint.swept_outline(/* time_horizon= */ 2.0);
+  assert_eq!(outline.len(), 4);
+  for vertex in [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(3.0, 0.0),
+    Vec2::new(3.0, 1.0),
+    Vec2::new(0.0, 1.0),
+  ] {
+    assert!(outline.contains(&vertex), "{vertex} not in {outline:?}");
+  }
+  assert!((reach - Vec2::new(2.5, 0.5).length()).abs() < 1e-5);
+
+  assert_eq!(
+    footprint.closest_boundary_point(Vec2::new(0.5, 0.25)),
+    (Vec2::new(0.5, 0.0), true)
+  );
+  assert_eq!(
+    footprint.closest_boundary_point(Vec2::new(2.0, 0.5)),
+    (Vec2::new(1.0, 0.5), false)
+  );
+}
+
+#[test]
+fn agent_avoids_avoidance_obstacle() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    obstacle_avoidance_time_horizon: 5.0,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(-10.0, -10.0),
+      Vec2::new(20.0, -10.0),
+      Vec2::new(20.0, 10.0),
+      Vec2::new(-10.0, 10.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .unwrap();
+  archipelago.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let agent = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::ZERO,
+      /* velocity= */ Vec2::new(1.0, 0.0),
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_target = Some(Vec2::new(15.0, 0.0));
+    agent
+  });
+  // A capsule standing across the agent's path.
+  let obstacle = archipelago.add_avoidance_obstacle(AvoidanceObstacle {
+    shape: ObstacleShape::Capsule { half_length: 1.0, radius: 0.5 },
+    transform: Transform {
+      translation: Vec2::new(3.0, 0.0),
+      rotation: std::f32::consts::FRAC_PI_2,
+      scale: 1.0,
+    },
+    velocity: Vec2::ZERO,
+    avoidance_layers: 1,
+  });
+
+  archipelago.update(0.01);
+  let desired_velocity =
+    *archipelago.get_agent(agent).unwrap().get_desired_velocity();
+  assert!(
+    desired_velocity.y.abs() > 0.1,
+    "desired_velocity={desired_velocity}"
+  );
+
+  // Once the agent ignores the obstacle, it goes straight through it.
+  archipelago.get_avoidance_obstacle_mut(obstacle).unwrap().avoidance_layers =
+    0b10;
+  archipelago.get_agent_mut(agent).unwrap().avoidance_mask = 0b01;
+  archipelago.update(0.01);
+  assert_eq!(
+    *archipelago.get_agent(agent).unwrap().get_desired_velocity(),
+    Vec2::new(1.0, 0.0)
+  );
+}
+
+#[test]
+fn agent_avoids_where_moving_character_will_be() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    obstacle_avoidance_time_horizon: 5.0,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(-10.0, -10.0),
+      Vec2::new(20.0, -10.0),
+      Vec2::new(20.0, 10.0),
+      Vec2::new(-10.0, 10.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .unwrap();
+  archipelago.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let agent = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::ZERO,
+      /* velocity= */ Vec2::new(1.0, 0.0),
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_target = Some(Vec2::new(15.0, 0.0));
+    agent
+  });
+  // A box beside the agent's path.
+  let character = archipelago.add_character(Character {
+    position: Vec2::new(3.0, 3.0),
+    velocity: Vec2::ZERO,
+    footprint: Some(CharacterFootprint {
+      shape: ObstacleShape::Polygon(vec![
+        Vec2::new(-0.5, -0.5),
+        Vec2::new(0.5, -0.5),
+        Vec2::new(0.5, 0.5),
+        Vec2::new(-0.5, 0.5),
+      ]),
+      rotation: 0.0,
+    }),
+    ..Default::default()
+  });
+
+  archipelago.update(0.01);
+  assert_eq!(
+    *archipelago.get_agent(agent).unwrap().get_desired_velocity(),
+    Vec2::new(1.0, 0.0)
+  );
+
+  // The box is moving into the agent's path, so the agent must avoid it.
+  archipelago.get_character_mut(character).unwrap().velocity =
+    Vec2::new(0.0, -1.0);
+  archipelago.update(0.01);
+  let desired_velocity =
+    *archipelago.get_agent(agent).unwrap().get_desired_velocity();
+  assert_ne!(desired_velocity, Vec2::new(1.0, 0.0));
+}
+
+#[test]
+fn separation_pushes_agent_out_of_footprint() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    obstacle_avoidance_time_horizon: 5.0,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(-10.0, -10.0),
+      Vec2::new(20.0, -10.0),
+      Vec2::new(20.0, 10.0),
+      Vec2::new(-10.0, 10.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .unwrap();
+  archipelago.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let agent = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::ZERO,
+      /* velocity= */ Vec2::new(1.0, 0.0),
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_target = Some(Vec2::new(15.0, 0.0));
+    agent
+  });
+  archipelago.set_avoidance_backend(SeparationAvoidance {
+    distance: 1.0,
+    strength: 1.0,
+  });
+  archipelago.get_agent_mut(agent).unwrap().current_target = None;
+  // The agent's center is just inside the bottom of a wide box.
+  archipelago.add_avoidance_obstacle(AvoidanceObstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(-2.0, -0.25),
+      Vec2::new(2.0, -0.25),
+      Vec2::new(2.0, 1.0),
+      Vec2::new(-2.0, 1.0),
+    ]),
+    transform: Transform::default(),
+    velocity: Vec2::ZERO,
+    avoidance_layers: 1,
+  });
+
+  archipelago.update(0.01);
+  let desired_velocity =
+    *archipelago.get_agent(agent).unwrap().get_desired_velocity();
+  assert!(
+    desired_velocity.abs_diff_eq(Vec2::new(0.0, -1.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+}
+
+#[test]
+fn agents_on_different_floors_do_not_avoid_each_other() {
+  let options = ArchipelagoOptions {
+    neighbourhood: 5.0,
+    avoidance_vertical_tolerance: Some(1.0),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  };
+
+  let avoid = |backend: &mut dyn AvoidanceBackend<XYZ>,
+               options: &ArchipelagoOptions<XYZ>| {
+    let nav_mesh = Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(-1.0, -1.0, 0.0),
+          Vec3::new(1.5, -1.0, 0.0),
+          Vec3::new(13.0, -1.0, 0.0),
+          Vec3::new(13.0, 3.0, 0.0),
+          Vec3::new(1.5, 3.0, 0.0),
+          Vec3::new(-1.0, 3.0, 0.0),
+        ],
+        polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+        polygon_type_indices: vec![0, 0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("Validation succeeded."),
+    );
+
+    // The balcony is directly above the floor, and the floor is made of two
+    // connected nodes.
+    let mut nav_data = NavigationData::<XYZ>::new();
+    let floor_id =
+      nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+    let balcony_id = nav_data.add_island(Island::new(
+      Transform {
+        translation: Vec3::new(0.0, 0.0, 3.0),
+        rotation: Quat::IDENTITY,
+        scale: 1.0,
+      },
+      nav_mesh,
+    ));
+
+    let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+    let agent_1 = agents.insert({
+      let mut agent = Agent::create(
+        /* position= */ Vec3::new(1.0, 1.0, 0.0),
+        /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+        /* radius= */ 1.0,
+        /* desired_speed= */ 1.0,
+        /* max_speed= */ 2.0,
+      );
+      agent.current_desired_move = Vec3::new(1.0, 0.0, 0.0);
+      agent
+    });
+    let agent_2 = agents.insert({
+      let mut agent = Agent::create(
+        /* position= */ Vec3::new(2.0, 1.5, 3.0),
+        /* velocity= */ Vec3::new(-1.0, 0.0, 0.0),
+        /* radius= */ 1.0,
+        /* desired_speed= */ 1.0,
+        /* max_speed= */ 2.0,
+      );
+      agent.current_desired_move = Vec3::new(-1.0, 0.0, 0.0);
+      agent
+    });
+
+    let agent_id_to_agent_node = HashMap::from([
+      (
+        agent_1,
+        (
+          agents.get(agent_1).unwrap().position,
+          NodeRef { island_id: floor_id, polygon_index: 0 },
+        ),
+      ),
+      (
+        agent_2,
+        (
+          agents.get(agent_2).unwrap().position,
+          NodeRef { island_id: balcony_id, polygon_index: 1 },
+        ),
+      ),
+    ]);
+    apply_avoidance_to_agents(
+      backend,
+      &mut agents,
+      &agent_id_to_agent_node,
+      /* characters= */ &DenseSlotMap::with_key(),
+      /* character_id_to_character_node= */ &HashMap::new(),
+      /* footprints= */ &[],
+      &nav_data,
+      options,
+      /* delta_time= */ 0.01,
+    );
+    (
+      *agents.get(agent_1).unwrap().get_desired_velocity(),
+      *agents.get(agent_2).unwrap().get_desired_velocity(),
+    )
+  };
+
+  let expected_velocities =
+    (Vec3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+  assert_eq!(avoid(&mut DodgyAvoidance, &options), expected_velocities);
+  assert_eq!(
+    avoid(&mut SeparationAvoidance { distance: 2.0, strength: 1.0 }, &options),
+    expected_velocities
+  );
+
+  // Without a vertical tolerance, the agents avoid each other as if they were
+  // on the same floor.
+  let options =
+    ArchipelagoOptions { avoidance_vertical_tolerance: None, ..options };
+  assert_ne!(avoid(&mut DodgyAvoidance, &options), expected_velocities);
+  assert_ne!(
+    avoid(&mut SeparationAvoidance { distance: 2.0, strength: 1.0 }, &options),
+    expected_velocities
+  );
+}
+
+#[test]
+fn agents_on_connected_nodes_avoid_each_other() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec3::new(-1.0, -1.0, 0.0),
+        Vec3::new(1.5, -1.0, 0.0),
+        Vec3::new(13.0, -1.0, 0.0),
+        Vec3::new(13.0, 3.0, 0.0),
+        Vec3::new(1.5, 3.0, 0.0),
+        Vec3::new(-1.0, 3.0, 0.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("Validation succeeded."),
+  );
+
+  // The balcony is directly above the floor, and the floor is made of two
+  // connected nodes.
+  let mut nav_data = NavigationData::<XYZ>::new();
+  let floor_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  nav_data.add_island(Island::new(
+    Transform {
+      translation: Vec3::new(0.0, 0.0, 3.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    nav_mesh,
+  ));
+
+  let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+  let agent_1 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(1.0, 1.0, 0.0),
+      /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(1.0, 0.0, 0.0);
+    agent
+  });
+  let agent_2 = agents.insert({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(2.0, 1.5, 0.0),
+      /* velocity= */ Vec3::new(-1.0, 0.0, 0.0),
+      /* radius= */ 1.0,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 2.0,
+    );
+    agent.current_desired_move = Vec3::new(-1.0, 0.0, 0.0);
+    agent
+  });
+
+  let agent_id_to_agent_node = HashMap::from([
+    (
+      agent_1,
+      (
+        agents.get(agent_1).unwrap().position,
+        NodeRef { island_id: floor_id, polygon_index: 0 },
+      ),
+    ),
+    (
+      agent_2,
+      (
+        agents.get(agent_2).unwrap().position,
+        NodeRef { island_id: floor_id, polygon_index: 1 },
+      ),
+    ),
+  ]);
+
+  apply_avoidance_to_agents(
+    &mut DodgyAvoidance,
+    &mut agents,
+    &agent_id_to_agent_node,
+    /* characters= */ &DenseSlotMap::with_key(),
+    /* character_id_to_character_node= */ &HashMap::new(),
+    /* footprints= */ &[],
+    &nav_data,
+    &ArchipelagoOptions {
+      neighbourhood: 5.0,
+      avoidance_vertical_tolerance: Some(1.0),
+      ..ArchipelagoOptions::from_agent_radius(0.5)
+    },
+    /* delta_time= */ 0.01,
+  );
+
+  assert_ne!(
+    *agents.get(agent_1).unwrap().get_desired_velocity(),
+    Vec3::new(1.0, 0.0, 0.0)
+  );
+  assert_ne!(
+    *agents.get(agent_2).unwrap().get_desired_velocity(),
+    Vec3::new(-1.0, 0.0, 0.0)
+  );
+}
+
+#[test]
+fn shares_walkable_layer_requires_connected_node() {
+  struct RecordWalkableLayers(Vec<bool>);
+  impl AvoidanceBackend<XYZ> for RecordWalkableLayers {
+    fn apply_avoidance(
+      &mut self,
+      context: &AvoidanceContext<'_, XYZ>,
+      _velocities: &mut AvoidanceVelocities<XYZ>,
+    ) {
+      let agents = context.agents().map(|agent| agent.id).collect::<Vec<_>>();
+      self.0 = agents
+        .iter()
+        .map(|&agent_id| {
+          context.shares_walkable_layer(
+            agent_id,
+            AvoidanceNeighbour::Agent(agents[1]),
+          )
+        })
+        .collect();
+    }
+  }
+
+  let shares_walkable_layer =
+    |agent_2_height: f32, agent_2_node: fn(IslandId, IslandId) -> NodeRef| {
+      let nav_mesh = Arc::new(
+        NavigationMesh {
+          vertices: vec![
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.5, -1.0, 0.0),
+            Vec3::new(13.0, -1.0, 0.0),
+            Vec3::new(13.0, 3.0, 0.0),
+            Vec3::new(1.5, 3.0, 0.0),
+            Vec3::new(-1.0, 3.0, 0.0),
+          ],
+          polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+          polygon_type_indices: vec![0, 0],
+          height_mesh: None,
+        }
+        .validate()
+        .expect("Validation succeeded."),
+      );
+
+      // The balcony is directly above the floor, and the floor is made of two
+      // connected nodes.
+      let mut nav_data = NavigationData::<XYZ>::new();
+      let floor_id = nav_data
+        .add_island(Island::new(Transform::default(), nav_mesh.clone()));
+      let balcony_id = nav_data.add_island(Island::new(
+        Transform {
+          translation: Vec3::new(0.0, 0.0, 3.0),
+          rotation: Quat::IDENTITY,
+          scale: 1.0,
+        },
+        nav_mesh,
+      ));
+
+      let mut agents = DenseSlotMap::<AgentId, _>::with_key();
+      let agent_1 = agents.insert({
+        let mut agent = Agent::create(
+          /* position= */ Vec3::new(1.0, 1.0, 0.0),
+          /* velocity= */ Vec3::new(1.0, 0.0, 0.0),
+          /* radius= */ 1.0,
+          /* desired_speed= */ 1.0,
+          /* max_speed= */ 2.0,
+        );
+        agent.current_desired_move = Vec3::new(1.0, 0.0, 0.0);
+        agent
+      });
+      let agent_2 = agents.insert({
+        let mut agent = Agent::create(
+          /* position= */ Vec3::new(2.0, 1.5, agent_2_height),
+          /* velocity= */ Vec3::new(-1.0, 0.0, 0.0),
+          /* radius= */ 1.0,
+          /* desired_speed= */ 1.0,
+          /* max_speed= */ 2.0,
+        );
+        agent.current_desired_move = Vec3::new(-1.0, 0.0, 0.0);
+        agent
+      });
+
+      let agent_id_to_agent_node = HashMap::from([
+        (
+          agent_1,
+          (
+            agents.get(agent_1).unwrap().position,
+            NodeRef { island_id: floor_id, polygon_index: 0 },
+          ),
+        ),
+        (
+          agent_2,
+          (
+            agents.get(agent_2).unwrap().position,
+            agent_2_node(floor_id, balcony_id),
+          ),
+        ),
+      ]);
+      let mut backend = RecordWalkableLayers(vec![]);
+      apply_avoidance_to_agents(
+        &mut backend,
+        &mut agents,
+        &agent_id_to_agent_node,
+        /* characters= */ &DenseSlotMap::with_key(),
+        /* character_id_to_character_node= */ &HashMap::new(),
+        /* footprints= */ &[],
+        &nav_data,
+        &ArchipelagoOptions {
+          avoidance_vertical_tolerance: Some(1.0),
+          ..ArchipelagoOptions::from_agent_radius(0.5)
+        },
+        /* delta_time= */ 0.01,
+      );
+      // The second agent always shares a walkable layer with itself.
+      assert!(backend.0[1]);
+      backend.0[0]
+    };
+
+  assert!(shares_walkable_layer(0.0, |floor_id, _| NodeRef {
+    island_id: floor_id,
+    polygon_index: 0
+  }));
+  assert!(shares_walkable_layer(0.0, |floor_id, _| NodeRef {
+    island_id: floor_id,
+    polygon_index: 1
+  }));
+  // The balcony is not connected to the floor, even if the agent is at the
+  // same height.
+  assert!(!shares_walkable_layer(0.0, |_, balcony_id| NodeRef {
+    island_id: balcony_id,
+    polygon_index: 1
+  }));
+  assert!(!shares_walkable_layer(3.0, |_, balcony_id| NodeRef {
+    island_id: balcony_id,
+    polygon_index: 1
+  }));
+}
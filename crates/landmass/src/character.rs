@@ -1,7 +1,7 @@
 use glam::Vec3;
 use slotmap::new_key_type;
 
-use crate::CoordinateSystem;
+use crate::{CoordinateSystem, ObstacleShape};
 
 new_key_type! {
   /// The ID of a character.
@@ -10,14 +10,31 @@ new_key_type! {
 
 /// A non-agent character. While agents are "managed" by the archipelago,
 /// characters are only as obstacles to be avoided by agents.
-#[derive(Debug)]
 pub struct Character<CS: CoordinateSystem> {
   /// The current position of the character.
   pub position: CS::Coordinate,
   /// The current velocity of the character.
   pub velocity: CS::Coordinate,
-  /// The radius of the character.
+  /// The radius of the character. Unused if the character has a
+  /// [`Self::footprint`].
   pub radius: f32,
+  /// The avoidance layers that the character is in, as a bitmask. Agents only
+  /// avoid the character if their [`crate::Agent::avoidance_mask`] shares a
+  /// layer with it.
+  pub avoidance_layers: u32,
+  /// The shape of the character, for characters that are badly approximated
+  /// by a circle (e.g., vehicles). If [`None`], the character is a circle with
+  /// [`Self::radius`].
+  pub footprint: Option<CharacterFootprint<CS>>,
+}
+
+/// The shape of a non-circular [`Character`].
+pub struct CharacterFootprint<CS: CoordinateSystem> {
+  /// The shape of the character, relative to its position and
+  /// [`Self::rotation`].
+  pub shape: ObstacleShape<CS>,
+  /// The rotation of the character.
+  pub rotation: CS::Rotation,
 }
 
 impl<CS: CoordinateSystem> Default for Character<CS> {
@@ -26,6 +43,44 @@ impl<CS: CoordinateSystem> Default for Character<CS> {
       position: CS::from_landmass(&Vec3::ZERO),
       velocity: CS::from_landmass(&Vec3::ZERO),
       radius: 0.0,
+      avoidance_layers: 1,
+      footprint: None,
     }
   }
 }
+
+// Manual Debug impl to avoid `CS` having a Debug bound itself.
+impl<
+  CS: CoordinateSystem<Coordinate: std::fmt::Debug, Rotation: std::fmt::Debug>,
+> std::fmt::Debug for Character<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Character")
+      .field("position", &self.position)
+      .field("velocity", &self.velocity)
+      .field("radius", &self.radius)
+      .field("avoidance_layers", &self.avoidance_layers)
+      .field("footprint", &self.footprint)
+      .finish()
+  }
+}
+
+// Manual Clone impl to avoid `CS` having a Clone bound itself.
+impl<CS: CoordinateSystem> Clone for CharacterFootprint<CS> {
+  fn clone(&self) -> Self {
+    Self { shape: self.shape.clone(), rotation: self.rotation.clone() }
+  }
+}
+
+// Manual Debug impl to avoid `CS` having a Debug bound itself.
+impl<
+  CS: CoordinateSystem<Coordinate: std::fmt::Debug, Rotation: std::fmt::Debug>,
+> std::fmt::Debug for CharacterFootprint<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("CharacterFootprint")
+      .field("shape", &self.shape)
+      .field("rotation", &self.rotation)
+      .finish()
+  }
+}
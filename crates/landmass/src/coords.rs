@@ -1,13 +1,15 @@
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 
 /// A coordinate system used to convert from a user-facing coordinate system
 /// into landmass's standard coordinate system. The standard coordinate system
 /// is [`crate::coords::XYZ`].
-pub trait CoordinateSystem {
+pub trait CoordinateSystem: Send + Sync {
   /// The user-facing coordinate type.
-  type Coordinate: Clone;
+  type Coordinate: Clone + Send + Sync;
   /// The type to use for point sampling options.
-  type SampleDistance: PointSampleDistance;
+  type SampleDistance: PointSampleDistance + Send + Sync;
+  /// The user-facing rotation type.
+  type Rotation: Clone + Send + Sync;
 
   /// Whether to flip polygons after conversion.
   ///
@@ -21,6 +23,10 @@ pub trait CoordinateSystem {
 
   /// Converts a standard coordinate into this system's coordinate.
   fn from_landmass(v: &Vec3) -> Self::Coordinate;
+
+  /// Converts a rotation in this system to a rotation in the standard
+  /// coordinate system.
+  fn to_landmass_rotation(rotation: &Self::Rotation) -> Quat;
 }
 
 /// A trait to create a default instance based on an agent's radius.
@@ -79,6 +85,7 @@ pub struct XYZ;
 impl CoordinateSystem for XYZ {
   type Coordinate = Vec3;
   type SampleDistance = PointSampleDistance3d;
+  type Rotation = Quat;
 
   const FLIP_POLYGONS: bool = false;
 
@@ -89,6 +96,10 @@ impl CoordinateSystem for XYZ {
   fn from_landmass(v: &Vec3) -> Self::Coordinate {
     *v
   }
+
+  fn to_landmass_rotation(rotation: &Self::Rotation) -> Quat {
+    *rotation
+  }
 }
 
 /// A [`PointSampleDistance`] type for 3D coordinate systems.
@@ -163,6 +174,8 @@ pub struct XY;
 impl CoordinateSystem for XY {
   type Coordinate = Vec2;
   type SampleDistance = f32;
+  /// The rotation (in radians) around the Z axis.
+  type Rotation = f32;
 
   const FLIP_POLYGONS: bool = false;
 
@@ -173,6 +186,10 @@ impl CoordinateSystem for XY {
   fn from_landmass(v: &Vec3) -> Self::Coordinate {
     Vec2::new(v.x, v.y)
   }
+
+  fn to_landmass_rotation(rotation: &Self::Rotation) -> Quat {
+    Quat::from_rotation_z(*rotation)
+  }
 }
 
 impl PointSampleDistance for f32 {
@@ -223,4 +240,15 @@ impl CorePointSampleDistance {
         .vertical_preference_ratio(),
     }
   }
+
+  /// Converts the distances into a frame that is scaled by `scale` (e.g., the
+  /// local frame of a scaled island).
+  pub(crate) fn to_scaled_frame(&self, scale: f32) -> Self {
+    Self {
+      horizontal_distance: self.horizontal_distance / scale,
+      distance_above: self.distance_above / scale,
+      distance_below: self.distance_below / scale,
+      vertical_preference_ratio: self.vertical_preference_ratio,
+    }
+  }
 }
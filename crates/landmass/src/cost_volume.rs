@@ -0,0 +1,123 @@
+use glam::Vec2;
+use slotmap::new_key_type;
+use thiserror::Error;
+
+use crate::{
+  CoordinateSystem, ObstacleShape,
+  obstacle::outline_bounds,
+  util::{BoundingBox, Transform},
+};
+
+new_key_type! {
+  /// The ID of a [`CostVolume`].
+  pub struct CostVolumeId;
+}
+
+/// A volume that changes the cost of the nodes it overlaps, without changing
+/// the navigation meshes themselves.
+///
+/// This is useful for temporary "danger zones" (e.g., fire or enemy
+/// sightlines) that agents should prefer to avoid.
+pub struct CostVolume<CS: CoordinateSystem> {
+  /// The shape of the volume, relative to `transform`.
+  pub shape: ObstacleShape<CS>,
+  /// The transform from the volume's frame to the Archipelago's frame.
+  pub transform: Transform<CS>,
+  /// The vertical extent of the volume (before scaling), starting at the
+  /// translation of `transform`. Only nodes that overlap this vertical range
+  /// are affected.
+  pub height: f32,
+  /// How the volume changes the cost of the nodes it overlaps.
+  pub effect: CostVolumeEffect,
+}
+
+/// How a [`CostVolume`] changes the cost of the nodes it overlaps.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CostVolumeEffect {
+  /// Replaces the type index of the nodes. If several type index volumes
+  /// overlap the same node, the volume with the lowest [`CostVolumeId`] is
+  /// used.
+  TypeIndex(usize),
+  /// Multiplies the cost of the nodes. Multipliers from overlapping volumes
+  /// are combined. Must be positive.
+  CostMultiplier(f32),
+}
+
+/// The cost override of a single node, combining all the [`CostVolume`]s that
+/// overlap it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct NodeCostOverride {
+  /// The type index replacing the node's type index, if any.
+  pub(crate) type_index: Option<usize>,
+  /// The multiplier applied to the cost of the node.
+  pub(crate) cost_multiplier: f32,
+}
+
+impl Default for NodeCostOverride {
+  fn default() -> Self {
+    Self { type_index: None, cost_multiplier: 1.0 }
+  }
+}
+
+// Manual Clone impl to avoid `CS` having a Clone bound itself.
+impl<CS: CoordinateSystem> Clone for CostVolume<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      shape: self.shape.clone(),
+      transform: self.transform.clone(),
+      height: self.height,
+      effect: self.effect,
+    }
+  }
+}
+
+// Manual PartialEq impl to avoid `CS` having a PartialEq bound itself.
+impl<CS: CoordinateSystem<Coordinate: PartialEq, Rotation: PartialEq>> PartialEq
+  for CostVolume<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.shape == other.shape
+      && self.transform == other.transform
+      && self.height == other.height
+      && self.effect == other.effect
+  }
+}
+
+impl<
+  CS: CoordinateSystem<Coordinate: std::fmt::Debug, Rotation: std::fmt::Debug>,
+> std::fmt::Debug for CostVolume<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("CostVolume")
+      .field("shape", &self.shape)
+      .field("transform", &self.transform)
+      .field("height", &self.height)
+      .field("effect", &self.effect)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> CostVolume<CS> {
+  /// Computes the (2D) outline of the volume in world space.
+  pub(crate) fn world_outline(&self) -> Vec<Vec2> {
+    self.shape.world_outline(&self.transform)
+  }
+
+  /// Computes the world space bounds of the volume, including its height.
+  pub(crate) fn world_bounds(&self, outline: &[Vec2]) -> BoundingBox {
+    outline_bounds(outline, &self.transform, self.height)
+  }
+}
+
+/// An error for adding a [`CostVolume`].
+#[derive(Clone, Copy, PartialEq, Error, Debug)]
+pub enum AddCostVolumeError {
+  #[error(
+    "The provided cost multiplier {0} is non-positive. Cost multipliers must be positive."
+  )]
+  NonPositiveCostMultiplier(f32),
+  #[error(
+    "The provided scale {0} is non-positive. Cost volume scales must be positive."
+  )]
+  NonPositiveScale(f32),
+}
@@ -254,7 +254,7 @@ pub fn draw_archipelago_debug<CS: CoordinateSystem>(
   }
 
   for (agent_id, agent) in archipelago.agents.iter() {
-    if agent.paused || agent.using_animation_link {
+    if agent.paused || agent.is_using_animation_link() {
       // Don't render paused agents.
       continue;
     }
@@ -363,6 +363,7 @@ fn draw_path<CS: CoordinateSystem>(
       &CorePointSampleDistance::new(
         &archipelago.archipelago_options.point_sample_distance,
       ),
+      &agent.permitted_type_indices,
     )
     .expect("Path exists, so sampling the agent should be fine.");
   let (target_sample_point, target_node_ref) = archipelago
@@ -372,6 +373,7 @@ fn draw_path<CS: CoordinateSystem>(
       &CorePointSampleDistance::new(
         &archipelago.archipelago_options.point_sample_distance,
       ),
+      &agent.permitted_type_indices,
     )
     .expect("Path exists, so sampling the agent should be fine.");
 
@@ -389,6 +391,7 @@ fn draw_path<CS: CoordinateSystem>(
       agent_sample_point,
       target_corridor_index,
       target_sample_point,
+      agent.radius,
     )
     .1;
   // Convert the path step into the point the agent is walking towards.
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use glam::Vec3;
+use glam::{Quat, Vec3};
 use googletest::{expect_that, matchers::*};
 
 use crate::{
@@ -43,23 +43,24 @@ impl FakeDrawer {
 
 #[googletest::test]
 fn draws_island_meshes_and_agents() {
+  // The portal between the first two nodes is wide enough for the agent.
   let nav_mesh = NavigationMesh {
     vertices: vec![
-      Vec3::new(1.0, 0.0, 0.0),
       Vec3::new(2.0, 0.0, 0.0),
-      Vec3::new(4.0, 1.0, 0.0),
-      Vec3::new(4.0, 2.0, 0.0),
-      Vec3::new(2.0, 3.0, 0.0),
-      Vec3::new(1.0, 3.0, 0.0),
+      Vec3::new(4.0, 0.0, 0.0),
+      Vec3::new(8.0, 2.0, 0.0),
+      Vec3::new(8.0, 4.0, 0.0),
+      Vec3::new(4.0, 6.0, 0.0),
+      Vec3::new(2.0, 6.0, 0.0),
+      Vec3::new(0.0, 4.0, 0.0),
       Vec3::new(0.0, 2.0, 0.0),
-      Vec3::new(0.0, 1.0, 0.0),
-      Vec3::new(1.0, 5.0, 0.0),
-      Vec3::new(2.0, 5.0, 0.0),
-      Vec3::new(2.0, 4.0, 0.0),
-      Vec3::new(3.0, 5.0, 1.0),
-      Vec3::new(3.0, 4.0, 1.0),
-      Vec3::new(3.0, 4.0, -2.0),
-      Vec3::new(3.0, 3.0, -2.0),
+      Vec3::new(2.0, 10.0, 0.0),
+      Vec3::new(4.0, 10.0, 0.0),
+      Vec3::new(4.0, 8.0, 0.0),
+      Vec3::new(6.0, 10.0, 2.0),
+      Vec3::new(6.0, 8.0, 2.0),
+      Vec3::new(6.0, 8.0, -4.0),
+      Vec3::new(6.0, 6.0, -4.0),
     ],
     polygons: vec![
       vec![0, 1, 2, 3, 4, 5, 6, 7],
@@ -74,22 +75,26 @@ fn draws_island_meshes_and_agents() {
   .expect("Mesh is valid.");
 
   let mut archipelago =
-    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
+    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(1.0));
   const TRANSLATION: Vec3 = Vec3::ONE;
   archipelago.add_island(Island::new(
-    Transform { translation: TRANSLATION, rotation: 0.0 },
+    Transform {
+      translation: TRANSLATION,
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::new(nav_mesh),
   ));
 
   let agent_id = archipelago.add_agent(Agent::create(
-    /* position= */ Vec3::new(3.9, 1.5, 0.0) + TRANSLATION,
+    /* position= */ Vec3::new(7.8, 3.0, 0.0) + TRANSLATION,
     /* velocity= */ Vec3::ZERO,
     /* radius= */ 1.0,
     /* desired_speed= */ 1.0,
     /* max_speed= */ 1.0,
   ));
   archipelago.get_agent_mut(agent_id).unwrap().current_target =
-    Some(Vec3::new(1.5, 4.5, 0.0) + TRANSLATION);
+    Some(Vec3::new(3.0, 9.0, 0.0) + TRANSLATION);
 
   // Update so everything is in sync.
   archipelago.update(1.0);
@@ -115,9 +120,9 @@ fn draws_island_meshes_and_agents() {
   expect_that!(
     fake_drawer.points,
     unordered_elements_are!(
-      &(PointType::AgentPosition(agent_id), Vec3::new(3.9, 1.5, 0.0)),
-      &(PointType::TargetPosition(agent_id), Vec3::new(1.5, 4.5, 0.0)),
-      &(PointType::Waypoint(agent_id), Vec3::new(2.0, 3.0, 0.0)),
+      &(PointType::AgentPosition(agent_id), Vec3::new(7.8, 3.0, 0.0)),
+      &(PointType::TargetPosition(agent_id), Vec3::new(3.0, 9.0, 0.0)),
+      &(PointType::Waypoint(agent_id), Vec3::new(3.0, 6.0, 0.0)),
     )
   );
   expect_that!(
@@ -125,95 +130,95 @@ fn draws_island_meshes_and_agents() {
     unordered_elements_are!(
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)]
+        [Vec3::new(0.0, 2.0, 0.0), Vec3::new(2.0, 0.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0)]
+        [Vec3::new(0.0, 4.0, 0.0), Vec3::new(0.0, 2.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)]
+        [Vec3::new(2.0, 0.0, 0.0), Vec3::new(4.0, 0.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(1.0, 3.0, 0.0), Vec3::new(0.0, 2.0, 0.0)]
+        [Vec3::new(2.0, 6.0, 0.0), Vec3::new(0.0, 4.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(1.0, 5.0, 0.0), Vec3::new(1.0, 3.0, 0.0)]
+        [Vec3::new(2.0, 10.0, 0.0), Vec3::new(2.0, 6.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(2.0, 0.0, 0.0), Vec3::new(4.0, 1.0, 0.0)]
+        [Vec3::new(4.0, 0.0, 0.0), Vec3::new(8.0, 2.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(2.0, 3.0, 0.0), Vec3::new(3.0, 3.0, -2.0)]
+        [Vec3::new(4.0, 6.0, 0.0), Vec3::new(6.0, 6.0, -4.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(2.0, 4.0, 0.0), Vec3::new(3.0, 4.0, 1.0)]
+        [Vec3::new(4.0, 8.0, 0.0), Vec3::new(6.0, 8.0, 2.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(2.0, 5.0, 0.0), Vec3::new(1.0, 5.0, 0.0)]
+        [Vec3::new(4.0, 10.0, 0.0), Vec3::new(2.0, 10.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(3.0, 3.0, -2.0), Vec3::new(3.0, 4.0, -2.0)]
+        [Vec3::new(6.0, 6.0, -4.0), Vec3::new(6.0, 8.0, -4.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(3.0, 4.0, -2.0), Vec3::new(2.0, 4.0, 0.0)]
+        [Vec3::new(6.0, 8.0, -4.0), Vec3::new(4.0, 8.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(3.0, 4.0, 1.0), Vec3::new(3.0, 5.0, 1.0)]
+        [Vec3::new(6.0, 8.0, 2.0), Vec3::new(6.0, 10.0, 2.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(3.0, 5.0, 1.0), Vec3::new(2.0, 5.0, 0.0)]
+        [Vec3::new(6.0, 10.0, 2.0), Vec3::new(4.0, 10.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(4.0, 1.0, 0.0), Vec3::new(4.0, 2.0, 0.0)]
+        [Vec3::new(8.0, 2.0, 0.0), Vec3::new(8.0, 4.0, 0.0)]
       ),
       &(
         LineType::BoundaryEdge,
-        [Vec3::new(4.0, 2.0, 0.0), Vec3::new(2.0, 3.0, 0.0)]
+        [Vec3::new(8.0, 4.0, 0.0), Vec3::new(4.0, 6.0, 0.0)]
       ),
       //
       &(
         LineType::ConnectivityEdge,
-        [Vec3::new(2.0, 3.0, 0.0), Vec3::new(1.0, 3.0, 0.0)]
+        [Vec3::new(4.0, 6.0, 0.0), Vec3::new(2.0, 6.0, 0.0)]
       ),
       &(
         LineType::ConnectivityEdge,
-        [Vec3::new(2.0, 3.0, 0.0), Vec3::new(2.0, 4.0, 0.0)]
+        [Vec3::new(4.0, 6.0, 0.0), Vec3::new(4.0, 8.0, 0.0)]
       ),
       &(
         LineType::ConnectivityEdge,
-        [Vec3::new(2.0, 4.0, 0.0), Vec3::new(2.0, 5.0, 0.0)]
+        [Vec3::new(4.0, 8.0, 0.0), Vec3::new(4.0, 10.0, 0.0)]
       ),
       //
       &(
         LineType::AgentCorridor(agent_id),
-        [Vec3::new(3.9, 1.5, 0.0), Vec3::new(1.5, 3.0, 0.0)]
+        [Vec3::new(7.8, 3.0, 0.0), Vec3::new(3.0, 6.0, 0.0)]
       ),
       &(
         LineType::AgentCorridor(agent_id),
-        [Vec3::new(1.5, 3.0, 0.0), Vec3::new(1.5, 4.5, 0.0)]
+        [Vec3::new(3.0, 6.0, 0.0), Vec3::new(3.0, 9.0, 0.0)]
       ),
       //
       &(
         LineType::Target(agent_id),
-        [Vec3::new(3.9, 1.5, 0.0), Vec3::new(1.5, 4.5, 0.0)]
+        [Vec3::new(7.8, 3.0, 0.0), Vec3::new(3.0, 9.0, 0.0)]
       ),
       //
       &(
         LineType::Waypoint(agent_id),
-        [Vec3::new(3.9, 1.5, 0.0), Vec3::new(2.0, 3.0, 0.0)]
+        [Vec3::new(7.8, 3.0, 0.0), Vec3::new(3.0, 6.0, 0.0)]
       ),
     )
   );
@@ -223,169 +228,169 @@ fn draws_island_meshes_and_agents() {
       &(
         TriangleType::Node,
         [
-          Vec3::new(0.0, 1.0, 0.0),
-          Vec3::new(1.0, 0.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(0.0, 2.0, 0.0),
+          Vec3::new(2.0, 0.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
+          Vec3::new(0.0, 4.0, 0.0),
           Vec3::new(0.0, 2.0, 0.0),
-          Vec3::new(0.0, 1.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(1.0, 0.0, 0.0),
           Vec3::new(2.0, 0.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(4.0, 0.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(1.0, 3.0, 0.0),
-          Vec3::new(0.0, 2.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(2.0, 6.0, 0.0),
+          Vec3::new(0.0, 4.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(1.0, 3.0, 0.0),
-          Vec3::new(2.0, 3.0, 0.0),
-          Vec3::new(1.6, 4.0, 0.0)
+          Vec3::new(2.0, 6.0, 0.0),
+          Vec3::new(4.0, 6.0, 0.0),
+          Vec3::new(3.2, 8.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(1.0, 5.0, 0.0),
-          Vec3::new(1.0, 3.0, 0.0),
-          Vec3::new(1.6, 4.0, 0.0)
+          Vec3::new(2.0, 10.0, 0.0),
+          Vec3::new(2.0, 6.0, 0.0),
+          Vec3::new(3.2, 8.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 0.0, 0.0),
-          Vec3::new(4.0, 1.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(4.0, 0.0, 0.0),
+          Vec3::new(8.0, 2.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 3.0, 0.0),
-          Vec3::new(1.0, 3.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(4.0, 6.0, 0.0),
+          Vec3::new(2.0, 6.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 3.0, 0.0),
-          Vec3::new(2.0, 4.0, 0.0),
-          Vec3::new(1.6, 4.0, 0.0)
+          Vec3::new(4.0, 6.0, 0.0),
+          Vec3::new(4.0, 8.0, 0.0),
+          Vec3::new(3.2, 8.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 3.0, 0.0),
-          Vec3::new(3.0, 3.0, -2.0),
-          Vec3::new(2.5, 3.5, -1.0)
+          Vec3::new(4.0, 6.0, 0.0),
+          Vec3::new(6.0, 6.0, -4.0),
+          Vec3::new(5.0, 7.0, -2.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 4.0, 0.0),
-          Vec3::new(2.0, 3.0, 0.0),
-          Vec3::new(2.5, 3.5, -1.0)
+          Vec3::new(4.0, 8.0, 0.0),
+          Vec3::new(4.0, 6.0, 0.0),
+          Vec3::new(5.0, 7.0, -2.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 4.0, 0.0),
-          Vec3::new(2.0, 5.0, 0.0),
-          Vec3::new(1.6, 4.0, 0.0)
+          Vec3::new(4.0, 8.0, 0.0),
+          Vec3::new(4.0, 10.0, 0.0),
+          Vec3::new(3.2, 8.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 4.0, 0.0),
-          Vec3::new(3.0, 4.0, 1.0),
-          Vec3::new(2.5, 4.5, 0.5)
+          Vec3::new(4.0, 8.0, 0.0),
+          Vec3::new(6.0, 8.0, 2.0),
+          Vec3::new(5.0, 9.0, 1.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 5.0, 0.0),
-          Vec3::new(1.0, 5.0, 0.0),
-          Vec3::new(1.6, 4., 0.00)
+          Vec3::new(4.0, 10.0, 0.0),
+          Vec3::new(2.0, 10.0, 0.0),
+          Vec3::new(3.2, 8.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(2.0, 5.0, 0.0),
-          Vec3::new(2.0, 4.0, 0.0),
-          Vec3::new(2.5, 4.5, 0.5)
+          Vec3::new(4.0, 10.0, 0.0),
+          Vec3::new(4.0, 8.0, 0.0),
+          Vec3::new(5.0, 9.0, 1.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(3.0, 3.0, -2.0),
-          Vec3::new(3.0, 4.0, -2.0),
-          Vec3::new(2.5, 3.5, -1.0)
+          Vec3::new(6.0, 6.0, -4.0),
+          Vec3::new(6.0, 8.0, -4.0),
+          Vec3::new(5.0, 7.0, -2.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(3.0, 4.0, -2.0),
-          Vec3::new(2.0, 4.0, 0.0),
-          Vec3::new(2.5, 3.5, -1.0)
+          Vec3::new(6.0, 8.0, -4.0),
+          Vec3::new(4.0, 8.0, 0.0),
+          Vec3::new(5.0, 7.0, -2.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(3.0, 4.0, 1.0),
-          Vec3::new(3.0, 5.0, 1.0),
-          Vec3::new(2.5, 4.5, 0.5)
+          Vec3::new(6.0, 8.0, 2.0),
+          Vec3::new(6.0, 10.0, 2.0),
+          Vec3::new(5.0, 9.0, 1.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(3.0, 5.0, 1.0),
-          Vec3::new(2.0, 5.0, 0.0),
-          Vec3::new(2.5, 4.5, 0.5)
+          Vec3::new(6.0, 10.0, 2.0),
+          Vec3::new(4.0, 10.0, 0.0),
+          Vec3::new(5.0, 9.0, 1.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(4.0, 1.0, 0.0),
-          Vec3::new(4.0, 2.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(8.0, 2.0, 0.0),
+          Vec3::new(8.0, 4.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
       &(
         TriangleType::Node,
         [
-          Vec3::new(4.0, 2.0, 0.0),
-          Vec3::new(2.0, 3.0, 0.0),
-          Vec3::new(1.75, 1.5, 0.0)
+          Vec3::new(8.0, 4.0, 0.0),
+          Vec3::new(4.0, 6.0, 0.0),
+          Vec3::new(3.5, 3.0, 0.0)
         ]
       ),
     )
@@ -414,7 +419,11 @@ fn draws_boundary_links() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -488,7 +497,11 @@ fn draws_animation_links() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let link_id = archipelago.add_animation_link(AnimationLink {
@@ -497,6 +510,8 @@ fn draws_animation_links() {
     kind: 0,
     cost: 1.0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let agent_id = archipelago.add_agent({
     let mut agent =
@@ -91,7 +91,7 @@ fn clips_edge_to_triangle_through() {
       (Vec3::new(0.0, 6.0, 7.0), Vec3::new(12.0, 6.0, 7.0)),
       1.0
     ),
-    Some((0.29166666, 0.70833333))
+    Some((0.29166666, 0.708_333_3))
   );
   expect_eq!(
     clip_edge_to_triangle(
@@ -99,7 +99,7 @@ fn clips_edge_to_triangle_through() {
       (Vec3::new(12.0, 6.0, 7.0), Vec3::new(0.0, 6.0, 7.0)),
       1.0
     ),
-    Some((0.29166666, 0.70833333))
+    Some((0.29166666, 0.708_333_3))
   );
 
   // Perfectly vertical, fully across.
@@ -1,10 +1,12 @@
 use std::sync::Arc;
 
+use glam::Vec3;
 use slotmap::new_key_type;
 
 use crate::{
   CoordinateSystem, ValidNavigationMesh,
-  util::{BoundingBox, Transform},
+  nav_mesh::nav_mesh_node_bbh,
+  util::{BoundingBox, BoundingBoxHierarchy, Transform},
 };
 
 new_key_type! {
@@ -21,21 +23,43 @@ pub struct Island<CS: CoordinateSystem> {
 
   /// The bounds of `nav_mesh` after being transformed by `transform`.
   pub(crate) transformed_bounds: BoundingBox,
+  /// The bounding box hierarchy of the nodes of `nav_mesh`, in the island's
+  /// frame. This is [`None`] if `nav_mesh` has no nodes.
+  pub(crate) node_bbh: Option<BoundingBoxHierarchy<usize>>,
   /// Whether the island has been updated recently.
   pub(crate) dirty: bool,
+  /// Whether the navigation mesh of the island has been replaced recently.
+  /// Unlike [`Self::dirty`], this is not set when only the transform changes,
+  /// since the nodes of the island (and so paths along it) are unchanged.
+  pub(crate) nav_mesh_dirty: bool,
+  /// The transform of the island at the previous update. This is used to
+  /// compute how fast the island is moving.
+  pub(crate) previous_transform: Transform<CS>,
+  /// Whether the island is enabled. Disabled islands are treated as absent
+  /// for pathfinding and sampling.
+  pub(crate) enabled: bool,
+  /// Whether the island has been enabled or disabled since the last update.
+  pub(crate) enabled_dirty: bool,
 }
 
 impl<CS: CoordinateSystem> Island<CS> {
-  /// Creates a new island.
+  /// Creates a new island. The scale of `transform` should be positive.
+  /// Non-positive (or NaN) scales are clamped to a small positive scale.
   pub fn new(
     transform: Transform<CS>,
     nav_mesh: Arc<ValidNavigationMesh<CS>>,
   ) -> Self {
+    let transform = transform.with_clamped_scale();
     Self {
       transformed_bounds: nav_mesh.get_bounds().transform(&transform),
+      node_bbh: node_bbh(&nav_mesh),
+      previous_transform: transform.clone(),
       transform,
       nav_mesh,
       dirty: true,
+      nav_mesh_dirty: true,
+      enabled: true,
+      enabled_dirty: false,
     }
   }
 
@@ -44,9 +68,13 @@ impl<CS: CoordinateSystem> Island<CS> {
     &self.transform
   }
 
-  /// Sets the current transform of the island.
+  /// Sets the current transform of the island. Paths along the island stay
+  /// valid, so this can be called every update to move the island (e.g., an
+  /// elevator or a boat). Links to other islands are still recomputed. The
+  /// scale of `transform` should be positive. Non-positive (or NaN) scales are
+  /// clamped to a small positive scale.
   pub fn set_transform(&mut self, transform: Transform<CS>) {
-    self.transform = transform;
+    self.transform = transform.with_clamped_scale();
     self.dirty = true;
 
     self.transformed_bounds =
@@ -62,8 +90,48 @@ impl<CS: CoordinateSystem> Island<CS> {
   pub fn set_nav_mesh(&mut self, nav_mesh: Arc<ValidNavigationMesh<CS>>) {
     self.nav_mesh = nav_mesh;
     self.dirty = true;
+    self.nav_mesh_dirty = true;
 
     self.transformed_bounds =
       self.nav_mesh.get_bounds().transform(&self.transform);
+    self.node_bbh = node_bbh(&self.nav_mesh);
   }
+
+  /// Returns whether the island is enabled.
+  pub fn is_enabled(&self) -> bool {
+    self.enabled
+  }
+
+  /// Sets whether the island is enabled. A disabled island is treated as
+  /// absent for pathfinding and sampling, but keeps its ID and its links to
+  /// other islands, so re-enabling it is cheap (e.g., for a drawbridge).
+  /// Disabling an island only invalidates paths that use it.
+  pub fn set_enabled(&mut self, enabled: bool) {
+    if self.enabled == enabled {
+      return;
+    }
+    self.enabled = enabled;
+    self.enabled_dirty = true;
+  }
+
+  /// Computes the velocity of the island at `point` (in world space) since the
+  /// previous update. This includes both the translation and rotation of the
+  /// island.
+  pub(crate) fn velocity_at(&self, point: Vec3, delta_time: f32) -> Vec3 {
+    if delta_time <= 0.0 {
+      return Vec3::ZERO;
+    }
+    let previous_point =
+      self.previous_transform.apply(self.transform.apply_inverse(point));
+    (point - previous_point) / delta_time
+  }
+}
+
+/// Creates the bounding box hierarchy of the nodes of `nav_mesh`, or [`None`]
+/// if there are no nodes.
+fn node_bbh<CS: CoordinateSystem>(
+  nav_mesh: &ValidNavigationMesh<CS>,
+) -> Option<BoundingBoxHierarchy<usize>> {
+  (!nav_mesh.polygons.is_empty())
+    .then(|| nav_mesh_node_bbh(nav_mesh, Vec3::ZERO))
 }
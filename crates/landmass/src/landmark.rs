@@ -0,0 +1,442 @@
+use std::{
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap},
+};
+
+use glam::Vec3;
+
+use crate::{
+  CoordinateSystem,
+  nav_data::{KindedOffMeshLink, NavigationData, NodeRef},
+  pathfinding::PathNode,
+  util::FloatOrd,
+};
+
+/// Precomputed costs between every state of the pathfinding graph and a few
+/// "landmark" states. By the triangle inequality, these give a lower bound on
+/// the cost of a path between any two states (the ALT heuristic), which is
+/// much tighter than the straight-line distance when paths need to take long
+/// detours.
+///
+/// The costs are computed on a "relaxed" graph that contains every agent's
+/// graph: every link is included (regardless of permissions, obstacles, or the
+/// agent's radius), and each node costs the default cost of its type index
+/// (ignoring cost volumes). Searches then scale the costs down so they never
+/// exceed the agent's real costs (see [`Landmarks::cost_scale`]).
+pub(crate) struct Landmarks {
+  /// The index of each state in the relaxed graph.
+  state_to_index: HashMap<PathNode, usize>,
+  /// The states of the relaxed graph. This is only used to keep landmarks
+  /// when the landmarks are recomputed.
+  states: Vec<PathNode>,
+  /// The states entering and exiting each node.
+  nodes: HashMap<NodeRef, LandmarkNode>,
+  /// The cost of each type index in the navigation meshes, as used by the
+  /// relaxed graph.
+  type_index_to_cost: HashMap<usize, f32>,
+  /// The landmarks.
+  landmarks: Vec<Landmark>,
+}
+
+/// A node in the relaxed graph.
+struct LandmarkNode {
+  /// The cost per unit of distance to move through the node.
+  cost: f32,
+  /// The states that enter the node, and the point they enter at.
+  entries: Vec<(usize, Vec3)>,
+  /// The states that can be reached from the node.
+  exits: Vec<Exit>,
+}
+
+/// A state that can be reached from a node.
+struct Exit {
+  /// The state that is reached.
+  state: usize,
+  /// The point in the node that the state is reached from.
+  point: Vec3,
+  /// The additional cost of reaching the state (e.g., the cost of an animation
+  /// link).
+  cost: f32,
+}
+
+/// A single landmark state.
+struct Landmark {
+  /// The cost of the cheapest path from the landmark to each state.
+  from_landmark: Vec<f32>,
+  /// The cost of the cheapest path from each state to the landmark.
+  to_landmark: Vec<f32>,
+}
+
+/// The bounds of the costs between the landmarks and the end of a path. This is
+/// computed once per path search.
+pub(crate) struct LandmarkEnd {
+  /// The amount to scale the relaxed costs to make them a lower bound for the
+  /// agent's costs.
+  scale: f32,
+  /// A lower bound of the cost from each landmark to the end point.
+  from_landmarks: Vec<f32>,
+  /// An upper bound of the cost from the end point to each landmark.
+  to_landmarks: Vec<f32>,
+}
+
+/// Computes [`Landmarks`] a few landmarks at a time, so changing islands
+/// doesn't require computing every landmark in a single update.
+pub(crate) struct LandmarksBuilder {
+  /// The landmarks computed so far.
+  landmarks: Landmarks,
+  /// The states to compute landmarks for. The first
+  /// `self.landmarks.landmarks.len()` states have already been computed.
+  landmark_states: Vec<usize>,
+  /// The edges of the relaxed graph from each state.
+  forward_edges: Vec<Vec<(usize, f32)>>,
+  /// The edges of the relaxed graph into each state.
+  backward_edges: Vec<Vec<(usize, f32)>>,
+}
+
+impl LandmarksBuilder {
+  /// Builds the relaxed graph for `nav_data` and selects `landmark_count`
+  /// landmark states. `previous_landmark_states` are reused if they still
+  /// exist. Returns [`None`] if there are no states to put landmarks on.
+  pub(crate) fn new<CS: CoordinateSystem>(
+    nav_data: &NavigationData<CS>,
+    landmark_count: usize,
+    previous_landmark_states: &[PathNode],
+  ) -> Option<Self> {
+    let mut state_to_index = HashMap::new();
+    let mut states = vec![];
+    let mut points = vec![];
+    let mut add_state = |state: PathNode, point: Vec3| {
+      *state_to_index.entry(state).or_insert_with(|| {
+        states.push(state);
+        points.push(point);
+        states.len() - 1
+      })
+    };
+
+    let mut island_ids = nav_data.get_island_ids().collect::<Vec<_>>();
+    island_ids.sort();
+    let mut nodes = HashMap::new();
+    let mut type_index_to_cost = HashMap::new();
+    for island_id in island_ids {
+      let island = nav_data.get_island(island_id).unwrap();
+      for (polygon_index, polygon) in
+        island.nav_mesh.polygons.iter().enumerate()
+      {
+        let cost =
+          nav_data.get_type_index_cost(polygon.type_index).unwrap_or(1.0);
+        // An infinite cost may be overriden to a finite cost by agents, so
+        // treat it as free to keep the relaxed graph a lower bound.
+        let cost = if cost.is_finite() { cost } else { 0.0 };
+        type_index_to_cost.insert(polygon.type_index, cost);
+
+        let node_ref = NodeRef { island_id, polygon_index };
+        let mut node = LandmarkNode { cost, entries: vec![], exits: vec![] };
+        for (edge_index, connectivity) in
+          polygon.connectivity.iter().enumerate()
+        {
+          let Some(connectivity) = connectivity else {
+            continue;
+          };
+          let (i, j) = polygon.get_edge_indices(edge_index);
+          let midpoint = island.transform.apply(
+            island.nav_mesh.vertices[i].midpoint(island.nav_mesh.vertices[j]),
+          );
+          let entry = add_state(
+            PathNode::NodeEdge { node: node_ref, start_edge: edge_index },
+            midpoint,
+          );
+          node.entries.push((entry, midpoint));
+          let exit = add_state(
+            PathNode::NodeEdge {
+              node: NodeRef {
+                island_id,
+                polygon_index: connectivity.polygon_index,
+              },
+              start_edge: connectivity.reverse_edge,
+            },
+            midpoint,
+          );
+          node.exits.push(Exit { state: exit, point: midpoint, cost: 0.0 });
+        }
+        nodes.insert(node_ref, node);
+      }
+    }
+
+    let mut off_mesh_links = nav_data
+      .node_to_off_mesh_link_ids
+      .iter()
+      .flat_map(|(&node_ref, link_ids)| {
+        link_ids.iter().map(move |&link_id| (link_id, node_ref))
+      })
+      .collect::<Vec<_>>();
+    off_mesh_links.sort();
+    for (link_id, node_ref) in off_mesh_links {
+      let link = nav_data.off_mesh_links.get(link_id).unwrap();
+      let (destination_portal, cost) = match &link.kinded {
+        KindedOffMeshLink::BoundaryLink { .. } => (link.portal, 0.0),
+        KindedOffMeshLink::AnimationLink {
+          destination_portal,
+          cost,
+          animation_link,
+          ..
+        } => {
+          // The link may be cheaper while it is busy.
+          let busy_cost = nav_data
+            .get_animation_link(*animation_link)
+            .and_then(|link| link.busy_cost)
+            .unwrap_or(*cost);
+          (*destination_portal, cost.min(busy_cost))
+        }
+      };
+      let destination_point =
+        destination_portal.0.midpoint(destination_portal.1);
+      let state = add_state(PathNode::OffMeshLink(link_id), destination_point);
+      nodes.get_mut(&node_ref).unwrap().exits.push(Exit {
+        state,
+        point: link.portal.0.midpoint(link.portal.1),
+        cost,
+      });
+      nodes
+        .get_mut(&link.destination_node)
+        .unwrap()
+        .entries
+        .push((state, destination_point));
+    }
+
+    if states.is_empty() {
+      return None;
+    }
+
+    let mut forward_edges = vec![vec![]; states.len()];
+    let mut backward_edges = vec![vec![]; states.len()];
+    for node in nodes.values() {
+      for &(entry, entry_point) in node.entries.iter() {
+        for exit in node.exits.iter() {
+          let cost = entry_point.distance(exit.point) * node.cost + exit.cost;
+          forward_edges[entry].push((exit.state, cost));
+          backward_edges[exit.state].push((entry, cost));
+        }
+      }
+    }
+
+    // Keep the previous landmarks that still exist, so the landmarks don't
+    // jump around whenever islands change.
+    let previous_landmark_states = previous_landmark_states
+      .iter()
+      .filter_map(|state| state_to_index.get(state).copied())
+      .collect();
+    let landmark_states =
+      select_landmark_states(&points, landmark_count, previous_landmark_states);
+
+    Some(Self {
+      landmarks: Landmarks {
+        state_to_index,
+        states,
+        nodes,
+        type_index_to_cost,
+        landmarks: vec![],
+      },
+      landmark_states,
+      forward_edges,
+      backward_edges,
+    })
+  }
+
+  /// The states selected to be landmarks, including the ones that have not
+  /// been computed yet.
+  pub(crate) fn landmark_states(&self) -> Vec<PathNode> {
+    self
+      .landmark_states
+      .iter()
+      .map(|&state| self.landmarks.states[state])
+      .collect()
+  }
+
+  /// Computes up to `max_landmarks` more landmarks. Returns true if every
+  /// landmark has been computed.
+  pub(crate) fn compute_landmarks(&mut self, max_landmarks: usize) -> bool {
+    let computed = self.landmarks.landmarks.len();
+    for &state in self.landmark_states.iter().skip(computed).take(max_landmarks)
+    {
+      self.landmarks.landmarks.push(Landmark {
+        from_landmark: shortest_costs(&self.forward_edges, state),
+        to_landmark: shortest_costs(&self.backward_edges, state),
+      });
+    }
+    self.landmarks.landmarks.len() == self.landmark_states.len()
+  }
+
+  /// Returns the landmarks computed so far.
+  pub(crate) fn finish(self) -> Landmarks {
+    self.landmarks
+  }
+}
+
+impl Landmarks {
+  /// Computes the amount to scale the relaxed costs by so they never exceed
+  /// the costs of an agent using `override_type_index_to_cost`.
+  /// `cheapest_type_index_cost` is the cheapest cost of any node for the agent.
+  pub(crate) fn cost_scale<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    override_type_index_to_cost: &HashMap<usize, f32>,
+    cheapest_type_index_cost: f32,
+  ) -> f32 {
+    let agent_cost = |type_index| {
+      override_type_index_to_cost.get(&type_index).copied().unwrap_or_else(
+        || nav_data.get_type_index_cost(type_index).unwrap_or(1.0),
+      )
+    };
+    // Animation link costs are not affected by type indices, so never scale
+    // up.
+    let mut scale = self
+      .type_index_to_cost
+      .iter()
+      .filter(|(_, cost)| **cost > 0.0)
+      .map(|(&type_index, &cost)| agent_cost(type_index) / cost)
+      .fold(1.0, f32::min)
+      * nav_data.cheapest_cost_multiplier();
+    // Cost volumes can replace the type index of a node, so the node may be as
+    // cheap as the cheapest type index.
+    if nav_data.cost_volume_type_indices().next().is_some() {
+      let most_expensive_cost =
+        self.type_index_to_cost.values().copied().fold(0.0, f32::max);
+      if most_expensive_cost > 0.0 {
+        scale = scale.min(cheapest_type_index_cost / most_expensive_cost);
+      }
+    }
+    scale
+  }
+
+  /// Computes the bounds of the costs between the landmarks and `end_point`
+  /// (in `end_node`). The costs are scaled by `scale` (see
+  /// [`Self::cost_scale`]). Returns [`None`] if `end_node` is not known to the
+  /// landmarks.
+  pub(crate) fn end(
+    &self,
+    end_node: NodeRef,
+    end_point: Vec3,
+    scale: f32,
+  ) -> Option<LandmarkEnd> {
+    let node = self.nodes.get(&end_node)?;
+    let (from_landmarks, to_landmarks) = self
+      .landmarks
+      .iter()
+      .map(|landmark| {
+        // Every path to the end point enters the end node first.
+        let from_landmark = node
+          .entries
+          .iter()
+          .map(|&(entry, _)| landmark.from_landmark[entry])
+          .fold(f32::INFINITY, f32::min);
+        let to_landmark = node
+          .exits
+          .iter()
+          .map(|exit| {
+            end_point.distance(exit.point) * node.cost
+              + exit.cost
+              + landmark.to_landmark[exit.state]
+          })
+          .fold(f32::INFINITY, f32::min);
+        (from_landmark, to_landmark)
+      })
+      .unzip();
+    Some(LandmarkEnd { scale, from_landmarks, to_landmarks })
+  }
+
+  /// Estimates the cost from `state` to the end point of `end`. This is
+  /// never more than the real cost (for the agent `end` was computed for).
+  pub(crate) fn estimate(&self, state: &PathNode, end: &LandmarkEnd) -> f32 {
+    let Some(&index) = self.state_to_index.get(state) else {
+      return 0.0;
+    };
+    let mut estimate = 0.0f32;
+    for ((landmark, &from_landmark_to_end), &to_landmark_from_end) in self
+      .landmarks
+      .iter()
+      .zip(end.from_landmarks.iter())
+      .zip(end.to_landmarks.iter())
+    {
+      let from_landmark = landmark.from_landmark[index];
+      if from_landmark.is_finite() && from_landmark_to_end.is_finite() {
+        estimate = estimate.max(from_landmark_to_end - from_landmark);
+      }
+      let to_landmark = landmark.to_landmark[index];
+      if to_landmark.is_finite() && to_landmark_from_end.is_finite() {
+        estimate = estimate.max(to_landmark - to_landmark_from_end);
+      }
+    }
+    estimate * end.scale
+  }
+}
+
+/// Selects up to `landmark_count` states to be landmarks, starting with
+/// `initial_states`. The remaining landmarks are spread out by repeatedly
+/// picking the state farthest from all the landmarks so far.
+fn select_landmark_states(
+  points: &[Vec3],
+  landmark_count: usize,
+  initial_states: Vec<usize>,
+) -> Vec<usize> {
+  let mut landmark_states = initial_states;
+  landmark_states.truncate(landmark_count);
+  let mut distances_to_landmarks = if landmark_states.is_empty() {
+    // Start from the state farthest from an arbitrary state.
+    points.iter().map(|point| point.distance(points[0])).collect::<Vec<_>>()
+  } else {
+    points
+      .iter()
+      .map(|point| {
+        landmark_states
+          .iter()
+          .map(|&state| point.distance(points[state]))
+          .fold(f32::INFINITY, f32::min)
+      })
+      .collect()
+  };
+  while landmark_states.len() < landmark_count {
+    let (next_state, distance) = distances_to_landmarks
+      .iter()
+      .copied()
+      .enumerate()
+      .max_by_key(|&(_, distance)| FloatOrd(distance))
+      .unwrap();
+    if distance == 0.0 {
+      // Every state is already at a landmark.
+      break;
+    }
+    landmark_states.push(next_state);
+    for (distance, point) in
+      distances_to_landmarks.iter_mut().zip(points.iter())
+    {
+      *distance = distance.min(point.distance(points[next_state]));
+    }
+  }
+  landmark_states
+}
+
+/// Computes the cost of the cheapest path from `start` to every state using
+/// `edges` (Dijkstra's algorithm). Unreachable states have an infinite cost.
+fn shortest_costs(edges: &[Vec<(usize, f32)>], start: usize) -> Vec<f32> {
+  let mut costs = vec![f32::INFINITY; edges.len()];
+  costs[start] = 0.0;
+  let mut open = BinaryHeap::new();
+  open.push(Reverse((FloatOrd(0.0), start)));
+  while let Some(Reverse((FloatOrd(cost), state))) = open.pop() {
+    if cost > costs[state] {
+      continue;
+    }
+    for &(next_state, edge_cost) in edges[state].iter() {
+      let next_cost = cost + edge_cost;
+      if next_cost < costs[next_state] {
+        costs[next_state] = next_cost;
+        open.push(Reverse((FloatOrd(next_cost), next_state)));
+      }
+    }
+  }
+  costs
+}
+
+#[cfg(test)]
+#[path = "landmark_test.rs"]
+mod test;
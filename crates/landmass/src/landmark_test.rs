@@ -0,0 +1,347 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::{Vec2, Vec3};
+use googletest::{expect_eq, expect_that, expect_true, matchers::*};
+
+use crate::{
+  Archipelago, ArchipelagoOptions, FromAgentRadius, Island, IslandId,
+  Transform,
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
+  coords::{CorePointSampleDistance, XY},
+  nav_mesh::NavigationMesh,
+  pathfinding::{PathNode, PathResult, find_path},
+};
+
+const GRID_WIDTH: usize = 11;
+const GRID_HEIGHT: usize = 6;
+const WALL_COLUMN: usize = 5;
+
+/// Finds a path across the wall in the grid, from the bottom-left cell to the
+/// bottom-right cell.
+fn find_path_across_wall(
+  archipelago: &Archipelago<XY>,
+  island_id: IslandId,
+  override_type_index_to_cost: &HashMap<usize, f32>,
+) -> PathResult {
+  let node_at = |point: Vec3| {
+    archipelago
+      .nav_data
+      .sample_point(
+        point,
+        &CorePointSampleDistance {
+          horizontal_distance: 0.1,
+          distance_above: 0.1,
+          distance_below: 0.1,
+          vertical_preference_ratio: 1.0,
+        },
+        &PermittedTypeIndices::All,
+      )
+      .unwrap()
+  };
+  let (start_point, start_node) = node_at(Vec3::new(4.5, 0.5, 0.0));
+  let (end_point, end_node) = node_at(Vec3::new(6.5, 0.5, 0.0));
+  assert_eq!(start_node.island_id, island_id);
+  find_path(
+    &archipelago.nav_data,
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    override_type_index_to_cost,
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
+  )
+}
+
+#[googletest::test]
+fn landmarks_reduce_explored_nodes() {
+  // A grid of unit squares, with a wall in the middle column that can only be
+  // passed at the top row. Cells right of the wall have type index 1.
+  let mut vertices = vec![];
+  for y in 0..=GRID_HEIGHT {
+    for x in 0..=GRID_WIDTH {
+      vertices.push(Vec2::new(x as f32, y as f32));
+    }
+  }
+  let vertex = |x: usize, y: usize| y * (GRID_WIDTH + 1) + x;
+  let mut polygons = vec![];
+  let mut polygon_type_indices = vec![];
+  for y in 0..GRID_HEIGHT {
+    for x in 0..GRID_WIDTH {
+      if x == WALL_COLUMN && y < GRID_HEIGHT - 1 {
+        continue;
+      }
+      polygons.push(vec![
+        vertex(x, y),
+        vertex(x + 1, y),
+        vertex(x + 1, y + 1),
+        vertex(x, y + 1),
+      ]);
+      polygon_type_indices.push(if x > WALL_COLUMN { 1 } else { 0 });
+    }
+  }
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices,
+      polygons,
+      polygon_type_indices,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("The grid is valid."),
+  );
+
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.update(1.0);
+  let without_landmarks =
+    find_path_across_wall(&archipelago, island_id, &HashMap::new());
+
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    landmark_count: 4,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // One landmark is computed per update.
+  for _ in 0..4 {
+    archipelago.update(1.0);
+  }
+  expect_eq!(
+    archipelago.nav_data.landmarks.as_ref().unwrap().landmarks.len(),
+    4
+  );
+  let with_landmarks =
+    find_path_across_wall(&archipelago, island_id, &HashMap::new());
+
+  // The landmarks know about the detour around the wall, so the search
+  // doesn't need to explore the cells next to the wall first.
+  expect_that!(without_landmarks.path, some(anything()));
+  expect_eq!(with_landmarks.path, without_landmarks.path);
+  expect_that!(
+    with_landmarks.stats.explored_nodes,
+    lt(without_landmarks.stats.explored_nodes)
+  );
+}
+
+#[googletest::test]
+fn landmarks_are_scaled_for_cheaper_agent_costs() {
+  // A grid of unit squares, with a wall in the middle column that can only be
+  // passed at the top row. Cells right of the wall have type index 1.
+  let mut vertices = vec![];
+  for y in 0..=GRID_HEIGHT {
+    for x in 0..=GRID_WIDTH {
+      vertices.push(Vec2::new(x as f32, y as f32));
+    }
+  }
+  let vertex = |x: usize, y: usize| y * (GRID_WIDTH + 1) + x;
+  let mut polygons = vec![];
+  let mut polygon_type_indices = vec![];
+  for y in 0..GRID_HEIGHT {
+    for x in 0..GRID_WIDTH {
+      if x == WALL_COLUMN && y < GRID_HEIGHT - 1 {
+        continue;
+      }
+      polygons.push(vec![
+        vertex(x, y),
+        vertex(x + 1, y),
+        vertex(x + 1, y + 1),
+        vertex(x, y + 1),
+      ]);
+      polygon_type_indices.push(if x > WALL_COLUMN { 1 } else { 0 });
+    }
+  }
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices,
+      polygons,
+      polygon_type_indices,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("The grid is valid."),
+  );
+
+  let override_type_index_to_cost = HashMap::from([(1, 0.1)]);
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.update(1.0);
+  archipelago.set_type_index_cost(1, 10.0).unwrap();
+  let without_landmarks = find_path_across_wall(
+    &archipelago,
+    island_id,
+    &override_type_index_to_cost,
+  );
+
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    landmark_count: 4,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // One landmark is computed per update.
+  for _ in 0..4 {
+    archipelago.update(1.0);
+  }
+  archipelago.set_type_index_cost(1, 10.0).unwrap();
+  let with_landmarks = find_path_across_wall(
+    &archipelago,
+    island_id,
+    &override_type_index_to_cost,
+  );
+
+  // The landmarks were computed with the expensive cost, but the agent is
+  // still guaranteed to get the cheapest path.
+  expect_that!(without_landmarks.path, some(anything()));
+  expect_eq!(with_landmarks.path, without_landmarks.path);
+}
+
+#[googletest::test]
+fn landmarks_are_kept_when_islands_change() {
+  // A grid of unit squares, with a wall in the middle column that can only be
+  // passed at the top row. Cells right of the wall have type index 1.
+  let mut vertices = vec![];
+  for y in 0..=GRID_HEIGHT {
+    for x in 0..=GRID_WIDTH {
+      vertices.push(Vec2::new(x as f32, y as f32));
+    }
+  }
+  let vertex = |x: usize, y: usize| y * (GRID_WIDTH + 1) + x;
+  let mut polygons = vec![];
+  let mut polygon_type_indices = vec![];
+  for y in 0..GRID_HEIGHT {
+    for x in 0..GRID_WIDTH {
+      if x == WALL_COLUMN && y < GRID_HEIGHT - 1 {
+        continue;
+      }
+      polygons.push(vec![
+        vertex(x, y),
+        vertex(x + 1, y),
+        vertex(x + 1, y + 1),
+        vertex(x, y + 1),
+      ]);
+      polygon_type_indices.push(if x > WALL_COLUMN { 1 } else { 0 });
+    }
+  }
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices,
+      polygons,
+      polygon_type_indices,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("The grid is valid."),
+  );
+
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    landmark_count: 3,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // One landmark is computed per update.
+  for _ in 0..3 {
+    archipelago.update(1.0);
+  }
+  let original_landmark_states = archipelago.nav_data.landmark_states.clone();
+  expect_eq!(original_landmark_states.len(), 3);
+
+  // Add a disconnected island, which changes the landmarks.
+  let other_island_id = archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(0.0, 20.0), rotation: 0.0, scale: 1.0 },
+    archipelago.get_island(island_id).unwrap().get_nav_mesh(),
+  ));
+  archipelago.archipelago_options.landmark_count = 4;
+  archipelago.update(1.0);
+
+  // The original landmarks are kept and a new one is added on the new island,
+  // since it is the farthest away.
+  let new_landmark_states = &archipelago.nav_data.landmark_states;
+  expect_eq!(new_landmark_states[..3], original_landmark_states[..]);
+  let PathNode::NodeEdge { node, .. } = new_landmark_states[3] else {
+    panic!("Expected a node edge, got {:?}", new_landmark_states[3]);
+  };
+  expect_eq!(node.island_id, other_island_id);
+}
+
+#[googletest::test]
+fn landmarks_are_computed_over_several_updates() {
+  // A grid of unit squares, with a wall in the middle column that can only be
+  // passed at the top row. Cells right of the wall have type index 1.
+  let mut vertices = vec![];
+  for y in 0..=GRID_HEIGHT {
+    for x in 0..=GRID_WIDTH {
+      vertices.push(Vec2::new(x as f32, y as f32));
+    }
+  }
+  let vertex = |x: usize, y: usize| y * (GRID_WIDTH + 1) + x;
+  let mut polygons = vec![];
+  let mut polygon_type_indices = vec![];
+  for y in 0..GRID_HEIGHT {
+    for x in 0..GRID_WIDTH {
+      if x == WALL_COLUMN && y < GRID_HEIGHT - 1 {
+        continue;
+      }
+      polygons.push(vec![
+        vertex(x, y),
+        vertex(x + 1, y),
+        vertex(x + 1, y + 1),
+        vertex(x, y + 1),
+      ]);
+      polygon_type_indices.push(if x > WALL_COLUMN { 1 } else { 0 });
+    }
+  }
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices,
+      polygons,
+      polygon_type_indices,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("The grid is valid."),
+  );
+
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    landmark_count: 3,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // One landmark is computed per update.
+  for _ in 0..3 {
+    archipelago.update(1.0);
+  }
+  expect_true!(archipelago.nav_data.landmarks.is_some());
+
+  // Moving the island changes the costs, so the landmarks are recomputed.
+  archipelago.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(0.0, -1.0),
+    rotation: 0.0,
+    scale: 1.0,
+  });
+  archipelago.update(1.0);
+  // The stale landmarks are not used while the new ones are computed, but
+  // paths can still be found with the plain heuristic.
+  expect_true!(archipelago.nav_data.landmarks.is_none());
+  expect_that!(
+    find_path_across_wall(&archipelago, island_id, &HashMap::new()).path,
+    some(anything())
+  );
+
+  archipelago.update(1.0);
+  expect_true!(archipelago.nav_data.landmarks.is_none());
+
+  archipelago.update(1.0);
+  expect_eq!(
+    archipelago.nav_data.landmarks.as_ref().unwrap().landmarks.len(),
+    3
+  );
+}
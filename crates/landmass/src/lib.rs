@@ -3,52 +3,78 @@
 mod agent;
 mod astar;
 mod avoidance;
+mod avoidance_obstacle;
 mod character;
 mod coords;
+mod cost_volume;
 mod geometry;
 mod island;
+mod landmark;
 mod link;
+mod link_generation;
 mod nav_data;
 mod nav_mesh;
+mod obstacle;
 mod path;
 mod pathfinding;
 mod query;
+mod region_graph;
 mod util;
 
-use agent::{RepathResult, does_agent_need_repath};
+use agent::{RepathResult, does_agent_need_repath, repair_path};
 use glam::Vec3Swizzles;
 use path::PathIndex;
+use pathfinding::{PathSearch, PathSearchStatus};
+use rand::Rng;
 use slotmap::DenseSlotMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use nav_data::NavigationData;
 
-pub use glam::Vec3;
+pub use glam::{Quat, Vec3};
 
 pub mod debug;
 
 pub use agent::{
   Agent, AgentId, AgentState, NotReachedAnimationLinkError,
-  PermittedAnimationLinks, ReachedAnimationLink, TargetReachedCondition,
+  PermittedAnimationLinks, PermittedTypeIndices, ReachedAnimationLink,
+  TargetReachedCondition,
 };
-pub use character::{Character, CharacterId};
+pub use avoidance::{
+  AvoidanceAgent, AvoidanceBackend, AvoidanceCharacter, AvoidanceContext,
+  AvoidanceFootprint, AvoidanceNeighbour, AvoidanceVelocities, BorderObstacle,
+  DodgyAvoidance, NoAvoidance, SeparationAvoidance,
+};
+pub use avoidance_obstacle::{AvoidanceObstacle, AvoidanceObstacleId};
+pub use character::{Character, CharacterFootprint, CharacterId};
 pub use coords::{
   CoordinateSystem, FromAgentRadius, PointSampleDistance,
   PointSampleDistance3d, XY, XYZ,
 };
+pub use cost_volume::{
+  AddCostVolumeError, CostVolume, CostVolumeEffect, CostVolumeId,
+};
 pub use island::{Island, IslandId};
 pub use link::{AnimationLink, AnimationLinkId};
-pub use nav_data::{IslandMut, SetTypeIndexCostError};
+pub use link_generation::AnimationLinkGenerationOptions;
+pub use nav_data::{IslandMut, ObstacleMut, SetTypeIndexCostError};
 pub use nav_mesh::{
   HeightNavigationMesh, HeightPolygon, NavigationMesh, ValidNavigationMesh,
   ValidationError,
 };
-pub use query::{FindPathError, PathStep, SamplePointError, SampledPoint};
+pub use obstacle::{Obstacle, ObstacleId, ObstacleShape};
+pub use query::{
+  FindPathError, NearestWall, PartialPath, PathStep, RandomPointError,
+  RaycastResult, ReachableNode, SamplePointError, SampledPoint,
+};
 pub use util::Transform;
 
 use crate::{
-  avoidance::apply_avoidance_to_agents, coords::CorePointSampleDistance,
-  nav_data::NodeRef, path::StraightPathStep,
+  avoidance::{Footprint, apply_avoidance_to_agents},
+  coords::CorePointSampleDistance,
+  nav_data::NodeRef,
+  path::StraightPathStep,
+  util::{for_each_maybe_parallel, map_maybe_parallel},
 };
 
 pub struct Archipelago<CS: CoordinateSystem> {
@@ -56,7 +82,9 @@ pub struct Archipelago<CS: CoordinateSystem> {
   nav_data: NavigationData<CS>,
   agents: DenseSlotMap<AgentId, Agent<CS>>,
   characters: DenseSlotMap<CharacterId, Character<CS>>,
+  avoidance_obstacles: DenseSlotMap<AvoidanceObstacleId, AvoidanceObstacle<CS>>,
   pathing_results: Vec<PathingResult>,
+  avoidance_backend: Box<dyn AvoidanceBackend<CS>>,
 }
 
 /// Options that apply to the entire archipelago.
@@ -76,6 +104,46 @@ pub struct ArchipelagoOptions<CS: CoordinateSystem> {
   /// would mean no avoidance responsibility, but a value of 0.0 is invalid and
   /// may panic. This should be a value between 0.0 and 1.0.
   pub reached_destination_avoidance_responsibility: f32,
+  /// The maximum number of nodes that pathfinding may explore in a single
+  /// update, shared between all agents. Agents whose path could not be found
+  /// within this budget are in the [`AgentState::Pathing`] state, and their
+  /// search continues on the next update. With
+  /// [`Self::hierarchical_pathfinding`], the regions explored to find the
+  /// corridor also count towards this budget. [`None`] means there is no
+  /// limit.
+  pub max_explored_nodes_per_update: Option<u32>,
+  /// The number of landmarks to precompute for the pathfinding heuristic. More
+  /// landmarks make the heuristic more accurate (so pathfinding explores fewer
+  /// nodes), especially when paths take long detours or cross expensive
+  /// nodes. However, each landmark costs memory, and the landmarks are
+  /// recomputed whenever islands (including moving islands) or animation
+  /// links change. One landmark is computed per update, and the plain
+  /// heuristic is used until all of them are computed. A value of 0 disables
+  /// landmarks.
+  pub landmark_count: usize,
+  /// Whether to use hierarchical pathfinding. Paths are first found through a
+  /// coarse graph of the island regions (connected by boundary links and
+  /// animation links), and then pathfinding only explores the regions along
+  /// that path. This explores far fewer nodes on archipelagos with many
+  /// islands, but paths may not be optimal, since the coarse graph only
+  /// estimates the cost through each region.
+  pub hierarchical_pathfinding: bool,
+  /// The maximum number of nodes that repairing an agent's path may explore.
+  /// When the target leaves the agent's path, the path is extended to the
+  /// target instead of being recomputed. When only part of the path ahead of
+  /// the agent is invalidated (e.g., an island along it changed), only that
+  /// part is replaced. If the repair can't find a path within this many nodes,
+  /// the whole path is recomputed. Repaired paths may not be optimal. A value
+  /// of 0 disables path repair.
+  pub max_path_repair_explored_nodes: u32,
+  /// The vertical distance within which agents avoid each other (and
+  /// characters and avoidance obstacles). Neighbours are only avoided if they
+  /// are on a walkable layer shared with the agent: on a node connected to the
+  /// agent's node by walkable ground (the nav mesh or boundary links between
+  /// islands, but not animation links). This prevents agents on different
+  /// floors (e.g., a balcony and the floor below) from avoiding each other.
+  /// [`None`] means every neighbour is avoided.
+  pub avoidance_vertical_tolerance: Option<f32>,
 }
 
 impl<CS: CoordinateSystem<SampleDistance: FromAgentRadius>> FromAgentRadius
@@ -88,6 +156,11 @@ impl<CS: CoordinateSystem<SampleDistance: FromAgentRadius>> FromAgentRadius
       avoidance_time_horizon: 0.5,
       obstacle_avoidance_time_horizon: 0.25,
       reached_destination_avoidance_responsibility: 0.1,
+      max_explored_nodes_per_update: None,
+      landmark_count: 0,
+      hierarchical_pathfinding: false,
+      max_path_repair_explored_nodes: 0,
+      avoidance_vertical_tolerance: None,
     }
   }
 }
@@ -99,10 +172,21 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       nav_data: NavigationData::new(),
       agents: DenseSlotMap::with_key(),
       characters: DenseSlotMap::with_key(),
+      avoidance_obstacles: DenseSlotMap::with_key(),
       pathing_results: Vec::new(),
+      avoidance_backend: Box::new(DodgyAvoidance),
     }
   }
 
+  /// Sets the backend used for local avoidance. By default, this is
+  /// [`DodgyAvoidance`].
+  pub fn set_avoidance_backend(
+    &mut self,
+    avoidance_backend: impl AvoidanceBackend<CS> + 'static,
+  ) {
+    self.avoidance_backend = Box::new(avoidance_backend);
+  }
+
   pub fn add_agent(&mut self, agent: Agent<CS>) -> AgentId {
     self.agents.insert(agent)
   }
@@ -157,6 +241,44 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     self.characters.keys()
   }
 
+  /// Adds an obstacle that agents avoid locally, without changing their paths.
+  pub fn add_avoidance_obstacle(
+    &mut self,
+    avoidance_obstacle: AvoidanceObstacle<CS>,
+  ) -> AvoidanceObstacleId {
+    self.avoidance_obstacles.insert(avoidance_obstacle)
+  }
+
+  pub fn remove_avoidance_obstacle(
+    &mut self,
+    avoidance_obstacle_id: AvoidanceObstacleId,
+  ) {
+    self
+      .avoidance_obstacles
+      .remove(avoidance_obstacle_id)
+      .expect("Avoidance obstacle should be present in the archipelago");
+  }
+
+  pub fn get_avoidance_obstacle(
+    &self,
+    avoidance_obstacle_id: AvoidanceObstacleId,
+  ) -> Option<&AvoidanceObstacle<CS>> {
+    self.avoidance_obstacles.get(avoidance_obstacle_id)
+  }
+
+  pub fn get_avoidance_obstacle_mut(
+    &mut self,
+    avoidance_obstacle_id: AvoidanceObstacleId,
+  ) -> Option<&mut AvoidanceObstacle<CS>> {
+    self.avoidance_obstacles.get_mut(avoidance_obstacle_id)
+  }
+
+  pub fn get_avoidance_obstacle_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = AvoidanceObstacleId> + '_ {
+    self.avoidance_obstacles.keys()
+  }
+
   pub fn add_island(&mut self, island: Island<CS>) -> IslandId {
     self.nav_data.add_island(island)
   }
@@ -198,12 +320,102 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     self.nav_data.get_animation_link(link_id)
   }
 
+  /// Sets whether the animation link is enabled. Disabled animation links are
+  /// never taken, but keep their ID and the portals they connect, so toggling
+  /// a link (e.g., a door opening and closing) is cheaper than removing and
+  /// re-adding it. Disabling a link only invalidates paths that use it. Panics
+  /// if the animation link ID is not in the archipelago.
+  pub fn set_animation_link_enabled(
+    &mut self,
+    link_id: AnimationLinkId,
+    enabled: bool,
+  ) {
+    self.nav_data.set_animation_link_enabled(link_id, enabled)
+  }
+
+  /// Returns whether the animation link is enabled, or [`None`] if the
+  /// animation link ID is not in the archipelago.
+  pub fn is_animation_link_enabled(
+    &self,
+    link_id: AnimationLinkId,
+  ) -> Option<bool> {
+    self.nav_data.is_animation_link_enabled(link_id)
+  }
+
   pub fn get_animation_link_ids(
     &self,
   ) -> impl ExactSizeIterator<Item = AnimationLinkId> {
     self.nav_data.get_animation_link_ids()
   }
 
+  /// Generates animation links for dropping down ledges and jumping across
+  /// gaps, by checking the boundary edges of every enabled island. The links
+  /// are not added to the archipelago, so they can be inspected (or baked)
+  /// first, and then added with [`Self::add_animation_link`]. The result is
+  /// deterministic for the same islands (added in the same order).
+  pub fn generate_animation_links(
+    &self,
+    options: &AnimationLinkGenerationOptions,
+  ) -> Vec<AnimationLink<CS>> {
+    link_generation::generate_animation_links(&self.nav_data, options)
+  }
+
+  /// Adds an obstacle that carves a hole into the navigation meshes it
+  /// overlaps. The obstacle takes effect on the next [`Self::update`].
+  pub fn add_obstacle(&mut self, obstacle: Obstacle<CS>) -> ObstacleId {
+    self.nav_data.add_obstacle(obstacle)
+  }
+
+  /// Removes the obstacle with `obstacle_id`, restoring the nodes it carved.
+  pub fn remove_obstacle(&mut self, obstacle_id: ObstacleId) {
+    self.nav_data.remove_obstacle(obstacle_id)
+  }
+
+  pub fn get_obstacle(&self, obstacle_id: ObstacleId) -> Option<&Obstacle<CS>> {
+    self.nav_data.get_obstacle(obstacle_id)
+  }
+
+  pub fn get_obstacle_mut(
+    &mut self,
+    obstacle_id: ObstacleId,
+  ) -> Option<ObstacleMut<'_, CS>> {
+    self.nav_data.get_obstacle_mut(obstacle_id)
+  }
+
+  pub fn get_obstacle_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = ObstacleId> + '_ {
+    self.nav_data.get_obstacle_ids()
+  }
+
+  /// Adds a volume that changes the cost of the nodes it overlaps. The volume
+  /// takes effect on the next [`Self::update`].
+  pub fn add_cost_volume(
+    &mut self,
+    cost_volume: CostVolume<CS>,
+  ) -> Result<CostVolumeId, AddCostVolumeError> {
+    self.nav_data.add_cost_volume(cost_volume)
+  }
+
+  /// Removes the cost volume with `cost_volume_id`, restoring the costs of the
+  /// nodes it overlapped.
+  pub fn remove_cost_volume(&mut self, cost_volume_id: CostVolumeId) {
+    self.nav_data.remove_cost_volume(cost_volume_id)
+  }
+
+  pub fn get_cost_volume(
+    &self,
+    cost_volume_id: CostVolumeId,
+  ) -> Option<&CostVolume<CS>> {
+    self.nav_data.get_cost_volume(cost_volume_id)
+  }
+
+  pub fn get_cost_volume_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = CostVolumeId> + '_ {
+    self.nav_data.get_cost_volume_ids()
+  }
+
   /// Sets the cost of `type_index` to `cost`. The cost is a multiplier on the
   /// distance travelled along this node (essentially the cost per meter).
   /// Agents will prefer to travel along low-cost terrain.
@@ -233,16 +445,20 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
   }
 
   /// Finds the nearest point on the navigation meshes to (and within
-  /// `distance_to_node` of) `point`.
+  /// `distance_to_node` of) `point`. Only nodes whose type index is in
+  /// `permitted_type_indices` are considered, so an agent with the same
+  /// permitted type indices could stand on the sampled point.
   pub fn sample_point(
     &self,
     point: CS::Coordinate,
     point_sample_distance: &CS::SampleDistance,
+    permitted_type_indices: PermittedTypeIndices,
   ) -> Result<SampledPoint<'_, CS>, SamplePointError> {
     query::sample_point(
       self,
       point,
       &CorePointSampleDistance::new(point_sample_distance),
+      &permitted_type_indices,
     )
   }
 
@@ -250,13 +466,15 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
   /// meshes. Only [`SampledPoint`]s from this archipelago are supported. This
   /// should only be used for querying (e.g., finding the walking distance to an
   /// object), not for controlling movement. For controlling movement, use
-  /// agents.
+  /// agents. Nodes whose type index is not in `permitted_type_indices` are
+  /// never entered.
   pub fn find_path(
     &self,
     start_point: &SampledPoint<'_, CS>,
     end_point: &SampledPoint<'_, CS>,
     override_type_index_costs: &HashMap<usize, f32>,
     permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
   ) -> Result<Vec<PathStep<CS>>, FindPathError> {
     query::find_path(
       self,
@@ -264,14 +482,171 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       end_point,
       override_type_index_costs,
       permitted_animation_links,
+      permitted_type_indices,
+      /* allow_partial_path= */ false,
+    )
+    .map(|path| path.steps)
+  }
+
+  /// Same as [`Self::find_path`], except if `end_point` is unreachable, the
+  /// returned path leads to the reachable point closest to `end_point`
+  /// instead, and is marked as [`PartialPath::is_partial`].
+  pub fn find_partial_path(
+    &self,
+    start_point: &SampledPoint<'_, CS>,
+    end_point: &SampledPoint<'_, CS>,
+    override_type_index_costs: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+  ) -> Result<PartialPath<CS>, FindPathError> {
+    query::find_path(
+      self,
+      start_point,
+      end_point,
+      override_type_index_costs,
+      permitted_animation_links,
+      permitted_type_indices,
+      /* allow_partial_path= */ true,
+    )
+  }
+
+  /// Finds every node that can be reached from `start_point` with a path cost
+  /// of at most `max_cost`, along with the cost to reach each of them. The
+  /// cost of a node is the cost of the path to where it enters the node (or 0
+  /// for the node containing `start_point`). The nodes are sorted by cost.
+  /// Nodes whose type index is not in `permitted_type_indices` are never
+  /// entered. This is useful for building cost fields (e.g., influence maps).
+  /// Only [`SampledPoint`]s from this archipelago are supported.
+  pub fn find_reachable_nodes<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    max_cost: f32,
+    override_type_index_costs: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+  ) -> Result<Vec<ReachableNode<'a, CS>>, FindPathError> {
+    query::find_reachable_nodes(
+      self,
+      start_point,
+      max_cost,
+      override_type_index_costs,
+      permitted_animation_links,
+      permitted_type_indices,
+    )
+  }
+
+  /// Finds the cost of the cheapest path from `start_point` to each of
+  /// `end_points`. This runs a single search for all the end points, so it is
+  /// much cheaper than calling [`Self::find_path`] for each of them. The
+  /// result is in the same order as `end_points`, and is None for end points
+  /// that are unreachable. Only [`SampledPoint`]s from this archipelago are
+  /// supported.
+  pub fn find_path_costs<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    end_points: &[SampledPoint<'a, CS>],
+    override_type_index_costs: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+  ) -> Result<Vec<Option<f32>>, FindPathError> {
+    query::find_path_costs(
+      self,
+      start_point,
+      end_points,
+      override_type_index_costs,
+      permitted_animation_links,
+      permitted_type_indices,
+    )
+  }
+
+  /// Casts a ray along the navigation meshes from `start_point` towards
+  /// `end_point`, to check whether an agent could walk in a straight line
+  /// between them. The ray is only checked horizontally. Only
+  /// [`SampledPoint`]s from this archipelago are supported.
+  pub fn raycast<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    end_point: CS::Coordinate,
+  ) -> RaycastResult<'a, CS> {
+    query::raycast(self, start_point, end_point)
+  }
+
+  /// Finds the nearest point on the boundary of the navigation meshes (a
+  /// "wall") to `point`, within `max_radius`. Seams between islands connected
+  /// by boundary links are not walls. Returns None if there is no wall within
+  /// `max_radius`. Only [`SampledPoint`]s from this archipelago are supported.
+  pub fn find_distance_to_wall(
+    &self,
+    point: &SampledPoint<'_, CS>,
+    max_radius: f32,
+  ) -> Option<NearestWall<CS>> {
+    query::find_distance_to_wall(self, point, max_radius)
+  }
+
+  /// Picks a uniformly random point on the navigation meshes. Only nodes whose
+  /// type index is in `permitted_type_indices` are considered.
+  pub fn random_point(
+    &self,
+    permitted_type_indices: PermittedTypeIndices,
+    rng: &mut impl Rng,
+  ) -> Result<SampledPoint<'_, CS>, RandomPointError> {
+    query::random_point(self, &permitted_type_indices, rng)
+  }
+
+  /// Picks a uniformly random point within `radius` of `start_point` that can
+  /// be reached from `start_point`. Only nodes whose type index is in
+  /// `permitted_type_indices` are considered. Only [`SampledPoint`]s from this
+  /// archipelago are supported.
+  pub fn random_point_in_radius<'a>(
+    &'a self,
+    start_point: &SampledPoint<'a, CS>,
+    radius: f32,
+    permitted_type_indices: PermittedTypeIndices,
+    permitted_animation_links: PermittedAnimationLinks,
+    rng: &mut impl Rng,
+  ) -> Result<SampledPoint<'a, CS>, RandomPointError> {
+    query::random_point_in_radius(
+      self,
+      start_point,
+      radius,
+      &permitted_type_indices,
+      permitted_animation_links,
+      rng,
     )
   }
 
   pub fn update(&mut self, delta_time: f32) {
     self.pathing_results.clear();
+    self.nav_data.set_landmark_count(self.archipelago_options.landmark_count);
+    self.nav_data.set_hierarchical_pathfinding(
+      self.archipelago_options.hierarchical_pathfinding,
+    );
+
+    // Agents that reached an animation link last update keep their spot on the
+    // link (as long as they still reach it).
+    let previously_reached_animation_links = self
+      .agents
+      .iter()
+      .filter_map(|(agent_id, agent)| {
+        agent
+          .current_animation_link
+          .as_ref()
+          .map(|animation_link| (agent_id, animation_link.link_id))
+      })
+      .collect::<HashMap<_, _>>();
+    let mut animation_link_users = HashMap::<AnimationLinkId, usize>::new();
+    for link_id in
+      self.agents.values().filter_map(|agent| agent.using_animation_link)
+    {
+      *animation_link_users.entry(link_id).or_default() += 1;
+    }
+    self.update_busy_animation_link_costs(
+      &animation_link_users,
+      &previously_reached_animation_links,
+    );
 
     // TODO: make the edge_link_distance configurable.
-    let (invalidated_off_mesh_links, invalidated_islands) =
+    let (invalidated_off_mesh_links, invalidated_islands, invalidated_nodes) =
       self.nav_data.update(
         /* edge_link_distance= */ 0.01,
         self
@@ -280,16 +655,39 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
           .animation_link_max_vertical_distance(),
       );
 
+    // In-progress path searches can only continue if the nodes and links they
+    // reached are unchanged. Partial paths are dropped if the connectivity may
+    // have changed, since the target may have become reachable.
+    let connectivity_changed = self.nav_data.regions_changed
+      || !invalidated_islands.is_empty()
+      || !invalidated_nodes.is_empty();
+    for agent in self.agents.values_mut() {
+      if agent.current_path_search.as_ref().is_some_and(|path_search| {
+        path_search.is_invalidated(
+          &invalidated_off_mesh_links,
+          &invalidated_islands,
+          &invalidated_nodes,
+        )
+      }) {
+        agent.current_path_search = None;
+      }
+      if connectivity_changed && agent.partial_path_target_node.is_some() {
+        agent.current_path = None;
+      }
+    }
+
     let mut agent_id_to_agent_node = HashMap::new();
     let mut agent_id_to_target_node = HashMap::new();
 
     for (agent_id, agent) in self.agents.iter_mut() {
+      agent.current_island = None;
+      agent.platform_velocity = CS::from_landmass(&Vec3::ZERO);
       if agent.paused {
         // We don't care to sample the agent location if the agent is paused.
         agent.state = AgentState::Paused;
         continue;
       }
-      if agent.using_animation_link {
+      if agent.is_using_animation_link() {
         // We don't care to sample the agent location if the agent is using an
         // animation link.
         agent.state = AgentState::UsingAnimationLink;
@@ -300,10 +698,20 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         &CorePointSampleDistance::new(
           &self.archipelago_options.point_sample_distance,
         ),
+        &agent.permitted_type_indices,
       ) {
         None => continue,
         Some(node_and_point) => node_and_point,
       };
+      let agent_island_id = agent_node_and_point.1.island_id;
+      agent.current_island = Some(agent_island_id);
+      agent.platform_velocity = CS::from_landmass(
+        &self
+          .nav_data
+          .get_island(agent_island_id)
+          .unwrap()
+          .velocity_at(agent_node_and_point.0, delta_time),
+      );
       let inserted =
         agent_id_to_agent_node.insert(agent_id, agent_node_and_point).is_none();
       debug_assert!(inserted);
@@ -314,6 +722,7 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
           &CorePointSampleDistance::new(
             &self.archipelago_options.point_sample_distance,
           ),
+          &agent.permitted_type_indices,
         ) {
           None => continue,
           Some(node_and_point) => node_and_point,
@@ -326,35 +735,93 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       }
     }
 
-    let mut character_id_to_nav_mesh_point = HashMap::new();
+    // All agents have computed their platform velocity, so we no longer need
+    // the previous transforms.
+    self.nav_data.store_previous_island_transforms();
+
+    let mut character_id_to_character_node = HashMap::new();
     for (character_id, character) in self.characters.iter() {
-      let character_point = match self.nav_data.sample_point(
+      let character_point_and_node = match self.nav_data.sample_point(
         CS::to_landmass(&character.position),
         &CorePointSampleDistance::new(
           &self.archipelago_options.point_sample_distance,
         ),
+        &PermittedTypeIndices::All,
       ) {
         None => continue,
-        Some(point_and_node) => point_and_node.0,
+        Some(point_and_node) => point_and_node,
+      };
+      character_id_to_character_node
+        .insert(character_id, character_point_and_node);
+    }
+
+    let mut footprints = self
+      .characters
+      .iter()
+      .filter_map(|(character_id, character)| {
+        let footprint = character.footprint.as_ref()?;
+        let character_node =
+          *character_id_to_character_node.get(&character_id)?;
+        Some(Footprint::new(
+          AvoidanceNeighbour::Character(character_id),
+          footprint.shape.world_outline(&Transform {
+            translation: character.position.clone(),
+            rotation: footprint.rotation.clone(),
+            scale: 1.0,
+          }),
+          character_node,
+          CS::to_landmass(&character.velocity),
+          character.avoidance_layers,
+        ))
+      })
+      .collect::<Vec<_>>();
+    for (avoidance_obstacle_id, avoidance_obstacle) in
+      self.avoidance_obstacles.iter()
+    {
+      let Some(obstacle_node) = self.nav_data.sample_point(
+        CS::to_landmass(&avoidance_obstacle.transform.translation),
+        &CorePointSampleDistance::new(
+          &self.archipelago_options.point_sample_distance,
+        ),
+        &PermittedTypeIndices::All,
+      ) else {
+        continue;
       };
-      character_id_to_nav_mesh_point.insert(character_id, character_point);
+      footprints.push(Footprint::new(
+        AvoidanceNeighbour::Obstacle(avoidance_obstacle_id),
+        avoidance_obstacle.shape.world_outline(
+          &avoidance_obstacle.transform.clone().with_clamped_scale(),
+        ),
+        obstacle_node,
+        CS::to_landmass(&avoidance_obstacle.velocity),
+        avoidance_obstacle.avoidance_layers,
+      ));
     }
 
     let mut agent_id_to_follow_path_indices = HashMap::new();
+    let mut agents_needing_repath = HashSet::new();
+    let mut agents_needing_repair = Vec::new();
+    let max_path_repair_explored_nodes =
+      self.archipelago_options.max_path_repair_explored_nodes;
 
     for (agent_id, agent) in self.agents.iter_mut() {
       // Clear the animation link whether the agent is paused or not. If we
       // still reached the same animation link, we'll re-set it.
       agent.current_animation_link = None;
 
-      if agent.paused || agent.using_animation_link {
+      if agent.paused || agent.is_using_animation_link() {
         if let Some(path) = agent.current_path.as_ref()
-          && !path.is_valid(&invalidated_off_mesh_links, &invalidated_islands)
+          && !path.is_valid(
+            &invalidated_off_mesh_links,
+            &invalidated_islands,
+            &invalidated_nodes,
+          )
         {
           // If the path has been invalidated, clear the path to keep the agent
           // consistent.
           agent.current_path = None;
         }
+        agent.current_path_search = None;
         continue;
       }
       let agent_point_and_node = agent_id_to_agent_node.get(&agent_id);
@@ -365,12 +832,25 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         target_point_and_node.map(|(_, node)| *node),
         &invalidated_off_mesh_links,
         &invalidated_islands,
+        &invalidated_nodes,
       ) {
         RepathResult::DoNothing => {}
         RepathResult::FollowPath(
           agent_node_in_corridor,
           target_node_in_corridor,
         ) => {
+          agent.current_path_search = None;
+          if max_path_repair_explored_nodes > 0
+            && agent.partial_path_target_node.is_none()
+          {
+            // Drop the rest of the path past the target, so if the target
+            // leaves the path, the path is extended from near the target.
+            agent
+              .current_path
+              .as_mut()
+              .unwrap()
+              .truncate(target_node_in_corridor);
+          }
           agent_id_to_follow_path_indices.insert(
             agent_id,
             (agent_node_in_corridor, target_node_in_corridor),
@@ -379,97 +859,260 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         RepathResult::ClearPathNoTarget => {
           agent.state = AgentState::Idle;
           agent.current_path = None;
+          agent.current_path_search = None;
         }
         RepathResult::ClearPathBadAgent => {
           agent.state = AgentState::AgentNotOnNavMesh;
           agent.current_path = None;
+          agent.current_path_search = None;
         }
         RepathResult::ClearPathBadTarget => {
           agent.state = AgentState::TargetNotOnNavMesh;
           agent.current_path = None;
+          agent.current_path_search = None;
+        }
+        RepathResult::RepairPath(agent_node_in_corridor, path_repair) => {
+          agent.current_path_search = None;
+          if max_path_repair_explored_nodes > 0 {
+            agents_needing_repair.push((
+              agent_id,
+              agent_node_in_corridor,
+              path_repair,
+            ));
+          } else {
+            agent.current_path = None;
+            agents_needing_repath.insert(agent_id);
+          }
         }
         RepathResult::NeedsRepath => {
           agent.current_path = None;
+          agents_needing_repath.insert(agent_id);
+        }
+      }
+    }
 
-          let (agent_point, agent_node) = agent_point_and_node.unwrap();
-          let (target_point, target_node) = target_point_and_node.unwrap();
-          let path_result = pathfinding::find_path(
-            &self.nav_data,
-            *agent_node,
-            *agent_point,
-            *target_node,
-            *target_point,
-            &agent.override_type_index_to_cost,
-            agent.permitted_animation_links.clone(),
-          );
+    // Repairs are small searches, so they go before the full searches and
+    // share the same budget.
+    let mut remaining_explored_nodes =
+      self.archipelago_options.max_explored_nodes_per_update;
+    for (agent_id, agent_node_in_corridor, path_repair) in agents_needing_repair
+    {
+      let agent = self.agents.get_mut(agent_id).unwrap();
+      let (_, agent_node) = agent_id_to_agent_node[&agent_id];
+      let (target_point, target_node) = agent_id_to_target_node[&agent_id];
+      let (repaired, explored_nodes) = repair_path(
+        agent,
+        &self.nav_data,
+        agent_node_in_corridor,
+        path_repair,
+        target_node,
+        target_point,
+        remaining_explored_nodes.map_or(
+          max_path_repair_explored_nodes,
+          |remaining_explored_nodes| {
+            remaining_explored_nodes.min(max_path_repair_explored_nodes)
+          },
+        ),
+      );
+      if let Some(remaining_explored_nodes) = remaining_explored_nodes.as_mut()
+      {
+        *remaining_explored_nodes =
+          remaining_explored_nodes.saturating_sub(explored_nodes);
+      }
+      if !repaired {
+        agent.current_path = None;
+        agents_needing_repath.insert(agent_id);
+        continue;
+      }
 
-          self.pathing_results.push(PathingResult {
-            agent: agent_id,
-            success: path_result.path.is_some(),
-            explored_nodes: path_result.stats.explored_nodes,
-          });
+      let path = agent.current_path.as_ref().unwrap();
+      agent_id_to_follow_path_indices.insert(
+        agent_id,
+        (
+          path.find_index_of_node(agent_node).unwrap(),
+          path.find_index_of_node_rev(target_node).unwrap(),
+        ),
+      );
+    }
 
-          let Some(new_path) = path_result.path else {
-            agent.state = AgentState::NoPath;
-            continue;
-          };
+    let mut agents_needing_repath = self
+      .agents
+      .iter_mut()
+      .filter(|(agent_id, _)| agents_needing_repath.contains(agent_id))
+      .collect::<Vec<_>>();
+    // Agents that already started searching go first, so that they eventually
+    // finish even if the budget is small.
+    agents_needing_repath
+      .sort_by_key(|(_, agent)| agent.current_path_search.is_none());
 
-          agent_id_to_follow_path_indices.insert(
-            agent_id,
-            (PathIndex::from_corridor_index(0, 0), new_path.last_index()),
-          );
-          agent.current_path = Some(new_path);
+    let nav_data = &self.nav_data;
+    let start_path_search = |agent_id: AgentId, agent: &mut Agent<CS>| {
+      let (agent_point, agent_node) = agent_id_to_agent_node[&agent_id];
+      let (target_point, target_node) = agent_id_to_target_node[&agent_id];
+      match agent.current_path_search.take() {
+        Some(path_search)
+          if path_search.start_node() == agent_node
+            && path_search.end_node() == target_node
+            && path_search.allow_partial_path()
+              == agent.allow_partial_paths =>
+        {
+          path_search
         }
+        _ => PathSearch::new(
+          nav_data,
+          agent_node,
+          agent_point,
+          target_node,
+          target_point,
+          &agent.override_type_index_to_cost,
+          agent.permitted_animation_links.clone(),
+          agent.permitted_type_indices.clone(),
+          agent.radius,
+          agent.allow_partial_paths,
+        ),
+      }
+    };
+
+    // Each entry is `None` if the agent's search did not finish in this update.
+    let path_results = match remaining_explored_nodes {
+      // Without a budget, searches don't affect each other, so they can be run
+      // in parallel.
+      None => {
+        map_maybe_parallel(&mut agents_needing_repath, |(agent_id, agent)| {
+          match start_path_search(*agent_id, agent).resume(nav_data, u32::MAX) {
+            PathSearchStatus::Incomplete(_) => {
+              unreachable!("an unlimited search always completes")
+            }
+            PathSearchStatus::Complete(path_result) => Some(path_result),
+          }
+        })
       }
+      Some(mut remaining_explored_nodes) => agents_needing_repath
+        .iter_mut()
+        .map(|(agent_id, agent)| {
+          let path_search = start_path_search(*agent_id, agent);
+          if remaining_explored_nodes == 0 {
+            agent.current_path_search = Some(path_search);
+            return None;
+          }
+
+          let previously_explored_nodes = path_search.explored_nodes();
+          match path_search.resume(nav_data, remaining_explored_nodes) {
+            PathSearchStatus::Incomplete(path_search) => {
+              remaining_explored_nodes = 0;
+              agent.current_path_search = Some(*path_search);
+              None
+            }
+            PathSearchStatus::Complete(path_result) => {
+              remaining_explored_nodes = remaining_explored_nodes
+                .saturating_sub(
+                  path_result.stats.explored_nodes - previously_explored_nodes,
+                );
+              Some(path_result)
+            }
+          }
+        })
+        .collect(),
+    };
+
+    for ((agent_id, agent), path_result) in
+      agents_needing_repath.into_iter().zip(path_results)
+    {
+      let Some(path_result) = path_result else {
+        agent.state = AgentState::Pathing;
+        continue;
+      };
+
+      self.pathing_results.push(PathingResult {
+        agent: agent_id,
+        success: path_result.path.is_some(),
+        explored_nodes: path_result.stats.explored_nodes,
+      });
+
+      let (new_path, partial_path_target_node) =
+        match (path_result.path, path_result.partial_path) {
+          (Some(path), _) => (path, None),
+          (None, Some(partial_path)) => {
+            (partial_path, Some(agent_id_to_target_node[&agent_id].1))
+          }
+          (None, None) => {
+            agent.state = AgentState::NoPath;
+            continue;
+          }
+        };
+
+      agent_id_to_follow_path_indices.insert(
+        agent_id,
+        (PathIndex::from_corridor_index(0, 0), new_path.last_index()),
+      );
+      agent.current_path = Some(new_path);
+      agent.partial_path_target_node = partial_path_target_node;
     }
 
-    for (agent_id, agent) in self.agents.iter_mut() {
+    let mut agents = self.agents.iter_mut().collect::<Vec<_>>();
+    for_each_maybe_parallel(&mut agents, |(agent_id, agent)| {
       let path = match &agent.current_path {
         None => {
           agent.current_desired_move = CS::from_landmass(&Vec3::ZERO);
-          continue;
+          return;
         }
         Some(path) => path,
       };
 
-      let Some(agent_point) =
-        agent_id_to_agent_node.get(&agent_id).map(|x| x.0)
+      let Some(agent_point) = agent_id_to_agent_node.get(agent_id).map(|x| x.0)
       else {
         // If the agent is paused, they may not have an agent node, even if the
         // agent has a path.
-        continue;
+        return;
       };
       let target_point = agent_id_to_target_node
-        .get(&agent_id)
+        .get(agent_id)
         .expect("Agent has a path and is not paused, so should have a valid target node")
         .0;
+      let is_partial_path = agent.partial_path_target_node.is_some();
+      // Partial paths end at the closest point to the target instead.
+      let target_point = if is_partial_path {
+        nav_data.closest_point_on_node(target_point, path.last_node())
+      } else {
+        target_point
+      };
+      let moving_state = if is_partial_path {
+        AgentState::MovingToClosestPoint
+      } else {
+        AgentState::Moving
+      };
 
       let &(agent_node_index_in_corridor, target_node_index_in_corridor) =
-        agent_id_to_follow_path_indices.get(&agent_id).expect(
+        agent_id_to_follow_path_indices.get(agent_id).expect(
           "Any agent with a path must have its follow path indices filled out.",
         );
 
       let next_waypoint = path.find_next_point_in_straight_path(
-        &self.nav_data,
+        nav_data,
         agent_node_index_in_corridor,
         agent_point,
         target_node_index_in_corridor,
         target_point,
+        agent.radius,
       );
 
       if agent.has_reached_target(
         path,
-        &self.nav_data,
+        nav_data,
         agent_point,
         next_waypoint,
         (target_node_index_in_corridor, target_point),
       ) {
         agent.current_desired_move = CS::from_landmass(&Vec3::ZERO);
-        agent.state = AgentState::ReachedTarget;
+        agent.state = if is_partial_path {
+          AgentState::MovingToClosestPoint
+        } else {
+          AgentState::ReachedTarget
+        };
       } else {
         let waypoint = match next_waypoint.1 {
           StraightPathStep::Waypoint(point) => {
-            agent.state = AgentState::Moving;
+            agent.state = moving_state;
             point
           }
           StraightPathStep::AnimationLink {
@@ -493,9 +1136,8 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
             }
             // Refine the start and end points of the animation link to be
             // actually on the nav mesh.
-            let start_point =
-              sample_point(&self.nav_data, start_point, start_node);
-            let end_point = sample_point(&self.nav_data, end_point, end_node);
+            let start_point = sample_point(nav_data, start_point, start_node);
+            let end_point = sample_point(nav_data, end_point, end_node);
 
             let distance = agent_point.distance(start_point);
             if distance <= agent.animation_link_reached_distance() {
@@ -506,7 +1148,7 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
                 link_id,
               });
             } else {
-              agent.state = AgentState::Moving;
+              agent.state = moving_state;
             }
             start_point
           }
@@ -520,18 +1162,76 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         agent.current_desired_move =
           CS::from_landmass(&desired_move.extend(0.0));
       }
+    });
+
+    // Agents that reached a full animation link must wait for their turn.
+    // Agents that already had a spot on the link go first.
+    let mut reached_animation_links = self
+      .agents
+      .iter()
+      .filter_map(|(agent_id, agent)| {
+        let link_id = agent.current_animation_link.as_ref()?.link_id;
+        let previously_reached =
+          previously_reached_animation_links.get(&agent_id) == Some(&link_id);
+        Some((agent_id, link_id, previously_reached))
+      })
+      .collect::<Vec<_>>();
+    reached_animation_links
+      .sort_by_key(|&(_, _, previously_reached)| !previously_reached);
+    for (agent_id, link_id, _) in reached_animation_links {
+      let Some(capacity) = self
+        .nav_data
+        .get_animation_link(link_id)
+        .and_then(|animation_link| animation_link.capacity)
+      else {
+        continue;
+      };
+      let users = animation_link_users.entry(link_id).or_default();
+      if *users < capacity {
+        *users += 1;
+        continue;
+      }
+      let agent = self.agents.get_mut(agent_id).unwrap();
+      agent.current_animation_link = None;
+      agent.state = AgentState::WaitingForAnimationLink;
+      agent.current_desired_move = CS::from_landmass(&Vec3::ZERO);
     }
 
     apply_avoidance_to_agents(
+      self.avoidance_backend.as_mut(),
       &mut self.agents,
       &agent_id_to_agent_node,
       &self.characters,
-      &character_id_to_nav_mesh_point,
+      &character_id_to_character_node,
+      &footprints,
       &self.nav_data,
       &self.archipelago_options,
       delta_time,
     );
   }
+
+  /// Determines the cost of each animation link that is full, given the number
+  /// of agents using each link and the agents that reached a link last update.
+  fn update_busy_animation_link_costs(
+    &mut self,
+    animation_link_users: &HashMap<AnimationLinkId, usize>,
+    previously_reached_animation_links: &HashMap<AgentId, AnimationLinkId>,
+  ) {
+    let mut animation_link_users = animation_link_users.clone();
+    for &link_id in previously_reached_animation_links.values() {
+      *animation_link_users.entry(link_id).or_default() += 1;
+    }
+    self.nav_data.busy_animation_link_costs = animation_link_users
+      .into_iter()
+      .filter_map(|(link_id, users)| {
+        let animation_link = self.nav_data.get_animation_link(link_id)?;
+        if animation_link.capacity.is_none_or(|capacity| users < capacity) {
+          return None;
+        }
+        animation_link.busy_cost.map(|busy_cost| (link_id, busy_cost))
+      })
+      .collect();
+  }
 }
 
 /// The result of path finding.
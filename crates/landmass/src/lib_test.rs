@@ -1,4 +1,9 @@
-use std::{collections::HashMap, f32::consts::PI, fmt::Debug, sync::Arc};
+use std::{
+  collections::{HashMap, HashSet},
+  f32::consts::PI,
+  fmt::Debug,
+  sync::Arc,
+};
 
 use glam::{Vec2, Vec3};
 use googletest::{
@@ -11,9 +16,9 @@ use googletest::{
 use crate::{
   Agent, AgentId, AgentState, AnimationLink, Archipelago, ArchipelagoOptions,
   Character, CharacterId, CoordinateSystem, FromAgentRadius, Island, IslandId,
-  NavigationMesh, PathStep, PointSampleDistance3d, ReachedAnimationLink,
-  Transform, ValidNavigationMesh,
-  agent::PermittedAnimationLinks,
+  NavigationMesh, Obstacle, ObstacleShape, PathStep, PathingResult,
+  PointSampleDistance3d, ReachedAnimationLink, Transform, ValidNavigationMesh,
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
   coords::{XY, XYZ},
   nav_data::NodeRef,
   path::Path,
@@ -192,10 +197,7 @@ fn computes_and_follows_path() {
   .validate()
   .expect("is valid");
 
-  archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  archipelago.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   archipelago.archipelago_options.neighbourhood = 0.0;
   archipelago.archipelago_options.obstacle_avoidance_time_horizon = 0.01;
@@ -274,7 +276,8 @@ fn computes_and_follows_path() {
 
   archipelago.update(/* delta_time= */ 0.01);
 
-  // These agents found a path and started following it.
+  // These agents found a path and started following it. The corridor is
+  // exactly as wide as the agents, so they walk along its center.
   assert_eq!(
     archipelago.get_agent(agent_1).unwrap().state(),
     AgentState::Moving
@@ -288,14 +291,14 @@ fn computes_and_follows_path() {
       .get_agent(agent_1)
       .unwrap()
       .get_desired_velocity()
-      .abs_diff_eq(Vec3::new(1.5, 0.5, 0.0).normalize() * 2.0, 1e-2)
+      .abs_diff_eq(Vec3::new(1.5, 0.0, 0.0).normalize() * 2.0, 1e-2)
   );
   assert!(
     archipelago
       .get_agent(agent_2)
       .unwrap()
       .get_desired_velocity()
-      .abs_diff_eq(Vec3::new(-0.5, -1.5, 0.0).normalize() * 2.0, 1e-2)
+      .abs_diff_eq(Vec3::new(0.0, -1.5, 0.0).normalize() * 2.0, 1e-2)
   );
   // These agents are not on the nav mesh, so they don't do anything.
   assert_eq!(
@@ -321,8 +324,8 @@ fn computes_and_follows_path() {
   assert_eq!(archipelago.get_pathing_results().len(), 2);
   let path_result_1 = archipelago.get_pathing_results()[0];
   let path_result_2 = archipelago.get_pathing_results()[1];
-  assert_eq!(path_result_1.success, true);
-  assert_eq!(path_result_2.success, true);
+  assert!(path_result_1.success);
+  assert!(path_result_2.success);
   assert!(path_result_1.explored_nodes > 0);
   assert!(path_result_2.explored_nodes > 0);
 
@@ -336,7 +339,7 @@ fn computes_and_follows_path() {
       .get_agent(agent_1)
       .unwrap()
       .get_desired_velocity()
-      .abs_diff_eq(Vec3::new(0.5, 0.5, 0.0).normalize() * 2.0, 1e-7)
+      .abs_diff_eq(Vec3::new(0.5, 0.0, 0.0).normalize() * 2.0, 1e-7)
   );
   // These agents don't change.
   assert!(
@@ -344,7 +347,7 @@ fn computes_and_follows_path() {
       .get_agent(agent_2)
       .unwrap()
       .get_desired_velocity()
-      .abs_diff_eq(Vec3::new(-0.5, -1.5, 0.0).normalize() * 2.0, 1e-2)
+      .abs_diff_eq(Vec3::new(0.0, -1.5, 0.0).normalize() * 2.0, 1e-2)
   );
   assert_eq!(
     *archipelago.get_agent(agent_off_mesh).unwrap().get_desired_velocity(),
@@ -398,7 +401,7 @@ fn computes_and_follows_path() {
       .get_agent(agent_2)
       .unwrap()
       .get_desired_velocity()
-      .abs_diff_eq(Vec3::new(-0.5, -0.5, 0.0).normalize() * 2.0, 1e-2)
+      .abs_diff_eq(Vec3::new(0.0, -0.5, 0.0).normalize() * 2.0, 1e-2)
   );
   // These agents don't change.
   assert_eq!(
@@ -471,6 +474,7 @@ fn agent_speeds_up_to_avoid_character() {
     position: Vec2::new(0.0, 5.0),
     velocity: Vec2::new(0.0, -1.0),
     radius: 0.5,
+    ..Default::default()
   });
 
   archipelago.update(0.01);
@@ -589,7 +593,7 @@ fn samples_point() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -598,7 +602,8 @@ fn samples_point() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(-0.5, 0.5),
-        /* distance_to_node= */ &0.6
+        /* distance_to_node= */ &0.6,
+        PermittedTypeIndices::All,
       )
       .map(|p| p.point()),
     Ok(offset + Vec2::new(0.0, 0.5))
@@ -607,7 +612,8 @@ fn samples_point() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(0.5, 0.5),
-        /* distance_to_node= */ &0.6
+        /* distance_to_node= */ &0.6,
+        PermittedTypeIndices::All,
       )
       .map(|p| p.point()),
     Ok(offset + Vec2::new(0.5, 0.5))
@@ -616,7 +622,8 @@ fn samples_point() {
     archipelago
       .sample_point(
         /* point= */ offset + Vec2::new(1.2, 1.2),
-        /* distance_to_node= */ &0.6
+        /* distance_to_node= */ &0.6,
+        PermittedTypeIndices::All,
       )
       .map(|p| p.point()),
     Ok(offset + Vec2::new(1.0, 1.0))
@@ -632,24 +639,40 @@ fn finds_path() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: 0.0, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(1.0, 0.0),
+      rotation: 0.0,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(2.0, 0.5), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(2.0, 0.5),
+      rotation: 0.0,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   archipelago.update(1.0);
 
   let start_point = archipelago
-    .sample_point(offset + Vec2::new(0.5, 0.5), &1e-5)
+    .sample_point(
+      offset + Vec2::new(0.5, 0.5),
+      &1e-5,
+      PermittedTypeIndices::All,
+    )
     .expect("point is on nav mesh.");
   let end_point = archipelago
-    .sample_point(offset + Vec2::new(2.5, 1.25), &1e-5)
+    .sample_point(
+      offset + Vec2::new(2.5, 1.25),
+      &1e-5,
+      PermittedTypeIndices::All,
+    )
     .expect("point is on nav mesh.");
   assert_eq!(
     archipelago.find_path(
@@ -657,6 +680,7 @@ fn finds_path() {
       &end_point,
       &HashMap::new(),
       PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
     ),
     Ok(vec![
       PathStep::Waypoint(offset + Vec2::new(0.5, 0.5)),
@@ -737,10 +761,316 @@ fn agent_overrides_node_costs() {
   archipelago.update(1.0);
 
   // The agent **could** go directly up, but due to its overridden node cost, it
-  // is better to take the detour to the right.
+  // is better to take the detour to the right. The corridor is as wide as the
+  // agent, so it walks along the center of the corridor.
   assert_eq!(
     *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
-    Vec2::new(1.5, 0.5).normalize(),
+    Vec2::new(1.0, 0.0),
+  );
+}
+
+#[test]
+fn agent_does_not_enter_forbidden_type_indices() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+        //
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 1.0),
+        //
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 1.0),
+        //
+        Vec2::new(2.0, 11.0),
+        Vec2::new(3.0, 11.0),
+        //
+        Vec2::new(2.0, 12.0),
+        Vec2::new(3.0, 12.0),
+        //
+        Vec2::new(1.0, 12.0),
+        Vec2::new(1.0, 11.0),
+        //
+        Vec2::new(0.0, 12.0),
+        Vec2::new(0.0, 11.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 2, 3],
+        vec![2, 1, 4, 5],
+        vec![5, 4, 6, 7],
+        //
+        vec![5, 7, 9, 8],
+        vec![8, 9, 11, 10],
+        //
+        vec![8, 10, 12, 13],
+        vec![13, 12, 14, 15],
+        //
+        vec![3, 2, 13, 15],
+      ],
+      polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.set_type_index_cost(1, 1.0).unwrap();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 0.5),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.permitted_type_indices =
+      PermittedTypeIndices::Exclude(Arc::new(HashSet::from([1])));
+    agent.current_target = Some(Vec2::new(0.5, 11.5));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  // The agent **could** go directly up, but it is not permitted to walk on type
+  // index 1, so it must take the detour to the right.
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(1.0, 0.0),
+  );
+}
+
+#[test]
+fn agents_of_different_sizes_take_different_paths() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(6.0, 0.0),
+        Vec2::new(6.0, 2.0),
+        Vec2::new(1.0, 5.0),
+        Vec2::new(0.0, 5.0),
+        Vec2::new(6.0, 5.0),
+        Vec2::new(3.0, 5.0),
+        Vec2::new(1.0, 7.0),
+        Vec2::new(0.0, 7.0),
+        Vec2::new(3.0, 7.0),
+        Vec2::new(6.0, 7.0),
+      ],
+      polygons: vec![
+        // Bottom row.
+        vec![0, 1, 2, 3],
+        vec![1, 4, 5, 2],
+        vec![4, 6, 7, 5],
+        // Narrow connection.
+        vec![3, 2, 8, 9],
+        // Wide connection.
+        vec![5, 7, 10, 11],
+        // Top row.
+        vec![9, 8, 12, 13],
+        vec![8, 11, 14, 12],
+        vec![11, 10, 15, 14],
+      ],
+      polygon_type_indices: vec![0; 8],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  let small_agent = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 1.0),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.25,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(0.5, 6.0));
+    agent
+  });
+  let large_agent = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 1.0),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.75,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(0.5, 6.0));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  // The small agent fits through the narrow connection, so it goes straight
+  // up. The large agent doesn't fit, so it takes the wide connection.
+  let corridor = |agent_id| {
+    let agent = archipelago.get_agent(agent_id).unwrap();
+    agent.current_path.as_ref().unwrap().island_segments[0].corridor.clone()
+  };
+  assert_eq!(corridor(small_agent), [0, 3, 5]);
+  assert_eq!(corridor(large_agent), [0, 1, 2, 4, 7, 6, 5]);
+}
+
+#[test]
+fn agent_walks_around_obstacle_in_node() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(0.0, 10.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(4.0, 3.0),
+      Vec2::new(6.0, 3.0),
+      Vec2::new(6.0, 7.0),
+      Vec2::new(4.0, 7.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(1.0, 5.0),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(9.0, 5.0));
+    agent
+  });
+
+  for _ in 0..200 {
+    archipelago.update(0.1);
+    let agent = archipelago.get_agent_mut(agent_id).unwrap();
+    if agent.state() == AgentState::ReachedTarget {
+      break;
+    }
+    agent.position += *agent.get_desired_velocity() * 0.1;
+    let position = agent.position;
+    let distance_to_obstacle = Vec2::new(
+      (4.0 - position.x).max(position.x - 6.0).max(0.0),
+      (3.0 - position.y).max(position.y - 7.0).max(0.0),
+    )
+    .length();
+    // Allow some leeway since the agent only keeps its radius away from the
+    // corners it turns around.
+    assert!(
+      distance_to_obstacle > 0.4,
+      "The agent walked into the obstacle at {position}."
+    );
+  }
+  assert_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::ReachedTarget
+  );
+}
+
+#[test]
+fn agent_walks_around_obstacle_across_nodes() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(5.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(5.0, 10.0),
+        Vec2::new(0.0, 10.0),
+      ],
+      // The obstacle straddles the edge between the two nodes.
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(4.0, 3.0),
+      Vec2::new(6.0, 3.0),
+      Vec2::new(6.0, 7.0),
+      Vec2::new(4.0, 7.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(1.0, 5.0),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(9.0, 5.0));
+    agent
+  });
+
+  for _ in 0..200 {
+    archipelago.update(0.1);
+    let agent = archipelago.get_agent_mut(agent_id).unwrap();
+    if agent.state() == AgentState::ReachedTarget {
+      break;
+    }
+    agent.position += *agent.get_desired_velocity() * 0.1;
+    let position = agent.position;
+    let distance_to_obstacle = Vec2::new(
+      (4.0 - position.x).max(position.x - 6.0).max(0.0),
+      (3.0 - position.y).max(position.y - 7.0).max(0.0),
+    )
+    .length();
+    // Allow some leeway since the agent only keeps its radius away from the
+    // corners it turns around.
+    assert!(
+      distance_to_obstacle > 0.4,
+      "The agent walked into the obstacle at {position}."
+    );
+  }
+  assert_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::ReachedTarget
   );
 }
 
@@ -913,11 +1243,11 @@ fn paused_agent_path_is_removed_when_invalid() {
   let island_1 =
     archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 1.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(0.0, 1.0), rotation: 0.0, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
 
@@ -1035,7 +1365,7 @@ fn agent_can_use_animation_link() {
 
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(3.0, 5.0), rotation: PI },
+    Transform { translation: Vec2::new(3.0, 5.0), rotation: PI, scale: 1.0 },
     nav_mesh.clone(),
   ));
   let link_id = archipelago.add_animation_link(AnimationLink {
@@ -1044,6 +1374,8 @@ fn agent_can_use_animation_link() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   let agent_id = archipelago.add_agent({
@@ -1064,7 +1396,7 @@ fn agent_can_use_animation_link() {
   expect_eq!(agent.state(), AgentState::Moving);
   expect_eq!(
     *agent.get_desired_velocity(),
-    (Vec2::new(1.0, 1.0) - Vec2::new(0.25, 0.75)).normalize()
+    (Vec2::new(1.0, 0.5) - Vec2::new(0.25, 0.75)).normalize()
   );
   expect_false!(agent.is_using_animation_link());
   expect_that!(agent.reached_animation_link(), none());
@@ -1075,7 +1407,10 @@ fn agent_can_use_animation_link() {
 
   let agent = archipelago.get_agent_mut(agent_id).unwrap();
   expect_eq!(agent.state(), AgentState::Moving);
-  expect_eq!(*agent.get_desired_velocity(), Vec2::new(0.0, 1.0));
+  expect_eq!(
+    *agent.get_desired_velocity(),
+    (Vec2::new(1.5, 1.0) - Vec2::new(1.25, 0.75)).normalize()
+  );
   expect_false!(agent.is_using_animation_link());
   expect_that!(agent.reached_animation_link(), none());
 
@@ -1116,12 +1451,103 @@ fn agent_can_use_animation_link() {
   expect_eq!(agent.state(), AgentState::Moving);
   expect_eq!(
     *agent.get_desired_velocity(),
-    (Vec2::new(2.0, 4.0) - Vec2::new(1.75, 3.1)).normalize()
+    (Vec2::new(1.5, 4.0) - Vec2::new(1.75, 3.1)).normalize()
   );
   expect_false!(agent.is_using_animation_link());
   expect_that!(agent.reached_animation_link(), none());
 }
 
+#[googletest::test]
+fn agents_wait_for_full_animation_link() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.1));
+  let nav_mesh = simple_one_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(0.0, 3.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  let link_id = archipelago.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.0, 0.9), Vec2::new(1.0, 0.9)),
+    end_edge: (Vec2::new(0.0, 3.1), Vec2::new(1.0, 3.1)),
+    cost: 1.0,
+    kind: 0,
+    bidirectional: false,
+    capacity: Some(1),
+    busy_cost: Some(10.0),
+  });
+
+  let create_agent = |position| {
+    let mut agent = Agent::create(position, Vec2::ZERO, 0.1, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(0.5, 3.5));
+    agent.animation_link_reached_distance = Some(0.5);
+    agent
+  };
+  let agent_1 = archipelago.add_agent(create_agent(Vec2::new(0.25, 0.75)));
+  let agent_2 = archipelago.add_agent(create_agent(Vec2::new(0.75, 0.75)));
+
+  archipelago.update(1.0);
+
+  // Only one agent fits on the link, so the other waits.
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::ReachedAnimationLink
+  );
+  let agent = archipelago.get_agent(agent_2).unwrap();
+  expect_eq!(agent.state(), AgentState::WaitingForAnimationLink);
+  expect_eq!(agent.get_desired_velocity(), &Vec2::ZERO);
+  expect_that!(agent.reached_animation_link(), none());
+  expect_that!(
+    archipelago.get_agent_mut(agent_2).unwrap().start_animation_link(),
+    err(anything())
+  );
+
+  archipelago.update(1.0);
+
+  // The first agent keeps its spot on the link, which is now busy.
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::ReachedAnimationLink
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::WaitingForAnimationLink
+  );
+  expect_that!(
+    archipelago.nav_data.busy_animation_link_costs,
+    unordered_elements_are!((eq(&link_id), eq(&10.0)))
+  );
+
+  expect_that!(
+    archipelago.get_agent_mut(agent_1).unwrap().start_animation_link(),
+    ok(())
+  );
+  archipelago.update(1.0);
+
+  // The first agent is still using the link, so the second agent still waits.
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::UsingAnimationLink
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::WaitingForAnimationLink
+  );
+
+  let agent = archipelago.get_agent_mut(agent_1).unwrap();
+  expect_that!(agent.end_animation_link(), ok(()));
+  agent.position = Vec2::new(0.25, 3.25);
+  archipelago.update(1.0);
+
+  // The link is free again, so the second agent can take it.
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::ReachedAnimationLink
+  );
+  expect_that!(archipelago.nav_data.busy_animation_link_costs, is_empty());
+}
+
 fn simple_two_node_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
   Arc::new(
     NavigationMesh {
@@ -1210,7 +1636,7 @@ fn agent_could_not_find_path() {
 
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   let agent_id = archipelago.add_agent({
@@ -1227,3 +1653,489 @@ fn agent_could_not_find_path() {
   expect_true!(agent.current_path.is_none());
   expect_eq!(agent.state(), AgentState::NoPath);
 }
+
+#[googletest::test]
+fn pathfinding_is_spread_across_updates() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    max_explored_nodes_per_update: Some(3),
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 1.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 8, 9],
+        vec![1, 2, 7, 8],
+        vec![2, 3, 6, 7],
+        vec![3, 4, 5, 6],
+      ],
+      polygon_type_indices: vec![0; 4],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  // This agent needs to explore 5 nodes to find its path.
+  let agent_1 = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.25, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(3.5, 0.5));
+    agent
+  });
+  // This agent needs to explore 3 nodes to find its path.
+  let agent_2 = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(2.5, 0.5), Vec2::ZERO, 0.25, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(3.5, 0.5));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  // The first agent used up the whole budget.
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::Pathing
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::Pathing
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().get_desired_velocity(),
+    &Vec2::ZERO
+  );
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+
+  archipelago.update(1.0);
+
+  // The first agent finished its path, and the second agent used up the rest
+  // of the budget.
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::Moving
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::Pathing
+  );
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(eq(&PathingResult {
+      agent: agent_1,
+      success: true,
+      explored_nodes: 5
+    }))
+  );
+
+  archipelago.update(1.0);
+
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::Moving
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::Moving
+  );
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(eq(&PathingResult {
+      agent: agent_2,
+      success: true,
+      explored_nodes: 3
+    }))
+  );
+}
+
+#[googletest::test]
+fn pathing_agent_becomes_idle_when_clearing_target() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    max_explored_nodes_per_update: Some(1),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.5, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(0.5, 1.5));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  expect_true!(agent.current_path_search.is_some());
+  expect_eq!(agent.state(), AgentState::Pathing);
+
+  agent.current_target = None;
+
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  expect_true!(agent.current_path_search.is_none());
+  expect_eq!(agent.state(), AgentState::Idle);
+}
+
+#[googletest::test]
+fn moving_unrelated_island_keeps_path_search() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    max_explored_nodes_per_update: Some(5),
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let mut vertices = vec![];
+  for i in 0..=60 {
+    vertices.push(Vec2::new(i as f32, 0.0));
+    vertices.push(Vec2::new(i as f32, 1.0));
+  }
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices,
+      polygons: (0..60)
+        .map(|i| vec![i * 2, i * 2 + 2, i * 2 + 3, i * 2 + 1])
+        .collect(),
+      polygon_type_indices: vec![0; 60],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let moving_island_id = archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(0.0, 10.0), rotation: 0.0, scale: 1.0 },
+    simple_one_node_nav_mesh(),
+  ));
+
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.25, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(59.5, 0.5));
+    agent
+  });
+
+  for i in 0..20 {
+    archipelago.get_island_mut(moving_island_id).unwrap().set_transform(
+      Transform {
+        translation: Vec2::new(i as f32, 10.0),
+        rotation: 0.0,
+        scale: 1.0,
+      },
+    );
+    archipelago.update(0.1);
+  }
+
+  // The search continues across updates even though the other island moves
+  // every update.
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::Moving
+  );
+}
+
+#[googletest::test]
+fn many_agents_update_deterministically() {
+  fn create_archipelago() -> (Archipelago<XY>, Vec<AgentId>) {
+    let mut archipelago =
+      Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+    archipelago.add_island(Island::new(
+      Transform::default(),
+      Arc::new(
+        NavigationMesh {
+          vertices: vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+          ],
+          polygons: vec![vec![0, 1, 2, 3]],
+          polygon_type_indices: vec![0],
+          height_mesh: None,
+        }
+        .validate()
+        .expect("nav mesh is valid"),
+      ),
+    ));
+
+    let mut agent_ids = vec![];
+    for i in 0..20 {
+      let x = 0.5 + (i % 5) as f32 * 2.0;
+      let y = 0.5 + (i / 5) as f32 * 2.0;
+      agent_ids.push(archipelago.add_agent({
+        let mut agent =
+          Agent::create(Vec2::new(x, y), Vec2::ZERO, 0.25, 1.0, 2.0);
+        agent.current_target = Some(Vec2::new(9.5 - x, 9.5 - y));
+        agent
+      }));
+    }
+    (archipelago, agent_ids)
+  }
+
+  let (mut archipelago_1, agent_ids_1) = create_archipelago();
+  let (mut archipelago_2, agent_ids_2) = create_archipelago();
+
+  for _ in 0..10 {
+    for archipelago in [&mut archipelago_1, &mut archipelago_2] {
+      archipelago.update(0.1);
+      let agent_ids = archipelago.get_agent_ids().collect::<Vec<_>>();
+      for agent_id in agent_ids {
+        let agent = archipelago.get_agent_mut(agent_id).unwrap();
+        agent.velocity = *agent.get_desired_velocity();
+        agent.position += agent.velocity * 0.1;
+      }
+    }
+
+    for (&agent_id_1, &agent_id_2) in agent_ids_1.iter().zip(&agent_ids_2) {
+      let agent_1 = archipelago_1.get_agent(agent_id_1).unwrap();
+      let agent_2 = archipelago_2.get_agent(agent_id_2).unwrap();
+      expect_eq!(agent_1.state(), agent_2.state());
+      expect_eq!(agent_1.position, agent_2.position);
+      expect_eq!(
+        agent_1.get_desired_velocity(),
+        agent_2.get_desired_velocity()
+      );
+    }
+    expect_eq!(
+      archipelago_1.get_pathing_results(),
+      archipelago_2.get_pathing_results()
+    );
+  }
+}
+
+#[googletest::test]
+fn agent_moves_to_closest_point_if_target_is_unreachable() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.1));
+  let nav_mesh = simple_one_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh.clone(),
+  ));
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.25, 0.5), Vec2::ZERO, 0.1, 1.0, 2.0);
+    // This target isn't connected to the agent.
+    agent.current_target = Some(Vec2::new(2.5, 0.5));
+    agent.allow_partial_paths = true;
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::MovingToClosestPoint);
+  expect_eq!(agent.get_desired_velocity(), &Vec2::new(1.0, 0.0));
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(eq(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 1
+    }))
+  );
+
+  // The partial path is reused while the target is in the same node.
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(2.5, 0.75));
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::MovingToClosestPoint);
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+
+  // Connect the two islands so the target becomes reachable.
+  archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::Moving);
+  expect_eq!(archipelago.get_pathing_results().len(), 1);
+  expect_true!(archipelago.get_pathing_results()[0].success);
+}
+
+#[googletest::test]
+fn agent_without_partial_paths_has_no_path_to_unreachable_target() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.1));
+  let nav_mesh = simple_one_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.25, 0.5), Vec2::ZERO, 0.1, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(2.5, 0.5));
+    agent.allow_partial_paths = true;
+    agent
+  });
+
+  archipelago.update(1.0);
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::MovingToClosestPoint
+  );
+
+  // Disallowing partial paths drops the partial path.
+  archipelago.get_agent_mut(agent_id).unwrap().allow_partial_paths = false;
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::NoPath);
+  expect_eq!(agent.get_desired_velocity(), &Vec2::ZERO);
+}
+
+#[googletest::test]
+fn agent_rides_moving_island() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.1));
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.1, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(0.5, 1.5));
+    agent
+  });
+
+  archipelago.update(0.5);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::Moving);
+  expect_eq!(agent.current_island(), Some(island_id));
+  expect_eq!(agent.get_platform_velocity(), &Vec2::ZERO);
+  expect_eq!(archipelago.get_pathing_results().len(), 1);
+
+  // Move the island, carrying the agent and its target along with it.
+  archipelago.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(2.0, 0.0),
+    rotation: 0.0,
+    scale: 1.0,
+  });
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  agent.position = Vec2::new(2.5, 0.5);
+  agent.current_target = Some(Vec2::new(2.5, 1.5));
+
+  archipelago.update(0.5);
+
+  // The path is still valid, so the agent doesn't need to repath.
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::Moving);
+  expect_eq!(agent.current_island(), Some(island_id));
+  expect_eq!(agent.get_platform_velocity(), &Vec2::new(4.0, 0.0));
+  expect_eq!(agent.get_desired_velocity(), &Vec2::new(0.0, 1.0));
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+
+  // The island stopped moving, so it has no velocity.
+  archipelago.update(0.5);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.get_platform_velocity(), &Vec2::ZERO);
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+
+  // Changing the nav mesh invalidates the path though.
+  archipelago.get_island_mut(island_id).unwrap().set_nav_mesh(nav_mesh);
+
+  archipelago.update(0.5);
+
+  expect_eq!(archipelago.get_pathing_results().len(), 1);
+}
+
+#[googletest::test]
+fn agent_path_is_repaired_instead_of_recomputed() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    max_path_repair_explored_nodes: 10,
+    ..ArchipelagoOptions::from_agent_radius(0.1)
+  });
+  let nav_mesh = simple_one_node_nav_mesh();
+
+  // A row of three islands.
+  let island_ids = (0..3)
+    .map(|i| {
+      archipelago.add_island(Island::new(
+        Transform {
+          translation: Vec2::new(i as f32, 0.0),
+          rotation: 0.0,
+          scale: 1.0,
+        },
+        nav_mesh.clone(),
+      ))
+    })
+    .collect::<Vec<_>>();
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.1, 1.0, 2.0);
+    agent.current_target = Some(Vec2::new(1.5, 0.5));
+    agent
+  });
+
+  let path_islands = |archipelago: &Archipelago<XY>| {
+    archipelago
+      .get_agent(agent_id)
+      .unwrap()
+      .current_path
+      .as_ref()
+      .unwrap()
+      .island_segments
+      .iter()
+      .map(|segment| segment.island_id)
+      .collect::<Vec<_>>()
+  };
+
+  archipelago.update(0.01);
+  expect_eq!(archipelago.get_pathing_results().len(), 1);
+  expect_eq!(path_islands(&archipelago), island_ids[..2]);
+
+  // The target leaves the path, so the path is extended to it without a full
+  // search.
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(2.5, 0.5));
+  archipelago.update(0.01);
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::Moving
+  );
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+  expect_eq!(path_islands(&archipelago), island_ids);
+
+  // Only the middle of the path is invalidated, so only the middle is
+  // replaced.
+  archipelago.get_island_mut(island_ids[1]).unwrap().set_nav_mesh(nav_mesh);
+  archipelago.update(0.01);
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::Moving
+  );
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+  expect_eq!(path_islands(&archipelago), island_ids);
+
+  // The target moves back along the path, so the path is trimmed.
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(1.5, 0.5));
+  archipelago.update(0.01);
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+  expect_eq!(path_islands(&archipelago), island_ids[..2]);
+}
@@ -32,6 +32,17 @@ pub struct AnimationLink<CS: CoordinateSystem> {
   /// This is a convenience to avoid needing to create two links to go in both
   /// directions.
   pub bidirectional: bool,
+  /// The number of agents that can use this link at the same time. Agents
+  /// that reach a full link wait near the start of the link (in the
+  /// [`crate::AgentState::WaitingForAnimationLink`] state) until there is room.
+  /// If [`None`], any number of agents can use the link at once.
+  pub capacity: Option<usize>,
+  /// The cost of taking this animation link while it is full, replacing
+  /// [`Self::cost`] for new paths. Setting this higher than [`Self::cost`]
+  /// makes agents route around busy links. If [`None`], [`Self::cost`] is
+  /// always used. This is only relevant if [`Self::capacity`] is set. Must be
+  /// non-negative.
+  pub busy_cost: Option<f32>,
 }
 
 /// The state of an animation link.
@@ -42,6 +53,8 @@ pub(crate) struct AnimationLinkState<CS: CoordinateSystem> {
   pub(crate) start_portals: Vec<NodePortal>,
   /// The portals that this animation link leads to.
   pub(crate) end_portals: Vec<NodePortal>,
+  /// Whether the link is enabled. Disabled links are never taken.
+  pub(crate) enabled: bool,
 }
 
 impl<CS: CoordinateSystem> AnimationLinkState<CS> {
@@ -50,6 +63,7 @@ impl<CS: CoordinateSystem> AnimationLinkState<CS> {
       main_link: link,
       start_portals: Default::default(),
       end_portals: Default::default(),
+      enabled: true,
     }
   }
 }
@@ -63,6 +77,8 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
       .field("end_edge", &self.end_edge)
       .field("kind", &self.kind)
       .field("cost", &self.cost)
+      .field("capacity", &self.capacity)
+      .field("busy_cost", &self.busy_cost)
       .finish()
   }
 }
@@ -75,6 +91,7 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
       .field("main_link", &self.main_link)
       .field("start_portals", &self.start_portals)
       .field("end_portals", &self.end_portals)
+      .field("enabled", &self.enabled)
       .finish()
   }
 }
@@ -0,0 +1,167 @@
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::{
+  AnimationLink, CoordinateSystem, IslandId, PermittedTypeIndices,
+  coords::CorePointSampleDistance, nav_data::NavigationData,
+};
+
+/// Options for generating animation links with
+/// [`crate::Archipelago::generate_animation_links`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationLinkGenerationOptions {
+  /// The maximum height that agents can drop down from a ledge.
+  pub max_drop_height: f32,
+  /// The maximum horizontal distance that agents can jump across a gap.
+  pub max_jump_distance: f32,
+  /// The height that agents can step up or down without a link. Drops must be
+  /// higher than this, and jumps must land within this height of where they
+  /// start.
+  pub step_height: f32,
+  /// How far past a ledge to look for somewhere to land. This is also the
+  /// spacing between the distances checked for jumps. This should be around
+  /// the agent's radius, and must be positive.
+  pub edge_offset: f32,
+  /// The kind of the generated drop links.
+  pub drop_kind: usize,
+  /// The kind of the generated jump links.
+  pub jump_kind: usize,
+}
+
+/// Generates the animation links for drops and jumps from the boundary edges
+/// of every enabled island in `nav_data`. The links are generated in a
+/// deterministic order (islands by ID, then edges by polygon and edge index).
+pub(crate) fn generate_animation_links<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  options: &AnimationLinkGenerationOptions,
+) -> Vec<AnimationLink<CS>> {
+  let mut island_ids = nav_data.get_island_ids().collect::<Vec<IslandId>>();
+  island_ids.sort();
+
+  let mut links = vec![];
+  for island_id in island_ids {
+    let island = nav_data.get_island(island_id).unwrap();
+    if !island.enabled {
+      continue;
+    }
+    let mut boundary_edges = island.nav_mesh.boundary_edges.clone();
+    boundary_edges.sort_by_key(|edge| (edge.polygon_index, edge.edge_index));
+
+    for edge in boundary_edges {
+      let (left, right) = island.nav_mesh.get_edge_points(edge);
+      let (left, right) =
+        (island.transform.apply(left), island.transform.apply(right));
+      // The edge is left-to-right when viewed from inside the node, so the
+      // outside of the node is to the left of the edge.
+      let outward = (right - left).xy().perp().extend(0.0).normalize_or_zero();
+      if outward == Vec3::ZERO {
+        continue;
+      }
+
+      // Start the link just inside the node, so the link is taken from the node
+      // and not the empty space next to it.
+      let start_edge = (
+        left - outward * options.edge_offset,
+        right - outward * options.edge_offset,
+      );
+      let midpoint = left.midpoint(right);
+
+      let mut add_link = |landing_point: Vec3, kind: usize| {
+        let offset = landing_point - midpoint;
+        links.push(AnimationLink {
+          start_edge: (
+            CS::from_landmass(&start_edge.0),
+            CS::from_landmass(&start_edge.1),
+          ),
+          end_edge: (
+            CS::from_landmass(&(left + offset)),
+            CS::from_landmass(&(right + offset)),
+          ),
+          kind,
+          cost: midpoint.distance(landing_point),
+          bidirectional: false,
+          capacity: None,
+          busy_cost: None,
+        });
+      };
+
+      let ledge_point = midpoint + outward * options.edge_offset;
+      if let Some(landing_point) = find_drop(nav_data, ledge_point, options) {
+        add_link(landing_point, options.drop_kind);
+      }
+      if let Some(landing_point) =
+        find_jump(nav_data, midpoint, outward, options)
+      {
+        add_link(landing_point, options.jump_kind);
+      }
+    }
+  }
+  links
+}
+
+/// Finds where an agent would land after dropping down from `ledge_point`.
+fn find_drop<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  ledge_point: Vec3,
+  options: &AnimationLinkGenerationOptions,
+) -> Option<Vec3> {
+  if options.max_drop_height <= options.step_height {
+    return None;
+  }
+  let half_range = (options.max_drop_height - options.step_height) * 0.5;
+  let (landing_point, _) = nav_data.sample_point(
+    ledge_point - Vec3::Z * (options.step_height + half_range),
+    &CorePointSampleDistance {
+      horizontal_distance: options.edge_offset * 0.5,
+      distance_above: half_range,
+      distance_below: half_range,
+      vertical_preference_ratio: 1.0,
+    },
+    &PermittedTypeIndices::All,
+  )?;
+  Some(landing_point)
+}
+
+/// Finds where an agent would land after jumping from `midpoint` across a gap
+/// in the `outward` direction.
+fn find_jump<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  midpoint: Vec3,
+  outward: Vec3,
+  options: &AnimationLinkGenerationOptions,
+) -> Option<Vec3> {
+  let sample_at_level = |distance: f32| {
+    nav_data
+      .sample_point(
+        midpoint + outward * distance,
+        &CorePointSampleDistance {
+          horizontal_distance: options.edge_offset * 0.5,
+          distance_above: options.step_height,
+          distance_below: options.step_height,
+          vertical_preference_ratio: 1.0,
+        },
+        &PermittedTypeIndices::All,
+      )
+      .map(|(point, _)| point)
+  };
+
+  // If there is ground right next to the edge, there is no gap to jump. This
+  // also guards against looping forever on a non-positive edge offset.
+  if options.edge_offset <= 0.0
+    || sample_at_level(options.edge_offset).is_some()
+  {
+    return None;
+  }
+
+  let mut distance = options.edge_offset * 2.0;
+  while distance <= options.max_jump_distance {
+    if let Some(landing_point) = sample_at_level(distance) {
+      return Some(landing_point);
+    }
+    distance += options.edge_offset;
+  }
+  None
+}
+
+#[cfg(test)]
+#[path = "link_generation_test.rs"]
+mod test;
@@ -0,0 +1,254 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::Vec3;
+use googletest::{expect_eq, expect_that, matchers::*};
+
+use crate::{
+  AnimationLink, Archipelago, ArchipelagoOptions, FromAgentRadius, Island,
+  NavigationMesh, PermittedAnimationLinks, PermittedTypeIndices, Transform,
+  XYZ,
+};
+
+use super::AnimationLinkGenerationOptions;
+
+type RoundedLink = (usize, (Vec3, Vec3), (Vec3, Vec3), f32);
+
+fn round_link(link: &AnimationLink<XYZ>) -> RoundedLink {
+  let round = |v: Vec3| (v * 1e4).round() / 1e4;
+  (
+    link.kind,
+    (round(link.start_edge.0), round(link.start_edge.1)),
+    (round(link.end_edge.0), round(link.end_edge.1)),
+    (link.cost * 1e4).round() / 1e4,
+  )
+}
+
+const OPTIONS: AnimationLinkGenerationOptions =
+  AnimationLinkGenerationOptions {
+    max_drop_height: 3.0,
+    max_jump_distance: 1.5,
+    step_height: 0.5,
+    edge_offset: 0.25,
+    drop_kind: 1,
+    jump_kind: 2,
+  };
+
+#[googletest::test]
+fn generates_drop_and_jump_links() {
+  let mut archipelago =
+    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  // Two platforms with a gap between them, above a large floor.
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(2.0, 2.0, 2.0),
+          Vec3::new(0.0, 2.0, 2.0),
+          Vec3::new(0.0, 0.0, 2.0),
+          Vec3::new(2.0, 0.0, 2.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(5.0, 2.0, 2.0),
+          Vec3::new(3.0, 2.0, 2.0),
+          Vec3::new(3.0, 0.0, 2.0),
+          Vec3::new(5.0, 0.0, 2.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(6.0, 3.0, 0.0),
+          Vec3::new(-1.0, 3.0, 0.0),
+          Vec3::new(-1.0, -1.0, 0.0),
+          Vec3::new(6.0, -1.0, 0.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+
+  let links = archipelago.generate_animation_links(&OPTIONS);
+
+  let drop_cost = (4.0f32 + 0.0625).sqrt();
+  let drop_cost = (drop_cost * 1e4).round() / 1e4;
+  expect_eq!(
+    links.iter().map(round_link).collect::<Vec<_>>(),
+    vec![
+      // First platform.
+      (
+        1,
+        (Vec3::new(0.0, 1.75, 2.0), Vec3::new(2.0, 1.75, 2.0)),
+        (Vec3::new(0.0, 2.25, 0.0), Vec3::new(2.0, 2.25, 0.0)),
+        drop_cost,
+      ),
+      (
+        1,
+        (Vec3::new(0.25, 0.0, 2.0), Vec3::new(0.25, 2.0, 2.0)),
+        (Vec3::new(-0.25, 0.0, 0.0), Vec3::new(-0.25, 2.0, 0.0)),
+        drop_cost,
+      ),
+      (
+        1,
+        (Vec3::new(2.0, 0.25, 2.0), Vec3::new(0.0, 0.25, 2.0)),
+        (Vec3::new(2.0, -0.25, 0.0), Vec3::new(0.0, -0.25, 0.0)),
+        drop_cost,
+      ),
+      (
+        1,
+        (Vec3::new(1.75, 2.0, 2.0), Vec3::new(1.75, 0.0, 2.0)),
+        (Vec3::new(2.25, 2.0, 0.0), Vec3::new(2.25, 0.0, 0.0)),
+        drop_cost,
+      ),
+      (
+        2,
+        (Vec3::new(1.75, 2.0, 2.0), Vec3::new(1.75, 0.0, 2.0)),
+        (Vec3::new(3.0, 2.0, 2.0), Vec3::new(3.0, 0.0, 2.0)),
+        1.0,
+      ),
+      // Second platform.
+      (
+        1,
+        (Vec3::new(3.0, 1.75, 2.0), Vec3::new(5.0, 1.75, 2.0)),
+        (Vec3::new(3.0, 2.25, 0.0), Vec3::new(5.0, 2.25, 0.0)),
+        drop_cost,
+      ),
+      (
+        1,
+        (Vec3::new(3.25, 0.0, 2.0), Vec3::new(3.25, 2.0, 2.0)),
+        (Vec3::new(2.75, 0.0, 0.0), Vec3::new(2.75, 2.0, 0.0)),
+        drop_cost,
+      ),
+      (
+        2,
+        (Vec3::new(3.25, 0.0, 2.0), Vec3::new(3.25, 2.0, 2.0)),
+        (Vec3::new(2.0, 0.0, 2.0), Vec3::new(2.0, 2.0, 2.0)),
+        1.0,
+      ),
+      (
+        1,
+        (Vec3::new(5.0, 0.25, 2.0), Vec3::new(3.0, 0.25, 2.0)),
+        (Vec3::new(5.0, -0.25, 0.0), Vec3::new(3.0, -0.25, 0.0)),
+        drop_cost,
+      ),
+      (
+        1,
+        (Vec3::new(4.75, 2.0, 2.0), Vec3::new(4.75, 0.0, 2.0)),
+        (Vec3::new(5.25, 2.0, 0.0), Vec3::new(5.25, 0.0, 0.0)),
+        drop_cost,
+      ),
+    ]
+  );
+
+  // Generating again gives the same links.
+  expect_eq!(
+    archipelago
+      .generate_animation_links(&OPTIONS)
+      .iter()
+      .map(round_link)
+      .collect::<Vec<_>>(),
+    links.iter().map(round_link).collect::<Vec<_>>()
+  );
+}
+
+#[googletest::test]
+fn generated_links_connect_islands() {
+  let mut archipelago =
+    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(2.0, 2.0, 2.0),
+          Vec3::new(0.0, 2.0, 2.0),
+          Vec3::new(0.0, 0.0, 2.0),
+          Vec3::new(2.0, 0.0, 2.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+  // The lower island starts right below the ledge of the upper island.
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(4.0, 2.0, 0.0),
+          Vec3::new(2.0, 2.0, 0.0),
+          Vec3::new(2.0, 0.0, 0.0),
+          Vec3::new(4.0, 0.0, 0.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+
+  for link in archipelago.generate_animation_links(&OPTIONS) {
+    archipelago.add_animation_link(link);
+  }
+  archipelago.update(1.0);
+
+  let sample_distance =
+    archipelago.archipelago_options.point_sample_distance.clone();
+  let start_point = archipelago
+    .sample_point(
+      Vec3::new(1.0, 1.0, 2.0),
+      &sample_distance,
+      PermittedTypeIndices::All,
+    )
+    .unwrap();
+  let end_point = archipelago
+    .sample_point(
+      Vec3::new(3.5, 1.0, 0.0),
+      &sample_distance,
+      PermittedTypeIndices::All,
+    )
+    .unwrap();
+
+  // The islands are only connected by the generated drop link.
+  expect_that!(
+    archipelago.find_path(
+      &start_point,
+      &end_point,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    ),
+    ok(anything())
+  );
+}
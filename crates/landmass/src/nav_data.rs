@@ -2,26 +2,41 @@ use std::{
   collections::{HashMap, HashSet, VecDeque},
   mem::swap,
   ops::{Deref, DerefMut},
-  sync::Mutex,
+  sync::{Arc, Mutex},
 };
 
 use disjoint::DisjointSet;
-use geo::{BooleanOps, Coord, LineString, LinesIter, MultiPolygon, Polygon};
+use geo::{
+  Area, BooleanOps, Closest, ClosestPoint, Contains, Coord, Distance,
+  Euclidean, Line, LineString, LinesIter, MultiLineString, MultiPolygon, Point,
+  Polygon, Relate,
+};
 use glam::{Vec2, Vec3, Vec3Swizzles};
 use kdtree::{KdTree, distance::squared_euclidean};
 use slotmap::{DenseSlotMap, SlotMap, new_key_type};
 use thiserror::Error;
 
 use crate::{
-  CoordinateSystem, PermittedAnimationLinks,
+  CoordinateSystem, PermittedAnimationLinks, PermittedTypeIndices,
   coords::CorePointSampleDistance,
-  geometry::edge_intersection,
+  cost_volume::{
+    AddCostVolumeError, CostVolume, CostVolumeEffect, CostVolumeId,
+    NodeCostOverride,
+  },
+  geometry::{edge_intersection, project_point_to_line_segment},
   island::{Island, IslandId},
+  landmark::{Landmarks, LandmarksBuilder},
   link::{AnimationLink, AnimationLinkId, AnimationLinkState, NodePortal},
-  nav_mesh::{MeshEdgeRef, nav_mesh_node_bbh},
+  nav_mesh::{MeshEdgeRef, ValidPolygon, nav_mesh_node_bbh},
+  obstacle::{Obstacle, ObstacleId, ObstacleState, outline_to_multi_polygon},
+  pathfinding::PathNode,
+  region_graph::{ClusterId, RegionGraph},
   util::{BoundingBox, BoundingBoxHierarchy, RaySegment},
 };
 
+/// The number of landmarks to compute in each update.
+const LANDMARKS_PER_UPDATE: usize = 1;
+
 /// The navigation data of a whole [`crate::Archipelago`]. This only includes
 /// "static" features.
 pub(crate) struct NavigationData<CS: CoordinateSystem> {
@@ -42,10 +57,20 @@ pub(crate) struct NavigationData<CS: CoordinateSystem> {
   /// Connectedness of regions based on their "region number" in
   /// [`Self::region_id_to_number`].
   region_connections: Mutex<DisjointSet>,
+  /// The walkable layer of each region that is joined to other regions by
+  /// boundary links. Joined regions share a walkable layer, named after one of
+  /// them. Regions that are not in this map are their own walkable layer.
+  region_id_to_walkable_layer: HashMap<ClusterId, ClusterId>,
   /// Maps a region number (the key of [`Self::region_connections`]) to the set
   /// of links that **could** connect the regions if agents are allowed to use
   /// them.
   region_number_to_possible_links: HashMap<usize, Vec<PossibleRegionLink>>,
+  /// The pairs of regions connected by enabled off mesh links, along with the
+  /// kind of animation link connecting them (or [`None`] for boundary links).
+  /// The regions are only recomputed when these change.
+  region_links: HashSet<(ClusterId, ClusterId, Option<usize>)>,
+  /// Whether the regions changed in the last update.
+  pub(crate) regions_changed: bool,
   /// The links that go off the mesh, connecting two node refs.
   pub(crate) off_mesh_links: SlotMap<OffMeshLinkId, OffMeshLink>,
   /// The links that can be taken from a particular node ref.
@@ -59,6 +84,51 @@ pub(crate) struct NavigationData<CS: CoordinateSystem> {
   new_animation_links: HashSet<AnimationLinkId>,
   /// The set of animation links deleted since the last update.
   deleted_animation_links: HashSet<AnimationLinkId>,
+  /// The obstacles in the [`crate::Archipelago`].
+  obstacles: DenseSlotMap<ObstacleId, ObstacleState<CS>>,
+  /// The obstacles that carve each node.
+  node_to_obstacle_ids: HashMap<NodeRef, HashSet<ObstacleId>>,
+  /// The nodes that were carved by obstacles that have been deleted since the
+  /// last update.
+  deleted_obstacle_nodes: HashSet<NodeRef>,
+  /// The nodes that have been carved by obstacles.
+  pub(crate) carved_nodes: HashMap<NodeRef, CarvedNode>,
+  /// The off mesh links whose portals are entirely covered by obstacles.
+  pub(crate) blocked_off_mesh_links: HashSet<OffMeshLinkId>,
+  /// The off mesh links that can't be taken because their animation link or
+  /// one of their islands is disabled.
+  pub(crate) disabled_off_mesh_links: HashSet<OffMeshLinkId>,
+  /// Whether an animation link has been enabled or disabled since the last
+  /// update.
+  animation_links_enabled_changed: bool,
+  /// The cost volumes in the [`crate::Archipelago`].
+  cost_volumes: DenseSlotMap<CostVolumeId, CostVolume<CS>>,
+  /// Whether a cost volume has been added or removed since the last update.
+  cost_volumes_changed: bool,
+  /// The combined cost override of each node overlapped by cost volumes.
+  node_cost_overrides: HashMap<NodeRef, NodeCostOverride>,
+  /// The cost to use for each animation link that is currently full (and has
+  /// an [`AnimationLink::busy_cost`]).
+  pub(crate) busy_animation_link_costs: HashMap<AnimationLinkId, f32>,
+  /// The number of landmarks to compute for the landmark heuristic.
+  landmark_count: usize,
+  /// Whether the landmarks need to be recomputed.
+  landmarks_dirty: bool,
+  /// The states of the most recently selected landmarks. These are reused
+  /// when the landmarks are recomputed.
+  pub(crate) landmark_states: Vec<PathNode>,
+  /// The builder for the landmarks that are still being computed.
+  landmarks_builder: Option<LandmarksBuilder>,
+  /// The landmarks for the landmark heuristic. This is [`None`] if there are
+  /// no landmarks, or while the landmarks are being recomputed.
+  pub(crate) landmarks: Option<Arc<Landmarks>>,
+  /// Whether to use the region graph for hierarchical pathfinding.
+  hierarchical_pathfinding: bool,
+  /// Whether the region graph needs to be recomputed.
+  region_graph_dirty: bool,
+  /// The region graph for hierarchical pathfinding. This is [`None`] if
+  /// hierarchical pathfinding is disabled.
+  pub(crate) region_graph: Option<RegionGraph>,
 }
 
 /// A reference to a node in the navigation data.
@@ -116,8 +186,34 @@ pub(crate) enum KindedOffMeshLink {
   },
 }
 
+/// The point where a ray left the navigation data. See
+/// [`NavigationData::raycast`].
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct RaycastHit {
+  /// The point where the ray left the navigation data, in world space.
+  pub(crate) point: Vec3,
+  /// The horizontal normal of the edge that was hit, pointing away from the
+  /// navigation data.
+  pub(crate) normal: Vec3,
+  /// The last node the ray was on.
+  pub(crate) node: NodeRef,
+}
+
+/// The nearest point on the boundary of the navigation data. See
+/// [`NavigationData::find_nearest_wall`].
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct WallHit {
+  /// The nearest point on the boundary, in world space.
+  pub(crate) point: Vec3,
+  /// The horizontal distance to [`Self::point`].
+  pub(crate) distance: f32,
+  /// The horizontal normal of the boundary edge, pointing away from the
+  /// navigation data.
+  pub(crate) normal: Vec3,
+}
+
 /// A node that has been modified (e.g., by being connected with a boundary link
-/// to another island).
+/// to another island, or by being carved by an obstacle).
 #[derive(PartialEq, Debug, Clone)]
 pub(crate) struct ModifiedNode {
   /// The new (2D) edges that make up the boundary of this node. These are
@@ -131,6 +227,197 @@ pub(crate) struct ModifiedNode {
   pub(crate) new_vertices: Vec<Vec2>,
 }
 
+/// A node that has been carved by obstacles.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct CarvedNode {
+  /// The clearance of each edge after carving. Obstacles can only narrow the
+  /// original [`ValidPolygon::edge_clearances`].
+  pub(crate) edge_clearances: Vec<f32>,
+  /// The longest part of each edge that is not covered by an obstacle, as
+  /// fractions along the edge (from its first vertex to its second). Edges
+  /// entirely covered by obstacles have an empty range.
+  pub(crate) uncovered_edge_ranges: Vec<(f32, f32)>,
+  /// The (2D) edges of the obstacles inside the node, in world space. The
+  /// walkable side of each edge is on its left.
+  pub(crate) obstacle_edges: Vec<(Vec2, Vec2)>,
+  /// The (2D) walkable parts of the node that remain after carving, in world
+  /// space. Obstacles may split a node into several disconnected parts.
+  parts: Vec<Polygon<f32>>,
+  /// The corners of the walkable parts that point into the holes. Shortest
+  /// paths around the holes only turn at these corners.
+  pub(crate) detour_corners: Vec<DetourCorner>,
+  /// The visibility graph of the detour corners. Each entry holds the indices
+  /// of the other corners that can be walked to in a straight line from that
+  /// corner without entering a hole.
+  pub(crate) corner_visibility: Vec<Vec<usize>>,
+}
+
+/// A corner of the walkable parts of a [`CarvedNode`] that points into a hole.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct DetourCorner {
+  /// The corner, in world space.
+  pub(crate) point: Vec2,
+  /// The offset from the corner (per unit of agent radius) that keeps an agent
+  /// clear of both edges of the corner.
+  pub(crate) offset: Vec2,
+}
+
+impl CarvedNode {
+  /// Creates a carved node from the walkable `parts` of the node that remain
+  /// after carving by `obstacle_edges`. `node_polygon` is the whole node. All
+  /// are in world space.
+  fn new(
+    node_polygon: &MultiPolygon<f32>,
+    parts: Vec<Polygon<f32>>,
+    obstacle_edges: Vec<(Vec2, Vec2)>,
+    edge_clearances: Vec<f32>,
+    uncovered_edge_ranges: Vec<(f32, f32)>,
+  ) -> Self {
+    let walkable_parts = MultiPolygon::new(parts.clone());
+    let mut detour_corners = vec![];
+    for polygon in parts.iter() {
+      for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors())
+      {
+        let ring = &ring.0[..ring.0.len().saturating_sub(1)];
+        for (i, vertex) in ring.iter().enumerate() {
+          let previous = ring[(i + ring.len() - 1) % ring.len()];
+          let next = ring[(i + 1) % ring.len()];
+          let point = Vec2::new(vertex.x, vertex.y);
+          let to_previous =
+            (Vec2::new(previous.x, previous.y) - point).normalize_or_zero();
+          let to_next = (Vec2::new(next.x, next.y) - point).normalize_or_zero();
+          let Some(away) = (-to_previous - to_next).try_normalize() else {
+            continue;
+          };
+          // Only corners where moving away from both neighbouring vertices
+          // stays walkable point into a hole. Corners on the node's boundary
+          // are where an obstacle crosses into a neighbouring node, so they
+          // aren't corners of the hole.
+          if !walkable_parts.contains(&Point::new(
+            point.x + away.x * 1e-3,
+            point.y + away.y * 1e-3,
+          )) || node_polygon.iter().any(|polygon| {
+            Euclidean
+              .distance(&Point::new(point.x, point.y), polygon.exterior())
+              < 1e-4
+          }) {
+            continue;
+          }
+          // Moving along the bisector by the radius divided by the sine of
+          // half the hole's angle keeps the agent a radius away from both
+          // edges. Sharp corners are limited to twice the radius.
+          let half_angle_sine =
+            ((1.0 - to_previous.dot(to_next)) * 0.5).max(0.0).sqrt();
+          detour_corners.push(DetourCorner {
+            point,
+            offset: away / half_angle_sine.max(0.5),
+          });
+        }
+      }
+    }
+
+    let mut corner_visibility = vec![vec![]; detour_corners.len()];
+    for i in 0..detour_corners.len() {
+      for j in (i + 1)..detour_corners.len() {
+        let (from, to) = (detour_corners[i].point, detour_corners[j].point);
+        if walkable_parts
+          .relate(&Line::new(
+            Coord::from((from.x, from.y)),
+            Coord::from((to.x, to.y)),
+          ))
+          .is_covers()
+        {
+          corner_visibility[i].push(j);
+          corner_visibility[j].push(i);
+        }
+      }
+    }
+
+    Self {
+      edge_clearances,
+      uncovered_edge_ranges,
+      obstacle_edges,
+      parts,
+      detour_corners,
+      corner_visibility,
+    }
+  }
+
+  /// Finds the indices of the walkable parts that `segment` (in world space)
+  /// touches. The segment may be degenerate (a single point).
+  pub(crate) fn parts_touching(&self, segment: (Vec3, Vec3)) -> Vec<usize> {
+    let line = Line::new(
+      Coord::from((segment.0.x, segment.0.y)),
+      Coord::from((segment.1.x, segment.1.y)),
+    );
+    self
+      .parts
+      .iter()
+      .enumerate()
+      .filter(|(_, part)| Euclidean.distance(&line, *part) < 1e-4)
+      .map(|(index, _)| index)
+      .collect()
+  }
+
+  /// The (2D) walkable parts of the node, in world space.
+  pub(crate) fn walkable_parts(&self) -> MultiPolygon<f32> {
+    MultiPolygon::new(self.parts.clone())
+  }
+
+  /// Whether obstacles cover the entire node, leaving no walkable parts.
+  pub(crate) fn is_fully_covered(&self) -> bool {
+    self.parts.is_empty()
+  }
+
+  /// Finds the point on the walkable parts of the node that is closest to
+  /// `point` (in world space). Returns None if the node is entirely covered.
+  fn closest_walkable_point(&self, point: Vec2) -> Option<Vec2> {
+    let point = Point::new(point.x, point.y);
+    self
+      .parts
+      .iter()
+      .filter_map(|part| match part.closest_point(&point) {
+        Closest::Intersection(closest) | Closest::SinglePoint(closest) => {
+          Some(Vec2::new(closest.x(), closest.y()))
+        }
+        Closest::Indeterminate => None,
+      })
+      .min_by(|a, b| {
+        let point = Vec2::new(point.x(), point.y());
+        a.distance_squared(point).total_cmp(&b.distance_squared(point))
+      })
+  }
+
+  /// Finds the first obstacle edge that the ray from `start` along `direction`
+  /// enters the obstacle through before `max_t`. Returns the fraction along
+  /// the ray and the obstacle edge.
+  fn raycast_obstacles(
+    &self,
+    start: Vec2,
+    direction: Vec2,
+    max_t: f32,
+  ) -> Option<(f32, Vec2)> {
+    let mut hit = None;
+    for &(edge_start, edge_end) in self.obstacle_edges.iter() {
+      let edge = edge_end - edge_start;
+      let direction_cross = edge.perp_dot(direction);
+      if direction_cross >= 0.0 {
+        // The ray is moving parallel to or away from this edge.
+        continue;
+      }
+      let t = edge.perp_dot(start - edge_start) / -direction_cross;
+      let s = direction.perp_dot(start - edge_start) / -direction_cross;
+      if t < 0.0 || !(0.0..=1.0).contains(&s) {
+        continue;
+      }
+      if t < max_t && hit.is_none_or(|(hit_t, _)| t < hit_t) {
+        hit = Some((t, edge));
+      }
+    }
+    hit
+  }
+}
+
 /// A possible connection between two regions.
 #[derive(Debug)]
 struct PossibleRegionLink {
@@ -152,13 +439,35 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       dirty: false,
       region_id_to_number: HashMap::new(),
       region_connections: Mutex::new(DisjointSet::new()),
+      region_id_to_walkable_layer: HashMap::new(),
       region_number_to_possible_links: HashMap::default(),
+      region_links: HashSet::new(),
+      regions_changed: false,
       off_mesh_links: SlotMap::with_key(),
       node_to_off_mesh_link_ids: HashMap::new(),
       modified_nodes: HashMap::new(),
       deleted_islands: HashSet::new(),
       new_animation_links: HashSet::new(),
       deleted_animation_links: HashSet::new(),
+      obstacles: DenseSlotMap::with_key(),
+      node_to_obstacle_ids: HashMap::new(),
+      deleted_obstacle_nodes: HashSet::new(),
+      carved_nodes: HashMap::new(),
+      blocked_off_mesh_links: HashSet::new(),
+      disabled_off_mesh_links: HashSet::new(),
+      animation_links_enabled_changed: false,
+      landmark_count: 0,
+      landmarks_dirty: false,
+      landmark_states: vec![],
+      landmarks_builder: None,
+      landmarks: None,
+      hierarchical_pathfinding: false,
+      region_graph_dirty: false,
+      region_graph: None,
+      cost_volumes: DenseSlotMap::with_key(),
+      cost_volumes_changed: false,
+      node_cost_overrides: HashMap::new(),
+      busy_animation_link_costs: HashMap::new(),
     }
   }
 
@@ -192,6 +501,31 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       .map(|(&type_index, &cost)| (type_index, cost))
   }
 
+  /// Sets the number of landmarks to compute for the landmark heuristic. The
+  /// landmarks are recomputed starting on the next update.
+  pub(crate) fn set_landmark_count(&mut self, landmark_count: usize) {
+    if self.landmark_count == landmark_count {
+      return;
+    }
+    self.landmark_count = landmark_count;
+    self.landmarks_dirty = true;
+    self.dirty = true;
+  }
+
+  /// Sets whether to use the region graph for hierarchical pathfinding. The
+  /// region graph is recomputed on the next update.
+  pub(crate) fn set_hierarchical_pathfinding(
+    &mut self,
+    hierarchical_pathfinding: bool,
+  ) {
+    if self.hierarchical_pathfinding == hierarchical_pathfinding {
+      return;
+    }
+    self.hierarchical_pathfinding = hierarchical_pathfinding;
+    self.region_graph_dirty = true;
+    self.dirty = true;
+  }
+
   /// Adds a new island to the navigation data.
   pub(crate) fn add_island(&mut self, island: Island<CS>) -> IslandId {
     // A new island means a new nav mesh - so mark it dirty.
@@ -237,6 +571,11 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     &mut self,
     link: AnimationLink<CS>,
   ) -> AnimationLinkId {
+    debug_assert!(
+      link.busy_cost.is_none_or(|busy_cost| busy_cost >= 0.0),
+      "The busy cost {:?} is negative or NaN. Busy costs must be non-negative.",
+      link.busy_cost
+    );
     self.dirty = true;
     let link_id = self.animation_links.insert(AnimationLinkState::new(link));
     self.new_animation_links.insert(link_id);
@@ -257,23 +596,498 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     self.animation_links.get(link_id).map(|state| &state.main_link)
   }
 
+  /// Sets whether the animation link with `link_id` is enabled. Panics if the
+  /// animation link ID is not in the navigation data.
+  pub(crate) fn set_animation_link_enabled(
+    &mut self,
+    link_id: AnimationLinkId,
+    enabled: bool,
+  ) {
+    let link = self
+      .animation_links
+      .get_mut(link_id)
+      .expect("Animation link should be present in the Archipelago");
+    if link.enabled == enabled {
+      return;
+    }
+    link.enabled = enabled;
+    self.dirty = true;
+    self.animation_links_enabled_changed = true;
+  }
+
+  /// Returns whether the animation link with `link_id` is enabled.
+  pub(crate) fn is_animation_link_enabled(
+    &self,
+    link_id: AnimationLinkId,
+  ) -> Option<bool> {
+    self.animation_links.get(link_id).map(|state| state.enabled)
+  }
+
   pub fn get_animation_link_ids(
     &self,
   ) -> impl ExactSizeIterator<Item = AnimationLinkId> {
     self.animation_links.keys()
   }
 
+  /// Adds a new obstacle to the navigation data.
+  pub(crate) fn add_obstacle(&mut self, obstacle: Obstacle<CS>) -> ObstacleId {
+    self.dirty = true;
+    self.obstacles.insert(ObstacleState::new(obstacle))
+  }
+
+  /// Removes the obstacle with `obstacle_id`. Panics if the obstacle ID is not
+  /// in the navigation data.
+  pub(crate) fn remove_obstacle(&mut self, obstacle_id: ObstacleId) {
+    self.dirty = true;
+    let state = self
+      .obstacles
+      .remove(obstacle_id)
+      .expect("Obstacle should be present in the Archipelago");
+    for node_ref in state.carved_nodes {
+      remove_node_obstacle(
+        &mut self.node_to_obstacle_ids,
+        node_ref,
+        obstacle_id,
+      );
+      self.deleted_obstacle_nodes.insert(node_ref);
+    }
+  }
+
+  /// Gets a borrow to the obstacle with `id`.
+  pub(crate) fn get_obstacle(&self, id: ObstacleId) -> Option<&Obstacle<CS>> {
+    self.obstacles.get(id).map(|state| &state.obstacle)
+  }
+
+  /// Gets a mutable borrow to the obstacle with `id`.
+  pub(crate) fn get_obstacle_mut(
+    &mut self,
+    id: ObstacleId,
+  ) -> Option<ObstacleMut<'_, CS>> {
+    self.obstacles.get_mut(id).map(|state| ObstacleMut {
+      id,
+      obstacle: &mut state.obstacle,
+      obstacle_dirty_flag: &mut state.dirty,
+      dirty_flag: &mut self.dirty,
+    })
+  }
+
+  pub(crate) fn get_obstacle_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = ObstacleId> + '_ {
+    self.obstacles.keys()
+  }
+
+  /// Adds a new cost volume to the navigation data.
+  pub(crate) fn add_cost_volume(
+    &mut self,
+    cost_volume: CostVolume<CS>,
+  ) -> Result<CostVolumeId, AddCostVolumeError> {
+    if let CostVolumeEffect::CostMultiplier(cost_multiplier) =
+      cost_volume.effect
+      && cost_multiplier <= 0.0
+    {
+      return Err(AddCostVolumeError::NonPositiveCostMultiplier(
+        cost_multiplier,
+      ));
+    }
+    if cost_volume.transform.scale <= 0.0
+      || cost_volume.transform.scale.is_nan()
+    {
+      return Err(AddCostVolumeError::NonPositiveScale(
+        cost_volume.transform.scale,
+      ));
+    }
+    self.dirty = true;
+    self.cost_volumes_changed = true;
+    Ok(self.cost_volumes.insert(cost_volume))
+  }
+
+  /// Removes the cost volume with `cost_volume_id`. Panics if the cost volume
+  /// ID is not in the navigation data.
+  pub(crate) fn remove_cost_volume(&mut self, cost_volume_id: CostVolumeId) {
+    self.dirty = true;
+    self.cost_volumes_changed = true;
+    self
+      .cost_volumes
+      .remove(cost_volume_id)
+      .expect("Cost volume should be present in the Archipelago");
+  }
+
+  /// Gets a borrow to the cost volume with `id`.
+  pub(crate) fn get_cost_volume(
+    &self,
+    id: CostVolumeId,
+  ) -> Option<&CostVolume<CS>> {
+    self.cost_volumes.get(id)
+  }
+
+  pub(crate) fn get_cost_volume_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = CostVolumeId> + '_ {
+    self.cost_volumes.keys()
+  }
+
+  /// Gets the type index of `node_ref`, after applying any cost volumes.
+  pub(crate) fn node_type_index(&self, node_ref: NodeRef) -> usize {
+    if let Some(type_index) = self
+      .node_cost_overrides
+      .get(&node_ref)
+      .and_then(|cost_override| cost_override.type_index)
+    {
+      return type_index;
+    }
+    let island = self.islands.get(node_ref.island_id).unwrap();
+    island.nav_mesh.polygons[node_ref.polygon_index].type_index
+  }
+
+  /// Gets the multiplier that cost volumes apply to the cost of `node_ref`.
+  pub(crate) fn node_cost_multiplier(&self, node_ref: NodeRef) -> f32 {
+    self
+      .node_cost_overrides
+      .get(&node_ref)
+      .map_or(1.0, |cost_override| cost_override.cost_multiplier)
+  }
+
+  /// Gets the type indices that cost volumes apply to nodes.
+  pub(crate) fn cost_volume_type_indices(
+    &self,
+  ) -> impl Iterator<Item = usize> + '_ {
+    self.cost_volumes.values().filter_map(|cost_volume| {
+      match cost_volume.effect {
+        CostVolumeEffect::TypeIndex(type_index) => Some(type_index),
+        CostVolumeEffect::CostMultiplier(_) => None,
+      }
+    })
+  }
+
+  /// Gets the smallest multiplier that cost volumes can apply to any node.
+  /// This is never more than 1.0 (since nodes without cost volumes are not
+  /// multiplied at all).
+  pub(crate) fn cheapest_cost_multiplier(&self) -> f32 {
+    self
+      .node_cost_overrides
+      .values()
+      .map(|cost_override| cost_override.cost_multiplier)
+      .fold(1.0, f32::min)
+  }
+
+  /// Determines whether an agent with `agent_radius` can cross `edge_index` of
+  /// `node_ref`, accounting for any obstacles carving the node or the node on
+  /// the other side of the edge.
+  pub(crate) fn can_cross_edge(
+    &self,
+    node_ref: NodeRef,
+    edge_index: usize,
+    agent_radius: f32,
+  ) -> bool {
+    let island = self.islands.get(node_ref.island_id).unwrap();
+    let polygon = &island.nav_mesh.polygons[node_ref.polygon_index];
+    let mut clearance =
+      polygon.edge_clearances[edge_index] * island.transform.scale;
+    if let Some(carved_node) = self.carved_nodes.get(&node_ref) {
+      clearance = clearance.min(carved_node.edge_clearances[edge_index]);
+    }
+    if let Some(connection) = &polygon.connectivity[edge_index]
+      && let Some(carved_node) = self.carved_nodes.get(&NodeRef {
+        island_id: node_ref.island_id,
+        polygon_index: connection.polygon_index,
+      })
+    {
+      clearance =
+        clearance.min(carved_node.edge_clearances[connection.reverse_edge]);
+    }
+    // Edges entirely covered by obstacles have no clearance and can't be
+    // crossed even by agents with no radius.
+    clearance > 0.0 && clearance >= 2.0 * agent_radius
+  }
+
+  /// Finds the point on `node` that is horizontally closest to `point`. Both
+  /// `point` and the result are in world space.
+  pub(crate) fn closest_point_on_node(
+    &self,
+    point: Vec3,
+    node: NodeRef,
+  ) -> Vec3 {
+    let island = self.get_island(node.island_id).unwrap();
+    let point = island.transform.apply_inverse(point);
+    let point =
+      island.nav_mesh.closest_point_on_node(point, node.polygon_index);
+    island.transform.apply(point)
+  }
+
+  /// Casts a ray horizontally from `start_point` (on `start_node`) towards
+  /// `end_point`, walking through connected nodes and boundary links. Returns
+  /// None if the ray reaches `end_point` without leaving the navigation data.
+  /// Both points are in world space.
+  pub(crate) fn raycast(
+    &self,
+    start_node: NodeRef,
+    start_point: Vec3,
+    end_point: Vec3,
+  ) -> Option<RaycastHit> {
+    let start = start_point.xy();
+    let direction = end_point.xy() - start;
+
+    let mut node = start_node;
+    let mut visited_nodes = HashSet::new();
+    loop {
+      visited_nodes.insert(node);
+      let island = self.get_island(node.island_id).unwrap();
+      let polygon = &island.nav_mesh.polygons[node.polygon_index];
+
+      // Find the edge that the ray leaves the polygon through. Since the
+      // polygon is convex, this is the first edge the ray crosses to the
+      // outside of.
+      let mut exit = None;
+      for (edge_index, (&i, &j)) in polygon
+        .vertices
+        .iter()
+        .zip(polygon.vertices.iter().cycle().skip(1))
+        .enumerate()
+      {
+        let edge_start =
+          island.transform.apply(island.nav_mesh.vertices[i]).xy();
+        let edge_end = island.transform.apply(island.nav_mesh.vertices[j]).xy();
+        let edge = edge_end - edge_start;
+        let direction_cross = edge.perp_dot(direction);
+        if direction_cross >= 0.0 {
+          // The ray is moving parallel to or into this edge.
+          continue;
+        }
+        let t = edge.perp_dot(start - edge_start) / -direction_cross;
+        if exit.is_none_or(|(_, exit_t, _)| t < exit_t) {
+          exit = Some((edge_index, t, edge));
+        }
+      }
+
+      let Some((edge_index, t, edge)) = exit else {
+        // The ray has no horizontal length, so it can't leave the node.
+        return None;
+      };
+      // Obstacles cut holes into carved nodes, so the ray may hit one before
+      // leaving the node.
+      if let Some((obstacle_t, obstacle_edge)) =
+        self.carved_nodes.get(&node).and_then(|carved_node| {
+          carved_node.raycast_obstacles(start, direction, t.min(1.0))
+        })
+      {
+        let hit_point = (start + direction * obstacle_t).extend(start_point.z);
+        return Some(RaycastHit {
+          point: self.closest_point_on_node(hit_point, node),
+          normal: Vec3::new(obstacle_edge.y, -obstacle_edge.x, 0.0).normalize(),
+          node,
+        });
+      }
+      if t >= 1.0 {
+        return None;
+      }
+      let exit_point = (start + direction * t.max(0.0)).extend(start_point.z);
+
+      let next_node = match &polygon.connectivity[edge_index] {
+        Some(connectivity) => Some(NodeRef {
+          island_id: node.island_id,
+          polygon_index: connectivity.polygon_index,
+        }),
+        None => self.boundary_link_destination(node, exit_point),
+      };
+      match next_node {
+        Some(next_node) if !visited_nodes.contains(&next_node) => {
+          node = next_node;
+        }
+        _ => {
+          return Some(RaycastHit {
+            point: self.closest_point_on_node(exit_point, node),
+            normal: Vec3::new(edge.y, -edge.x, 0.0).normalize(),
+            node,
+          });
+        }
+      }
+    }
+  }
+
+  /// Finds the node that a boundary link on `node` leads to if the boundary
+  /// link's portal contains `point` (horizontally).
+  fn boundary_link_destination(
+    &self,
+    node: NodeRef,
+    point: Vec3,
+  ) -> Option<NodeRef> {
+    const PORTAL_EPSILON: f32 = 1e-4;
+
+    let point = point.xy().extend(0.0);
+    self.node_to_off_mesh_link_ids.get(&node)?.iter().find_map(|link_id| {
+      let link = self.off_mesh_links.get(*link_id).unwrap();
+      if !matches!(link.kinded, KindedOffMeshLink::BoundaryLink { .. })
+        || self.disabled_off_mesh_links.contains(link_id)
+      {
+        return None;
+      }
+      let portal =
+        (link.portal.0.xy().extend(0.0), link.portal.1.xy().extend(0.0));
+      let (projected_point, _) = project_point_to_line_segment(point, portal);
+      (projected_point.distance_squared(point)
+        <= PORTAL_EPSILON * PORTAL_EPSILON)
+        .then_some(link.destination_node)
+    })
+  }
+
+  /// Finds the nearest point on the boundary of the navigation data to
+  /// `point` (on `start_node`), within `max_distance` (horizontally). Boundary
+  /// links between islands are not considered part of the boundary, but the
+  /// edges of holes cut by obstacles are. `point` and the result are in world
+  /// space.
+  pub(crate) fn find_nearest_wall(
+    &self,
+    start_node: NodeRef,
+    point: Vec3,
+    max_distance: f32,
+  ) -> Option<WallHit> {
+    let flat_point = point.xy().extend(0.0);
+    let distance_to_edge = |edge: (Vec2, Vec2)| {
+      let (projected_point, _) = project_point_to_line_segment(
+        flat_point,
+        (edge.0.extend(0.0), edge.1.extend(0.0)),
+      );
+      (projected_point, projected_point.distance(flat_point))
+    };
+
+    let mut best: Option<(WallHit, NodeRef)> = None;
+    let mut explored_nodes = HashSet::new();
+    let mut next_nodes = vec![start_node];
+    while let Some(node) = next_nodes.pop() {
+      if !explored_nodes.insert(node) {
+        continue;
+      }
+      let carved_node = self.carved_nodes.get(&node);
+      if carved_node.is_some_and(CarvedNode::is_fully_covered) {
+        // Nodes entirely covered by obstacles are behind the walls of the
+        // holes in their neighbours.
+        continue;
+      }
+      let best_distance = best.map_or(max_distance, |(hit, _)| hit.distance);
+
+      let island = self.get_island(node.island_id).unwrap();
+      let polygon = &island.nav_mesh.polygons[node.polygon_index];
+      let vertex_to_world =
+        |index: usize| island.transform.apply(island.nav_mesh.vertices[index]);
+
+      // Boundary edges of the node, written counter-clockwise.
+      let mut boundary_edges: Vec<(Vec2, Vec2)> =
+        match self.modified_nodes.get(&node) {
+          Some(modified_node) => {
+            let index_to_vertex = |index: usize| {
+              if index >= island.nav_mesh.vertices.len() {
+                modified_node.new_vertices
+                  [index - island.nav_mesh.vertices.len()]
+              } else {
+                vertex_to_world(index).xy()
+              }
+            };
+            modified_node
+              .new_boundary
+              .iter()
+              .map(|&(left, right)| {
+                (index_to_vertex(left), index_to_vertex(right))
+              })
+              .collect()
+          }
+          None => polygon
+            .connectivity
+            .iter()
+            .enumerate()
+            .filter(|(_, connectivity)| connectivity.is_none())
+            .map(|(edge_index, _)| {
+              let (end, start) = polygon.get_edge_indices(edge_index);
+              (vertex_to_world(start).xy(), vertex_to_world(end).xy())
+            })
+            .collect(),
+        };
+      // Obstacles cut holes into carved nodes. The modified boundary drops
+      // short edges near the original vertices, so use the obstacle edges
+      // directly.
+      if let Some(carved_node) = carved_node {
+        boundary_edges.extend(carved_node.obstacle_edges.iter().copied());
+      }
+
+      let mut node_best_distance = best_distance;
+      for edge in boundary_edges {
+        let (projected_point, distance) = distance_to_edge(edge);
+        if distance > node_best_distance {
+          continue;
+        }
+        node_best_distance = distance;
+        let edge_direction = edge.1 - edge.0;
+        best = Some((
+          WallHit {
+            point: projected_point,
+            distance,
+            normal: Vec3::new(edge_direction.y, -edge_direction.x, 0.0)
+              .normalize(),
+          },
+          node,
+        ));
+      }
+
+      for (edge_index, connectivity) in polygon.connectivity.iter().enumerate()
+      {
+        let Some(connectivity) = connectivity else {
+          continue;
+        };
+        let (end, start) = polygon.get_edge_indices(edge_index);
+        let (_, distance) = distance_to_edge((
+          vertex_to_world(start).xy(),
+          vertex_to_world(end).xy(),
+        ));
+        if distance <= node_best_distance {
+          next_nodes.push(NodeRef {
+            island_id: node.island_id,
+            polygon_index: connectivity.polygon_index,
+          });
+        }
+      }
+      for link_id in
+        self.node_to_off_mesh_link_ids.get(&node).into_iter().flatten()
+      {
+        let link = self.off_mesh_links.get(*link_id).unwrap();
+        if !matches!(link.kinded, KindedOffMeshLink::BoundaryLink { .. })
+          || self.disabled_off_mesh_links.contains(link_id)
+        {
+          continue;
+        }
+        let (_, distance) =
+          distance_to_edge((link.portal.0.xy(), link.portal.1.xy()));
+        if distance <= node_best_distance {
+          next_nodes.push(link.destination_node);
+        }
+      }
+    }
+
+    best.map(|(hit, node)| WallHit {
+      point: self.closest_point_on_node(hit.point, node),
+      ..hit
+    })
+  }
+
   /// Finds the node nearest to (and within `distance_to_node` of) `point`.
-  /// Returns the point on the nav data nearest to `point` and the reference to
-  /// the corresponding node.
+  /// Only nodes whose type index is in `permitted_type_indices` are
+  /// considered. Returns the point on the nav data nearest to `point` and the
+  /// reference to the corresponding node.
   pub(crate) fn sample_point(
     &self,
     point: Vec3,
     point_sample_distance: &CorePointSampleDistance,
+    permitted_type_indices: &PermittedTypeIndices,
   ) -> Option<(Vec3, NodeRef)> {
     let mut best_point = None;
     for (island_id, island) in self.islands.iter() {
+      if !island.enabled {
+        continue;
+      }
       let relative_point = island.transform.apply_inverse(point);
+      // The sample distances are measured along the island's axes, so tilted
+      // islands are sampled along their own "up" direction.
+      let point_sample_distance =
+        &point_sample_distance.to_scaled_frame(island.transform.scale);
       if !island
         .nav_mesh
         .mesh_bounds
@@ -300,13 +1114,36 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
 
       let (sampled_point, sampled_node) = match island
         .nav_mesh
-        .sample_point(relative_point, point_sample_distance)
-      {
+        .sample_point_filtered(relative_point, point_sample_distance, |node| {
+          let node_ref = NodeRef { island_id, polygon_index: node };
+          permitted_type_indices.is_permitted(self.node_type_index(node_ref))
+            && !self
+              .carved_nodes
+              .get(&node_ref)
+              .is_some_and(|carved_node| carved_node.parts.is_empty())
+        }) {
         Some(sampled) => sampled,
         None => continue,
       };
 
-      let distance = relative_point.distance_squared(sampled_point);
+      let mut sampled_point = island.transform.apply(sampled_point);
+      let sampled_node_ref = NodeRef { island_id, polygon_index: sampled_node };
+      if let Some(carved_node) = self.carved_nodes.get(&sampled_node_ref) {
+        // Points inside the holes cut by obstacles are moved out of the holes.
+        let walkable_point = carved_node
+          .closest_walkable_point(sampled_point.xy())
+          .expect("fully covered nodes are filtered out");
+        if walkable_point.distance(point.xy())
+          > point_sample_distance.horizontal_distance * island.transform.scale
+        {
+          continue;
+        }
+        sampled_point = self.closest_point_on_node(
+          walkable_point.extend(sampled_point.z),
+          sampled_node_ref,
+        );
+      }
+      let distance = point.distance_squared(sampled_point);
       match best_point {
         Some((best_distance, _)) if distance >= best_distance => continue,
         _ => {}
@@ -314,10 +1151,7 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
 
       best_point = Some((
         distance,
-        (
-          island.transform.apply(sampled_point),
-          NodeRef { island_id, polygon_index: sampled_node },
-        ),
+        (sampled_point, NodeRef { island_id, polygon_index: sampled_node }),
       ));
     }
     best_point.map(|(_, b)| b)
@@ -723,7 +1557,7 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
           }
           node_bbh = Some(nav_mesh_node_bbh(
             island.nav_mesh.as_ref(),
-            Vec3::new(0.0, 0.0, max_vertical_distance),
+            Vec3::new(0.0, 0.0, max_vertical_distance / island.transform.scale),
           ));
         }
 
@@ -735,6 +1569,8 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
           node_bbh: &BoundingBoxHierarchy<usize>,
           max_vertical_distance: f32,
         ) -> Vec<NodePortal> {
+          let max_vertical_distance =
+            max_vertical_distance / island.transform.scale;
           if edge.0 == edge.1 {
             let point = island.transform.apply_inverse(edge.0);
             island
@@ -833,25 +1669,24 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     // data (the nav mesh was removed), should be removed.
     let Some(island) = self.islands.get(node_ref.island_id) else {
       self.modified_nodes.remove(&node_ref);
+      self.carved_nodes.remove(&node_ref);
       return;
     };
-    // Any nodes without off mesh links don't need to be modified.
-    let Some(off_mesh_links) = self.node_to_off_mesh_link_ids.get(&node_ref)
-    else {
-      self.modified_nodes.remove(&node_ref);
-      return;
-    };
-
-    // If none of the off mesh links for this node are boundary links, the node
-    // doesn't need to be modified.
-    'has_boundary_link: {
-      for off_mesh_link in off_mesh_links {
+    let off_mesh_links = self.node_to_off_mesh_link_ids.get(&node_ref);
+    // Only boundary links modify the boundary of the node.
+    let has_boundary_link = off_mesh_links.is_some_and(|off_mesh_links| {
+      off_mesh_links.iter().any(|off_mesh_link| {
         let link = self.off_mesh_links.get(*off_mesh_link).unwrap();
-        if let KindedOffMeshLink::BoundaryLink { .. } = &link.kinded {
-          break 'has_boundary_link;
-        }
-      }
+        matches!(link.kinded, KindedOffMeshLink::BoundaryLink { .. })
+      })
+    });
+    let obstacle_footprint = self.obstacle_footprint(node_ref);
+
+    // If the node has no boundary links and isn't carved by any obstacles, the
+    // node doesn't need to be modified.
+    if !has_boundary_link && obstacle_footprint.is_none() {
       self.modified_nodes.remove(&node_ref);
+      self.carved_nodes.remove(&node_ref);
       return;
     }
 
@@ -904,7 +1739,7 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       multi_line_string.push(LineString(current_line_string));
     }
 
-    let boundary_edges = geo::MultiLineString(multi_line_string);
+    let mut boundary_edges = MultiLineString(multi_line_string);
 
     fn boundary_link_to_clip_polygon(
       link: &OffMeshLink,
@@ -924,18 +1759,140 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       )])
     }
 
-    let mut clip_polygons = off_mesh_links
-      .iter()
-      .map(|link_id| self.off_mesh_links.get(*link_id).unwrap())
-      .map(|link| boundary_link_to_clip_polygon(link, edge_link_distance));
+    if has_boundary_link {
+      let mut clip_polygons = off_mesh_links
+        .unwrap()
+        .iter()
+        .map(|link_id| self.off_mesh_links.get(*link_id).unwrap())
+        .map(|link| boundary_link_to_clip_polygon(link, edge_link_distance));
 
-    let mut link_clip = clip_polygons.next().unwrap();
-    for clip_polygon in clip_polygons {
-      link_clip = link_clip.union(&clip_polygon);
+      let mut link_clip = clip_polygons.next().unwrap();
+      for clip_polygon in clip_polygons {
+        link_clip = link_clip.union(&clip_polygon);
+      }
+
+      boundary_edges = link_clip.clip(&boundary_edges, /* invert= */ true);
+    }
+
+    if let Some(obstacle_footprint) = &obstacle_footprint {
+      // Walls covered by obstacles are replaced by the outline of the
+      // obstacles (below).
+      boundary_edges =
+        obstacle_footprint.clip(&boundary_edges, /* invert= */ true);
     }
 
-    let clipped_boundary_edges =
-      link_clip.clip(&boundary_edges, /* invert= */ true);
+    let polygon_center = island.transform.apply(polygon.center).xy();
+
+    // Ensure the winding order of the modified node boundary matches the
+    // polygon edges.
+    let mut new_edges = boundary_edges
+      .lines_iter()
+      .map(|edge| (coord_to_vec2(edge.start), coord_to_vec2(edge.end)))
+      .map(|(start, end)| {
+        if (start - polygon_center).perp_dot(end - polygon_center) < 0.0 {
+          (end, start)
+        } else {
+          (start, end)
+        }
+      })
+      .collect::<Vec<_>>();
+
+    if let Some(obstacle_footprint) = &obstacle_footprint {
+      let obstacle_outline = MultiLineString(
+        obstacle_footprint
+          .iter()
+          .flat_map(|polygon| {
+            std::iter::once(polygon.exterior())
+              .chain(polygon.interiors())
+              .cloned()
+          })
+          .collect(),
+      );
+      let node_polygon = node_to_multi_polygon(island, polygon);
+      let clipped_obstacle_outline =
+        node_polygon.clip(&obstacle_outline, /* invert= */ false);
+      let mut obstacle_edges = vec![];
+      for edge in clipped_obstacle_outline.lines_iter() {
+        let (start, end) = (coord_to_vec2(edge.start), coord_to_vec2(edge.end));
+        if start.distance_squared(end) < 1e-8 {
+          continue;
+        }
+        // The walkable side of the boundary is on the left, so the obstacle
+        // must be on the right.
+        let left_of_midpoint =
+          start.midpoint(end) + (end - start).normalize().perp() * 1e-3;
+        if obstacle_footprint
+          .contains(&Point::from(vec2_to_coord(left_of_midpoint)))
+        {
+          obstacle_edges.push((end, start));
+        } else {
+          obstacle_edges.push((start, end));
+        }
+      }
+      new_edges.extend(obstacle_edges.iter().copied());
+
+      // Obstacles narrow the connections to other nodes, so limit the
+      // clearance of connected edges to the longest part not covered by an
+      // obstacle.
+      let mut edge_clearances = polygon
+        .edge_clearances
+        .iter()
+        .map(|clearance| clearance * island.transform.scale)
+        .collect::<Vec<_>>();
+      let mut uncovered_edge_ranges = vec![(0.0, 1.0); polygon.vertices.len()];
+      for (edge_index, clearance) in edge_clearances.iter_mut().enumerate() {
+        if polygon.connectivity[edge_index].is_none() {
+          continue;
+        }
+        let (i, j) = polygon.get_edge_indices(edge_index);
+        let mut edge = vec![];
+        push_vertex(i, island, &mut edge);
+        push_vertex(j, island, &mut edge);
+        let (edge_start, edge_end) =
+          (coord_to_vec2(edge[0]), coord_to_vec2(edge[1]));
+        let uncovered_edge = obstacle_footprint.clip(
+          &MultiLineString(vec![LineString(edge)]),
+          /* invert= */ true,
+        );
+        let edge_fraction = |point: Vec2| {
+          (point - edge_start).dot(edge_end - edge_start)
+            / edge_start.distance_squared(edge_end)
+        };
+        let (longest_uncovered, uncovered_range) = uncovered_edge
+          .iter()
+          .filter_map(|line_string| {
+            let start = coord_to_vec2(*line_string.0.first()?);
+            let end = coord_to_vec2(*line_string.0.last()?);
+            let (start_fraction, end_fraction) =
+              (edge_fraction(start), edge_fraction(end));
+            Some((
+              start.distance(end),
+              (
+                start_fraction.min(end_fraction).clamp(0.0, 1.0),
+                start_fraction.max(end_fraction).clamp(0.0, 1.0),
+              ),
+            ))
+          })
+          .fold((0.0, (0.0, 0.0)), |longest, uncovered| {
+            if uncovered.0 > longest.0 { uncovered } else { longest }
+          });
+        *clearance = clearance.min(longest_uncovered);
+        uncovered_edge_ranges[edge_index] = uncovered_range;
+      }
+      let parts = node_polygon.difference(obstacle_footprint).0;
+      self.carved_nodes.insert(
+        node_ref,
+        CarvedNode::new(
+          &node_polygon,
+          parts,
+          obstacle_edges,
+          edge_clearances,
+          uncovered_edge_ranges,
+        ),
+      );
+    } else {
+      self.carved_nodes.remove(&node_ref);
+    }
 
     let mut original_vertices = KdTree::new(/* dimensions= */ 2);
     for &index in polygon.vertices.iter() {
@@ -953,64 +1910,270 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       });
     let modified_node = modified_node.get_mut();
 
-    for line_string in clipped_boundary_edges.iter() {
-      for edge in line_string.lines_iter() {
-        let start_index = original_vertices
-          .nearest(
-            &[edge.start.x, edge.start.y],
-            /* num= */ 1,
-            &squared_euclidean,
-          )
-          .unwrap()
-          .first()
-          .filter(|&(distance, _)| *distance < 0.01)
-          .map(|&(_, &index)| index);
-
-        let end_index = original_vertices
-          .nearest(
-            &[edge.end.x, edge.end.y],
-            /* num= */ 1,
-            &squared_euclidean,
-          )
-          .unwrap()
-          .first()
-          .filter(|&(distance, _)| *distance < 0.01)
-          .map(|&(_, &index)| index);
+    let original_vertex_index = |point: Vec2| {
+      original_vertices
+        .nearest(&[point.x, point.y], /* num= */ 1, &squared_euclidean)
+        .unwrap()
+        .first()
+        .filter(|&(distance, _)| *distance < 0.01)
+        .map(|&(_, &index)| index)
+    };
 
-        if let (Some(start_index), Some(end_index)) = (start_index, end_index) {
-          // We don't want degenerate edges, so ignore edges with equal indices.
-          if start_index == end_index {
-            continue;
-          }
+    for (start, end) in new_edges {
+      let start_index = original_vertex_index(start);
+      let end_index = original_vertex_index(end);
+
+      if let (Some(start_index), Some(end_index)) = (start_index, end_index) {
+        // We don't want degenerate edges, so ignore edges with equal indices.
+        if start_index == end_index {
+          continue;
         }
+      }
 
-        let mut start_index = start_index.unwrap_or_else(|| {
-          modified_node.new_vertices.push(coord_to_vec2(edge.start));
-          island.nav_mesh.vertices.len() + modified_node.new_vertices.len() - 1
-        });
+      let start_index = start_index.unwrap_or_else(|| {
+        modified_node.new_vertices.push(start);
+        island.nav_mesh.vertices.len() + modified_node.new_vertices.len() - 1
+      });
 
-        let mut end_index = end_index.unwrap_or_else(|| {
-          modified_node.new_vertices.push(coord_to_vec2(edge.end));
-          island.nav_mesh.vertices.len() + modified_node.new_vertices.len() - 1
-        });
+      let end_index = end_index.unwrap_or_else(|| {
+        modified_node.new_vertices.push(end);
+        island.nav_mesh.vertices.len() + modified_node.new_vertices.len() - 1
+      });
+
+      modified_node.new_boundary.push((start_index, end_index));
+    }
+  }
+
+  /// Computes the union of the (2D) outlines of the obstacles carving
+  /// `node_ref`. Returns None if no obstacles carve the node.
+  fn obstacle_footprint(&self, node_ref: NodeRef) -> Option<MultiPolygon<f32>> {
+    let obstacle_ids = self.node_to_obstacle_ids.get(&node_ref)?;
+    obstacle_ids
+      .iter()
+      .map(|obstacle_id| {
+        let obstacle = &self.obstacles.get(*obstacle_id).unwrap().obstacle;
+        outline_to_multi_polygon(&obstacle.world_outline())
+      })
+      .reduce(|footprint, outline| footprint.union(&outline))
+  }
 
-        let polygon_center = island.transform.apply(polygon.center).xy();
+  /// Determines whether `portal` is entirely covered by the obstacles carving
+  /// `node_ref`.
+  fn is_portal_covered(&self, node_ref: NodeRef, portal: (Vec3, Vec3)) -> bool {
+    let Some(obstacle_footprint) = self.obstacle_footprint(node_ref) else {
+      return false;
+    };
+    let (start, end) = (portal.0.xy(), portal.1.xy());
+    if start.distance_squared(end) < 1e-8 {
+      return obstacle_footprint
+        .contains(&Point::from(Coord::from((start.x, start.y))));
+    }
+    obstacle_footprint
+      .clip(
+        &MultiLineString(vec![LineString(vec![
+          Coord::from((start.x, start.y)),
+          Coord::from((end.x, end.y)),
+        ])]),
+        /* invert= */ true,
+      )
+      .lines_iter()
+      .all(|line| {
+        Vec2::new(line.start.x, line.start.y)
+          .distance_squared(Vec2::new(line.end.x, line.end.y))
+          < 1e-8
+      })
+  }
 
-        // Ensure the winding order of the modified node boundary matches the
-        // polygon edges.
-        if (coord_to_vec2(edge.start) - polygon_center)
-          .perp_dot(coord_to_vec2(edge.end) - polygon_center)
-          < 0.0
+  /// Updates which nodes are carved by each obstacle. Returns the nodes whose
+  /// carving may have changed.
+  fn update_obstacles(
+    &mut self,
+    changed_islands: &HashSet<IslandId>,
+  ) -> HashSet<NodeRef> {
+    let mut changed_nodes = std::mem::take(&mut self.deleted_obstacle_nodes);
+
+    // Nodes in changed islands may have moved or no longer exist, so drop
+    // their carving and recompute it below.
+    if !changed_islands.is_empty() {
+      changed_nodes.extend(
+        self
+          .modified_nodes
+          .keys()
+          .chain(self.node_to_obstacle_ids.keys())
+          .filter(|node_ref| changed_islands.contains(&node_ref.island_id))
+          .copied(),
+      );
+      self
+        .node_to_obstacle_ids
+        .retain(|node_ref, _| !changed_islands.contains(&node_ref.island_id));
+    }
+
+    for (obstacle_id, state) in self.obstacles.iter_mut() {
+      let island_ids = if state.dirty {
+        state.dirty = false;
+        for node_ref in state.carved_nodes.drain() {
+          remove_node_obstacle(
+            &mut self.node_to_obstacle_ids,
+            node_ref,
+            obstacle_id,
+          );
+          changed_nodes.insert(node_ref);
+        }
+        self.islands.keys().collect::<Vec<_>>()
+      } else if changed_islands.is_empty() {
+        continue;
+      } else {
+        state
+          .carved_nodes
+          .retain(|node_ref| !changed_islands.contains(&node_ref.island_id));
+        changed_islands
+          .iter()
+          .copied()
+          .filter(|&island_id| self.islands.contains_key(island_id))
+          .collect::<Vec<_>>()
+      };
+
+      let outline = state.obstacle.world_outline();
+      let obstacle_bounds = state.obstacle.world_bounds(&outline);
+      for node_ref in find_overlapping_nodes(
+        &self.islands,
+        island_ids,
+        &outline,
+        &obstacle_bounds,
+      ) {
+        state.carved_nodes.insert(node_ref);
+        self
+          .node_to_obstacle_ids
+          .entry(node_ref)
+          .or_default()
+          .insert(obstacle_id);
+        changed_nodes.insert(node_ref);
+      }
+    }
+
+    changed_nodes
+  }
+
+  /// Recomputes the cost overrides of nodes from the cost volumes. Returns the
+  /// nodes whose cost override changed.
+  fn update_cost_volumes(
+    &mut self,
+    changed_islands: &HashSet<IslandId>,
+  ) -> HashSet<NodeRef> {
+    if !self.cost_volumes_changed
+      && (changed_islands.is_empty() || self.cost_volumes.is_empty())
+    {
+      return HashSet::new();
+    }
+    self.cost_volumes_changed = false;
+
+    // Apply the volumes in ID order, so overlapping type index volumes are
+    // resolved consistently.
+    let mut cost_volume_ids = self.cost_volumes.keys().collect::<Vec<_>>();
+    cost_volume_ids.sort();
+
+    let mut node_cost_overrides = HashMap::<NodeRef, NodeCostOverride>::new();
+    for cost_volume_id in cost_volume_ids {
+      let cost_volume = self.cost_volumes.get(cost_volume_id).unwrap();
+      let outline = cost_volume.world_outline();
+      let bounds = cost_volume.world_bounds(&outline);
+      for node_ref in find_overlapping_nodes(
+        &self.islands,
+        self.islands.keys(),
+        &outline,
+        &bounds,
+      ) {
+        let cost_override = node_cost_overrides.entry(node_ref).or_default();
+        match cost_volume.effect {
+          CostVolumeEffect::TypeIndex(type_index) => {
+            cost_override.type_index.get_or_insert(type_index);
+          }
+          CostVolumeEffect::CostMultiplier(cost_multiplier) => {
+            cost_override.cost_multiplier *= cost_multiplier;
+          }
+        }
+      }
+    }
+
+    let changed_nodes = node_cost_overrides
+      .iter()
+      .filter(|(node_ref, cost_override)| {
+        self.node_cost_overrides.get(node_ref) != Some(cost_override)
+      })
+      .map(|(node_ref, _)| *node_ref)
+      .chain(
+        self
+          .node_cost_overrides
+          .keys()
+          .filter(|node_ref| !node_cost_overrides.contains_key(node_ref))
+          .copied(),
+      )
+      .collect();
+    self.node_cost_overrides = node_cost_overrides;
+    changed_nodes
+  }
+
+  /// Recomputes the off mesh links whose portals are entirely covered by
+  /// obstacles.
+  fn update_blocked_off_mesh_links(&mut self) {
+    self.blocked_off_mesh_links.clear();
+    if self.node_to_obstacle_ids.is_empty() {
+      return;
+    }
+
+    let mut blocked_off_mesh_links = HashSet::new();
+    for (&node_ref, link_ids) in self.node_to_off_mesh_link_ids.iter() {
+      for &link_id in link_ids {
+        let link = self.off_mesh_links.get(link_id).unwrap();
+        let destination_portal = match &link.kinded {
+          KindedOffMeshLink::BoundaryLink { .. } => link.portal,
+          KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
+            *destination_portal
+          }
+        };
+        if self.is_portal_covered(node_ref, link.portal)
+          || self.is_portal_covered(link.destination_node, destination_portal)
         {
-          swap(&mut start_index, &mut end_index);
+          blocked_off_mesh_links.insert(link_id);
         }
+      }
+    }
+    self.blocked_off_mesh_links = blocked_off_mesh_links;
+  }
+
+  /// Finds the off mesh links that can't be taken because their animation link
+  /// or one of their islands is disabled.
+  fn update_disabled_off_mesh_links(&mut self) {
+    self.disabled_off_mesh_links.clear();
+    if self.islands.values().all(|island| island.enabled)
+      && self.animation_links.values().all(|link| link.enabled)
+    {
+      return;
+    }
 
-        modified_node.new_boundary.push((start_index, end_index));
+    let is_island_enabled =
+      |island_id: IslandId| self.islands.get(island_id).unwrap().enabled;
+    for (&node_ref, link_ids) in self.node_to_off_mesh_link_ids.iter() {
+      let source_enabled = is_island_enabled(node_ref.island_id);
+      for &link_id in link_ids {
+        let link = self.off_mesh_links.get(link_id).unwrap();
+        let link_enabled = match &link.kinded {
+          KindedOffMeshLink::BoundaryLink { .. } => true,
+          KindedOffMeshLink::AnimationLink { animation_link, .. } => {
+            self.animation_links.get(*animation_link).unwrap().enabled
+          }
+        };
+        if !source_enabled
+          || !link_enabled
+          || !is_island_enabled(link.destination_node.island_id)
+        {
+          self.disabled_off_mesh_links.insert(link_id);
+        }
       }
     }
   }
 
-  fn node_to_region_id(&self, node_ref: NodeRef) -> (IslandId, usize) {
+  pub(crate) fn node_to_region_id(&self, node_ref: NodeRef) -> ClusterId {
     let region =
       self.islands.get(node_ref.island_id).unwrap().nav_mesh.polygons
         [node_ref.polygon_index]
@@ -1018,13 +2181,36 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     (node_ref.island_id, region)
   }
 
+  /// The walkable layer of `node_ref`. Nodes share a walkable layer if they are
+  /// connected by walkable ground, meaning by their nav mesh or boundary links
+  /// (but not animation links).
+  pub(crate) fn node_to_walkable_layer(&self, node_ref: NodeRef) -> ClusterId {
+    let region_id = self.node_to_region_id(node_ref);
+    self
+      .region_id_to_walkable_layer
+      .get(&region_id)
+      .copied()
+      .unwrap_or(region_id)
+  }
+
   /// Determines whether `node_1` and `node_2` can be connected by some path.
+  /// Nodes whose type index is not in `permitted_type_indices` are never
+  /// connected. Note that regions are computed ignoring type indices, so this
+  /// may still return true if the only paths between the nodes go through
+  /// forbidden nodes.
   pub(crate) fn are_nodes_connected(
     &self,
     node_1: NodeRef,
     node_2: NodeRef,
     permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: &PermittedTypeIndices,
   ) -> bool {
+    if !permitted_type_indices.is_permitted(self.node_type_index(node_1))
+      || !permitted_type_indices.is_permitted(self.node_type_index(node_2))
+    {
+      return false;
+    }
+
     let region_id_1 = self.node_to_region_id(node_1);
     let region_id_2 = self.node_to_region_id(node_2);
     if region_id_1 == region_id_2 {
@@ -1086,28 +2272,50 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     false
   }
 
-  fn update_regions(&mut self) {
-    self.region_id_to_number.clear();
-    let mut region_connections = self.region_connections.lock().unwrap();
-    region_connections.clear();
-
-    let links =
-      self.node_to_off_mesh_link_ids.iter().flat_map(|(&node_ref, links)| {
+  /// Recomputes the regions from the enabled off mesh links. Returns whether
+  /// the regions changed.
+  fn update_regions(&mut self) -> bool {
+    let region_links = self
+      .node_to_off_mesh_link_ids
+      .iter()
+      .flat_map(|(&node_ref, links)| {
         links
           .iter()
+          // Disabled links can't connect regions.
+          .filter(|link_id| !self.disabled_off_mesh_links.contains(link_id))
           .map(|link_id| self.off_mesh_links.get(*link_id).unwrap())
           .map(move |link| (node_ref, link))
-      });
+      })
+      .map(|(node_ref, link)| {
+        let link_kind = match &link.kinded {
+          KindedOffMeshLink::BoundaryLink { .. } => None,
+          KindedOffMeshLink::AnimationLink { kind, .. } => Some(*kind),
+        };
+        (
+          self.node_to_region_id(node_ref),
+          self.node_to_region_id(link.destination_node),
+          link_kind,
+        )
+      })
+      .collect::<HashSet<_>>();
+    // Moving an island recreates its links, but usually between the same
+    // regions, in which case the regions are unchanged.
+    if region_links == self.region_links {
+      return false;
+    }
+    self.region_links = region_links;
+
+    self.region_id_to_number.clear();
+    self.region_number_to_possible_links.clear();
+    let mut region_connections = self.region_connections.lock().unwrap();
+    region_connections.clear();
 
     // First, join all the regions where we know for sure they will be
     // connected.
-    for (node_ref, link) in links.clone() {
-      match &link.kinded {
-        KindedOffMeshLink::BoundaryLink { .. } => {}
-        KindedOffMeshLink::AnimationLink { .. } => continue,
+    for &(start_region, end_region, link_kind) in self.region_links.iter() {
+      if link_kind.is_some() {
+        continue;
       }
-      let start_region = self.node_to_region_id(node_ref);
-      let end_region = self.node_to_region_id(link.destination_node);
 
       let start_region = *self
         .region_id_to_number
@@ -1121,17 +2329,27 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       region_connections.join(start_region, end_region);
     }
 
+    // Only boundary links have been joined so far, so each set of joined
+    // regions is one walkable layer.
+    let mut root_to_walkable_layer = HashMap::new();
+    self.region_id_to_walkable_layer = self
+      .region_id_to_number
+      .iter()
+      .map(|(&region_id, &region_number)| {
+        let walkable_layer = *root_to_walkable_layer
+          .entry(region_connections.root_of(region_number))
+          .or_insert(region_id);
+        (region_id, walkable_layer)
+      })
+      .collect();
+
     // Then, any links that could potentially link two regions (but not always)
     // are stored in `self.region_number_to_animation_links`.
-    for (node_ref, link) in links {
-      let link_kind = match &link.kinded {
-        KindedOffMeshLink::BoundaryLink { .. } => continue,
-        KindedOffMeshLink::AnimationLink { kind, .. } => *kind,
+    for &(start_region, end_region, link_kind) in self.region_links.iter() {
+      let Some(link_kind) = link_kind else {
+        continue;
       };
 
-      let start_region = self.node_to_region_id(node_ref);
-      let end_region = self.node_to_region_id(link.destination_node);
-
       let start_region = *self
         .region_id_to_number
         .entry(start_region)
@@ -1153,30 +2371,123 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
         .or_default()
         .push(PossibleRegionLink { link_kind, destination_region: end_region });
     }
+    true
   }
 
   pub(crate) fn update(
     &mut self,
     edge_link_distance: f32,
     animation_link_distance: f32,
-  ) -> (HashSet<OffMeshLinkId>, HashSet<IslandId>) {
+  ) -> (HashSet<OffMeshLinkId>, HashSet<IslandId>, HashSet<NodeRef>) {
     if !self.dirty {
-      return (HashSet::new(), HashSet::new());
+      self.update_landmarks();
+      return (HashSet::new(), HashSet::new(), HashSet::new());
     }
     self.dirty = false;
 
     let animation_links_changed = !self.new_animation_links.is_empty()
       || !self.deleted_animation_links.is_empty();
+    let mut enabled_changed =
+      std::mem::take(&mut self.animation_links_enabled_changed);
+
+    // Islands that only moved keep the same nodes, so only deleted islands,
+    // islands with a new nav mesh, and disabled islands invalidate paths along
+    // them.
+    let mut invalidated_islands = self.deleted_islands.clone();
+    for (island_id, island) in self.islands.iter_mut() {
+      if island.nav_mesh_dirty {
+        island.nav_mesh_dirty = false;
+        invalidated_islands.insert(island_id);
+      }
+      if island.enabled_dirty {
+        island.enabled_dirty = false;
+        enabled_changed = true;
+        if !island.enabled {
+          invalidated_islands.insert(island_id);
+        }
+      }
+    }
 
-    let (dropped_links, changed_islands, modified_node_refs_to_update) =
+    let (mut dropped_links, changed_islands, mut modified_node_refs_to_update) =
       self.update_islands(edge_link_distance, animation_link_distance);
+    let mut changed_nodes = self.update_obstacles(&changed_islands);
+    modified_node_refs_to_update.extend(changed_nodes.iter().copied());
     for node_ref in modified_node_refs_to_update {
       self.update_modified_node(node_ref, edge_link_distance);
     }
-    if animation_links_changed || !changed_islands.is_empty() {
-      self.update_regions();
+    self.update_blocked_off_mesh_links();
+    let previously_disabled_off_mesh_links =
+      std::mem::take(&mut self.disabled_off_mesh_links);
+    self.update_disabled_off_mesh_links();
+    // Only newly disabled links can be in paths, so only those invalidate
+    // paths.
+    dropped_links.extend(
+      self
+        .disabled_off_mesh_links
+        .difference(&previously_disabled_off_mesh_links)
+        .copied(),
+    );
+    changed_nodes.extend(self.update_cost_volumes(&changed_islands));
+    self.regions_changed = false;
+    if animation_links_changed || !changed_islands.is_empty() || enabled_changed
+    {
+      self.regions_changed = self.update_regions();
+      // The region graph is built from the same links as the regions.
+      self.region_graph_dirty |= self.hierarchical_pathfinding;
+    }
+    if self.region_graph_dirty {
+      self.region_graph_dirty = false;
+      self.region_graph =
+        self.hierarchical_pathfinding.then(|| RegionGraph::new(self));
+    }
+    // Landmarks only depend on the nodes and links, so obstacles, cost volumes,
+    // and enabling or disabling don't require recomputing them.
+    if (animation_links_changed || !changed_islands.is_empty())
+      && self.landmark_count > 0
+    {
+      self.landmarks_dirty = true;
+    }
+    self.update_landmarks();
+    (dropped_links, invalidated_islands, changed_nodes)
+  }
+
+  /// Computes the next few landmarks, restarting if the landmarks are dirty.
+  /// Computing each landmark requires searching the whole relaxed graph
+  /// twice, so the landmarks are spread out over several updates. Until all
+  /// of them are computed, pathfinding uses the plain heuristic.
+  fn update_landmarks(&mut self) {
+    if self.landmarks_dirty {
+      self.landmarks_dirty = false;
+      // The previous landmarks may overestimate costs now, so they can't be
+      // used while the new ones are computed.
+      self.landmarks = None;
+      self.landmarks_builder = None;
+      if self.landmark_count == 0 {
+        self.landmark_states.clear();
+        return;
+      }
+      self.landmarks_builder =
+        LandmarksBuilder::new(self, self.landmark_count, &self.landmark_states);
+      if let Some(landmarks_builder) = self.landmarks_builder.as_ref() {
+        self.landmark_states = landmarks_builder.landmark_states();
+      }
+    }
+    if let Some(landmarks_builder) = self.landmarks_builder.as_mut()
+      && landmarks_builder.compute_landmarks(LANDMARKS_PER_UPDATE)
+    {
+      self.landmarks = self
+        .landmarks_builder
+        .take()
+        .map(|landmarks_builder| Arc::new(landmarks_builder.finish()));
+    }
+  }
+
+  /// Stores the current transform of each island as its previous transform,
+  /// so the next update can compute how much each island moved.
+  pub(crate) fn store_previous_island_transforms(&mut self) {
+    for island in self.islands.values_mut() {
+      island.previous_transform = island.transform.clone();
     }
-    (dropped_links, changed_islands)
   }
 }
 
@@ -1222,6 +2533,109 @@ impl<CS: CoordinateSystem> IslandMut<'_, CS> {
   }
 }
 
+/// A mutable borrow to an obstacle.
+pub struct ObstacleMut<'nav_data, CS: CoordinateSystem> {
+  /// The ID of the obstacle.
+  id: ObstacleId,
+  /// The borrow.
+  obstacle: &'nav_data mut Obstacle<CS>,
+  /// A borrow to the obstacle's dirty flag. Mutating the obstacle should set
+  /// this flag, so the nodes it carves are recomputed.
+  obstacle_dirty_flag: &'nav_data mut bool,
+  /// A borrow to the navigation data's dirty flag. Mutating the obstacle
+  /// should set this flag.
+  dirty_flag: &'nav_data mut bool,
+}
+
+impl<CS: CoordinateSystem> Deref for ObstacleMut<'_, CS> {
+  type Target = Obstacle<CS>;
+
+  fn deref(&self) -> &Self::Target {
+    self.obstacle
+  }
+}
+
+impl<CS: CoordinateSystem> DerefMut for ObstacleMut<'_, CS> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    *self.obstacle_dirty_flag = true;
+    *self.dirty_flag = true;
+    self.obstacle
+  }
+}
+
+impl<CS: CoordinateSystem> ObstacleMut<'_, CS> {
+  /// Returns the ID of the borrowed obstacle.
+  pub fn id(&self) -> ObstacleId {
+    self.id
+  }
+}
+
+/// Removes `obstacle_id` from the obstacles carving `node_ref`.
+fn remove_node_obstacle(
+  node_to_obstacle_ids: &mut HashMap<NodeRef, HashSet<ObstacleId>>,
+  node_ref: NodeRef,
+  obstacle_id: ObstacleId,
+) {
+  let Some(obstacle_ids) = node_to_obstacle_ids.get_mut(&node_ref) else {
+    return;
+  };
+  obstacle_ids.remove(&obstacle_id);
+  if obstacle_ids.is_empty() {
+    node_to_obstacle_ids.remove(&node_ref);
+  }
+}
+
+/// Finds the nodes in `island_ids` that overlap `outline` (in world space).
+/// Nodes must also overlap `bounds`, which limits the vertical range. Nodes
+/// that only touch the outline are not considered overlapping.
+fn find_overlapping_nodes<CS: CoordinateSystem>(
+  islands: &DenseSlotMap<IslandId, Island<CS>>,
+  island_ids: impl IntoIterator<Item = IslandId>,
+  outline: &[Vec2],
+  bounds: &BoundingBox,
+) -> Vec<NodeRef> {
+  let outline_polygon = outline_to_multi_polygon(outline);
+  let mut nodes = vec![];
+  for island_id in island_ids {
+    let island = islands.get(island_id).unwrap();
+    if !island.transformed_bounds.intersects_bounds(bounds) {
+      continue;
+    }
+    for (polygon_index, polygon) in island.nav_mesh.polygons.iter().enumerate()
+    {
+      if !polygon.bounds.transform(&island.transform).intersects_bounds(bounds)
+      {
+        continue;
+      }
+      if node_to_multi_polygon(island, polygon)
+        .intersection(&outline_polygon)
+        .unsigned_area()
+        < 1e-6
+      {
+        continue;
+      }
+      nodes.push(NodeRef { island_id, polygon_index });
+    }
+  }
+  nodes
+}
+
+/// Converts the (2D) outline of a node into a polygon usable for clipping.
+pub(crate) fn node_to_multi_polygon<CS: CoordinateSystem>(
+  island: &Island<CS>,
+  polygon: &ValidPolygon,
+) -> MultiPolygon<f32> {
+  outline_to_multi_polygon(
+    &polygon
+      .vertices
+      .iter()
+      .map(|&vertex| {
+        island.transform.apply(island.nav_mesh.vertices[vertex]).xy()
+      })
+      .collect::<Vec<_>>(),
+  )
+}
+
 fn edge_ref_to_world_edge<CS: CoordinateSystem>(
   edge: MeshEdgeRef,
   island: &Island<CS>,
@@ -1362,6 +2776,7 @@ fn world_portal_to_node_portals<CS: CoordinateSystem>(
       continue;
     }
 
+    let max_vertical_distance = max_vertical_distance / island.transform.scale;
     let node_bbh = island_to_node_bbh.entry(island_id).or_insert_with(|| {
       nav_mesh_node_bbh(
         island.nav_mesh.as_ref(),
@@ -1409,11 +2824,13 @@ fn sample_animation_link_point<CS: CoordinateSystem>(
         distance_below: max_vertical_distance,
         horizontal_distance: 0.0,
         vertical_preference_ratio: 1.0,
-      },
+      }
+      .to_scaled_frame(island.transform.scale),
     ) else {
       continue;
     };
-    let distance = relative_point.distance_squared(sampled_point);
+    let distance =
+      point.distance_squared(island.transform.apply(sampled_point));
     match best_point {
       Some((best_distance, _)) if distance >= best_distance => continue,
       _ => {}
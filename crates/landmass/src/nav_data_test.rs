@@ -5,9 +5,9 @@ use std::{
   sync::Arc,
 };
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{
-  expect_eq, expect_false, expect_that, expect_true, matchers::*,
+  expect_eq, expect_false, expect_ne, expect_that, expect_true, matchers::*,
   prelude::container_eq,
 };
 use slotmap::{DenseSlotMap, SlotMap};
@@ -15,12 +15,16 @@ use slotmap::{DenseSlotMap, SlotMap};
 use crate::{
   Archipelago, ArchipelagoOptions, CoordinateSystem, FromAgentRadius,
   HeightNavigationMesh, HeightPolygon, IslandId, PermittedAnimationLinks,
-  PointSampleDistance3d, SetTypeIndexCostError, Transform,
+  PermittedTypeIndices, PointSampleDistance3d, SetTypeIndexCostError,
+  Transform,
   coords::{CorePointSampleDistance, XY, XYZ},
+  cost_volume::{CostVolume, CostVolumeEffect},
   island::Island,
   link::{AnimationLink, NodePortal},
   nav_data::{KindedOffMeshLink, NodeRef, OffMeshLink},
   nav_mesh::NavigationMesh,
+  obstacle::{Obstacle, ObstacleShape},
+  util::MIN_SCALE,
 };
 
 use super::{
@@ -50,12 +54,14 @@ fn samples_points() {
   let nav_mesh = Arc::new(nav_mesh);
 
   let mut nav_data = NavigationData::<XYZ>::new();
-  let island_id_1 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  let island_id_1 = nav_data
+    .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
   let island_id_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(5.0, 0.0, 0.1), rotation: PI * 0.5 },
+    Transform {
+      translation: Vec3::new(5.0, 0.0, 0.1),
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -70,6 +76,7 @@ fn samples_points() {
         vertical_preference_ratio: 1.0,
         animation_link_max_vertical_distance: 1.0,
       }),
+      &PermittedTypeIndices::All,
     ),
     Some((
       Vec3::new(1.5, 1.5, 1.0),
@@ -87,6 +94,7 @@ fn samples_points() {
         vertical_preference_ratio: 1.0,
         animation_link_max_vertical_distance: 1.0,
       }),
+      &PermittedTypeIndices::All,
     ),
     Some((
       Vec3::new(1.0, 1.0, 1.0),
@@ -104,6 +112,7 @@ fn samples_points() {
         vertical_preference_ratio: 1.0,
         animation_link_max_vertical_distance: 1.0,
       }),
+      &PermittedTypeIndices::All,
     ),
     Some((
       Vec3::new(3.5, 1.5, 1.0),
@@ -122,6 +131,7 @@ fn samples_points() {
           vertical_preference_ratio: 1.0,
           animation_link_max_vertical_distance: 1.0,
         }),
+        &PermittedTypeIndices::All,
       )
       .map(|(p, n)| ((p * 1e6).round() / 1e6, n)),
     Some((
@@ -131,6 +141,60 @@ fn samples_points() {
   );
 }
 
+#[googletest::test]
+fn samples_points_on_scaled_and_tilted_island() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      Vec3::new(1.0, 1.0, 0.0),
+      Vec3::new(0.0, 1.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("is valid");
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  // The island becomes a 2x2 wall in the XZ plane whose "up" is -Y.
+  let island_id = nav_data.add_island(Island::new(
+    Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_x(PI * 0.5),
+      scale: 2.0,
+    },
+    Arc::new(nav_mesh),
+  ));
+
+  let sample_distance = CorePointSampleDistance::new(&PointSampleDistance3d {
+    horizontal_distance: 0.2,
+    distance_below: 0.2,
+    distance_above: 0.2,
+    vertical_preference_ratio: 1.0,
+    animation_link_max_vertical_distance: 1.0,
+  });
+
+  let sample = |point| {
+    nav_data
+      .sample_point(point, &sample_distance, &PermittedTypeIndices::All)
+      .map(|(point, node_ref)| ((point * 1e4).round() / 1e4, node_ref))
+  };
+
+  // Distances are in world units, even though the island is scaled.
+  expect_eq!(
+    sample(Vec3::new(1.0, -0.15, 1.0)),
+    Some((Vec3::new(1.0, 0.0, 1.0), NodeRef { island_id, polygon_index: 0 }))
+  );
+  expect_eq!(sample(Vec3::new(1.0, -0.3, 1.0)), None);
+  expect_eq!(
+    sample(Vec3::new(2.1, -0.05, 1.0)),
+    Some((Vec3::new(2.0, 0.0, 1.0), NodeRef { island_id, polygon_index: 0 }))
+  );
+  expect_eq!(sample(Vec3::new(2.3, -0.05, 1.0)), None);
+}
+
 fn node_ref_to_num(node_ref: &NodeRef, island_order: &[IslandId]) -> u32 {
   let island_index =
     island_order.iter().position(|id| node_ref.island_id == *id).unwrap();
@@ -165,13 +229,13 @@ fn clone_sort_round_links(
           })
           .collect::<Vec<_>>();
         v.sort_by_key(|link| {
-          node_ref_to_num(&link.destination_node, &island_order)
+          node_ref_to_num(&link.destination_node, island_order)
         });
         v
       })
     })
     .collect::<Vec<_>>();
-  links.sort_by_key(|(a, _)| node_ref_to_num(a, &island_order));
+  links.sort_by_key(|(a, _)| node_ref_to_num(a, island_order));
   links
 }
 
@@ -244,8 +308,11 @@ fn link_edges_between_islands_links_touching_islands() {
   let island_1_id = slotmap.insert(0);
   let island_2_id = slotmap.insert(0);
 
-  let transform =
-    Transform { translation: Vec3::new(1.0, 2.0, 3.0), rotation: PI * -0.25 };
+  let transform = Transform {
+    translation: Vec3::new(1.0, 2.0, 3.0),
+    rotation: Quat::from_rotation_z(PI * -0.25),
+    scale: 1.0,
+  };
 
   let island_1 = Island::new(transform.clone(), Arc::clone(&nav_mesh_1));
   let island_2 = Island::new(transform.clone(), Arc::clone(&nav_mesh_2));
@@ -525,24 +592,38 @@ fn update_links_islands_and_unlinks_on_delete() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
 
-  let island_1_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  let island_1_id = nav_data
+    .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: PI * 0.5 },
+    Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_3_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: PI },
+    Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_z(PI),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_4_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(3.0, 0.0, 0.0), rotation: PI },
+    Transform {
+      translation: Vec3::new(3.0, 0.0, 0.0),
+      rotation: Quat::from_rotation_z(PI),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_5_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 3.0, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(2.0, 3.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -667,7 +748,11 @@ fn update_links_islands_and_unlinks_on_delete() {
     .islands
     .get_mut(island_5_id)
     .expect("island_5 still exists")
-    .set_transform(Transform { translation: Vec3::ZERO, rotation: PI * 0.5 });
+    .set_transform(Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    });
 
   nav_data.update(
     /* edge_link_distance= */ 0.01,
@@ -824,16 +909,22 @@ fn modifies_node_boundaries_for_linked_islands() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
 
-  let island_1_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  let island_1_id = nav_data
+    .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, -1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, -1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_3_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 3.5, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(2.0, 3.5, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -896,12 +987,13 @@ fn stale_modified_nodes_are_removed() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
 
-  nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  nav_data.add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, -1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, -1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -942,16 +1034,14 @@ fn modified_node_is_removed_for_no_boundary_links() {
 
   let mut nav_data = NavigationData::<XY>::new();
 
-  let island_1_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  let island_1_id = nav_data
+    .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(-2.0, 0.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(-2.0, 0.0), rotation: 0.0, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
 
@@ -961,6 +1051,8 @@ fn modified_node_is_removed_for_no_boundary_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(1e-5, 1e-5);
@@ -986,6 +1078,289 @@ fn modified_node_is_removed_for_no_boundary_links() {
   );
 }
 
+fn square_obstacle(min: Vec2, max: Vec2) -> Obstacle<XY> {
+  Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      min,
+      Vec2::new(max.x, min.y),
+      max,
+      Vec2::new(min.x, max.y),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  }
+}
+
+#[googletest::test]
+fn obstacle_carves_node_and_is_restored_on_removal() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 5, 4], vec![1, 2, 6, 5], vec![2, 3, 7, 6]],
+      polygon_type_indices: vec![0, 0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid."),
+  );
+
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh));
+  let obstacle_id = nav_data.add_obstacle(square_obstacle(
+    Vec2::new(1.25, 0.25),
+    Vec2::new(1.75, 0.75),
+  ));
+
+  let carved_node = NodeRef { island_id, polygon_index: 1 };
+  let (_, _, carved_nodes) = nav_data.update(1e-5, 1e-5);
+  expect_eq!(carved_nodes, HashSet::from([carved_node]));
+  expect_that!(
+    nav_data.modified_nodes.keys().copied().collect::<Vec<_>>(),
+    elements_are!(eq(&carved_node))
+  );
+
+  let modified_node = nav_data.modified_nodes.get(&carved_node).unwrap();
+  // The two original walls, plus the four edges of the hole.
+  expect_eq!(modified_node.new_boundary.len(), 6);
+  // New vertices are not shared between edges.
+  expect_eq!(modified_node.new_vertices.len(), 8);
+  // The boundary winds CCW around the walkable area, so the obstacle is on
+  // the right of each edge of the hole.
+  for &(start, end) in modified_node.new_boundary.iter() {
+    if start < 8 || end < 8 {
+      continue;
+    }
+    let start = modified_node.new_vertices[start - 8];
+    let end = modified_node.new_vertices[end - 8];
+    expect_that!((end - start).perp_dot(Vec2::new(1.5, 0.5) - start), lt(0.0));
+  }
+
+  // The obstacle doesn't cover the edges to the other nodes.
+  expect_true!(nav_data.can_cross_edge(carved_node, 1, 0.1));
+  expect_true!(nav_data.can_cross_edge(carved_node, 3, 0.1));
+
+  nav_data.remove_obstacle(obstacle_id);
+  let (_, _, carved_nodes) = nav_data.update(1e-5, 1e-5);
+  expect_eq!(carved_nodes, HashSet::from([carved_node]));
+  expect_that!(nav_data.modified_nodes, is_empty());
+}
+
+#[googletest::test]
+fn carved_node_precomputes_detour_corners() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 5, 4], vec![1, 2, 6, 5], vec![2, 3, 7, 6]],
+      polygon_type_indices: vec![0, 0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid."),
+  );
+
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh));
+  nav_data.add_obstacle(square_obstacle(
+    Vec2::new(1.25, 0.25),
+    Vec2::new(1.75, 0.75),
+  ));
+  nav_data.update(1e-5, 1e-5);
+
+  let carved_node = nav_data
+    .carved_nodes
+    .get(&NodeRef { island_id, polygon_index: 1 })
+    .unwrap();
+  // Each corner of the hole is a detour corner, offset diagonally so it is a
+  // radius away from both edges of the hole.
+  let hole_center = Vec2::new(1.5, 0.5);
+  expect_eq!(carved_node.detour_corners.len(), 4);
+  for corner in carved_node.detour_corners.iter() {
+    expect_that!(
+      corner.point.distance(hole_center),
+      approx_eq(0.125f32.sqrt())
+    );
+    let expected_offset =
+      (corner.point - hole_center).normalize() * 2.0f32.sqrt();
+    expect_that!(corner.offset.x, approx_eq(expected_offset.x));
+    expect_that!(corner.offset.y, approx_eq(expected_offset.y));
+  }
+  // Each corner can see the corners next to it, but not the corner across the
+  // hole.
+  for (index, visible_corners) in
+    carved_node.corner_visibility.iter().enumerate()
+  {
+    let point = carved_node.detour_corners[index].point;
+    let mut distances = visible_corners
+      .iter()
+      .map(|&other| carved_node.detour_corners[other].point.distance(point))
+      .collect::<Vec<_>>();
+    distances.sort_by(f32::total_cmp);
+    expect_that!(distances, elements_are!(approx_eq(0.5), approx_eq(0.5)));
+  }
+}
+
+#[googletest::test]
+fn obstacle_limits_clearance_of_covered_edges() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(2.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid."),
+  );
+
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh));
+  let obstacle_id = nav_data
+    .add_obstacle(square_obstacle(Vec2::new(0.75, -1.0), Vec2::new(1.25, 2.0)));
+
+  let (_, _, carved_nodes) = nav_data.update(1e-5, 1e-5);
+  let node_1 = NodeRef { island_id, polygon_index: 0 };
+  let node_2 = NodeRef { island_id, polygon_index: 1 };
+  expect_eq!(carved_nodes, HashSet::from([node_1, node_2]));
+
+  // The shared edge is entirely covered, so it can't be crossed in either
+  // direction.
+  expect_false!(nav_data.can_cross_edge(node_1, 1, 0.0));
+  expect_false!(nav_data.can_cross_edge(node_2, 3, 0.0));
+
+  // Only cover the top half of the shared edge.
+  nav_data.get_obstacle_mut(obstacle_id).unwrap().shape =
+    square_obstacle(Vec2::new(0.75, 0.5), Vec2::new(1.25, 2.0)).shape;
+  nav_data.update(1e-5, 1e-5);
+
+  expect_true!(nav_data.can_cross_edge(node_1, 1, 0.2));
+  expect_true!(nav_data.can_cross_edge(node_2, 3, 0.2));
+  expect_false!(nav_data.can_cross_edge(node_1, 1, 0.3));
+  expect_false!(nav_data.can_cross_edge(node_2, 3, 0.3));
+}
+
+#[googletest::test]
+fn obstacle_outside_vertical_range_does_not_carve() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid."),
+  );
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  nav_data.add_island(Island::new(Transform::default(), nav_mesh));
+  let obstacle_id = nav_data.add_obstacle(Obstacle {
+    shape: ObstacleShape::Circle { radius: 0.25 },
+    transform: Transform {
+      translation: Vec3::new(0.5, 0.5, 2.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    height: 1.0,
+  });
+
+  let (_, _, carved_nodes) = nav_data.update(1e-5, 1e-5);
+  expect_that!(carved_nodes, is_empty());
+  expect_that!(nav_data.modified_nodes, is_empty());
+
+  // Lowering the obstacle onto the node carves it.
+  nav_data.get_obstacle_mut(obstacle_id).unwrap().transform.translation =
+    Vec3::new(0.5, 0.5, -0.5);
+  let (_, _, carved_nodes) = nav_data.update(1e-5, 1e-5);
+  expect_eq!(carved_nodes.len(), 1);
+  expect_eq!(nav_data.modified_nodes.len(), 1);
+}
+
+#[googletest::test]
+fn cost_volumes_invalidate_overlapped_nodes() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(2.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid."),
+  );
+
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh));
+  nav_data.update(1e-5, 1e-5);
+
+  let cost_volume_id = nav_data
+    .add_cost_volume(CostVolume {
+      shape: ObstacleShape::Circle { radius: 0.25 },
+      transform: Transform {
+        translation: Vec2::new(1.5, 0.5),
+        rotation: 0.0,
+        scale: 1.0,
+      },
+      height: 1.0,
+      effect: CostVolumeEffect::TypeIndex(3),
+    })
+    .unwrap();
+
+  let node_1 = NodeRef { island_id, polygon_index: 0 };
+  let node_2 = NodeRef { island_id, polygon_index: 1 };
+  let (_, _, changed_nodes) = nav_data.update(1e-5, 1e-5);
+  expect_eq!(changed_nodes, HashSet::from([node_2]));
+  expect_eq!(nav_data.node_type_index(node_1), 0);
+  expect_eq!(nav_data.node_type_index(node_2), 3);
+  // Cost volumes don't modify the nodes themselves.
+  expect_that!(nav_data.modified_nodes, is_empty());
+
+  nav_data.remove_cost_volume(cost_volume_id);
+  let (_, _, changed_nodes) = nav_data.update(1e-5, 1e-5);
+  expect_eq!(changed_nodes, HashSet::from([node_2]));
+  expect_eq!(nav_data.node_type_index(node_2), 0);
+}
+
 #[test]
 fn empty_navigation_mesh_is_safe() {
   let full_nav_mesh = Arc::new(
@@ -1040,6 +1415,90 @@ fn error_on_set_zero_or_negative_type_index_cost() {
   );
 }
 
+#[googletest::test]
+fn clamps_zero_island_scale() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![],
+    polygons: vec![],
+    polygon_type_indices: vec![],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("An empty nav mesh is valid.");
+
+  let island = Island::<XY>::new(
+    Transform { translation: Vec2::ZERO, rotation: 0.0, scale: 0.0 },
+    Arc::new(nav_mesh),
+  );
+  expect_eq!(island.get_transform().scale, MIN_SCALE);
+}
+
+#[googletest::test]
+fn clamps_setting_negative_or_nan_island_scale() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![],
+    polygons: vec![],
+    polygon_type_indices: vec![],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("An empty nav mesh is valid.");
+
+  let mut island = Island::<XY>::new(Transform::default(), Arc::new(nav_mesh));
+  island.set_transform(Transform {
+    translation: Vec2::ZERO,
+    rotation: 0.0,
+    scale: -1.0,
+  });
+  expect_eq!(island.get_transform().scale, MIN_SCALE);
+
+  island.set_transform(Transform {
+    translation: Vec2::ZERO,
+    rotation: 0.0,
+    scale: f32::NAN,
+  });
+  expect_eq!(island.get_transform().scale, MIN_SCALE);
+}
+
+#[test]
+#[should_panic(expected = "The busy cost Some(-1.0) is negative or NaN.")]
+fn panics_on_negative_busy_cost() {
+  let mut nav_data = NavigationData::<XY>::new();
+  nav_data.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)),
+    end_edge: (Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)),
+    kind: 0,
+    cost: 1.0,
+    bidirectional: false,
+    capacity: Some(1),
+    busy_cost: Some(-1.0),
+  });
+}
+
+#[googletest::test]
+fn clamps_non_positive_obstacle_scale() {
+  let clamped_obstacle = Obstacle {
+    transform: Transform {
+      translation: Vec2::ZERO,
+      rotation: 0.0,
+      scale: MIN_SCALE,
+    },
+    ..square_obstacle(Vec2::ZERO, Vec2::ONE)
+  };
+  for scale in [0.0, -1.0, f32::NAN] {
+    let obstacle = Obstacle {
+      transform: Transform { translation: Vec2::ZERO, rotation: 0.0, scale },
+      ..square_obstacle(Vec2::ZERO, Vec2::ONE)
+    };
+    let outline = obstacle.world_outline();
+    expect_eq!(outline, clamped_obstacle.world_outline());
+    expect_eq!(
+      obstacle.world_bounds(&outline),
+      clamped_obstacle.world_bounds(&outline)
+    );
+  }
+}
+
 #[googletest::test]
 fn changed_island_rebuilds_region_connectivity() {
   let mut nav_data = NavigationData::<XY>::new();
@@ -1063,7 +1522,7 @@ fn changed_island_rebuilds_region_connectivity() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   nav_data.update(
@@ -1074,12 +1533,14 @@ fn changed_island_rebuilds_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
 
   // Making the islands touch should result in the regions being connected.
   nav_data.get_island_mut(island_2).unwrap().set_transform(Transform {
     translation: Vec2::new(0.0, 1.0),
     rotation: 0.0,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1089,12 +1550,14 @@ fn changed_island_rebuilds_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
 
   // Making the islands no longer touch again should remove the connectivity.
   nav_data.get_island_mut(island_2).unwrap().set_transform(Transform {
     translation: Vec2::new(0.0, 2.0),
     rotation: 0.0,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1104,6 +1567,7 @@ fn changed_island_rebuilds_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
 }
 
@@ -1130,7 +1594,7 @@ fn changed_animation_link_rebuilds_region_connectivity() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   nav_data.update(
@@ -1141,6 +1605,7 @@ fn changed_animation_link_rebuilds_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
 
   // Creating a new link should result in the regions being connected.
@@ -1150,6 +1615,8 @@ fn changed_animation_link_rebuilds_region_connectivity() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1159,6 +1626,7 @@ fn changed_animation_link_rebuilds_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
 
   // Removing the link should remove the connectivity.
@@ -1171,6 +1639,239 @@ fn changed_animation_link_rebuilds_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
+  ));
+}
+
+#[googletest::test]
+fn walkable_layers_are_joined_by_boundary_links() {
+  let mut nav_data = NavigationData::<XY>::new();
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("A square nav mesh is valid."),
+  );
+
+  // The first two islands touch, and the third island is only reachable by
+  // an animation link.
+  let island_1 =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let island_2 = nav_data.add_island(Island::new(
+    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh.clone(),
+  ));
+  let island_3 = nav_data.add_island(Island::new(
+    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  nav_data.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.1, 0.9), Vec2::new(0.9, 0.9)),
+    end_edge: (Vec2::new(0.1, 2.1), Vec2::new(0.9, 2.1)),
+    cost: 1.0,
+    kind: 0,
+    bidirectional: false,
+    capacity: None,
+    busy_cost: None,
+  });
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let walkable_layer = |island_id| {
+    nav_data.node_to_walkable_layer(NodeRef { island_id, polygon_index: 0 })
+  };
+  expect_eq!(walkable_layer(island_1), walkable_layer(island_2));
+  expect_ne!(walkable_layer(island_1), walkable_layer(island_3));
+}
+
+#[googletest::test]
+fn disabled_animation_link_keeps_off_mesh_links() {
+  let mut nav_data = NavigationData::<XY>::new();
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("A square nav mesh is valid."),
+  );
+
+  let island_1 =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let island_2 = nav_data.add_island(Island::new(
+    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  let link_id = nav_data.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.1, 0.9), Vec2::new(0.9, 0.9)),
+    end_edge: (Vec2::new(0.1, 2.1), Vec2::new(0.9, 2.1)),
+    cost: 1.0,
+    kind: 0,
+    bidirectional: false,
+    capacity: None,
+    busy_cost: None,
+  });
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let off_mesh_link_ids = nav_data.off_mesh_links.keys().collect::<Vec<_>>();
+  assert_eq!(off_mesh_link_ids.len(), 1);
+
+  nav_data.set_animation_link_enabled(link_id, false);
+  let (invalidated_off_mesh_links, invalidated_islands, _) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  // The link is disabled, but its off mesh link is kept.
+  expect_eq!(nav_data.is_animation_link_enabled(link_id), Some(false));
+  expect_eq!(
+    nav_data.off_mesh_links.keys().collect::<Vec<_>>(),
+    off_mesh_link_ids
+  );
+  expect_eq!(invalidated_off_mesh_links, HashSet::from([off_mesh_link_ids[0]]));
+  expect_eq!(invalidated_islands, HashSet::new());
+  expect_false!(nav_data.are_nodes_connected(
+    NodeRef { island_id: island_1, polygon_index: 0 },
+    NodeRef { island_id: island_2, polygon_index: 0 },
+    PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
+  ));
+
+  // Re-enabling the link doesn't invalidate anything.
+  nav_data.set_animation_link_enabled(link_id, true);
+  let (invalidated_off_mesh_links, invalidated_islands, _) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  expect_eq!(nav_data.is_animation_link_enabled(link_id), Some(true));
+  expect_eq!(
+    nav_data.off_mesh_links.keys().collect::<Vec<_>>(),
+    off_mesh_link_ids
+  );
+  expect_eq!(invalidated_off_mesh_links, HashSet::new());
+  expect_eq!(invalidated_islands, HashSet::new());
+  expect_true!(nav_data.are_nodes_connected(
+    NodeRef { island_id: island_1, polygon_index: 0 },
+    NodeRef { island_id: island_2, polygon_index: 0 },
+    PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
+  ));
+}
+
+#[googletest::test]
+fn disabled_island_is_not_sampled() {
+  let mut nav_data = NavigationData::<XY>::new();
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("A square nav mesh is valid."),
+  );
+
+  let island_1 =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let island_2 = nav_data.add_island(Island::new(
+    Transform { translation: Vec2::new(0.0, 1.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let off_mesh_link_ids =
+    nav_data.off_mesh_links.keys().collect::<HashSet<_>>();
+  assert_eq!(off_mesh_link_ids.len(), 2);
+
+  let sample_distance = CorePointSampleDistance {
+    horizontal_distance: 0.1,
+    distance_above: 0.1,
+    distance_below: 0.1,
+    vertical_preference_ratio: 1.0,
+  };
+  let sample_island_2 = |nav_data: &NavigationData<XY>| {
+    nav_data.sample_point(
+      Vec3::new(0.5, 1.5, 0.0),
+      &sample_distance,
+      &PermittedTypeIndices::All,
+    )
+  };
+
+  nav_data.get_island_mut(island_2).unwrap().set_enabled(false);
+  let (invalidated_off_mesh_links, invalidated_islands, _) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  // The island is disabled, but its boundary links are kept.
+  expect_false!(nav_data.get_island(island_2).unwrap().is_enabled());
+  expect_eq!(
+    nav_data.off_mesh_links.keys().collect::<HashSet<_>>(),
+    off_mesh_link_ids
+  );
+  expect_eq!(invalidated_off_mesh_links, off_mesh_link_ids);
+  expect_eq!(invalidated_islands, HashSet::from([island_2]));
+  expect_eq!(sample_island_2(&nav_data), None);
+  expect_false!(nav_data.are_nodes_connected(
+    NodeRef { island_id: island_1, polygon_index: 0 },
+    NodeRef { island_id: island_2, polygon_index: 0 },
+    PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
+  ));
+
+  // Re-enabling the island doesn't invalidate anything.
+  nav_data.get_island_mut(island_2).unwrap().set_enabled(true);
+  let (invalidated_off_mesh_links, invalidated_islands, _) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  expect_true!(nav_data.get_island(island_2).unwrap().is_enabled());
+  expect_eq!(
+    nav_data.off_mesh_links.keys().collect::<HashSet<_>>(),
+    off_mesh_link_ids
+  );
+  expect_eq!(invalidated_off_mesh_links, HashSet::new());
+  expect_eq!(invalidated_islands, HashSet::new());
+  expect_eq!(
+    sample_island_2(&nav_data),
+    Some((
+      Vec3::new(0.5, 1.5, 0.0),
+      NodeRef { island_id: island_2, polygon_index: 0 }
+    ))
+  );
+  expect_true!(nav_data.are_nodes_connected(
+    NodeRef { island_id: island_1, polygon_index: 0 },
+    NodeRef { island_id: island_2, polygon_index: 0 },
+    PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
 }
 
@@ -1197,7 +1898,7 @@ fn permitted_animation_link_blocks_region_connectivity() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0 },
+    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   // We have 2 animation links with different kinds.
@@ -1207,6 +1908,8 @@ fn permitted_animation_link_blocks_region_connectivity() {
     kind: 0,
     cost: 1.0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 0.9), Vec2::new(1.0, 0.9)),
@@ -1214,6 +1917,8 @@ fn permitted_animation_link_blocks_region_connectivity() {
     kind: 1,
     cost: 1.0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1224,23 +1929,27 @@ fn permitted_animation_link_blocks_region_connectivity() {
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::All,
+    &PermittedTypeIndices::All,
   ));
   // Using either link still causes the nodes to be connected.
   expect_true!(nav_data.are_nodes_connected(
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::Kinds(Arc::new(HashSet::from([0]))),
+    &PermittedTypeIndices::All,
   ));
   expect_true!(nav_data.are_nodes_connected(
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::Kinds(Arc::new(HashSet::from([1]))),
+    &PermittedTypeIndices::All,
   ));
   // Using neither link cases the nodes to be unconnected.
   expect_false!(nav_data.are_nodes_connected(
     NodeRef { island_id: island_1, polygon_index: 0 },
     NodeRef { island_id: island_2, polygon_index: 0 },
     PermittedAnimationLinks::Kinds(Arc::new(HashSet::from([2]))),
+    &PermittedTypeIndices::All,
   ));
 }
 
@@ -1282,15 +1991,27 @@ fn generates_animation_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_3 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(-2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(-2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_4 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 4.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 4.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -1300,6 +2021,8 @@ fn generates_animation_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(0.9, 0.1, 13.0), Vec3::new(0.9, 0.9, 13.0)),
@@ -1307,6 +2030,8 @@ fn generates_animation_links() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_3 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(-1.1, 0.1, 13.0), Vec3::new(-1.1, 0.9, 13.0)),
@@ -1314,6 +2039,8 @@ fn generates_animation_links() {
     cost: 1.0,
     kind: 2,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -1450,7 +2177,11 @@ fn removing_animation_link_removes_off_mesh_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -1465,6 +2196,8 @@ fn removing_animation_link_removes_off_mesh_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(2.1, 1.1, 7.0), Vec3::new(2.1, 1.9, 7.0)),
@@ -1472,6 +2205,8 @@ fn removing_animation_link_removes_off_mesh_links() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -1581,6 +2316,8 @@ fn removing_animation_link_removes_off_mesh_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1629,6 +2366,8 @@ fn existing_animation_link_is_linked_for_new_island() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(2.1, 1.1, 7.0), Vec3::new(2.1, 1.9, 7.0)),
@@ -1636,6 +2375,8 @@ fn existing_animation_link_is_linked_for_new_island() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   // Prevent the animation links from being brand new.
@@ -1685,7 +2426,11 @@ fn existing_animation_link_is_linked_for_new_island() {
 
   // Adding the second island should now complete the links.
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   nav_data.update(
@@ -1791,7 +2536,11 @@ fn added_island_mixes_new_and_old_portals() {
   let island_00 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_11 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -1801,6 +2550,8 @@ fn added_island_mixes_new_and_old_portals() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(0.5, 2.5, 7.0), Vec3::new(2.5, 2.5, 7.0)),
@@ -1808,6 +2559,8 @@ fn added_island_mixes_new_and_old_portals() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -1849,11 +2602,19 @@ fn added_island_mixes_new_and_old_portals() {
 
   // Add in the remaining corners.
   let island_01 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_10 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2030,6 +2791,8 @@ fn same_island_animation_link() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -2111,6 +2874,8 @@ fn point_animation_links_are_connected() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   // This link has a whole end edge, but the start edge is actually a point. As
   // a result, the end edge should actually be treated as a point.
@@ -2120,6 +2885,8 @@ fn point_animation_links_are_connected() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -2235,6 +3002,8 @@ fn point_animation_links_are_connected_when_not_new() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   // This link has a whole end edge, but the start edge is actually a point. As
   // a result, the end edge should actually be treated as a point.
@@ -2244,6 +3013,8 @@ fn point_animation_links_are_connected_when_not_new() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   // Make the links not new to see that adding islands underneath also maintains
@@ -2358,15 +3129,27 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links() {
   let island_00 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_10 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(3.5, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(3.5, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_11 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(3.0, 3.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(3.0, 3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_01 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(-0.5, 3.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(-0.5, 3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2376,6 +3159,8 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   // Do an initial update so all the links are built.
@@ -2458,11 +3243,13 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links() {
   // Now we change two islands to see that the links are updated.
   nav_data.get_island_mut(island_01).unwrap().set_transform(Transform {
     translation: Vec3::new(0.0, 3.0, 0.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.get_island_mut(island_10).unwrap().set_transform(Transform {
     translation: Vec3::new(3.0, 0.0, 0.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -2566,7 +3353,11 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2576,6 +3367,8 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(0.5, 2.5, 7.0), Vec3::new(1.0, 2.5, 7.0)),
@@ -2583,6 +3376,8 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   // Do an initial update so all the links are built.
@@ -2672,7 +3467,8 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
   // Now we change one of the islands to see that the links are updated.
   nav_data.get_island_mut(island_2).unwrap().set_transform(Transform {
     translation: Vec3::new(0.1, 2.0, 0.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -2791,7 +3587,11 @@ fn generates_animation_links_at_correct_height() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2803,6 +3603,8 @@ fn generates_animation_links_at_correct_height() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   // This link has both edges just outside the vertical limit of the
   // animation links.
@@ -2812,6 +3614,8 @@ fn generates_animation_links_at_correct_height() {
     cost: 1.0,
     kind: 1,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   // Use an animation link distance of 1.0.
@@ -2918,7 +3722,11 @@ fn generates_animation_links_using_height_mesh() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2930,6 +3738,8 @@ fn generates_animation_links_using_height_mesh() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   // This link has both edges just outside the vertical limit of the
   // animation links.
@@ -2939,6 +3749,8 @@ fn generates_animation_links_using_height_mesh() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   // Use an animation link distance of 1.0.
@@ -3017,7 +3829,11 @@ fn animation_link_along_angled_surface() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -3027,6 +3843,8 @@ fn animation_link_along_angled_surface() {
     kind: 0,
     cost: 1.0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(0.01, 0.4);
@@ -3079,6 +3897,8 @@ fn animation_link_along_angled_surface() {
     kind: 0,
     cost: 1.0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(0.01, 0.6);
@@ -3208,15 +4028,27 @@ fn generates_bidirectional_animation_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_3 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(-2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(-2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_4 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 4.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 4.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -3226,6 +4058,8 @@ fn generates_bidirectional_animation_links() {
     cost: 1.0,
     kind: 0,
     bidirectional: true,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(0.9, 0.1, 13.0), Vec3::new(0.9, 0.9, 13.0)),
@@ -3233,6 +4067,8 @@ fn generates_bidirectional_animation_links() {
     cost: 1.0,
     kind: 1,
     bidirectional: true,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_3 = nav_data.add_animation_link(AnimationLink {
     start_edge: (Vec3::new(-1.1, 0.1, 13.0), Vec3::new(-1.1, 0.9, 13.0)),
@@ -3240,6 +4076,8 @@ fn generates_bidirectional_animation_links() {
     cost: 1.0,
     kind: 2,
     bidirectional: true,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -3445,6 +4283,8 @@ fn bidirectional_links_collapse_either_end() {
     cost: 1.0,
     kind: 0,
     bidirectional: true,
+    capacity: None,
+    busy_cost: None,
   });
   // This link has a whole end edge, but the start edge is actually a point.
   // This should collapse the end edge to a point.
@@ -3454,6 +4294,8 @@ fn bidirectional_links_collapse_either_end() {
     cost: 1.0,
     kind: 1,
     bidirectional: true,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
@@ -3575,7 +4417,8 @@ fn bidirectional_links_collapse_either_end() {
 
   nav_data.get_island_mut(island).unwrap().set_transform(Transform {
     translation: Vec3::new(100.0, 100.0, 100.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
 
   nav_data.update(
@@ -3680,6 +4523,8 @@ fn bidirectional_links_use_correct_indices() {
     cost: 1.0,
     kind: 0,
     bidirectional: true,
+    capacity: None,
+    busy_cost: None,
   });
 
   nav_data.update(
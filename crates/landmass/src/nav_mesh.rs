@@ -7,15 +7,27 @@ use std::{
 
 use disjoint::DisjointSet;
 use glam::{Vec3, swizzles::Vec3Swizzles};
+use rand::Rng;
 use thiserror::Error;
 
 use crate::{
   coords::{CoordinateSystem, CorePointSampleDistance},
-  geometry::clip_edge_to_triangle,
-  util::{BoundingBox, BoundingBoxHierarchy, FloatOrd, RaySegment},
+  geometry::{clip_edge_to_triangle, project_point_to_line_segment},
+  util::{
+    BoundingBox, BoundingBoxHierarchy, FloatOrd, RaySegment,
+    random_weighted_index,
+  },
 };
 
 /// A navigation mesh.
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(bound(
+    serialize = "CS::Coordinate: serde::Serialize",
+    deserialize = "CS::Coordinate: serde::Deserialize<'de>"
+  ))
+)]
 pub struct NavigationMesh<CS: CoordinateSystem> {
   /// The vertices that make up the polygons.
   pub vertices: Vec<CS::Coordinate>,
@@ -49,6 +61,14 @@ pub struct NavigationMesh<CS: CoordinateSystem> {
 /// ground will be recognized as being on the correct node. In other words, we
 /// use the height mesh to figure out where the agent is, then use the regular
 /// nav mesh to do the actual pathfinding.
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(bound(
+    serialize = "CS::Coordinate: serde::Serialize",
+    deserialize = "CS::Coordinate: serde::Deserialize<'de>"
+  ))
+)]
 pub struct HeightNavigationMesh<CS: CoordinateSystem> {
   /// The list of height polygons that correspond to the original polygons.
   ///
@@ -72,6 +92,7 @@ pub struct HeightNavigationMesh<CS: CoordinateSystem> {
 /// While regular polygons are used for finding paths, this polygon is used to
 /// help determine which node a given point is on.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HeightPolygon {
   /// The index of the first vertex in [`HeightNavigationMesh::vertices`] used
   /// by this polygon's triangles. The indices that make up a triangle are
@@ -350,6 +371,8 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
           center: polygon_vertices.iter().map(|i| vertices[*i]).sum::<Vec3>()
             / polygon_vertices.len() as f32,
           connectivity: vec![None; polygon_vertices.len()],
+          // Computed once we know the boundary vertices.
+          edge_clearances: vec![],
           vertices: polygon_vertices,
           region: {
             let region = region_sets.root_of(polygon_index);
@@ -371,10 +394,15 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
       .collect::<Vec<_>>();
 
     let mut boundary_edges = Vec::new();
+    let mut boundary_vertices = HashSet::new();
     for connectivity_state in connectivity_set.values() {
       match connectivity_state {
         ConnectivityState::Disconnected => panic!("Value is never stored"),
         &ConnectivityState::Boundary { polygon, edge } => {
+          let (left_vertex, right_vertex) =
+            polygons[polygon].get_edge_indices(edge);
+          boundary_vertices.insert(left_vertex);
+          boundary_vertices.insert(right_vertex);
           boundary_edges
             .push(MeshEdgeRef { edge_index: edge, polygon_index: polygon });
         }
@@ -396,11 +424,29 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
       }
     }
 
+    for polygon in polygons.iter_mut() {
+      polygon.edge_clearances = (0..polygon.vertices.len())
+        .map(|edge| {
+          let (left_vertex, right_vertex) = polygon.get_edge_indices(edge);
+          // If either end of the edge is not a wall, the agent can go around
+          // that end, so the edge doesn't restrict the agent.
+          if boundary_vertices.contains(&left_vertex)
+            && boundary_vertices.contains(&right_vertex)
+          {
+            vertices[left_vertex].xy().distance(vertices[right_vertex].xy())
+          } else {
+            f32::INFINITY
+          }
+        })
+        .collect();
+    }
+
     Ok(ValidNavigationMesh {
       mesh_bounds,
       polygons,
       vertices,
       boundary_edges,
+      boundary_vertices,
       height_mesh,
       marker: Default::default(),
     })
@@ -480,6 +526,19 @@ impl<CS: CoordinateSystem> HeightNavigationMesh<CS> {
 
 /// A navigation mesh which has been validated and derived data has been
 /// computed.
+///
+/// With the `serde` feature, this can be serialized along with all its derived
+/// data, so deserializing it does not need to run
+/// [`NavigationMesh::validate`] again. This is useful for baking navigation
+/// meshes offline and loading them quickly, especially with a compact binary
+/// format (e.g., `postcard` or `bincode`). The deserialized data is trusted to
+/// be valid, so only deserialize data that was serialized from a
+/// [`ValidNavigationMesh`] by the same version of `landmass`.
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(bound = "")
+)]
 pub struct ValidNavigationMesh<CS: CoordinateSystem> {
   /// The bounds of the mesh data itself. This is a tight bounding box around
   /// the vertices of the navigation mesh.
@@ -493,6 +552,10 @@ pub struct ValidNavigationMesh<CS: CoordinateSystem> {
   /// (e.0, e.1) from e.0 to e.1 will move counter-clockwise along the
   /// boundary. The order of edges is undefined.
   pub(crate) boundary_edges: Vec<MeshEdgeRef>,
+  /// The vertices that lie on [`Self::boundary_edges`]. Agents need to keep
+  /// their distance from these vertices, since they are the "corners" of the
+  /// walls of the navigation mesh.
+  pub(crate) boundary_vertices: HashSet<usize>,
   /// The height mesh used to "refine" point positions. See
   /// [`HeightNavigationMesh`] for more details.
   pub(crate) height_mesh: Option<ValidHeightNavigationMesh>,
@@ -503,6 +566,7 @@ pub struct ValidNavigationMesh<CS: CoordinateSystem> {
 /// A version of [`HeightNavigationMesh`] after it has been validated and
 /// converted to the standard coordinate system.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct ValidHeightNavigationMesh {
   /// The list of height polygons that correspond to the original polygons.
   ///
@@ -528,6 +592,7 @@ impl<CS: CoordinateSystem> Clone for ValidNavigationMesh<CS> {
       vertices: self.vertices.clone(),
       polygons: self.polygons.clone(),
       boundary_edges: self.boundary_edges.clone(),
+      boundary_vertices: self.boundary_vertices.clone(),
       height_mesh: self.height_mesh.clone(),
       marker: self.marker,
     }
@@ -542,6 +607,7 @@ impl<CS: CoordinateSystem> std::fmt::Debug for ValidNavigationMesh<CS> {
       .field("vertices", &self.vertices)
       .field("polygons", &self.polygons)
       .field("boundary_edges", &self.boundary_edges)
+      .field("boundary_vertices", &self.boundary_vertices)
       .field("height_mesh", &self.height_mesh)
       .field("marker", &self.marker)
       .finish()
@@ -551,6 +617,7 @@ impl<CS: CoordinateSystem> std::fmt::Debug for ValidNavigationMesh<CS> {
 /// A valid polygon. This means the polygon is convex and indexes the `vertices`
 /// Vec of the corresponding ValidNavigationMesh.
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct ValidPolygon {
   /// The vertices are indexes to the `vertices` Vec of the corresponding
   /// ValidNavigationMesh.
@@ -560,6 +627,11 @@ pub(crate) struct ValidPolygon {
   /// Entries that are `None` correspond to the boundary of the navigation
   /// mesh, while `Some` entries are connected to another node.
   pub(crate) connectivity: Vec<Option<Connectivity>>,
+  /// The clearance of each edge in the polygon. This is the same length as
+  /// the number of edges. The clearance is the widest an agent can be (twice
+  /// its radius) while still crossing the edge. Edges that are not between
+  /// two walls have infinite clearance.
+  pub(crate) edge_clearances: Vec<f32>,
   /// The "region" that this polygon belongs to. Each region is disjoint from
   /// every other. A "direct" path only exists if the region matches between
   /// two nodes. An "indirect" path exists if regions are joined together
@@ -575,6 +647,7 @@ pub(crate) struct ValidPolygon {
 }
 
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Connectivity {
   /// The index of the polygon that this edge leads to.
   pub(crate) polygon_index: usize,
@@ -584,6 +657,7 @@ pub(crate) struct Connectivity {
 
 /// A reference to an edge on a navigation mesh.
 #[derive(PartialEq, Eq, Debug, Clone, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct MeshEdgeRef {
   /// The index of the polygon that this edge belongs to.
   pub(crate) polygon_index: usize,
@@ -615,6 +689,17 @@ impl<CS: CoordinateSystem> ValidNavigationMesh<CS> {
     &self,
     point: Vec3,
     point_sample_distance: &CorePointSampleDistance,
+  ) -> Option<(Vec3, usize)> {
+    self.sample_point_filtered(point, point_sample_distance, |_| true)
+  }
+
+  /// Same as [`Self::sample_point`], except only polygons whose index passes
+  /// `filter` are considered.
+  pub(crate) fn sample_point_filtered(
+    &self,
+    point: Vec3,
+    point_sample_distance: &CorePointSampleDistance,
+    filter: impl Fn(usize) -> bool,
   ) -> Option<(Vec3, usize)> {
     let sample_box = BoundingBox::new_box(
       point
@@ -670,6 +755,9 @@ impl<CS: CoordinateSystem> ValidNavigationMesh<CS> {
       if !sample_box.intersects_bounds(&polygon.bounds) {
         continue;
       }
+      if !filter(polygon_index) {
+        continue;
+      }
 
       // Whether we are using the normal mesh or the height mesh, we want the
       // triangles to be handled the same. So factor out the test and turn it
@@ -813,6 +901,84 @@ impl<CS: CoordinateSystem> ValidNavigationMesh<CS> {
     )
   }
 
+  /// Finds the point on the provided node that is horizontally closest to
+  /// `point`. Unlike [`Self::sample_point_on_node`], `point` does not need to
+  /// project to the node.
+  pub(crate) fn closest_point_on_node(&self, point: Vec3, node: usize) -> Vec3 {
+    let polygon = &self.polygons[node];
+    let flat_point = point.xy();
+
+    let mut is_inside = true;
+    let mut closest_point = flat_point;
+    let mut closest_distance_squared = f32::INFINITY;
+    for (index, &i) in polygon.vertices.iter().enumerate() {
+      let j = polygon.vertices[(index + 1) % polygon.vertices.len()];
+      let (start, end) = (self.vertices[i].xy(), self.vertices[j].xy());
+      // Polygons are CCW, so the point is outside if it's to the right of any
+      // edge.
+      if (end - start).perp_dot(flat_point - start) < 0.0 {
+        is_inside = false;
+      }
+
+      let (projected_point, _) = project_point_to_line_segment(
+        flat_point.extend(0.0),
+        (start.extend(0.0), end.extend(0.0)),
+      );
+      let distance_squared = projected_point.xy().distance_squared(flat_point);
+      if distance_squared < closest_distance_squared {
+        closest_distance_squared = distance_squared;
+        closest_point = projected_point.xy();
+      }
+    }
+
+    if is_inside {
+      closest_point = flat_point;
+    }
+    self.sample_point_on_node(closest_point.extend(point.z), node)
+  }
+
+  /// Computes the (horizontal) areas of the triangles in the triangle fan of
+  /// the provided node.
+  fn node_triangle_areas(&self, node: usize) -> Vec<f32> {
+    let polygon = &self.polygons[node];
+    let origin = self.vertices[polygon.vertices[0]].xy();
+    (2..polygon.vertices.len())
+      .map(|i| {
+        let b = self.vertices[polygon.vertices[i - 1]].xy();
+        let c = self.vertices[polygon.vertices[i]].xy();
+        (b - origin).perp_dot(c - origin) * 0.5
+      })
+      .collect()
+  }
+
+  /// Computes the (horizontal) area of the provided node.
+  pub(crate) fn node_area(&self, node: usize) -> f32 {
+    self.node_triangle_areas(node).iter().sum()
+  }
+
+  /// Picks a uniformly random point on the provided node.
+  pub(crate) fn random_point_on_node(
+    &self,
+    node: usize,
+    rng: &mut impl Rng,
+  ) -> Vec3 {
+    let polygon = &self.polygons[node];
+    let triangle = random_weighted_index(&self.node_triangle_areas(node), rng)
+      .unwrap_or_default()
+      + 2;
+    let a = self.vertices[polygon.vertices[0]];
+    let b = self.vertices[polygon.vertices[triangle - 1]];
+    let c = self.vertices[polygon.vertices[triangle]];
+
+    let (mut u, mut v) = (rng.random::<f32>(), rng.random::<f32>());
+    // Points past the diagonal of the parallelogram are mirrored back into the
+    // triangle.
+    if u + v > 1.0 {
+      (u, v) = (1.0 - u, 1.0 - v);
+    }
+    self.sample_point_on_node(a + (b - a) * u + (c - a) * v, node)
+  }
+
   /// Samples the `edge` on this nav mesh clipping to a max vertical distance.
   ///
   /// `node_bbh` must correspond to this navigation mesh's polygons.
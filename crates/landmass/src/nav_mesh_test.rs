@@ -1,4 +1,4 @@
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{expect_that, matchers::*};
 
 use crate::{
@@ -11,6 +11,8 @@ use crate::{
   util::BoundingBox,
 };
 
+#[cfg(feature = "serde")]
+use super::ValidNavigationMesh;
 use super::{NavigationMesh, ValidationError};
 
 #[test]
@@ -78,6 +80,7 @@ fn polygons_derived_and_vertices_copied() {
     ValidPolygon {
       vertices: source_mesh.polygons[0].clone(),
       connectivity: vec![None, None, None],
+      edge_clearances: vec![1.0, 2.0f32.sqrt(), 5.0f32.sqrt()],
       region: 0,
       type_index: 1337,
       bounds: BoundingBox::new_box(
@@ -89,6 +92,7 @@ fn polygons_derived_and_vertices_copied() {
     ValidPolygon {
       vertices: source_mesh.polygons[1].clone(),
       connectivity: vec![None, None, None],
+      edge_clearances: vec![1.0625f32.sqrt(), 0.5, 1.0625f32.sqrt()],
       region: 1,
       type_index: 123,
       bounds: BoundingBox::new_box(
@@ -139,6 +143,7 @@ struct FlippedXYZ;
 impl CoordinateSystem for FlippedXYZ {
   type Coordinate = Vec3;
   type SampleDistance = PointSampleDistance3d;
+  type Rotation = Quat;
 
   const FLIP_POLYGONS: bool = true;
 
@@ -149,6 +154,10 @@ impl CoordinateSystem for FlippedXYZ {
   fn to_landmass(v: &Self::Coordinate) -> Vec3 {
     *v
   }
+
+  fn to_landmass_rotation(rotation: &Self::Rotation) -> Quat {
+    *rotation
+  }
 }
 
 #[test]
@@ -423,6 +432,49 @@ fn finds_regions() {
   );
 }
 
+#[test]
+fn computes_edge_clearances() {
+  let mesh = NavigationMesh::<XY> {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(3.0, 0.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(3.0, 1.0),
+      Vec2::new(0.0, 3.0),
+      Vec2::new(1.0, 3.0),
+      Vec2::new(3.0, 3.0),
+    ],
+    polygons: vec![
+      vec![0, 1, 4, 3],
+      vec![1, 2, 5, 4],
+      vec![3, 4, 7, 6],
+      vec![4, 5, 8, 7],
+    ],
+    polygon_type_indices: vec![0, 0, 0, 0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  // Edges touching the center vertex are not between two walls, so they are
+  // unrestricted. All other edges are on the boundary.
+  assert_eq!(
+    mesh
+      .polygons
+      .iter()
+      .map(|polygon| polygon.edge_clearances.clone())
+      .collect::<Vec<_>>(),
+    [
+      vec![1.0, f32::INFINITY, f32::INFINITY, 1.0],
+      vec![2.0, 1.0, f32::INFINITY, f32::INFINITY],
+      vec![f32::INFINITY, f32::INFINITY, 1.0, 2.0],
+      vec![f32::INFINITY, 2.0, 2.0, f32::INFINITY],
+    ],
+  );
+}
+
 #[test]
 fn sample_point_returns_none_for_far_point() {
   let mesh = NavigationMesh::<XYZ> {
@@ -708,6 +760,7 @@ fn valid_polygon_gets_edge_indices() {
     region: 0,
     type_index: 0,
     connectivity: vec![],
+    edge_clearances: vec![],
     center: Vec3::ZERO,
   };
 
@@ -1340,3 +1393,100 @@ fn samples_edge_along_stairs() {
     )
   );
 }
+
+#[test]
+fn finds_closest_point_on_node() {
+  let nav_mesh = NavigationMesh::<XYZ> {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 1.0),
+      Vec3::new(1.0, 0.0, 1.0),
+      Vec3::new(1.0, 1.0, 1.0),
+      Vec3::new(0.0, 1.0, 1.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  // Points inside the node are just projected onto it.
+  assert_eq!(
+    nav_mesh.closest_point_on_node(Vec3::new(0.25, 0.5, 3.0), 0),
+    Vec3::new(0.25, 0.5, 1.0)
+  );
+  // Points outside the node go to the nearest edge.
+  assert_eq!(
+    nav_mesh.closest_point_on_node(Vec3::new(2.0, 0.5, 0.0), 0),
+    Vec3::new(1.0, 0.5, 1.0)
+  );
+  assert_eq!(
+    nav_mesh.closest_point_on_node(Vec3::new(0.75, -3.0, 0.0), 0),
+    Vec3::new(0.75, 0.0, 1.0)
+  );
+  // Points past a corner go to the corner.
+  assert_eq!(
+    nav_mesh.closest_point_on_node(Vec3::new(2.0, 2.0, 0.0), 0),
+    Vec3::new(1.0, 1.0, 1.0)
+  );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serializes_and_deserializes_nav_mesh() {
+  let source_mesh = NavigationMesh::<XYZ> {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      Vec3::new(1.0, 1.0, 0.0),
+      Vec3::new(0.0, 1.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![3],
+    height_mesh: Some(HeightNavigationMesh {
+      vertices: vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.5),
+        Vec3::new(0.0, 1.0, 0.0),
+      ],
+      triangles: vec![[0, 1, 2], [2, 3, 0]],
+      polygons: vec![HeightPolygon {
+        base_vertex_index: 0,
+        vertex_count: 4,
+        base_triangle_index: 0,
+        triangle_count: 2,
+      }],
+    }),
+  };
+
+  let bytes = postcard::to_allocvec(&source_mesh).unwrap();
+  let deserialized_mesh: NavigationMesh<XYZ> =
+    postcard::from_bytes(&bytes).unwrap();
+  assert_eq!(deserialized_mesh.vertices, source_mesh.vertices);
+  assert_eq!(deserialized_mesh.polygons, source_mesh.polygons);
+  assert_eq!(
+    deserialized_mesh.polygon_type_indices,
+    source_mesh.polygon_type_indices
+  );
+  let deserialized_height_mesh = deserialized_mesh.height_mesh.unwrap();
+  let source_height_mesh = source_mesh.height_mesh.as_ref().unwrap();
+  assert_eq!(deserialized_height_mesh.vertices, source_height_mesh.vertices);
+  assert_eq!(deserialized_height_mesh.triangles, source_height_mesh.triangles);
+  assert_eq!(deserialized_height_mesh.polygons, source_height_mesh.polygons);
+
+  let valid_mesh = source_mesh.validate().expect("nav mesh is valid");
+  let bytes = postcard::to_allocvec(&valid_mesh).unwrap();
+  let deserialized_mesh: ValidNavigationMesh<XYZ> =
+    postcard::from_bytes(&bytes).unwrap();
+  assert_eq!(deserialized_mesh.mesh_bounds, valid_mesh.mesh_bounds);
+  assert_eq!(deserialized_mesh.vertices, valid_mesh.vertices);
+  assert_eq!(deserialized_mesh.polygons, valid_mesh.polygons);
+  assert_eq!(deserialized_mesh.boundary_edges, valid_mesh.boundary_edges);
+  assert_eq!(deserialized_mesh.boundary_vertices, valid_mesh.boundary_vertices);
+  let deserialized_height_mesh = deserialized_mesh.height_mesh.unwrap();
+  let valid_height_mesh = valid_mesh.height_mesh.as_ref().unwrap();
+  assert_eq!(deserialized_height_mesh.vertices, valid_height_mesh.vertices);
+  assert_eq!(deserialized_height_mesh.triangles, valid_height_mesh.triangles);
+  assert_eq!(deserialized_height_mesh.polygons, valid_height_mesh.polygons);
+}
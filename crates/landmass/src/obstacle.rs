@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use slotmap::new_key_type;
+
+use crate::{
+  CoordinateSystem,
+  nav_data::NodeRef,
+  util::{BoundingBox, Transform},
+};
+
+new_key_type! {
+  /// The ID of an [`Obstacle`].
+  pub struct ObstacleId;
+}
+
+/// An obstacle that carves a hole into the navigation meshes it overlaps.
+///
+/// Unlike characters (which are avoided locally), obstacles block navigation
+/// entirely: agents will path around them. This is useful for doors,
+/// barricades, parked vehicles, etc. Removing the obstacle restores the
+/// original navigation meshes.
+pub struct Obstacle<CS: CoordinateSystem> {
+  /// The shape of the obstacle, relative to `transform`.
+  pub shape: ObstacleShape<CS>,
+  /// The transform from the obstacle's frame to the Archipelago's frame.
+  pub transform: Transform<CS>,
+  /// The vertical extent of the obstacle (before scaling), starting at the
+  /// translation of `transform`. Only nodes that overlap this vertical range
+  /// are carved.
+  pub height: f32,
+}
+
+/// The shape of an [`Obstacle`] or a [`crate::CostVolume`].
+pub enum ObstacleShape<CS: CoordinateSystem> {
+  /// A circle centered on the obstacle's origin.
+  Circle {
+    /// The radius of the circle.
+    radius: f32,
+  },
+  /// A convex polygon. Only the horizontal part of each vertex is used.
+  Polygon(Vec<CS::Coordinate>),
+  /// A capsule centered on the obstacle's origin. The capsule's segment lies
+  /// along the X axis of the obstacle's frame.
+  Capsule {
+    /// Half the length of the capsule's segment (excluding the caps).
+    half_length: f32,
+    /// The radius of the capsule.
+    radius: f32,
+  },
+}
+
+/// The state of an obstacle.
+pub(crate) struct ObstacleState<CS: CoordinateSystem> {
+  /// The obstacle given to us by the user.
+  pub(crate) obstacle: Obstacle<CS>,
+  /// The nodes that this obstacle carves.
+  pub(crate) carved_nodes: HashSet<NodeRef>,
+  /// Whether the obstacle has been mutated since the last update.
+  pub(crate) dirty: bool,
+}
+
+impl<CS: CoordinateSystem> ObstacleState<CS> {
+  pub(crate) fn new(obstacle: Obstacle<CS>) -> Self {
+    Self { obstacle, carved_nodes: HashSet::new(), dirty: true }
+  }
+}
+
+/// The number of edges used to approximate a circle obstacle.
+const CIRCLE_EDGES: usize = 16;
+
+// Manual Clone impl to avoid `CS` having a Clone bound itself.
+impl<CS: CoordinateSystem> Clone for Obstacle<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      shape: self.shape.clone(),
+      transform: self.transform.clone(),
+      height: self.height,
+    }
+  }
+}
+
+// Manual Clone impl to avoid `CS` having a Clone bound itself.
+impl<CS: CoordinateSystem> Clone for ObstacleShape<CS> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Circle { radius } => Self::Circle { radius: *radius },
+      Self::Polygon(vertices) => Self::Polygon(vertices.clone()),
+      Self::Capsule { half_length, radius } => {
+        Self::Capsule { half_length: *half_length, radius: *radius }
+      }
+    }
+  }
+}
+
+// Manual PartialEq impl to avoid `CS` having a PartialEq bound itself.
+impl<CS: CoordinateSystem<Coordinate: PartialEq, Rotation: PartialEq>> PartialEq
+  for Obstacle<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.shape == other.shape
+      && self.transform == other.transform
+      && self.height == other.height
+  }
+}
+
+// Manual PartialEq impl to avoid `CS` having a PartialEq bound itself.
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for ObstacleShape<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::Circle { radius }, Self::Circle { radius: other_radius }) => {
+        radius == other_radius
+      }
+      (Self::Polygon(vertices), Self::Polygon(other_vertices)) => {
+        vertices == other_vertices
+      }
+      (
+        Self::Capsule { half_length, radius },
+        Self::Capsule { half_length: other_half_length, radius: other_radius },
+      ) => half_length == other_half_length && radius == other_radius,
+      _ => false,
+    }
+  }
+}
+
+impl<
+  CS: CoordinateSystem<Coordinate: std::fmt::Debug, Rotation: std::fmt::Debug>,
+> std::fmt::Debug for Obstacle<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Obstacle")
+      .field("shape", &self.shape)
+      .field("transform", &self.transform)
+      .field("height", &self.height)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for ObstacleShape<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Circle { radius } => {
+        f.debug_struct("Circle").field("radius", radius).finish()
+      }
+      Self::Polygon(vertices) => {
+        f.debug_tuple("Polygon").field(vertices).finish()
+      }
+      Self::Capsule { half_length, radius } => f
+        .debug_struct("Capsule")
+        .field("half_length", half_length)
+        .field("radius", radius)
+        .finish(),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem> Obstacle<CS> {
+  /// Computes the (2D) outline of the obstacle in world space.
+  pub(crate) fn world_outline(&self) -> Vec<Vec2> {
+    self.shape.world_outline(&self.clamped_transform())
+  }
+
+  /// Computes the world space bounds of the obstacle, including its height.
+  pub(crate) fn world_bounds(&self, outline: &[Vec2]) -> BoundingBox {
+    outline_bounds(outline, &self.clamped_transform(), self.height)
+  }
+
+  /// Returns the transform of the obstacle with its scale clamped to be
+  /// positive. Obstacles can be mutated freely, so the scale is clamped
+  /// whenever it is used rather than when it is set.
+  fn clamped_transform(&self) -> Transform<CS> {
+    self.transform.clone().with_clamped_scale()
+  }
+}
+
+impl<CS: CoordinateSystem> ObstacleShape<CS> {
+  /// Computes the (2D) outline of the shape in world space after applying
+  /// `transform`. Circles (and the caps of capsules) are approximated by a
+  /// regular polygon.
+  pub(crate) fn world_outline(&self, transform: &Transform<CS>) -> Vec<Vec2> {
+    match self {
+      Self::Circle { radius } => {
+        let center = transform.apply(Vec3::ZERO).xy();
+        let radius = *radius * transform.scale;
+        (0..CIRCLE_EDGES)
+          .map(|i| {
+            let angle = i as f32 / CIRCLE_EDGES as f32 * std::f32::consts::TAU;
+            center + Vec2::from_angle(angle) * radius
+          })
+          .collect()
+      }
+      Self::Polygon(vertices) => vertices
+        .iter()
+        .map(|vertex| transform.apply(CS::to_landmass(vertex)).xy())
+        .collect(),
+      Self::Capsule { half_length, radius } => {
+        // Each cap is half a circle, starting from the bottom of the right cap
+        // going counter-clockwise.
+        let cap_edges = CIRCLE_EDGES / 2;
+        [
+          (*half_length, -std::f32::consts::FRAC_PI_2),
+          (-*half_length, std::f32::consts::FRAC_PI_2),
+        ]
+        .into_iter()
+        .flat_map(|(center_x, start_angle)| {
+          (0..=cap_edges).map(move |i| {
+            let angle =
+              start_angle + i as f32 / cap_edges as f32 * std::f32::consts::PI;
+            Vec2::new(center_x, 0.0) + Vec2::from_angle(angle) * *radius
+          })
+        })
+        .map(|point| transform.apply(point.extend(0.0)).xy())
+        .collect()
+      }
+    }
+  }
+}
+
+/// Computes the world space bounds of `outline`, extending `height` (scaled by
+/// the scale of `transform`) up from the translation of `transform`.
+pub(crate) fn outline_bounds<CS: CoordinateSystem>(
+  outline: &[Vec2],
+  transform: &Transform<CS>,
+  height: f32,
+) -> BoundingBox {
+  let base = transform.apply(Vec3::ZERO).z;
+  let height = height * transform.scale;
+  outline.iter().fold(BoundingBox::Empty, |bounds, point| {
+    bounds
+      .expand_to_point(point.extend(base))
+      .expand_to_point(point.extend(base + height))
+  })
+}
+
+/// Converts the outline of a shape into a polygon usable for clipping.
+pub(crate) fn outline_to_multi_polygon(outline: &[Vec2]) -> MultiPolygon<f32> {
+  MultiPolygon::new(vec![Polygon::new(
+    LineString(
+      outline.iter().map(|point| Coord::from((point.x, point.y))).collect(),
+    ),
+    vec![],
+  )])
+}
@@ -1,12 +1,18 @@
 use std::{cmp::Ordering, collections::HashSet};
 
-use glam::{Vec3, Vec3Swizzles};
+use geo::{
+  Distance, Euclidean, Line, LineIntersection, Point,
+  line_intersection::line_intersection,
+};
+use glam::{Vec2, Vec3, Vec3Swizzles};
 
 use crate::{
   CoordinateSystem, IslandId, NavigationData,
   geometry::project_point_to_line_segment,
   link::AnimationLinkId,
-  nav_data::{KindedOffMeshLink, NodeRef, OffMeshLinkId},
+  nav_data::{
+    KindedOffMeshLink, NodeRef, OffMeshLinkId, node_to_multi_polygon,
+  },
 };
 
 /// A path computed on the navigation data.
@@ -78,10 +84,14 @@ enum Portal {
 
 impl IslandSegment {
   /// Determines the endpoints of the portal at `portal_index` in `nav_data`.
+  /// The portal is clipped to the part not covered by obstacles, and shrunk by
+  /// `agent_radius` at any endpoint that is on the boundary of the navigation
+  /// mesh or an obstacle.
   fn get_portal_endpoints<CS: CoordinateSystem>(
     &self,
     portal_index: usize,
     nav_data: &NavigationData<CS>,
+    agent_radius: f32,
   ) -> Portal {
     let polygon_index = self.corridor[portal_index];
     let edge = self.portal_edge_index[portal_index];
@@ -89,13 +99,45 @@ impl IslandSegment {
     let island_data = nav_data
       .get_island(self.island_id)
       .expect("only called if path is still valid");
-    let (left_vertex, right_vertex) =
-      island_data.nav_mesh.polygons[polygon_index].get_edge_indices(edge);
-
-    Portal::Walkable(
-      island_data.transform.apply(island_data.nav_mesh.vertices[left_vertex]),
-      island_data.transform.apply(island_data.nav_mesh.vertices[right_vertex]),
-    )
+    let polygon = &island_data.nav_mesh.polygons[polygon_index];
+    let (left_vertex, right_vertex) = polygon.get_edge_indices(edge);
+
+    // Obstacles may cover either end of the portal, from either side.
+    let (mut start, mut end) = (0.0f32, 1.0f32);
+    if let Some(carved_node) = nav_data
+      .carved_nodes
+      .get(&NodeRef { island_id: self.island_id, polygon_index })
+    {
+      let (range_start, range_end) = carved_node.uncovered_edge_ranges[edge];
+      (start, end) = (start.max(range_start), end.min(range_end));
+    }
+    if let Some(connection) = &polygon.connectivity[edge]
+      && let Some(carved_node) = nav_data.carved_nodes.get(&NodeRef {
+        island_id: self.island_id,
+        polygon_index: connection.polygon_index,
+      })
+    {
+      // The reverse edge goes in the opposite direction.
+      let (range_start, range_end) =
+        carved_node.uncovered_edge_ranges[connection.reverse_edge];
+      (start, end) = (start.max(1.0 - range_end), end.min(1.0 - range_start));
+    }
+    let end = end.max(start);
+
+    let left =
+      island_data.transform.apply(island_data.nav_mesh.vertices[left_vertex]);
+    let right =
+      island_data.transform.apply(island_data.nav_mesh.vertices[right_vertex]);
+    let (left, right) = shrink_portal(
+      left.lerp(right, start),
+      left.lerp(right, end),
+      start > 0.0
+        || island_data.nav_mesh.boundary_vertices.contains(&left_vertex),
+      end < 1.0
+        || island_data.nav_mesh.boundary_vertices.contains(&right_vertex),
+      agent_radius,
+    );
+    Portal::Walkable(left, right)
   }
 }
 
@@ -111,6 +153,8 @@ impl OffMeshLinkSegment {
       .expect("only called if path is still valid");
     let portal = off_mesh_link.portal;
     match &off_mesh_link.kinded {
+      // Boundary links are not shrunk, since the ends of the portal are
+      // usually just where the islands stop overlapping, not walls.
       KindedOffMeshLink::BoundaryLink { .. } => {
         Portal::Walkable(portal.0, portal.1)
       }
@@ -129,6 +173,38 @@ impl OffMeshLinkSegment {
   }
 }
 
+/// Shrinks the portal from `left` to `right` by `agent_radius` at the ends that
+/// are walls (according to `left_is_wall` and `right_is_wall`). Each wall end
+/// is moved `agent_radius` (horizontally) along the portal, regardless of the
+/// portal's length. This keeps agents from cutting corners too tightly. If the
+/// portal is too narrow, it is collapsed to a single point.
+fn shrink_portal(
+  left: Vec3,
+  right: Vec3,
+  left_is_wall: bool,
+  right_is_wall: bool,
+  agent_radius: f32,
+) -> (Vec3, Vec3) {
+  let length = left.xy().distance(right.xy());
+  if agent_radius <= 0.0 || length == 0.0 {
+    return (left, right);
+  }
+
+  // Scaled so that moving by `direction * distance` moves `distance`
+  // horizontally, while following the slope of the portal.
+  let direction = (right - left) / length;
+  let left_offset = if left_is_wall { agent_radius } else { 0.0 };
+  let right_offset = if right_is_wall { agent_radius } else { 0.0 };
+  if left_offset + right_offset > length {
+    // Pick the point that is as far from the walls as possible.
+    let point = left
+      + direction
+        * ((length + left_offset - right_offset) * 0.5).clamp(0.0, length);
+    return (point, point);
+  }
+  (left + direction * left_offset, right - direction * right_offset)
+}
+
 /// An index in a path.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub(crate) struct PathIndex {
@@ -176,6 +252,20 @@ impl PathIndex {
   }
 }
 
+/// The part of a path that was invalidated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PathInvalidation {
+  /// None of the path was invalidated.
+  Valid,
+  /// The path was invalidated between the nodes at these indices. The nodes
+  /// at these indices are still valid, so the path can be repaired by
+  /// replacing the nodes between them.
+  Between(PathIndex, PathIndex),
+  /// The first or last node of the path was invalidated, so the path can't be
+  /// repaired.
+  Unrepairable,
+}
+
 /// A step in a straight-line path.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum StraightPathStep {
@@ -200,11 +290,13 @@ pub(crate) enum StraightPathStep {
 
 impl Path {
   /// Determines the endpoints of the portal at `segment_index` at
-  /// `portal_index` in `nav_data`.
+  /// `portal_index` in `nav_data`. Portals within an island are shrunk by
+  /// `agent_radius`.
   fn get_portal_endpoints<CS: CoordinateSystem>(
     &self,
     path_index: PathIndex,
     nav_data: &NavigationData<CS>,
+    agent_radius: f32,
   ) -> Portal {
     if path_index.portal_index
       == self.island_segments[path_index.segment_index].portal_edge_index.len()
@@ -212,8 +304,11 @@ impl Path {
       self.off_mesh_link_segments[path_index.segment_index]
         .get_portal_endpoints(nav_data)
     } else {
-      self.island_segments[path_index.segment_index]
-        .get_portal_endpoints(path_index.portal_index, nav_data)
+      self.island_segments[path_index.segment_index].get_portal_endpoints(
+        path_index.portal_index,
+        nav_data,
+        agent_radius,
+      )
     }
   }
 
@@ -223,14 +318,245 @@ impl Path {
   /// indices into `self`. Returns the index of the node in the path where the
   /// next point is, and that next point. Note this can be called repeatedly by
   /// passing in the returned tuple as the `start_index` and `start_point` to
-  /// generate the full straight path.
+  /// generate the full straight path. Waypoints are kept `agent_radius` away
+  /// from the corners of the navigation mesh, and walk around the holes cut by
+  /// obstacles.
   pub(crate) fn find_next_point_in_straight_path<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    start_index: PathIndex,
+    start_point: Vec3,
+    end_index: PathIndex,
+    end_point: Vec3,
+    agent_radius: f32,
+  ) -> (PathIndex, StraightPathStep) {
+    let (next_index, next_step) = self.find_next_point_in_funnel(
+      nav_data,
+      start_index,
+      start_point,
+      end_index,
+      end_point,
+      agent_radius,
+    );
+    let (last_index, next_point) = match next_step {
+      StraightPathStep::Waypoint(point) => (next_index, point),
+      StraightPathStep::AnimationLink { start_point, .. } => {
+        // The animation link starts at the end of the segment before
+        // `next_index`.
+        let segment_index = next_index.segment_index - 1;
+        (
+          PathIndex {
+            segment_index,
+            portal_index: self.island_segments[segment_index]
+              .portal_edge_index
+              .len(),
+          },
+          start_point,
+        )
+      }
+    };
+    match self.find_detour(
+      nav_data,
+      start_index,
+      start_point,
+      last_index,
+      next_point,
+      agent_radius,
+    ) {
+      Some((detour_index, detour_point)) => {
+        (detour_index, StraightPathStep::Waypoint(detour_point))
+      }
+      None => (next_index, next_step),
+    }
+  }
+
+  /// Finds a point to walk to from `start_point` (at `start_index`) in order to
+  /// walk around the holes cut by obstacles on the way to `end_point` (at
+  /// `end_index`). The point is kept `agent_radius` away from the holes.
+  /// Returns None if no obstacles are in the way, or if there is no way around
+  /// them through the nodes between the two indices.
+  fn find_detour<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    start_index: PathIndex,
+    start_point: Vec3,
+    end_index: PathIndex,
+    end_point: Vec3,
+    agent_radius: f32,
+  ) -> Option<(PathIndex, Vec3)> {
+    // The indices of the start and end points in the visibility graph.
+    const START_VERTEX: usize = 0;
+    const END_VERTEX: usize = 1;
+
+    let mut nodes = vec![];
+    let mut index = start_index;
+    while index <= end_index {
+      nodes.push((index, self.node_at(index)));
+      index = index.next(self);
+    }
+    let carved_nodes = nodes
+      .iter()
+      .filter_map(|(_, node)| nav_data.carved_nodes.get(node))
+      .collect::<Vec<_>>();
+    if carved_nodes.is_empty() {
+      return None;
+    }
+    let (start, end) = (start_point.xy(), end_point.xy());
+    if start.distance_squared(end) < 1e-8 {
+      return None;
+    }
+
+    let to_point = |point: Vec2| Point::new(point.x, point.y);
+    let to_line =
+      |from: Vec2, to: Vec2| Line::new(to_point(from), to_point(to));
+    let hole_edges = carved_nodes
+      .iter()
+      .flat_map(|carved_node| carved_node.obstacle_edges.iter())
+      .map(|&(from, to)| to_line(from, to))
+      .collect::<Vec<_>>();
+    let walls = corridor_walls(nav_data, &nodes)
+      .into_iter()
+      .map(|(from, to)| to_line(from, to))
+      .collect::<Vec<_>>();
+    let crosses_any = |line: &Line<f32>, edges: &[Line<f32>]| {
+      edges.iter().any(|edge| {
+        matches!(
+          line_intersection(*line, *edge),
+          Some(LineIntersection::SinglePoint { is_proper: true, .. })
+        )
+      })
+    };
+
+    let clearance = |point: Vec2| {
+      hole_edges
+        .iter()
+        .map(|edge| Euclidean.distance(&to_point(point), edge))
+        .fold(agent_radius, f32::min)
+    };
+
+    // Shortest paths around the holes only turn at the corners of the holes,
+    // moved away from the holes by the agent's radius. Each vertex stores its
+    // point, the carved node and corner it came from, and its clearance from
+    // the holes. The start and end points may be too close to the holes (e.g.,
+    // the target is right next to an obstacle, or the agent drifted slightly
+    // while walking past a hole). In that case, only require that walking to
+    // and from them doesn't get any closer to the holes.
+    let mut vertices =
+      vec![(start, None, clearance(start)), (end, None, clearance(end))];
+    for (carved_index, carved_node) in carved_nodes.iter().enumerate() {
+      for (corner_index, corner) in
+        carved_node.detour_corners.iter().enumerate()
+      {
+        let point = corner.point + corner.offset * agent_radius;
+        if point.distance_squared(start) < 1e-6
+          || clearance(point) < agent_radius - 1e-4
+        {
+          continue;
+        }
+        vertices.push((
+          point,
+          Some((carved_index, corner_index)),
+          agent_radius,
+        ));
+      }
+    }
+
+    let is_visible = |from: usize, to: usize| {
+      let (
+        (from_position, from_corner, from_clearance),
+        (to_position, to_corner, to_clearance),
+      ) = (vertices[from], vertices[to]);
+      // Corners of the same node use its visibility graph.
+      if let (
+        Some((from_carved_index, from_corner_index)),
+        Some((to_carved_index, to_corner_index)),
+      ) = (from_corner, to_corner)
+        && from_carved_index == to_carved_index
+        && !carved_nodes[from_carved_index].corner_visibility[from_corner_index]
+          .contains(&to_corner_index)
+      {
+        return false;
+      }
+      let line = to_line(from_position, to_position);
+      if crosses_any(&line, &walls) || crosses_any(&line, &hole_edges) {
+        return false;
+      }
+      let required_clearance = from_clearance.min(to_clearance) - 1e-4;
+      hole_edges
+        .iter()
+        .all(|edge| Euclidean.distance(&line, edge) >= required_clearance)
+    };
+
+    if is_visible(START_VERTEX, END_VERTEX) {
+      return None;
+    }
+
+    // Dijkstra's algorithm over the visibility graph of the corners.
+    let mut distances = vec![f32::INFINITY; vertices.len()];
+    let mut previous = vec![None; vertices.len()];
+    let mut explored = vec![false; vertices.len()];
+    distances[START_VERTEX] = 0.0;
+    loop {
+      let current = (0..vertices.len())
+        .filter(|&vertex| !explored[vertex] && distances[vertex].is_finite())
+        .min_by(|&a, &b| distances[a].total_cmp(&distances[b]))?;
+      if current == END_VERTEX {
+        break;
+      }
+      explored[current] = true;
+      for next in 0..vertices.len() {
+        let distance =
+          distances[current] + vertices[current].0.distance(vertices[next].0);
+        if explored[next]
+          || distance >= distances[next]
+          || !is_visible(current, next)
+        {
+          continue;
+        }
+        distances[next] = distance;
+        previous[next] = Some(current);
+      }
+    }
+
+    // Walk to the first corner of the detour.
+    let mut first_corner = END_VERTEX;
+    while let Some(previous_vertex) = previous[first_corner]
+      && previous_vertex != START_VERTEX
+    {
+      first_corner = previous_vertex;
+    }
+    if first_corner == END_VERTEX {
+      return None;
+    }
+    let point = vertices[first_corner].0;
+
+    let (index, node) = nodes.iter().find(|(_, node)| {
+      let island = nav_data.get_island(node.island_id).unwrap();
+      Euclidean.distance(
+        &to_point(point),
+        &node_to_multi_polygon(
+          island,
+          &island.nav_mesh.polygons[node.polygon_index],
+        ),
+      ) < 1e-4
+    })?;
+    Some((
+      *index,
+      nav_data.closest_point_on_node(point.extend(start_point.z), *node),
+    ))
+  }
+
+  /// Determines the next point along `self` that the agent can walk straight
+  /// towards, ignoring any holes cut by obstacles. See
+  /// [`Self::find_next_point_in_straight_path`].
+  fn find_next_point_in_funnel<CS: CoordinateSystem>(
     &self,
     nav_data: &NavigationData<CS>,
     start_index: PathIndex,
     start_point: Vec3,
     mut end_index: PathIndex,
     end_point: Vec3,
+    agent_radius: f32,
   ) -> (PathIndex, StraightPathStep) {
     let apex = start_point;
     let (mut left_index, mut right_index) = (start_index, start_index);
@@ -250,7 +576,7 @@ impl Path {
     let (mut current_left, mut current_right) = if start_index == end_index {
       (end_point, end_point)
     } else {
-      match self.get_portal_endpoints(start_index, nav_data) {
+      match self.get_portal_endpoints(start_index, nav_data, agent_radius) {
         Portal::Walkable(left, right) => (left, right),
         Portal::AnimationLink {
           start_portal,
@@ -286,7 +612,7 @@ impl Path {
       let (portal_left, portal_right) = if portal_index == end_index {
         (end_point, end_point)
       } else {
-        match self.get_portal_endpoints(portal_index, nav_data) {
+        match self.get_portal_endpoints(portal_index, nav_data, agent_radius) {
           Portal::Walkable(left, right) => (left, right),
           Portal::AnimationLink {
             start_portal,
@@ -375,18 +701,39 @@ impl Path {
     }
   }
 
+  /// The node at the end of the path.
+  pub(crate) fn last_node(&self) -> NodeRef {
+    let last_segment = self.island_segments.last().unwrap();
+    NodeRef {
+      island_id: last_segment.island_id,
+      polygon_index: *last_segment.corridor.last().unwrap(),
+    }
+  }
+
   /// Determines if a path is valid. A path may be invalid if an island it
-  /// travelled across was invalidared, or a off mesh link it used was
+  /// travelled across was invalidared, a node it travelled across was
+  /// invalidated (e.g., carved by an obstacle), or a off mesh link it used was
   /// invalidated.
   pub(crate) fn is_valid(
     &self,
     invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
     invalidated_islands: &HashSet<IslandId>,
+    invalidated_nodes: &HashSet<NodeRef>,
   ) -> bool {
     for island_segment in self.island_segments.iter() {
       if invalidated_islands.contains(&island_segment.island_id) {
         return false;
       }
+      if !invalidated_nodes.is_empty()
+        && island_segment.corridor.iter().any(|&polygon_index| {
+          invalidated_nodes.contains(&NodeRef {
+            island_id: island_segment.island_id,
+            polygon_index,
+          })
+        })
+      {
+        return false;
+      }
     }
     for off_mesh_link_segment in self.off_mesh_link_segments.iter() {
       if invalidated_off_mesh_links
@@ -399,6 +746,152 @@ impl Path {
     true
   }
 
+  /// Finds the part of the path from `start_index` onwards that was
+  /// invalidated (see [`Path::is_valid`]). Any earlier parts of the path are
+  /// ignored.
+  pub(crate) fn find_invalidation(
+    &self,
+    start_index: PathIndex,
+    invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
+    invalidated_islands: &HashSet<IslandId>,
+    invalidated_nodes: &HashSet<NodeRef>,
+  ) -> PathInvalidation {
+    let mut last_valid_index = None;
+    let mut invalidated_range: Option<(PathIndex, PathIndex)> = None;
+    let mut is_invalid = false;
+    for segment_index in start_index.segment_index..self.island_segments.len() {
+      if segment_index > start_index.segment_index
+        && invalidated_off_mesh_links.contains(
+          &self.off_mesh_link_segments[segment_index - 1].off_mesh_link,
+        )
+      {
+        is_invalid = true;
+      }
+
+      let island_segment = &self.island_segments[segment_index];
+      let island_invalidated =
+        invalidated_islands.contains(&island_segment.island_id);
+      let first_portal_index = if segment_index == start_index.segment_index {
+        start_index.portal_index
+      } else {
+        0
+      };
+      for (portal_index, &polygon_index) in
+        island_segment.corridor.iter().enumerate().skip(first_portal_index)
+      {
+        if island_invalidated
+          || invalidated_nodes.contains(&NodeRef {
+            island_id: island_segment.island_id,
+            polygon_index,
+          })
+        {
+          is_invalid = true;
+          continue;
+        }
+
+        let index = PathIndex { segment_index, portal_index };
+        if is_invalid {
+          is_invalid = false;
+          let Some(last_valid_index) = last_valid_index else {
+            // The start of the path was invalidated.
+            return PathInvalidation::Unrepairable;
+          };
+          // Several invalidated parts are merged into one, so they are all
+          // replaced together.
+          let range_start =
+            invalidated_range.map_or(last_valid_index, |(start, _)| start);
+          invalidated_range = Some((range_start, index));
+        }
+        last_valid_index = Some(index);
+      }
+    }
+
+    if is_invalid {
+      // The end of the path was invalidated.
+      return PathInvalidation::Unrepairable;
+    }
+    match invalidated_range {
+      None => PathInvalidation::Valid,
+      Some((start, end)) => PathInvalidation::Between(start, end),
+    }
+  }
+
+  /// The node at `index` in the path.
+  pub(crate) fn node_at(&self, index: PathIndex) -> NodeRef {
+    let island_segment = &self.island_segments[index.segment_index];
+    NodeRef {
+      island_id: island_segment.island_id,
+      polygon_index: island_segment.corridor[index.portal_index],
+    }
+  }
+
+  /// Replaces the nodes between `start_index` and `end_index` (inclusive) with
+  /// `replacement`. `replacement` must start in the node at `start_index` and
+  /// end in the node at `end_index`.
+  pub(crate) fn replace(
+    &mut self,
+    start_index: PathIndex,
+    end_index: PathIndex,
+    mut replacement: Path,
+  ) {
+    let start_segment = &self.island_segments[start_index.segment_index];
+    let first_segment = replacement.island_segments.first_mut().unwrap();
+    first_segment.corridor.splice(
+      0..0,
+      start_segment.corridor[..start_index.portal_index].iter().copied(),
+    );
+    first_segment.portal_edge_index.splice(
+      0..0,
+      start_segment.portal_edge_index[..start_index.portal_index]
+        .iter()
+        .copied(),
+    );
+
+    let end_segment = &self.island_segments[end_index.segment_index];
+    let last_segment = replacement.island_segments.last_mut().unwrap();
+    last_segment
+      .corridor
+      .extend_from_slice(&end_segment.corridor[end_index.portal_index + 1..]);
+    last_segment.portal_edge_index.extend_from_slice(
+      &end_segment.portal_edge_index[end_index.portal_index..],
+    );
+
+    self.island_segments.splice(
+      start_index.segment_index..=end_index.segment_index,
+      replacement.island_segments,
+    );
+    self.off_mesh_link_segments.splice(
+      start_index.segment_index..end_index.segment_index,
+      replacement.off_mesh_link_segments,
+    );
+  }
+
+  /// Extends the path with `extension`, which must start in the last node of
+  /// the path.
+  pub(crate) fn extend(&mut self, extension: Path) {
+    let last_index = self.last_index();
+    self.end_point = extension.end_point;
+    self.replace(last_index, last_index, extension);
+  }
+
+  /// Removes every node after `end_index` from the path.
+  pub(crate) fn truncate(&mut self, end_index: PathIndex) {
+    self.island_segments.truncate(end_index.segment_index + 1);
+    self.off_mesh_link_segments.truncate(end_index.segment_index);
+    let last_segment = self.island_segments.last_mut().unwrap();
+    last_segment.corridor.truncate(end_index.portal_index + 1);
+    last_segment.portal_edge_index.truncate(end_index.portal_index);
+  }
+
+  /// Removes every node before `start_index` from the path.
+  pub(crate) fn remove_before(&mut self, start_index: PathIndex) {
+    self.island_segments.drain(..start_index.segment_index);
+    self.off_mesh_link_segments.drain(..start_index.segment_index);
+    let first_segment = self.island_segments.first_mut().unwrap();
+    first_segment.corridor.drain(..start_index.portal_index);
+    first_segment.portal_edge_index.drain(..start_index.portal_index);
+  }
+
   /// Finds the index of `node` in the path.
   pub(crate) fn find_index_of_node(&self, node: NodeRef) -> Option<PathIndex> {
     for (segment_index, island_segment) in
@@ -446,6 +939,60 @@ impl Path {
   }
 }
 
+/// Finds the (2D) edges of `nodes` that don't lead to another node in
+/// `nodes`, in world space. These are the walls of the corridor formed by
+/// `nodes`.
+fn corridor_walls<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  nodes: &[(PathIndex, NodeRef)],
+) -> Vec<(Vec2, Vec2)> {
+  let corridor_nodes =
+    nodes.iter().map(|&(_, node)| node).collect::<HashSet<_>>();
+  let mut walls = vec![];
+  for &(_, node) in nodes {
+    let island = nav_data.get_island(node.island_id).unwrap();
+    let polygon = &island.nav_mesh.polygons[node.polygon_index];
+    // Boundary links only cover part of an edge, so find the edges they are
+    // on by their midpoints.
+    let boundary_link_midpoints = nav_data
+      .node_to_off_mesh_link_ids
+      .get(&node)
+      .into_iter()
+      .flatten()
+      .map(|link_id| nav_data.off_mesh_links.get(*link_id).unwrap())
+      .filter(|link| {
+        matches!(link.kinded, KindedOffMeshLink::BoundaryLink { .. })
+          && corridor_nodes.contains(&link.destination_node)
+      })
+      .map(|link| link.portal.0.midpoint(link.portal.1).xy())
+      .collect::<Vec<_>>();
+    for (edge_index, connectivity) in polygon.connectivity.iter().enumerate() {
+      let (left_vertex, right_vertex) = polygon.get_edge_indices(edge_index);
+      let left =
+        island.transform.apply(island.nav_mesh.vertices[left_vertex]).xy();
+      let right =
+        island.transform.apply(island.nav_mesh.vertices[right_vertex]).xy();
+      let leads_to_corridor = match connectivity {
+        Some(connectivity) => corridor_nodes.contains(&NodeRef {
+          island_id: node.island_id,
+          polygon_index: connectivity.polygon_index,
+        }),
+        None => boundary_link_midpoints.iter().any(|&midpoint| {
+          let (projected_point, _) = project_point_to_line_segment(
+            midpoint.extend(0.0),
+            (left.extend(0.0), right.extend(0.0)),
+          );
+          projected_point.xy().distance_squared(midpoint) < 1e-6
+        }),
+      };
+      if !leads_to_corridor {
+        walls.push((left, right));
+      }
+    }
+  }
+  walls
+}
+
 #[cfg(test)]
 #[path = "path_test.rs"]
 mod test;
@@ -1,6 +1,6 @@
 use std::{collections::HashSet, f32::consts::PI, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{expect_that, matchers::*};
 use slotmap::DenseSlotMap;
 
@@ -11,10 +11,12 @@ use crate::{
   link::{AnimationLink, AnimationLinkId},
   nav_data::{KindedOffMeshLink, NavigationData, NodeRef, OffMeshLinkId},
   nav_mesh::NavigationMesh,
-  path::{IslandSegment, OffMeshLinkSegment, StraightPathStep},
+  path::{
+    IslandSegment, OffMeshLinkSegment, PathInvalidation, StraightPathStep,
+  },
 };
 
-use super::{Path, PathIndex};
+use super::{Path, PathIndex, shrink_portal};
 
 fn collect_straight_path<CS: CoordinateSystem>(
   path: &Path,
@@ -22,6 +24,7 @@ fn collect_straight_path<CS: CoordinateSystem>(
   start: (PathIndex, Vec3),
   end: (PathIndex, Vec3),
   iteration_limit: u32,
+  agent_radius: f32,
 ) -> Vec<(PathIndex, StraightPathStep)> {
   let mut straight_path = Vec::with_capacity(iteration_limit as usize);
 
@@ -44,6 +47,7 @@ fn collect_straight_path<CS: CoordinateSystem>(
       current_point,
       end.0,
       end.1,
+      agent_radius,
     );
     if let StraightPathStep::AnimationLink { .. } = &current.1 {
       // Make sure to do an extra iteration after an animation link so we see
@@ -88,8 +92,11 @@ fn finds_next_point_for_organic_map() {
   .validate()
   .expect("Mesh is valid.");
 
-  let transform =
-    Transform { translation: Vec3::new(5.0, 9.0, 7.0), rotation: PI * -0.35 };
+  let transform = Transform {
+    translation: Vec3::new(5.0, 9.0, 7.0),
+    rotation: Quat::from_rotation_z(PI * -0.35),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -115,6 +122,7 @@ fn finds_next_point_for_organic_map() {
       /* end= */
       (PathIndex::from_corridor_index(0, 2), path.end_point),
       /* iteration_limit= */ 3,
+      /* agent_radius= */ 0.0,
     ),
     [
       (
@@ -189,8 +197,11 @@ fn finds_next_point_in_zig_zag() {
   .validate()
   .expect("Mesh is valid.");
 
-  let transform =
-    Transform { translation: Vec2::new(-1.0, -3.0), rotation: PI * -1.8 };
+  let transform = Transform {
+    translation: Vec2::new(-1.0, -3.0),
+    rotation: PI * -1.8,
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -216,6 +227,7 @@ fn finds_next_point_in_zig_zag() {
       /* end= */
       (PathIndex::from_corridor_index(0, 14), path.end_point),
       /* iteration_limit= */ 5,
+      /* agent_radius= */ 0.0,
     ),
     [
       (
@@ -278,10 +290,8 @@ fn starts_at_end_index_goes_to_end_point() {
 
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let path = Path {
     island_segments: vec![IslandSegment {
@@ -301,6 +311,7 @@ fn starts_at_end_index_goes_to_end_point() {
       /* start_point= */ Vec3::new(0.25, 1.1, 0.0),
       /* end_index= */ PathIndex::from_corridor_index(0, 1),
       /* end_point= */ Vec3::new(0.75, 1.9, 0.0),
+      /* agent_radius= */ 0.0,
     ),
     (
       PathIndex::from_corridor_index(0, 1),
@@ -358,10 +369,8 @@ fn straight_path_includes_animation_link() {
 
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let animation_link_id = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 3.0), Vec2::new(1.0, 3.0)),
@@ -369,6 +378,8 @@ fn straight_path_includes_animation_link() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   archipelago.update(1.0);
@@ -405,6 +416,7 @@ fn straight_path_includes_animation_link() {
       (PathIndex::from_corridor_index(0, 0), Vec3::new(0.1, 0.1, 0.0)),
       (PathIndex::from_corridor_index(1, 1), Vec3::new(0.9, 6.9, 0.0)),
       2,
+      0.0,
     ),
     elements_are!(
       &(
@@ -484,10 +496,8 @@ fn multiple_animation_links_in_a_row() {
 
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let link_id_1 = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)),
@@ -495,6 +505,8 @@ fn multiple_animation_links_in_a_row() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_2 = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 3.0), Vec2::new(1.0, 3.0)),
@@ -502,6 +514,8 @@ fn multiple_animation_links_in_a_row() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let link_id_3 = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 5.0), Vec2::new(1.0, 5.0)),
@@ -509,6 +523,8 @@ fn multiple_animation_links_in_a_row() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   archipelago.update(1.0);
@@ -559,6 +575,7 @@ fn multiple_animation_links_in_a_row() {
       (PathIndex::from_corridor_index(0, 0), Vec3::new(0.5, 0.5, 0.0)),
       (PathIndex::from_corridor_index(3, 1), Vec3::new(0.5, 7.5, 0.0)),
       10,
+      0.0,
     ),
     elements_are!(
       &(
@@ -647,10 +664,8 @@ fn obscured_animation_link_is_made_visible_by_straight_path() {
 
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let link_id = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 3.0), Vec2::new(8.0, 3.0)),
@@ -658,6 +673,8 @@ fn obscured_animation_link_is_made_visible_by_straight_path() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   archipelago.update(1.0);
@@ -693,6 +710,7 @@ fn obscured_animation_link_is_made_visible_by_straight_path() {
       (PathIndex::from_corridor_index(0, 0), Vec3::new(6.5, 0.5, 0.0)),
       (PathIndex::from_corridor_index(1, 1), Vec3::new(0.1, 6.9, 0.0)),
       10,
+      0.0,
     ),
     elements_are!(
       &(
@@ -761,10 +779,8 @@ fn end_point_after_animation_link_is_reported() {
 
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let animation_link_1 = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)),
@@ -772,6 +788,8 @@ fn end_point_after_animation_link_is_reported() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   let animation_link_2 = archipelago.add_animation_link(AnimationLink {
     start_edge: (Vec2::new(0.0, 3.0), Vec2::new(1.0, 3.0)),
@@ -779,6 +797,8 @@ fn end_point_after_animation_link_is_reported() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   archipelago.update(1.0);
@@ -819,6 +839,7 @@ fn end_point_after_animation_link_is_reported() {
       // want to test what happens when the end index is on the animation link.
       (PathIndex::from_corridor_index(1, 0), Vec3::new(0.5, 2.5, 0.0)),
       10,
+      0.0,
     ),
     elements_are!(
       &(
@@ -840,7 +861,7 @@ fn end_point_after_animation_link_is_reported() {
 }
 
 #[test]
-fn path_not_valid_for_invalidated_islands_or_off_mesh_links() {
+fn path_not_valid_for_invalidated_islands_nodes_or_off_mesh_links() {
   // Create unused slotmaps just to get `IslandId`s and `OffMeshLinkId`s.
   let mut slotmap = DenseSlotMap::<IslandId, _>::with_key();
   let island_id_1 = slotmap.insert(0);
@@ -886,21 +907,25 @@ fn path_not_valid_for_invalidated_islands_or_off_mesh_links() {
 
   assert!(path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
-    /* invalidated_islands= */ &HashSet::new()
+    /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
 
   // Each island is invalidated.
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
     /* invalidated_islands= */ &HashSet::from([island_id_1]),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
     /* invalidated_islands= */ &HashSet::from([island_id_2]),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
     /* invalidated_islands= */ &HashSet::from([island_id_3]),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
 
   // Each boundary link is invalidated.
@@ -908,11 +933,28 @@ fn path_not_valid_for_invalidated_islands_or_off_mesh_links() {
     /* invalidated_boundary_links= */
     &HashSet::from([boundary_link_id_1]),
     /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */
     &HashSet::from([boundary_link_id_2]),
     /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */ &HashSet::new(),
+  ));
+
+  // A node along the path is invalidated.
+  assert!(!path.is_valid(
+    /* invalidated_boundary_links= */ &HashSet::new(),
+    /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */
+    &HashSet::from([NodeRef { island_id: island_id_2, polygon_index: 1 }]),
+  ));
+  // A node not along the path is invalidated, so the path is still valid.
+  assert!(path.is_valid(
+    /* invalidated_boundary_links= */ &HashSet::new(),
+    /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */
+    &HashSet::from([NodeRef { island_id: island_id_2, polygon_index: 2 }]),
   ));
 }
 
@@ -1087,3 +1129,489 @@ fn indices_in_path_are_found_rev() {
     None
   );
 }
+
+#[test]
+fn straight_path_keeps_agent_radius_from_corners() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(2.0, 0.0),
+      Vec2::new(4.0, 0.0),
+      Vec2::new(0.0, 2.0),
+      Vec2::new(2.0, 2.0),
+      Vec2::new(4.0, 2.0),
+      Vec2::new(2.0, 4.0),
+      Vec2::new(4.0, 4.0),
+    ],
+    polygons: vec![vec![0, 1, 4, 3], vec![1, 2, 5, 4], vec![4, 5, 7, 6]],
+    polygon_type_indices: vec![0, 0, 0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let path = Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 1, 2],
+      portal_edge_index: vec![1, 2],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point: Vec3::new(1.0, 1.0, 0.0),
+    end_point: Vec3::new(3.0, 3.5, 0.0),
+  };
+
+  let start = (PathIndex::from_corridor_index(0, 0), path.start_point);
+  let end = (PathIndex::from_corridor_index(0, 2), path.end_point);
+
+  // Without a radius, the path hugs the corner.
+  assert_eq!(
+    collect_straight_path(
+      &path,
+      &archipelago.nav_data,
+      start,
+      end,
+      /* iteration_limit= */ 3,
+      /* agent_radius= */ 0.0,
+    ),
+    [
+      (
+        PathIndex::from_corridor_index(0, 1),
+        StraightPathStep::Waypoint(Vec3::new(2.0, 2.0, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 2),
+        StraightPathStep::Waypoint(Vec3::new(3.0, 3.5, 0.0))
+      ),
+    ]
+  );
+
+  // With a radius, the path keeps its distance from the corner on each portal.
+  assert_eq!(
+    collect_straight_path(
+      &path,
+      &archipelago.nav_data,
+      start,
+      end,
+      /* iteration_limit= */ 3,
+      /* agent_radius= */ 0.5,
+    ),
+    [
+      (
+        PathIndex::from_corridor_index(0, 0),
+        StraightPathStep::Waypoint(Vec3::new(2.0, 1.5, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 1),
+        StraightPathStep::Waypoint(Vec3::new(2.5, 2.0, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 2),
+        StraightPathStep::Waypoint(Vec3::new(3.0, 3.5, 0.0))
+      ),
+    ]
+  );
+}
+
+#[test]
+fn shrinks_portal_by_agent_radius_regardless_of_length() {
+  // Short and long portals are both shrunk by exactly the agent radius.
+  assert_eq!(
+    shrink_portal(
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(2.0, 0.0, 0.0),
+      /* left_is_wall= */ true,
+      /* right_is_wall= */ true,
+      /* agent_radius= */ 0.5,
+    ),
+    (Vec3::new(0.5, 0.0, 0.0), Vec3::new(1.5, 0.0, 0.0))
+  );
+  assert_eq!(
+    shrink_portal(
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(10.0, 0.0, 0.0),
+      /* left_is_wall= */ true,
+      /* right_is_wall= */ true,
+      /* agent_radius= */ 0.5,
+    ),
+    (Vec3::new(0.5, 0.0, 0.0), Vec3::new(9.5, 0.0, 0.0))
+  );
+  // Only wall ends are shrunk, and sloped portals are shrunk horizontally.
+  assert_eq!(
+    shrink_portal(
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(4.0, 0.0, 2.0),
+      /* left_is_wall= */ false,
+      /* right_is_wall= */ true,
+      /* agent_radius= */ 1.0,
+    ),
+    (Vec3::new(0.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 1.5))
+  );
+  // Portals narrower than the agent collapse to the point furthest from the
+  // walls.
+  assert_eq!(
+    shrink_portal(
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      /* left_is_wall= */ true,
+      /* right_is_wall= */ true,
+      /* agent_radius= */ 1.0,
+    ),
+    (Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.5, 0.0, 0.0))
+  );
+  assert_eq!(
+    shrink_portal(
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      /* left_is_wall= */ true,
+      /* right_is_wall= */ false,
+      /* agent_radius= */ 2.0,
+    ),
+    (Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))
+  );
+}
+
+#[test]
+fn straight_path_does_not_avoid_interior_vertices() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(2.0, 0.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(2.0, 1.0),
+      Vec2::new(0.0, 2.0),
+      Vec2::new(1.0, 2.0),
+      Vec2::new(2.0, 2.0),
+    ],
+    polygons: vec![
+      vec![0, 1, 4, 3],
+      vec![1, 2, 5, 4],
+      vec![3, 4, 7, 6],
+      vec![4, 5, 8, 7],
+    ],
+    polygon_type_indices: vec![0, 0, 0, 0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let path = Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 1, 3],
+      portal_edge_index: vec![1, 2],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point: Vec3::new(0.5, 0.5, 0.0),
+    end_point: Vec3::new(1.5, 1.5, 0.0),
+  };
+
+  // The center vertex is not on the boundary, so the agent can walk right past
+  // it.
+  assert_eq!(
+    path.find_next_point_in_straight_path(
+      &archipelago.nav_data,
+      /* start_index= */ PathIndex::from_corridor_index(0, 0),
+      /* start_point= */ path.start_point,
+      /* end_index= */ PathIndex::from_corridor_index(0, 2),
+      /* end_point= */ path.end_point,
+      /* agent_radius= */ 0.25,
+    ),
+    (
+      PathIndex::from_corridor_index(0, 2),
+      StraightPathStep::Waypoint(Vec3::new(1.5, 1.5, 0.0))
+    )
+  );
+}
+
+#[test]
+fn invalidated_part_of_path_is_found() {
+  // Create unused slotmaps just to get `IslandId`s and `OffMeshLinkId`s.
+  let mut slotmap = DenseSlotMap::<IslandId, _>::with_key();
+  let island_id_1 = slotmap.insert(0);
+  let island_id_2 = slotmap.insert(0);
+  let island_id_3 = slotmap.insert(0);
+  let mut slotmap = DenseSlotMap::<OffMeshLinkId, _>::with_key();
+  let boundary_link_id_1 = slotmap.insert(0);
+  let boundary_link_id_2 = slotmap.insert(0);
+
+  let path = Path {
+    island_segments: vec![
+      IslandSegment {
+        island_id: island_id_1,
+        corridor: vec![0, 1],
+        portal_edge_index: vec![0],
+      },
+      IslandSegment {
+        island_id: island_id_2,
+        corridor: vec![0, 1],
+        portal_edge_index: vec![0],
+      },
+      IslandSegment {
+        island_id: island_id_3,
+        corridor: vec![0, 1],
+        portal_edge_index: vec![0],
+      },
+    ],
+    off_mesh_link_segments: vec![
+      OffMeshLinkSegment {
+        starting_node: NodeRef { island_id: island_id_1, polygon_index: 1 },
+        end_node: NodeRef { island_id: island_id_2, polygon_index: 0 },
+        off_mesh_link: boundary_link_id_1,
+      },
+      OffMeshLinkSegment {
+        starting_node: NodeRef { island_id: island_id_2, polygon_index: 1 },
+        end_node: NodeRef { island_id: island_id_3, polygon_index: 0 },
+        off_mesh_link: boundary_link_id_2,
+      },
+    ],
+    start_point: Vec3::ZERO,
+    end_point: Vec3::ZERO,
+  };
+  let start_index = PathIndex::from_corridor_index(0, 0);
+
+  assert_eq!(
+    path.find_invalidation(
+      start_index,
+      &HashSet::new(),
+      &HashSet::new(),
+      &HashSet::new(),
+    ),
+    PathInvalidation::Valid
+  );
+
+  // The middle island is replaced between the nodes next to it.
+  assert_eq!(
+    path.find_invalidation(
+      start_index,
+      &HashSet::new(),
+      &HashSet::from([island_id_2]),
+      &HashSet::new(),
+    ),
+    PathInvalidation::Between(
+      PathIndex::from_corridor_index(0, 1),
+      PathIndex::from_corridor_index(2, 0)
+    )
+  );
+
+  // A link is replaced between the nodes it connects.
+  assert_eq!(
+    path.find_invalidation(
+      start_index,
+      &HashSet::from([boundary_link_id_2]),
+      &HashSet::new(),
+      &HashSet::new(),
+    ),
+    PathInvalidation::Between(
+      PathIndex::from_corridor_index(1, 1),
+      PathIndex::from_corridor_index(2, 0)
+    )
+  );
+
+  // Separate invalidated parts are merged.
+  assert_eq!(
+    path.find_invalidation(
+      start_index,
+      &HashSet::from([boundary_link_id_1, boundary_link_id_2]),
+      &HashSet::new(),
+      &HashSet::new(),
+    ),
+    PathInvalidation::Between(
+      PathIndex::from_corridor_index(0, 1),
+      PathIndex::from_corridor_index(2, 0)
+    )
+  );
+
+  // Invalidated nodes before the start index are ignored.
+  assert_eq!(
+    path.find_invalidation(
+      PathIndex::from_corridor_index(1, 0),
+      &HashSet::from([boundary_link_id_1]),
+      &HashSet::new(),
+      &HashSet::from([NodeRef { island_id: island_id_1, polygon_index: 0 }]),
+    ),
+    PathInvalidation::Valid
+  );
+
+  // The start or the end of the path is invalidated.
+  assert_eq!(
+    path.find_invalidation(
+      start_index,
+      &HashSet::new(),
+      &HashSet::from([island_id_1]),
+      &HashSet::new(),
+    ),
+    PathInvalidation::Unrepairable
+  );
+  assert_eq!(
+    path.find_invalidation(
+      start_index,
+      &HashSet::new(),
+      &HashSet::new(),
+      &HashSet::from([NodeRef { island_id: island_id_3, polygon_index: 1 }]),
+    ),
+    PathInvalidation::Unrepairable
+  );
+}
+
+#[test]
+fn path_is_replaced_extended_and_trimmed() {
+  // Create unused slotmaps just to get `IslandId`s and `OffMeshLinkId`s.
+  let mut slotmap = DenseSlotMap::<IslandId, _>::with_key();
+  let island_id_1 = slotmap.insert(0);
+  let island_id_2 = slotmap.insert(0);
+  let mut slotmap = DenseSlotMap::<OffMeshLinkId, _>::with_key();
+  let boundary_link_id_1 = slotmap.insert(0);
+  let boundary_link_id_2 = slotmap.insert(0);
+
+  let link_segment = |starting_node: NodeRef, end_node: NodeRef, link_id| {
+    OffMeshLinkSegment { starting_node, end_node, off_mesh_link: link_id }
+  };
+  let node = |island_id, polygon_index| NodeRef { island_id, polygon_index };
+
+  let mut path = Path {
+    island_segments: vec![IslandSegment {
+      island_id: island_id_1,
+      corridor: vec![0, 1, 2, 3],
+      portal_edge_index: vec![10, 11, 12],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point: Vec3::ZERO,
+    end_point: Vec3::ONE,
+  };
+
+  // Replace nodes 1 to 3 with a detour through another island.
+  path.replace(
+    PathIndex::from_corridor_index(0, 1),
+    PathIndex::from_corridor_index(0, 3),
+    Path {
+      island_segments: vec![
+        IslandSegment {
+          island_id: island_id_1,
+          corridor: vec![1],
+          portal_edge_index: vec![],
+        },
+        IslandSegment {
+          island_id: island_id_2,
+          corridor: vec![0],
+          portal_edge_index: vec![],
+        },
+        IslandSegment {
+          island_id: island_id_1,
+          corridor: vec![4, 3],
+          portal_edge_index: vec![20],
+        },
+      ],
+      off_mesh_link_segments: vec![
+        link_segment(
+          node(island_id_1, 1),
+          node(island_id_2, 0),
+          boundary_link_id_1,
+        ),
+        link_segment(
+          node(island_id_2, 0),
+          node(island_id_1, 4),
+          boundary_link_id_2,
+        ),
+      ],
+      start_point: Vec3::ZERO,
+      end_point: Vec3::ZERO,
+    },
+  );
+  assert_eq!(
+    path,
+    Path {
+      island_segments: vec![
+        IslandSegment {
+          island_id: island_id_1,
+          corridor: vec![0, 1],
+          portal_edge_index: vec![10],
+        },
+        IslandSegment {
+          island_id: island_id_2,
+          corridor: vec![0],
+          portal_edge_index: vec![],
+        },
+        IslandSegment {
+          island_id: island_id_1,
+          corridor: vec![4, 3],
+          portal_edge_index: vec![20],
+        },
+      ],
+      off_mesh_link_segments: vec![
+        link_segment(
+          node(island_id_1, 1),
+          node(island_id_2, 0),
+          boundary_link_id_1
+        ),
+        link_segment(
+          node(island_id_2, 0),
+          node(island_id_1, 4),
+          boundary_link_id_2
+        ),
+      ],
+      start_point: Vec3::ZERO,
+      end_point: Vec3::ONE,
+    }
+  );
+
+  // Extend the path from the last node.
+  path.extend(Path {
+    island_segments: vec![IslandSegment {
+      island_id: island_id_1,
+      corridor: vec![3, 5],
+      portal_edge_index: vec![30],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point: Vec3::ZERO,
+    end_point: Vec3::new(2.0, 2.0, 2.0),
+  });
+  assert_eq!(
+    path.island_segments[2],
+    IslandSegment {
+      island_id: island_id_1,
+      corridor: vec![4, 3, 5],
+      portal_edge_index: vec![20, 30],
+    }
+  );
+  assert_eq!(path.end_point, Vec3::new(2.0, 2.0, 2.0));
+
+  // Trim both ends of the path.
+  path.truncate(PathIndex::from_corridor_index(2, 1));
+  path.remove_before(PathIndex::from_corridor_index(1, 0));
+  assert_eq!(
+    path.island_segments,
+    vec![
+      IslandSegment {
+        island_id: island_id_2,
+        corridor: vec![0],
+        portal_edge_index: vec![],
+      },
+      IslandSegment {
+        island_id: island_id_1,
+        corridor: vec![4, 3],
+        portal_edge_index: vec![20],
+      },
+    ]
+  );
+  assert_eq!(
+    path.off_mesh_link_segments,
+    vec![link_segment(
+      node(island_id_2, 0),
+      node(island_id_1, 4),
+      boundary_link_id_2
+    )]
+  );
+}
@@ -1,17 +1,22 @@
 use std::{
   borrow::Cow,
   collections::{HashMap, HashSet},
+  sync::Arc,
 };
 
 use glam::Vec3;
 
 use crate::{
   CoordinateSystem, NavigationData,
-  agent::PermittedAnimationLinks,
-  astar::{self, AStarProblem, PathStats},
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
+  astar::{self, AStarProblem, PathStats, SearchStatus},
+  island::IslandId,
+  landmark::{LandmarkEnd, Landmarks},
+  link::AnimationLinkId,
   nav_data::{KindedOffMeshLink, NodeRef, OffMeshLinkId},
   nav_mesh::MeshEdgeRef,
   path::{IslandSegment, OffMeshLinkSegment, Path},
+  region_graph::{ClusterId, CorridorSearch, CorridorSearchStatus},
   util::FloatOrd,
 };
 
@@ -24,23 +29,35 @@ struct ArchipelagoPathProblem<'a, CS: CoordinateSystem> {
   /// The center of the start_node. This is just a cached point for easy
   /// access.
   start_point: Vec3,
-  /// The node the target is in.
-  end_node: NodeRef,
-  /// The center of the end_node. This is just a cached point for easy access.
-  end_point: Vec3,
+  /// The nodes the targets are in, and the points of the targets in those
+  /// nodes. Reaching each of these is a separate goal state. May be empty to
+  /// explore without any goal.
+  ends: Vec<(NodeRef, Vec3)>,
   /// The cheapest type index cost in [`Self::nav_data`]. This is cached once
   /// since it is constant for the whole problem.
   cheapest_type_index_cost: f32,
   /// Replacement costs for the `nav_data.type_index_to_cost`.
   override_type_index_to_cost: &'a HashMap<usize, f32>,
   /// The set of permitted animation links for the agent.
-  permitted_animation_links: PermittedAnimationLinks,
+  permitted_animation_links: &'a PermittedAnimationLinks,
+  /// The set of type indices the agent is permitted to walk on.
+  permitted_type_indices: &'a PermittedTypeIndices,
+  /// The radius of the agent. Portals narrower than the agent are skipped.
+  agent_radius: f32,
+  /// The landmarks and the bounds for the landmark heuristic, if landmarks
+  /// should be used.
+  landmarks: Option<(&'a Landmarks, &'a LandmarkEnd)>,
+  /// The clusters that the search is restricted to, if any. Nodes outside
+  /// these clusters are never entered.
+  corridor: Option<&'a HashSet<ClusterId>>,
+  /// The cost to use for each animation link that is full.
+  busy_animation_link_costs: &'a HashMap<AnimationLinkId, f32>,
 }
 
 /// An action taken in the path.
 #[derive(Clone, Copy)]
 enum PathStep {
-  /// Just head directly to the end. This is only valid when inside the end
+  /// Just head directly to an end. This is only valid when inside that end's
   /// node.
   GoToEnd,
   /// Take the node connection at the specified edge index in the current node.
@@ -51,12 +68,12 @@ enum PathStep {
 
 /// A node in the path. This generally corresponds to an edge in the navigation
 /// data.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-enum PathNode {
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum PathNode {
   /// The start of the path.
   Start,
-  /// The end of the path.
-  End,
+  /// The end of the path with the specified index.
+  End(usize),
   /// An edge of a node in the navigation data.
   NodeEdge {
     /// The node that we are now in.
@@ -75,6 +92,23 @@ impl<CS: CoordinateSystem> ArchipelagoPathProblem<'_, CS> {
       || self.nav_data.get_type_index_cost(type_index).unwrap_or(1.0),
     )
   }
+
+  /// Determines whether the agent is permitted to enter `node_ref`.
+  fn is_node_permitted(&self, node_ref: NodeRef) -> bool {
+    self
+      .permitted_type_indices
+      .is_permitted(self.nav_data.node_type_index(node_ref))
+      && self.corridor.is_none_or(|corridor| {
+        corridor.contains(&self.nav_data.node_to_region_id(node_ref))
+      })
+  }
+
+  /// Determines the cost of `node_ref`, honoring any cost volumes overlapping
+  /// the node.
+  fn node_to_cost(&self, node_ref: NodeRef) -> f32 {
+    self.type_index_to_cost(self.nav_data.node_type_index(node_ref))
+      * self.nav_data.node_cost_multiplier(node_ref)
+  }
 }
 
 impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
@@ -90,12 +124,19 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
     &self,
     state: &Self::StateType,
   ) -> Vec<(f32, Self::ActionType, Self::StateType)> {
-    let (node_ref, island, polygon, point, ignore_step) = match state {
+    let (node_ref, island, polygon, point, entry, ignore_step) = match state {
       PathNode::Start => {
         let island =
           self.nav_data.get_island(self.start_node.island_id).unwrap();
         let polygon = &island.nav_mesh.polygons[self.start_node.polygon_index];
-        (self.start_node, island, polygon, self.start_point, None)
+        (
+          self.start_node,
+          island,
+          polygon,
+          self.start_point,
+          (self.start_point, self.start_point),
+          None,
+        )
       }
       PathNode::NodeEdge { node, start_edge: edge } => {
         let island = self.nav_data.get_island(node.island_id).unwrap();
@@ -110,6 +151,10 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
           island,
           polygon,
           island.transform.apply(local_midpoint),
+          (
+            island.transform.apply(island.nav_mesh.vertices[i]),
+            island.transform.apply(island.nav_mesh.vertices[j]),
+          ),
           Some(PathStep::NodeConnection(*edge)),
         )
       }
@@ -134,10 +179,11 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
           island,
           polygon,
           portal.0.midpoint(portal.1),
+          portal,
           ignore_step,
         )
       }
-      PathNode::End => {
+      PathNode::End(_) => {
         unreachable!("we never need the successors of the goal node")
       }
     };
@@ -147,93 +193,168 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
       .get(&node_ref)
       .map_or(Cow::Owned(HashSet::new()), Cow::Borrowed);
 
-    let current_node_cost = self.type_index_to_cost(polygon.type_index);
+    let current_node_cost = self.node_to_cost(node_ref);
 
-    if node_ref == self.end_node {
-      let cost = point.distance(self.end_point) * current_node_cost;
-      return vec![(cost, PathStep::GoToEnd, PathNode::End)];
-    }
+    // Obstacles may split a carved node into disconnected parts, so only the
+    // parts touching where the node was entered are reachable.
+    let carved_node = self.nav_data.carved_nodes.get(&node_ref);
+    let reachable_parts =
+      carved_node.map(|carved_node| carved_node.parts_touching(entry));
+    let is_reachable =
+      |segment: (Vec3, Vec3)| match (carved_node, &reachable_parts) {
+        (Some(carved_node), Some(reachable_parts)) => carved_node
+          .parts_touching(segment)
+          .iter()
+          .any(|part| reachable_parts.contains(part)),
+        _ => true,
+      };
 
-    polygon
-      .connectivity
+    let end_successors = self
+      .ends
       .iter()
       .enumerate()
-      .filter_map(|(edge_index, conn)| {
-        conn.as_ref().map(|conn| (edge_index, conn))
+      .filter(|(_, (end_node, end_point))| {
+        *end_node == node_ref && is_reachable((*end_point, *end_point))
       })
-      .filter_map(|(edge_index, conn)| {
-        if let Some(PathStep::NodeConnection(ignore_edge)) = ignore_step
-          && edge_index == ignore_edge
-        {
-          return None;
-        }
+      .map(|(end_index, (_, end_point))| {
+        let cost = point.distance(*end_point) * current_node_cost;
+        (cost, PathStep::GoToEnd, PathNode::End(end_index))
+      })
+      .collect::<Vec<_>>();
+    // If every end is in this node, going directly to them is always cheapest,
+    // so there's no need to leave the node.
+    if !end_successors.is_empty() && end_successors.len() == self.ends.len() {
+      return end_successors;
+    }
 
-        let target_node_cost = self.type_index_to_cost(
-          island.nav_mesh.polygons[conn.polygon_index].type_index,
-        );
-        if !target_node_cost.is_finite() {
-          return None;
-        }
+    end_successors
+      .into_iter()
+      .chain(
+        polygon
+          .connectivity
+          .iter()
+          .enumerate()
+          .filter_map(|(edge_index, conn)| {
+            conn.as_ref().map(|conn| (edge_index, conn))
+          })
+          .filter_map(|(edge_index, conn)| {
+            if let Some(PathStep::NodeConnection(ignore_edge)) = ignore_step
+              && edge_index == ignore_edge
+            {
+              return None;
+            }
 
-        let (i, j) = polygon.get_edge_indices(edge_index);
-        let local_midpoint =
-          island.nav_mesh.vertices[i].midpoint(island.nav_mesh.vertices[j]);
-        let cost = point.distance(island.transform.apply(local_midpoint))
-          * current_node_cost;
-
-        Some((
-          cost,
-          PathStep::NodeConnection(edge_index),
-          PathNode::NodeEdge {
-            node: NodeRef {
+            let target_node = NodeRef {
               island_id: node_ref.island_id,
               polygon_index: conn.polygon_index,
-            },
-            start_edge: conn.reverse_edge,
-          },
-        ))
-      })
-      .chain(off_mesh_links.iter().filter_map(|link_id| {
-        if let Some(PathStep::OffMeshLink(ignore_link)) = ignore_step
-          && *link_id == ignore_link
-        {
-          return None;
-        }
+            };
+            if !self.is_node_permitted(target_node) {
+              return None;
+            }
+            let target_node_cost = self.node_to_cost(target_node);
+            if !target_node_cost.is_finite() {
+              return None;
+            }
 
-        let link = self.nav_data.off_mesh_links.get(*link_id).unwrap();
-        let destination_node_cost =
-          self.type_index_to_cost(link.destination_type_index);
-        if !destination_node_cost.is_finite() {
-          return None;
-        }
+            if !self.nav_data.can_cross_edge(
+              node_ref,
+              edge_index,
+              self.agent_radius,
+            ) {
+              return None;
+            }
 
-        let link_cost = match link.kinded {
-          // Boundary links have no additional cost, so 0.0 is correct.
-          KindedOffMeshLink::BoundaryLink { .. } => 0.0,
-          KindedOffMeshLink::AnimationLink { cost, kind, .. } => {
-            if !self.permitted_animation_links.is_permitted(kind) {
+            let (i, j) = polygon.get_edge_indices(edge_index);
+            if !is_reachable((
+              island.transform.apply(island.nav_mesh.vertices[i]),
+              island.transform.apply(island.nav_mesh.vertices[j]),
+            )) {
+              return None;
+            }
+            let local_midpoint =
+              island.nav_mesh.vertices[i].midpoint(island.nav_mesh.vertices[j]);
+            let cost = point.distance(island.transform.apply(local_midpoint))
+              * current_node_cost;
+
+            Some((
+              cost,
+              PathStep::NodeConnection(edge_index),
+              PathNode::NodeEdge {
+                node: NodeRef {
+                  island_id: node_ref.island_id,
+                  polygon_index: conn.polygon_index,
+                },
+                start_edge: conn.reverse_edge,
+              },
+            ))
+          })
+          .chain(off_mesh_links.iter().filter_map(|link_id| {
+            if let Some(PathStep::OffMeshLink(ignore_link)) = ignore_step
+              && *link_id == ignore_link
+            {
               return None;
             }
-            cost
-          }
-        };
 
-        let cost = point.distance(link.portal.0.midpoint(link.portal.1))
-          * current_node_cost
-          + link_cost;
-        Some((
-          cost,
-          PathStep::OffMeshLink(*link_id),
-          PathNode::OffMeshLink(*link_id),
-        ))
-      }))
+            // Links covered by obstacles or disabled can't be taken.
+            if self.nav_data.blocked_off_mesh_links.contains(link_id)
+              || self.nav_data.disabled_off_mesh_links.contains(link_id)
+            {
+              return None;
+            }
+
+            let link = self.nav_data.off_mesh_links.get(*link_id).unwrap();
+            if !is_reachable(link.portal) {
+              return None;
+            }
+            if !self.is_node_permitted(link.destination_node) {
+              return None;
+            }
+            let destination_node_cost =
+              self.node_to_cost(link.destination_node);
+            if !destination_node_cost.is_finite() {
+              return None;
+            }
+
+            let link_cost = match link.kinded {
+              // Boundary links have no additional cost, so 0.0 is correct.
+              // These are also never too narrow, since the ends of the portal
+              // are usually just where the islands stop overlapping, not
+              // walls.
+              KindedOffMeshLink::BoundaryLink { .. } => 0.0,
+              KindedOffMeshLink::AnimationLink {
+                cost,
+                kind,
+                animation_link,
+                ..
+              } => {
+                if !self.permitted_animation_links.is_permitted(kind) {
+                  return None;
+                }
+                self
+                  .busy_animation_link_costs
+                  .get(&animation_link)
+                  .copied()
+                  .unwrap_or(cost)
+              }
+            };
+
+            let cost = point.distance(link.portal.0.midpoint(link.portal.1))
+              * current_node_cost
+              + link_cost;
+            Some((
+              cost,
+              PathStep::OffMeshLink(*link_id),
+              PathNode::OffMeshLink(*link_id),
+            ))
+          })),
+      )
       .collect()
   }
 
   fn heuristic(&self, state: &Self::StateType) -> f32 {
     let world_point = match state {
       PathNode::Start => self.start_point,
-      PathNode::End => return 0.0,
+      PathNode::End(_) => return 0.0,
       PathNode::NodeEdge { node, start_edge: edge } => {
         let island = self.nav_data.get_island(node.island_id).unwrap();
         let edge = island.get_nav_mesh().get_edge_points(MeshEdgeRef {
@@ -253,14 +374,66 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
         portal.0.midpoint(portal.1)
       }
     };
-    world_point.distance(self.end_point) * self.cheapest_type_index_cost
+    // The closest end is the one that determines the estimate. This is still
+    // consistent since the minimum of consistent heuristics is consistent.
+    let estimate = self
+      .ends
+      .iter()
+      .map(|(_, end_point)| FloatOrd(world_point.distance(*end_point)))
+      .min()
+      .map_or(0.0, |distance| distance.0)
+      * self.cheapest_type_index_cost;
+    // Both estimates never exceed the real cost, so their maximum doesn't
+    // either.
+    match self.landmarks {
+      Some((landmarks, landmark_end)) => {
+        estimate.max(landmarks.estimate(state, landmark_end))
+      }
+      None => estimate,
+    }
   }
 
   fn is_goal_state(&self, state: &Self::StateType) -> bool {
-    matches!(state, PathNode::End)
+    matches!(state, PathNode::End(_))
   }
 }
 
+/// Finds the cheapest type index cost in `nav_data`, after replacing costs
+/// with `override_type_index_to_cost`. This also accounts for the type indices
+/// and cost multipliers of cost volumes.
+fn cheapest_type_index_cost<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  override_type_index_to_cost: &HashMap<usize, f32>,
+) -> f32 {
+  let type_index_to_cost = |type_index| {
+    override_type_index_to_cost.get(&type_index).copied().unwrap_or_else(|| {
+      nav_data.get_type_index_cost(type_index).unwrap_or(1.0)
+    })
+  };
+  nav_data
+    .get_type_index_costs()
+    .map(|(type_index, cost)| {
+      (
+        type_index,
+        // Replace any type indices with their overriden value, but only if it
+        // was overriden.
+        override_type_index_to_cost.get(&type_index).copied().unwrap_or(cost),
+      )
+    })
+    .chain(
+      nav_data
+        .cost_volume_type_indices()
+        .map(|type_index| (type_index, type_index_to_cost(type_index))),
+    )
+    .filter(|pair| pair.1.is_finite())
+    .map(|pair| FloatOrd(pair.1))
+    .chain(std::iter::once(FloatOrd(1.0)))
+    .min()
+    .unwrap()
+    .0
+    * nav_data.cheapest_cost_multiplier()
+}
+
 /// The results of pathfinding.
 #[derive(Debug)]
 pub(crate) struct PathResult {
@@ -268,12 +441,363 @@ pub(crate) struct PathResult {
   pub(crate) stats: PathStats,
   /// The path if one was found.
   pub(crate) path: Option<Path>,
+  /// The path to the explored node closest to the end point. Only set if no
+  /// path was found and partial paths were requested.
+  pub(crate) partial_path: Option<Path>,
+}
+
+/// A path search that may be spread across several calls to
+/// [`PathSearch::resume`]. This stores everything needed to recreate the A*
+/// problem, so the problem is consistent between calls.
+pub(crate) struct PathSearch {
+  /// The node to start the path from.
+  start_node: NodeRef,
+  /// The point to start the path from, in world space.
+  start_point: Vec3,
+  /// The node to end the path at.
+  end_node: NodeRef,
+  /// The point to end the path at, in world space.
+  end_point: Vec3,
+  /// The cheapest type index cost, cached from the start of the search.
+  cheapest_type_index_cost: f32,
+  /// Replacement costs for the `nav_data.type_index_to_cost`.
+  override_type_index_to_cost: HashMap<usize, f32>,
+  /// The set of permitted animation links for the agent.
+  permitted_animation_links: PermittedAnimationLinks,
+  /// The set of type indices the agent is permitted to walk on.
+  permitted_type_indices: PermittedTypeIndices,
+  /// The radius of the agent.
+  agent_radius: f32,
+  /// Whether to produce a partial path if no path is found.
+  allow_partial_path: bool,
+  /// The landmarks from the start of the search and the bounds for the
+  /// landmark heuristic. The landmarks are kept so the heuristic stays the same
+  /// even if the landmarks are recomputed. This is [`None`] if there are no
+  /// landmarks.
+  landmarks: Option<(Arc<Landmarks>, LandmarkEnd)>,
+  /// The search through the region graph for [`Self::corridor`]. This is
+  /// [`None`] once the search finishes, or if no corridor is used.
+  corridor_search: Option<CorridorSearch>,
+  /// The clusters selected by the region graph that the search is restricted
+  /// to. This is [`None`] if the search is not restricted.
+  corridor: Option<HashSet<ClusterId>>,
+  /// The number of clusters explored by the finished [`CorridorSearch`].
+  region_explored_nodes: u32,
+  /// The number of nodes explored while restricted to [`Self::corridor`], if
+  /// that search failed and was replaced by an unrestricted search.
+  corridor_explored_nodes: u32,
+  /// The cost to use for each animation link that was full at the start of the
+  /// search. This is kept so the costs stay the same for the whole search,
+  /// even as agents start and finish using links.
+  busy_animation_link_costs: HashMap<AnimationLinkId, f32>,
+  /// The A* search. This is [`None`] if the start and end nodes are known to
+  /// be disconnected.
+  search: Option<astar::Search<PathStep, PathNode>>,
+}
+
+/// The status of a [`PathSearch`] after resuming it.
+pub(crate) enum PathSearchStatus {
+  /// The search ran out of budget before finishing. Resume the contained
+  /// search to continue.
+  Incomplete(Box<PathSearch>),
+  /// The search finished.
+  Complete(PathResult),
+}
+
+impl PathSearch {
+  /// Starts a search for a path in `nav_data` from `start_node` to `end_node`.
+  /// Type index costs are overriden with `override_type_index_to_cost`, and
+  /// nodes whose type index is not in `permitted_type_indices` are never
+  /// entered. Portals narrower than `agent_radius * 2.0` are not used.
+  /// `start_point` and `end_point` are assumed to be in the corresponding
+  /// nodes, and in world space. If `allow_partial_path` is true and no path is
+  /// found, a path to the explored node closest to `end_point` is produced
+  /// instead. No nodes are explored until [`Self::resume`] is called.
+  #[expect(clippy::too_many_arguments)]
+  pub(crate) fn new<CS: CoordinateSystem>(
+    nav_data: &NavigationData<CS>,
+    start_node: NodeRef,
+    start_point: Vec3,
+    end_node: NodeRef,
+    end_point: Vec3,
+    override_type_index_to_cost: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    permitted_type_indices: PermittedTypeIndices,
+    agent_radius: f32,
+    allow_partial_path: bool,
+  ) -> Self {
+    let cheapest_type_index_cost =
+      cheapest_type_index_cost(nav_data, override_type_index_to_cost);
+    // Partial paths pick the explored node with the lowest heuristic, which
+    // should be the closest to the end point, so only use the straight-line
+    // heuristic for them.
+    let landmarks =
+      nav_data.landmarks.as_ref().filter(|_| !allow_partial_path).and_then(
+        |landmarks| {
+          let landmark_end = landmarks.end(
+            end_node,
+            end_point,
+            landmarks.cost_scale(
+              nav_data,
+              override_type_index_to_cost,
+              cheapest_type_index_cost,
+            ),
+          )?;
+          Some((landmarks.clone(), landmark_end))
+        },
+      );
+    // The corridor is only a guess for the region containing the path, so
+    // partial paths also search everything to find the closest node.
+    let corridor_search =
+      nav_data.region_graph.as_ref().filter(|_| !allow_partial_path).map(
+        |region_graph| {
+          CorridorSearch::new(
+            nav_data,
+            region_graph,
+            start_node,
+            start_point,
+            end_node,
+            end_point,
+            cheapest_type_index_cost,
+            &permitted_animation_links,
+          )
+        },
+      );
+    let mut path_search = Self {
+      start_node,
+      start_point,
+      end_node,
+      end_point,
+      cheapest_type_index_cost,
+      override_type_index_to_cost: override_type_index_to_cost.clone(),
+      permitted_animation_links,
+      permitted_type_indices,
+      agent_radius,
+      allow_partial_path,
+      landmarks,
+      corridor_search,
+      corridor: None,
+      region_explored_nodes: 0,
+      corridor_explored_nodes: 0,
+      busy_animation_link_costs: nav_data.busy_animation_link_costs.clone(),
+      search: None,
+    };
+    // Partial paths need to explore the nodes regardless of whether the end
+    // node is reachable.
+    if allow_partial_path
+      || nav_data.are_nodes_connected(
+        start_node,
+        end_node,
+        path_search.permitted_animation_links.clone(),
+        &path_search.permitted_type_indices,
+      )
+    {
+      path_search.search =
+        Some(astar::Search::new(&path_search.problem(nav_data)));
+    }
+    path_search
+  }
+
+  /// The node that the search starts from.
+  pub(crate) fn start_node(&self) -> NodeRef {
+    self.start_node
+  }
+
+  /// The node that the search ends at.
+  pub(crate) fn end_node(&self) -> NodeRef {
+    self.end_node
+  }
+
+  /// Whether the search produces a partial path if no path is found.
+  pub(crate) fn allow_partial_path(&self) -> bool {
+    self.allow_partial_path
+  }
+
+  /// The number of nodes explored by the search so far.
+  pub(crate) fn explored_nodes(&self) -> u32 {
+    self.region_explored_nodes
+      + self
+        .corridor_search
+        .as_ref()
+        .map_or(0, |corridor_search| corridor_search.explored_nodes())
+      + self.corridor_explored_nodes
+      + self.search.as_ref().map_or(0, |search| search.stats().explored_nodes)
+  }
+
+  /// Creates the A* problem for this search.
+  fn problem<'a, CS: CoordinateSystem>(
+    &'a self,
+    nav_data: &'a NavigationData<CS>,
+  ) -> ArchipelagoPathProblem<'a, CS> {
+    ArchipelagoPathProblem {
+      nav_data,
+      start_node: self.start_node,
+      start_point: self.start_point,
+      ends: vec![(self.end_node, self.end_point)],
+      cheapest_type_index_cost: self.cheapest_type_index_cost,
+      override_type_index_to_cost: &self.override_type_index_to_cost,
+      permitted_animation_links: &self.permitted_animation_links,
+      permitted_type_indices: &self.permitted_type_indices,
+      agent_radius: self.agent_radius,
+      landmarks: self
+        .landmarks
+        .as_ref()
+        .map(|(landmarks, landmark_end)| (landmarks.as_ref(), landmark_end)),
+      corridor: self.corridor.as_ref(),
+      busy_animation_link_costs: &self.busy_animation_link_costs,
+    }
+  }
+
+  /// Determines whether the search can no longer be resumed after `nav_data`
+  /// was updated, since the search reached nodes or off mesh links that were
+  /// invalidated. The arguments are the invalidated off mesh links, islands,
+  /// and nodes, as returned by [`NavigationData::update`].
+  pub(crate) fn is_invalidated(
+    &self,
+    invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
+    invalidated_islands: &HashSet<IslandId>,
+    invalidated_nodes: &HashSet<NodeRef>,
+  ) -> bool {
+    let is_node_invalidated = |node_ref: &NodeRef| {
+      invalidated_islands.contains(&node_ref.island_id)
+        || invalidated_nodes.contains(node_ref)
+    };
+    if is_node_invalidated(&self.start_node)
+      || is_node_invalidated(&self.end_node)
+    {
+      return true;
+    }
+    if let Some(corridor) = self.corridor.as_ref()
+      && corridor
+        .iter()
+        .any(|(island_id, _)| invalidated_islands.contains(island_id))
+    {
+      return true;
+    }
+    if let Some(corridor_search) = self.corridor_search.as_ref()
+      && corridor_search.is_invalidated(invalidated_off_mesh_links)
+    {
+      return true;
+    }
+    let Some(search) = self.search.as_ref() else {
+      // The start and end nodes were disconnected, but the changes may have
+      // connected them.
+      return true;
+    };
+    search.states().any(|state| match state {
+      PathNode::Start | PathNode::End(_) => false,
+      PathNode::NodeEdge { node, .. } => is_node_invalidated(node),
+      PathNode::OffMeshLink(link_id) => {
+        invalidated_off_mesh_links.contains(link_id)
+      }
+    })
+  }
+
+  /// Continues the search, exploring at most `max_explored_nodes` nodes. This
+  /// includes the clusters explored to find the corridor. `nav_data` must not have changed since the search was started, except in
+  /// ways that don't invalidate the search (see [`Self::is_invalidated`]).
+  pub(crate) fn resume<CS: CoordinateSystem>(
+    mut self,
+    nav_data: &NavigationData<CS>,
+    mut max_explored_nodes: u32,
+  ) -> PathSearchStatus {
+    let Some(mut search) = self.search.take() else {
+      return PathSearchStatus::Complete(PathResult {
+        stats: PathStats { explored_nodes: 0 },
+        path: None,
+        partial_path: None,
+      });
+    };
+
+    if let Some(mut corridor_search) = self.corridor_search.take() {
+      let previously_explored_nodes = corridor_search.explored_nodes();
+      match corridor_search.resume(
+        nav_data,
+        &self.permitted_animation_links,
+        max_explored_nodes,
+      ) {
+        CorridorSearchStatus::Incomplete => {
+          self.corridor_search = Some(corridor_search);
+          self.search = Some(search);
+          return PathSearchStatus::Incomplete(Box::new(self));
+        }
+        CorridorSearchStatus::Complete(corridor) => {
+          self.corridor = corridor;
+          self.region_explored_nodes = corridor_search.explored_nodes();
+          max_explored_nodes -=
+            self.region_explored_nodes - previously_explored_nodes;
+        }
+      }
+    }
+
+    let mut path_result = loop {
+      let previously_explored_nodes = search.stats().explored_nodes;
+      let path_result =
+        match search.resume(&self.problem(nav_data), max_explored_nodes) {
+          SearchStatus::Incomplete => {
+            self.search = Some(search);
+            return PathSearchStatus::Incomplete(Box::new(self));
+          }
+          SearchStatus::Complete(path_result) => path_result,
+        };
+      if path_result.path.is_some() || self.corridor.is_none() {
+        break path_result;
+      }
+      // The corridor can be unusable for this agent (e.g., its portals may be
+      // too narrow), so search without the corridor instead.
+      self.corridor = None;
+      self.corridor_explored_nodes = path_result.stats.explored_nodes;
+      max_explored_nodes -=
+        path_result.stats.explored_nodes - previously_explored_nodes;
+      search = astar::Search::new(&self.problem(nav_data));
+    };
+    path_result.stats.explored_nodes +=
+      self.region_explored_nodes + self.corridor_explored_nodes;
+
+    let Some(astar_path) = path_result.path else {
+      let partial_path = path_result
+        .closest_path
+        .filter(|_| self.allow_partial_path)
+        .map(|closest_path| {
+          let mut partial_path = astar_path_to_path(
+            nav_data,
+            self.start_node,
+            self.start_point,
+            self.end_point,
+            closest_path,
+          );
+          partial_path.end_point = nav_data
+            .closest_point_on_node(self.end_point, partial_path.last_node());
+          partial_path
+        });
+      return PathSearchStatus::Complete(PathResult {
+        stats: path_result.stats,
+        path: None,
+        partial_path,
+      });
+    };
+
+    PathSearchStatus::Complete(PathResult {
+      stats: path_result.stats,
+      path: Some(astar_path_to_path(
+        nav_data,
+        self.start_node,
+        self.start_point,
+        self.end_point,
+        astar_path,
+      )),
+      partial_path: None,
+    })
+  }
 }
 
 /// Finds a path in `nav_data` from `start_node` to `end_node`. Type index costs
-/// are overriden with `override_type_index_to_cost`. Returns an `Err` if no
-/// path was found. `start_point` and `end_point` are assumed to be in the
-/// corresponding nodes, and in world space.
+/// are overriden with `override_type_index_to_cost`, and nodes whose type index
+/// is not in `permitted_type_indices` are never entered. Portals narrower than
+/// `agent_radius * 2.0` are not used. Returns an `Err` if no path was found.
+/// `start_point` and `end_point` are assumed to be in the corresponding nodes,
+/// and in world space. See [`PathSearch::new`] for `allow_partial_path`.
+#[expect(clippy::too_many_arguments)]
 pub(crate) fn find_path<CS: CoordinateSystem>(
   nav_data: &NavigationData<CS>,
   start_node: NodeRef,
@@ -282,45 +806,166 @@ pub(crate) fn find_path<CS: CoordinateSystem>(
   end_point: Vec3,
   override_type_index_to_cost: &HashMap<usize, f32>,
   permitted_animation_links: PermittedAnimationLinks,
+  permitted_type_indices: PermittedTypeIndices,
+  agent_radius: f32,
+  allow_partial_path: bool,
 ) -> PathResult {
-  if !nav_data.are_nodes_connected(
+  match PathSearch::new(
+    nav_data,
     start_node,
+    start_point,
     end_node,
-    permitted_animation_links.clone(),
-  ) {
-    return PathResult { stats: PathStats { explored_nodes: 0 }, path: None };
+    end_point,
+    override_type_index_to_cost,
+    permitted_animation_links,
+    permitted_type_indices,
+    agent_radius,
+    allow_partial_path,
+  )
+  .resume(nav_data, u32::MAX)
+  {
+    PathSearchStatus::Incomplete(_) => {
+      unreachable!("an unlimited search always completes")
+    }
+    PathSearchStatus::Complete(path_result) => path_result,
   }
+}
 
-  let path_problem = ArchipelagoPathProblem {
+/// Finds every node in `nav_data` that can be reached from `start_node` with
+/// a path cost of at most `max_cost`, along with the cost of the cheapest path
+/// to each of them. The cost of a node is the cost to reach the point where
+/// the path enters it (or 0 for `start_node`). Type index costs are overriden
+/// with `override_type_index_to_cost`, and nodes whose type index is not in
+/// `permitted_type_indices` are never entered. Portals narrower than
+/// `agent_radius * 2.0` are not used. `start_point` is assumed to be in
+/// `start_node`, and in world space.
+#[expect(clippy::too_many_arguments)]
+pub(crate) fn find_reachable_nodes<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  start_node: NodeRef,
+  start_point: Vec3,
+  max_cost: f32,
+  override_type_index_to_cost: &HashMap<usize, f32>,
+  permitted_animation_links: PermittedAnimationLinks,
+  permitted_type_indices: PermittedTypeIndices,
+  agent_radius: f32,
+) -> HashMap<NodeRef, f32> {
+  let problem = ArchipelagoPathProblem {
     nav_data,
     start_node,
-    end_node,
     start_point,
-    end_point,
-    cheapest_type_index_cost: *nav_data
-      .get_type_index_costs()
-      .map(|(type_index, cost)| {
-        (
-          type_index,
-          // Replace any type indices with their overriden value, but only if
-          // it was overriden.
-          override_type_index_to_cost.get(&type_index).copied().unwrap_or(cost),
-        )
-      })
-      .filter(|pair| pair.1.is_finite())
-      .map(|pair| FloatOrd(pair.1))
-      .chain(std::iter::once(FloatOrd(1.0)))
-      .min()
-      .unwrap(),
+    // Without any ends, the heuristic is always 0, so the search becomes
+    // Dijkstra's algorithm, and the estimate is just the cost.
+    ends: vec![],
+    cheapest_type_index_cost: cheapest_type_index_cost(
+      nav_data,
+      override_type_index_to_cost,
+    ),
     override_type_index_to_cost,
-    permitted_animation_links,
+    permitted_animation_links: &permitted_animation_links,
+    permitted_type_indices: &permitted_type_indices,
+    agent_radius,
+    landmarks: None,
+    corridor: None,
+    busy_animation_link_costs: &nav_data.busy_animation_link_costs,
   };
+  let explore_result = astar::Search::new(&problem)
+    .explore(&problem, max_cost, /* goal_states= */ 0);
+
+  let mut node_costs = HashMap::<NodeRef, f32>::new();
+  for (state, cost) in explore_result.state_costs {
+    let node_ref = match state {
+      PathNode::Start => start_node,
+      PathNode::NodeEdge { node, .. } => node,
+      PathNode::OffMeshLink(link) => {
+        nav_data.off_mesh_links.get(link).unwrap().destination_node
+      }
+      PathNode::End(_) => unreachable!("there are no ends"),
+    };
+    node_costs
+      .entry(node_ref)
+      .and_modify(|node_cost| *node_cost = node_cost.min(cost))
+      .or_insert(cost);
+  }
+  node_costs
+}
 
-  let path_result = astar::find_path(&path_problem);
-  let Some(astar_path) = path_result.path else {
-    return PathResult { stats: path_result.stats, path: None };
+/// Finds the cost of the cheapest path in `nav_data` from `start_node` to each
+/// of `ends`, using a single search. Each end is the node and the point (in
+/// world space) in that node. The result is in the same order as `ends`, and
+/// is None for ends that are unreachable. Type index costs are overriden with
+/// `override_type_index_to_cost`, and nodes whose type index is not in
+/// `permitted_type_indices` are never entered. Portals narrower than
+/// `agent_radius * 2.0` are not used. `start_point` is assumed to be in
+/// `start_node`, and in world space.
+#[expect(clippy::too_many_arguments)]
+pub(crate) fn find_path_costs<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  start_node: NodeRef,
+  start_point: Vec3,
+  ends: &[(NodeRef, Vec3)],
+  override_type_index_to_cost: &HashMap<usize, f32>,
+  permitted_animation_links: PermittedAnimationLinks,
+  permitted_type_indices: PermittedTypeIndices,
+  agent_radius: f32,
+) -> Vec<Option<f32>> {
+  // Only search for the reachable ends. Otherwise the search would explore
+  // every reachable node looking for the unreachable ends.
+  let (reachable_end_indices, reachable_ends): (Vec<_>, Vec<_>) = ends
+    .iter()
+    .enumerate()
+    .filter(|(_, (end_node, _))| {
+      nav_data.are_nodes_connected(
+        start_node,
+        *end_node,
+        permitted_animation_links.clone(),
+        &permitted_type_indices,
+      )
+    })
+    .map(|(end_index, end)| (end_index, *end))
+    .unzip();
+
+  let mut costs = vec![None; ends.len()];
+  if reachable_ends.is_empty() {
+    return costs;
+  }
+
+  let goal_states = reachable_ends.len();
+  let problem = ArchipelagoPathProblem {
+    nav_data,
+    start_node,
+    start_point,
+    ends: reachable_ends,
+    cheapest_type_index_cost: cheapest_type_index_cost(
+      nav_data,
+      override_type_index_to_cost,
+    ),
+    override_type_index_to_cost,
+    permitted_animation_links: &permitted_animation_links,
+    permitted_type_indices: &permitted_type_indices,
+    agent_radius,
+    landmarks: None,
+    corridor: None,
+    busy_animation_link_costs: &nav_data.busy_animation_link_costs,
   };
+  let explore_result =
+    astar::Search::new(&problem).explore(&problem, f32::INFINITY, goal_states);
+
+  for (reachable_index, end_index) in reachable_end_indices.iter().enumerate() {
+    costs[*end_index] =
+      explore_result.state_costs.get(&PathNode::End(reachable_index)).copied();
+  }
+  costs
+}
 
+/// Converts the actions of `astar_path` into a [`Path`].
+fn astar_path_to_path<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  start_node: NodeRef,
+  start_point: Vec3,
+  end_point: Vec3,
+  astar_path: Vec<PathStep>,
+) -> Path {
   let mut output_path = Path {
     island_segments: vec![],
     off_mesh_link_segments: vec![],
@@ -378,7 +1023,7 @@ pub(crate) fn find_path<CS: CoordinateSystem>(
     }
   }
 
-  PathResult { stats: path_result.stats, path: Some(output_path) }
+  output_path
 }
 
 #[cfg(test)]
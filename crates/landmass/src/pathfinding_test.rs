@@ -4,12 +4,12 @@ use std::{
   sync::Arc,
 };
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 
 use crate::{
   Archipelago, ArchipelagoOptions, CoordinateSystem, FromAgentRadius, Island,
   Transform,
-  agent::PermittedAnimationLinks,
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
   coords::{XY, XYZ},
   link::{AnimationLink, AnimationLinkId},
   nav_data::{KindedOffMeshLink, NavigationData, NodeRef, OffMeshLinkId},
@@ -18,7 +18,7 @@ use crate::{
   pathfinding::PathResult,
 };
 
-use super::find_path;
+use super::{PathSearch, PathSearchStatus, find_path};
 
 // Same as `find_path`, but derives the start and end points from the center of
 // the specified nodes.
@@ -45,6 +45,9 @@ fn find_path_between_nodes<CS: CoordinateSystem>(
       end_point,
       override_type_index_to_cost,
       PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* agent_radius= */ 0.0,
+      /* allow_partial_path= */ false,
     ),
   )
 }
@@ -83,10 +86,8 @@ fn finds_path_in_archipelago() {
 
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::new(nav_mesh),
-  ));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
 
   let nav_data = &archipelago.nav_data;
 
@@ -189,13 +190,15 @@ fn finds_paths_on_two_islands() {
 
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id_1 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  let island_id_1 = archipelago
+    .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
 
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(6.0, 0.0, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(6.0, 0.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -279,13 +282,15 @@ fn no_path_between_disconnected_islands() {
 
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
-  let island_id_1 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    Arc::clone(&nav_mesh),
-  ));
+  let island_id_1 = archipelago
+    .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
 
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(6.0, 0.0, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(6.0, 0.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -338,24 +343,40 @@ fn find_path_across_connected_islands() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
 
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::ZERO },
+    Transform { rotation: Quat::IDENTITY, translation: Vec3::ZERO, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 0.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      translation: Vec3::new(1.0, 0.0, 0.0),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   // island_id_3 is unused.
   archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, -1.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      translation: Vec3::new(1.0, -1.0, 0.0),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_id_4 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 1.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      translation: Vec3::new(1.0, 1.0, 0.0),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_id_5 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 2.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      translation: Vec3::new(1.0, 2.0, 0.0),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -467,11 +488,15 @@ fn finds_path_across_different_islands() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
 
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::ZERO },
+    Transform { rotation: Quat::IDENTITY, translation: Vec3::ZERO, scale: 1.0 },
     nav_mesh_1,
   ));
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 0.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      translation: Vec3::new(1.0, 0.0, 0.0),
+      scale: 1.0,
+    },
     nav_mesh_2,
   ));
 
@@ -543,16 +568,22 @@ fn aborts_early_for_unconnected_regions() {
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
 
-  let island_id_1 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
-    nav_mesh.clone(),
-  ));
+  let island_id_1 =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_id_3 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(1.5, 2.0, 0.0), rotation: PI * 0.5 },
+    Transform {
+      translation: Vec3::new(1.5, 2.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -765,8 +796,8 @@ fn detour_for_high_cost_path_across_boundary_links() {
           [&NodeRef { island_id: island_id_1, polygon_index: 2 }]
           .iter()
           .next()
-          .unwrap()
-          .clone(),
+          .copied()
+          .unwrap(),
         starting_node: NodeRef { island_id: island_id_1, polygon_index: 2 },
         end_node: NodeRef { island_id: island_id_2, polygon_index: 4 },
       }],
@@ -1151,6 +1182,9 @@ fn start_and_end_point_influences_path() {
     end_point,
     &HashMap::default(),
     PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
   );
 
   assert_eq!(
@@ -1240,6 +1274,8 @@ fn animation_link_is_used() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   archipelago.update(1.0);
 
@@ -1255,6 +1291,9 @@ fn animation_link_is_used() {
     end_point,
     &HashMap::default(),
     PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
   );
 
   assert_eq!(
@@ -1344,6 +1383,8 @@ fn animation_link_is_used_if_cheaper() {
     // This link is more expensive than taking the nav mesh.
     cost: 1.5,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   archipelago.update(1.0);
 
@@ -1357,6 +1398,9 @@ fn animation_link_is_used_if_cheaper() {
     end_point,
     &HashMap::default(),
     PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
   );
 
   assert_eq!(
@@ -1381,6 +1425,8 @@ fn animation_link_is_used_if_cheaper() {
     // Now the link is cheaper than taking the nav mesh.
     cost: 0.75,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   archipelago.update(1.0);
 
@@ -1394,6 +1440,9 @@ fn animation_link_is_used_if_cheaper() {
     end_point,
     &HashMap::default(),
     PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
   );
 
   assert_eq!(
@@ -1478,6 +1527,8 @@ fn animation_link_is_not_used_if_not_permitted() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
   archipelago.update(1.0);
 
@@ -1491,6 +1542,9 @@ fn animation_link_is_not_used_if_not_permitted() {
     end_point,
     &HashMap::default(),
     PermittedAnimationLinks::Kinds(Arc::new(HashSet::from([]))),
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
   );
 
   // Despite there being an animation link that could take us all the way to the
@@ -1509,3 +1563,363 @@ fn animation_link_is_not_used_if_not_permitted() {
     })
   );
 }
+
+#[test]
+fn skips_portals_narrower_than_agent() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(6.0, 0.0),
+        Vec2::new(6.0, 2.0),
+        Vec2::new(1.0, 5.0),
+        Vec2::new(0.0, 5.0),
+        Vec2::new(6.0, 5.0),
+        Vec2::new(3.0, 5.0),
+        Vec2::new(1.0, 7.0),
+        Vec2::new(0.0, 7.0),
+        Vec2::new(3.0, 7.0),
+        Vec2::new(6.0, 7.0),
+      ],
+      polygons: vec![
+        // Bottom row.
+        vec![0, 1, 2, 3],
+        vec![1, 4, 5, 2],
+        vec![4, 6, 7, 5],
+        // Narrow connection.
+        vec![3, 2, 8, 9],
+        // Wide connection.
+        vec![5, 7, 10, 11],
+        // Top row.
+        vec![9, 8, 12, 13],
+        vec![8, 11, 14, 12],
+        vec![11, 10, 15, 14],
+      ],
+      polygon_type_indices: vec![0; 8],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  let start_point = Vec3::new(0.5, 1.0, 0.0);
+  let end_point = Vec3::new(0.5, 6.0, 0.0);
+
+  let path_result = find_path(
+    &archipelago.nav_data,
+    NodeRef { island_id, polygon_index: 0 },
+    start_point,
+    NodeRef { island_id, polygon_index: 5 },
+    end_point,
+    &HashMap::default(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.5,
+    /* allow_partial_path= */ false,
+  );
+
+  // The agent fits exactly through the narrow connection.
+  assert_eq!(
+    path_result.path,
+    Some(Path {
+      island_segments: vec![IslandSegment {
+        island_id,
+        corridor: vec![0, 3, 5],
+        portal_edge_index: vec![2, 2],
+      }],
+      off_mesh_link_segments: vec![],
+      start_point,
+      end_point,
+    })
+  );
+
+  let path_result = find_path(
+    &archipelago.nav_data,
+    NodeRef { island_id, polygon_index: 0 },
+    start_point,
+    NodeRef { island_id, polygon_index: 5 },
+    end_point,
+    &HashMap::default(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.75,
+    /* allow_partial_path= */ false,
+  );
+
+  // The agent is too big for the narrow connection, so it must take the long
+  // way around.
+  assert_eq!(
+    path_result.path,
+    Some(Path {
+      island_segments: vec![IslandSegment {
+        island_id,
+        corridor: vec![0, 1, 2, 4, 7, 6, 5],
+        portal_edge_index: vec![1, 1, 2, 2, 3, 3],
+      }],
+      off_mesh_link_segments: vec![],
+      start_point,
+      end_point,
+    })
+  );
+
+  let path_result = find_path(
+    &archipelago.nav_data,
+    NodeRef { island_id, polygon_index: 0 },
+    start_point,
+    NodeRef { island_id, polygon_index: 5 },
+    end_point,
+    &HashMap::default(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 1.1,
+    /* allow_partial_path= */ false,
+  );
+
+  // The agent doesn't even fit in the rooms.
+  assert_eq!(path_result.path, None);
+}
+
+#[test]
+fn resumed_path_search_finds_same_path() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      Vec3::new(2.0, 0.0, 0.0),
+      Vec3::new(3.0, 0.0, 0.0),
+      Vec3::new(4.0, 0.0, 0.0),
+      Vec3::new(4.0, 1.0, 0.0),
+      Vec3::new(3.0, 1.0, 0.0),
+      Vec3::new(2.0, 1.0, 0.0),
+      Vec3::new(1.0, 1.0, 0.0),
+      Vec3::new(0.0, 1.0, 0.0),
+    ],
+    polygons: vec![
+      vec![0, 1, 8, 9],
+      vec![1, 2, 7, 8],
+      vec![2, 3, 6, 7],
+      vec![3, 4, 5, 6],
+    ],
+    polygon_type_indices: vec![0, 0, 0, 0],
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  let mut archipelago =
+    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+  archipelago.update(1.0);
+
+  let nav_data = &archipelago.nav_data;
+
+  let (start_point, end_point, expected_path_result) = find_path_between_nodes(
+    nav_data,
+    NodeRef { island_id, polygon_index: 0 },
+    NodeRef { island_id, polygon_index: 3 },
+    &HashMap::new(),
+  );
+
+  let mut path_search = PathSearch::new(
+    nav_data,
+    NodeRef { island_id, polygon_index: 0 },
+    start_point,
+    NodeRef { island_id, polygon_index: 3 },
+    end_point,
+    &HashMap::new(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
+  );
+  let mut resumes = 0;
+  let path_result = loop {
+    resumes += 1;
+    match path_search.resume(nav_data, 1) {
+      PathSearchStatus::Incomplete(next_path_search) => {
+        assert_eq!(next_path_search.explored_nodes(), resumes);
+        path_search = *next_path_search;
+      }
+      PathSearchStatus::Complete(path_result) => break path_result,
+    }
+  };
+
+  assert_eq!(path_result.path, expected_path_result.path);
+  assert_eq!(path_result.stats.explored_nodes, 5);
+  assert_eq!(resumes, 5);
+}
+
+#[test]
+fn resumed_path_search_keeps_busy_costs() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // Same as `animation_link_is_used_if_cheaper`, but the link becomes busy
+  // in the middle of the search.
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(0.0, 3.0),
+        Vec2::new(2.0, 3.0),
+        Vec2::new(3.0, 3.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 4, 3],
+        vec![1, 2, 5, 4],
+        vec![4, 5, 8, 7],
+        vec![7, 8, 11, 10],
+        vec![6, 7, 10, 9],
+      ],
+      polygon_type_indices: vec![0; 5],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let link_id = archipelago.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)),
+    end_edge: (Vec2::new(0.0, 2.0), Vec2::new(1.0, 2.0)),
+    kind: 0,
+    cost: 0.75,
+    bidirectional: false,
+    capacity: Some(1),
+    busy_cost: Some(100.0),
+  });
+  archipelago.update(1.0);
+
+  let mut path_search = PathSearch::new(
+    &archipelago.nav_data,
+    NodeRef { island_id, polygon_index: 1 },
+    Vec3::new(2.5, 0.5, 0.0),
+    NodeRef { island_id, polygon_index: 4 },
+    Vec3::new(0.5, 2.5, 0.0),
+    &HashMap::new(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
+  );
+  path_search = match path_search.resume(&archipelago.nav_data, 1) {
+    PathSearchStatus::Incomplete(path_search) => *path_search,
+    PathSearchStatus::Complete(_) => panic!("search should be incomplete"),
+  };
+
+  archipelago.nav_data.busy_animation_link_costs.insert(link_id, 100.0);
+
+  let PathSearchStatus::Complete(path_result) =
+    path_search.resume(&archipelago.nav_data, u32::MAX)
+  else {
+    panic!("search should be complete");
+  };
+  // The link was not busy when the search started, so it is still taken.
+  assert_eq!(
+    path_result.path.expect("path is found").off_mesh_link_segments.len(),
+    1
+  );
+}
+
+#[test]
+fn finds_partial_path_to_unreachable_node() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(2.0, 1.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("Mesh is valid."),
+  );
+
+  let mut archipelago =
+    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let island_id_1 =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let island_id_2 = archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec3::new(5.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    nav_mesh,
+  ));
+  archipelago.update(1.0);
+
+  let nav_data = &archipelago.nav_data;
+  let start_point = Vec3::new(0.5, 0.5, 0.0);
+  let end_point = Vec3::new(5.5, 0.25, 0.0);
+
+  let path_result = find_path(
+    nav_data,
+    NodeRef { island_id: island_id_1, polygon_index: 0 },
+    start_point,
+    NodeRef { island_id: island_id_2, polygon_index: 0 },
+    end_point,
+    &HashMap::new(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
+  );
+  assert!(path_result.path.is_none());
+  assert!(path_result.partial_path.is_none());
+  // The nodes are known to be disconnected, so nothing is explored.
+  assert_eq!(path_result.stats.explored_nodes, 0);
+
+  let path_result = find_path(
+    nav_data,
+    NodeRef { island_id: island_id_1, polygon_index: 0 },
+    start_point,
+    NodeRef { island_id: island_id_2, polygon_index: 0 },
+    end_point,
+    &HashMap::new(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ true,
+  );
+  assert!(path_result.path.is_none());
+  assert_eq!(
+    path_result.partial_path,
+    Some(Path {
+      island_segments: vec![IslandSegment {
+        island_id: island_id_1,
+        corridor: vec![0, 1],
+        portal_edge_index: vec![1],
+      }],
+      off_mesh_link_segments: vec![],
+      start_point,
+      end_point: Vec3::new(2.0, 0.25, 0.0),
+    })
+  );
+}
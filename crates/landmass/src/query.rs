@@ -1,15 +1,19 @@
-use std::{collections::HashMap, marker::PhantomData};
+use std::{collections::HashMap, f32::consts::TAU, marker::PhantomData};
 
+use geo::{Area, BooleanOps, LineString, Polygon, Triangle, TriangulateEarcut};
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use rand::Rng;
 use thiserror::Error;
 
 use crate::{
   Archipelago, CoordinateSystem, IslandId,
-  agent::PermittedAnimationLinks,
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
   coords::CorePointSampleDistance,
   link::AnimationLinkId,
-  nav_data::NodeRef,
+  nav_data::{CarvedNode, NodeRef, node_to_multi_polygon},
   path::{PathIndex, StraightPathStep},
   pathfinding,
+  util::{BoundingBox, random_weighted_index},
 };
 
 /// A point on the navigation meshes.
@@ -66,33 +70,288 @@ pub enum SamplePointError {
 }
 
 /// Finds the nearest point on the navigation meshes to (and within
-/// `distance_to_node` of) `point`.
+/// `distance_to_node` of) `point`. Only nodes whose type index is in
+/// `permitted_type_indices` are considered.
 pub(crate) fn sample_point<'archipelago, CS: CoordinateSystem>(
   archipelago: &'archipelago Archipelago<CS>,
   point: CS::Coordinate,
   point_sample_distance: &'_ CorePointSampleDistance,
+  permitted_type_indices: &PermittedTypeIndices,
 ) -> Result<SampledPoint<'archipelago, CS>, SamplePointError> {
   if archipelago.nav_data.dirty {
     return Err(SamplePointError::NavDataDirty);
   }
-  let Some((point, node_ref)) = archipelago
-    .nav_data
-    .sample_point(CS::to_landmass(&point), point_sample_distance)
-  else {
+  let Some((point, node_ref)) = archipelago.nav_data.sample_point(
+    CS::to_landmass(&point),
+    point_sample_distance,
+    permitted_type_indices,
+  ) else {
     return Err(SamplePointError::OutOfRange);
   };
 
-  let island = archipelago.nav_data.get_island(node_ref.island_id).unwrap();
-  let type_index = island.nav_mesh.polygons[node_ref.polygon_index].type_index;
-
   Ok(SampledPoint {
     point: CS::from_landmass(&point),
     node_ref,
-    type_index,
+    type_index: archipelago.nav_data.node_type_index(node_ref),
     marker: PhantomData,
   })
 }
 
+/// Creates a [`SampledPoint`] for `point` (in world space) on `node_ref`.
+fn sampled_point_on_node<'archipelago, CS: CoordinateSystem>(
+  archipelago: &'archipelago Archipelago<CS>,
+  point: Vec3,
+  node_ref: NodeRef,
+) -> SampledPoint<'archipelago, CS> {
+  SampledPoint {
+    point: CS::from_landmass(&point),
+    node_ref,
+    type_index: archipelago.nav_data.node_type_index(node_ref),
+    marker: PhantomData,
+  }
+}
+
+/// An error while picking a random point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum RandomPointError {
+  #[error("There are no nodes that a point can be picked from.")]
+  NoNodes,
+  #[error(
+    "The navigation data of the archipelago has been mutated since the last update."
+  )]
+  NavDataDirty,
+}
+
+/// Picks a uniformly random point on one of `nodes`. Returns the point (in
+/// world space) and the node it is on, or None if `nodes` has no area.
+fn random_point_on_nodes<CS: CoordinateSystem>(
+  archipelago: &Archipelago<CS>,
+  nodes: &[NodeRef],
+  node_areas: &[f32],
+  rng: &mut impl Rng,
+) -> Option<(Vec3, NodeRef)> {
+  let node_ref = nodes[random_weighted_index(node_areas, rng)?];
+  let island = archipelago.nav_data.get_island(node_ref.island_id).unwrap();
+  let Some(carved_node) = archipelago.nav_data.carved_nodes.get(&node_ref)
+  else {
+    let point =
+      island.nav_mesh.random_point_on_node(node_ref.polygon_index, rng);
+    return Some((island.transform.apply(point), node_ref));
+  };
+
+  // Only the walkable parts of nodes carved by obstacles can be picked.
+  // Picking a triangle by area and then a point in that triangle is uniform
+  // over the walkable parts.
+  let triangles = carved_node
+    .walkable_parts()
+    .iter()
+    .flat_map(|polygon| polygon.earcut_triangles())
+    .collect::<Vec<_>>();
+  let triangle_areas = triangles
+    .iter()
+    .map(|triangle| triangle.unsigned_area())
+    .collect::<Vec<_>>();
+  let triangle = triangles[random_weighted_index(&triangle_areas, rng)?];
+  let point = random_point_in_triangle(&triangle, rng);
+  let center = island
+    .transform
+    .apply(island.nav_mesh.polygons[node_ref.polygon_index].center);
+  Some((
+    archipelago
+      .nav_data
+      .closest_point_on_node(point.extend(center.z), node_ref),
+    node_ref,
+  ))
+}
+
+/// Computes the areas of `nodes`. Only the walkable parts of nodes carved by
+/// obstacles are counted.
+fn node_areas<CS: CoordinateSystem>(
+  archipelago: &Archipelago<CS>,
+  nodes: &[NodeRef],
+) -> Vec<f32> {
+  nodes
+    .iter()
+    .map(|node_ref| {
+      if let Some(carved_node) = archipelago.nav_data.carved_nodes.get(node_ref)
+      {
+        return carved_node.walkable_parts().unsigned_area();
+      }
+      let island = archipelago.nav_data.get_island(node_ref.island_id).unwrap();
+      island.nav_mesh.node_area(node_ref.polygon_index)
+        * island.transform.scale
+        * island.transform.scale
+    })
+    .collect()
+}
+
+/// Picks a uniformly random point on the navigation meshes. Only nodes whose
+/// type index is in `permitted_type_indices` are considered, and points are
+/// never picked inside the holes cut by obstacles.
+pub(crate) fn random_point<'archipelago, CS: CoordinateSystem>(
+  archipelago: &'archipelago Archipelago<CS>,
+  permitted_type_indices: &PermittedTypeIndices,
+  rng: &mut impl Rng,
+) -> Result<SampledPoint<'archipelago, CS>, RandomPointError> {
+  if archipelago.nav_data.dirty {
+    return Err(RandomPointError::NavDataDirty);
+  }
+
+  let nodes = archipelago
+    .nav_data
+    .get_island_ids()
+    .filter(|&island_id| {
+      archipelago.nav_data.get_island(island_id).unwrap().enabled
+    })
+    .flat_map(|island_id| {
+      let island = archipelago.nav_data.get_island(island_id).unwrap();
+      (0..island.nav_mesh.polygons.len()).filter_map(move |polygon_index| {
+        let node_ref = NodeRef { island_id, polygon_index };
+        // Nodes entirely covered by obstacles have nowhere to put a point.
+        (permitted_type_indices
+          .is_permitted(archipelago.nav_data.node_type_index(node_ref))
+          && !archipelago
+            .nav_data
+            .carved_nodes
+            .get(&node_ref)
+            .is_some_and(CarvedNode::is_fully_covered))
+        .then_some(node_ref)
+      })
+    })
+    .collect::<Vec<_>>();
+
+  let node_areas = node_areas(archipelago, &nodes);
+  let (point, node_ref) =
+    random_point_on_nodes(archipelago, &nodes, &node_areas, rng)
+      .ok_or(RandomPointError::NoNodes)?;
+  Ok(sampled_point_on_node(archipelago, point, node_ref))
+}
+
+/// The number of sides of the polygon approximating the circle in
+/// [`random_point_in_radius`].
+const CIRCLE_SIDES: usize = 32;
+
+/// Picks a uniformly random point within `radius` (horizontally) of
+/// `start_point` that is reachable from `start_point`. Only nodes whose type
+/// index is in `permitted_type_indices` are considered.
+pub(crate) fn random_point_in_radius<'archipelago, CS: CoordinateSystem>(
+  archipelago: &'archipelago Archipelago<CS>,
+  start_point: &SampledPoint<'archipelago, CS>,
+  radius: f32,
+  permitted_type_indices: &PermittedTypeIndices,
+  permitted_animation_links: PermittedAnimationLinks,
+  rng: &mut impl Rng,
+) -> Result<SampledPoint<'archipelago, CS>, RandomPointError> {
+  // See `find_path` for why this assert is fine.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  let center = CS::to_landmass(&start_point.point);
+  // The circle is approximated by a regular polygon so it can be clipped by
+  // the nodes.
+  let circle = Polygon::new(
+    LineString::from(
+      (0..CIRCLE_SIDES)
+        .map(|i| {
+          let point = center.xy()
+            + Vec2::from_angle(i as f32 / CIRCLE_SIDES as f32 * TAU) * radius;
+          (point.x, point.y)
+        })
+        .collect::<Vec<_>>(),
+    ),
+    vec![],
+  );
+
+  // Every node in a region is connected to the same nodes, so only check each
+  // region once.
+  let mut region_is_connected = HashMap::new();
+  let mut triangles = Vec::new();
+  for island_id in archipelago.nav_data.get_island_ids() {
+    let island = archipelago.nav_data.get_island(island_id).unwrap();
+    if !island.enabled {
+      continue;
+    }
+    let Some(node_bbh) = island.node_bbh.as_ref() else {
+      continue;
+    };
+    let local_center = island.transform.apply_inverse(center);
+    let local_radius = radius / island.transform.scale;
+    let query_box = BoundingBox::new_box(
+      local_center - Vec3::new(local_radius, local_radius, f32::INFINITY),
+      local_center + Vec3::new(local_radius, local_radius, f32::INFINITY),
+    );
+    for &polygon_index in node_bbh.query_box(query_box) {
+      let node_ref = NodeRef { island_id, polygon_index };
+      if !permitted_type_indices
+        .is_permitted(archipelago.nav_data.node_type_index(node_ref))
+      {
+        continue;
+      }
+      if !*region_is_connected
+        .entry(archipelago.nav_data.node_to_region_id(node_ref))
+        .or_insert_with(|| {
+          archipelago.nav_data.are_nodes_connected(
+            start_point.node_ref,
+            node_ref,
+            permitted_animation_links.clone(),
+            permitted_type_indices,
+          )
+        })
+      {
+        continue;
+      }
+      // Only the walkable parts of nodes carved by obstacles can be picked.
+      let node_region = match archipelago.nav_data.carved_nodes.get(&node_ref) {
+        Some(carved_node) => carved_node.walkable_parts(),
+        None => node_to_multi_polygon(
+          island,
+          &island.nav_mesh.polygons[polygon_index],
+        ),
+      };
+      for polygon in node_region.intersection(&circle) {
+        triangles.extend(
+          polygon
+            .earcut_triangles()
+            .into_iter()
+            .map(|triangle| (node_ref, triangle)),
+        );
+      }
+    }
+  }
+
+  // Picking a triangle by area and then a point in that triangle is uniform
+  // over the reachable area within the radius.
+  let triangle_areas = triangles
+    .iter()
+    .map(|(_, triangle)| triangle.unsigned_area())
+    .collect::<Vec<_>>();
+  let (node_ref, triangle) =
+    triangles[random_weighted_index(&triangle_areas, rng)
+      .ok_or(RandomPointError::NoNodes)?];
+  let point = random_point_in_triangle(&triangle, rng);
+  let point = archipelago
+    .nav_data
+    .closest_point_on_node(point.extend(center.z), node_ref);
+  Ok(sampled_point_on_node(archipelago, point, node_ref))
+}
+
+/// Picks a uniformly random point in `triangle`.
+fn random_point_in_triangle(
+  triangle: &Triangle<f32>,
+  rng: &mut impl Rng,
+) -> Vec2 {
+  let [a, b, c] = triangle.to_array().map(|coord| Vec2::new(coord.x, coord.y));
+  let (mut u, mut v) = (rng.random::<f32>(), rng.random::<f32>());
+  // Points past the diagonal of the parallelogram are mirrored back into the
+  // triangle.
+  if u + v > 1.0 {
+    (u, v) = (1.0 - u, 1.0 - v);
+  }
+  a + (b - a) * u + (c - a) * v
+}
+
 /// An error from finding a path between two sampled points.
 #[derive(Clone, Copy, Debug, PartialEq, Error)]
 pub enum FindPathError {
@@ -180,15 +439,54 @@ impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for PathStep<CS> {
   }
 }
 
+/// A path that may only reach part of the way to the end point. See
+/// [`crate::Archipelago::find_partial_path`].
+pub struct PartialPath<CS: CoordinateSystem> {
+  /// The steps of the path. If [`Self::is_partial`], the last step is the
+  /// reachable point closest to the end point.
+  pub steps: Vec<PathStep<CS>>,
+  /// Whether the path is incomplete, meaning the end point is unreachable.
+  pub is_partial: bool,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for PartialPath<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("PartialPath")
+      .field("steps", &self.steps)
+      .field("is_partial", &self.is_partial)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: Clone>> Clone for PartialPath<CS> {
+  fn clone(&self) -> Self {
+    Self { steps: self.steps.clone(), is_partial: self.is_partial }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for PartialPath<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.steps == other.steps && self.is_partial == other.is_partial
+  }
+}
+
 /// Finds a straight-line path across the navigation meshes from `start_point`
-/// to `end_point`.
+/// to `end_point`. If `allow_partial_path` is true and `end_point` is
+/// unreachable, the path instead leads to the reachable point closest to
+/// `end_point`.
 pub(crate) fn find_path<'a, CS: CoordinateSystem>(
   archipelago: &'a Archipelago<CS>,
   start_point: &SampledPoint<'a, CS>,
   end_point: &SampledPoint<'a, CS>,
   override_type_index_costs: &HashMap<usize, f32>,
   permitted_animation_links: PermittedAnimationLinks,
-) -> Result<Vec<PathStep<CS>>, FindPathError> {
+  permitted_type_indices: PermittedTypeIndices,
+  allow_partial_path: bool,
+) -> Result<PartialPath<CS>, FindPathError> {
   // This assert can actually be triggered. This can happen if a user samples
   // points from one archipelago, but finds a path in a **different**
   // archipelago. This seems almost malicious though, so I don't think we should
@@ -200,13 +498,9 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
     "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
   );
 
-  for (type_index, cost) in override_type_index_costs.iter() {
-    if *cost <= 0.0 {
-      return Err(FindPathError::NonPositiveTypeIndexCost(*type_index, *cost));
-    }
-  }
+  check_override_type_index_costs(override_type_index_costs)?;
 
-  let Some(path) = pathfinding::find_path(
+  let path_result = pathfinding::find_path(
     &archipelago.nav_data,
     start_point.node_ref,
     CS::to_landmass(&start_point.point),
@@ -214,28 +508,38 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
     CS::to_landmass(&end_point.point),
     override_type_index_costs,
     permitted_animation_links,
-  )
-  .path
-  else {
-    return Err(FindPathError::NoPathFound);
-  };
+    permitted_type_indices,
+    /* agent_radius= */ 0.0,
+    allow_partial_path,
+  );
+  let (path, last_point, is_partial) =
+    match (path_result.path, path_result.partial_path) {
+      (Some(path), _) => (path, end_point.point(), false),
+      (None, Some(partial_path)) => {
+        let last_point = CS::from_landmass(&partial_path.end_point);
+        (partial_path, last_point, true)
+      }
+      (None, None) => return Err(FindPathError::NoPathFound),
+    };
 
   let mut current_index = PathIndex::from_corridor_index(0, 0);
   let mut current_point = CS::to_landmass(&start_point.point());
 
   let last_index = path.last_index();
-  let last_point = CS::to_landmass(&end_point.point());
 
   let mut path_points = vec![PathStep::Waypoint(start_point.point())];
-  if current_index == last_index {
-    path_points.push(PathStep::Waypoint(end_point.point.clone()));
-    return Ok(path_points);
+  let last_point = CS::to_landmass(&last_point);
+  if current_index == last_index && current_point == last_point {
+    path_points.push(PathStep::Waypoint(CS::from_landmass(&last_point)));
+    return Ok(PartialPath { steps: path_points, is_partial });
   }
 
-  // Keep looping until we reach the end index. If it's the last index, but the
+  // Keep looping until we reach the end point (waypoints that walk around
+  // obstacles may be on the last index). If it's the last index, but the
   // previous step was an animation link, run once more to get the waypoint to
   // the end point.
   while current_index != last_index
+    || current_point != last_point
     || matches!(path_points.last().unwrap(), PathStep::AnimationLink { .. })
   {
     let next_step;
@@ -245,6 +549,7 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
       current_point,
       last_index,
       last_point,
+      /* agent_radius= */ 0.0,
     );
     let next_path_step;
     (current_point, next_path_step) = match next_step {
@@ -269,7 +574,246 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
     path_points.push(next_path_step);
   }
 
-  Ok(path_points)
+  Ok(PartialPath { steps: path_points, is_partial })
+}
+
+/// A node that can be reached within a cost budget. See
+/// [`crate::Archipelago::find_reachable_nodes`].
+pub struct ReachableNode<'archipelago, CS: CoordinateSystem> {
+  /// The center of the node.
+  pub center: SampledPoint<'archipelago, CS>,
+  /// The cost of the cheapest path to reach the node.
+  pub cost: f32,
+}
+
+// Manual Clone impl for `ReachableNode` to avoid the Clone bound on CS.
+impl<CS: CoordinateSystem> Clone for ReachableNode<'_, CS> {
+  fn clone(&self) -> Self {
+    Self { center: self.center.clone(), cost: self.cost }
+  }
+}
+
+/// Checks that none of `override_type_index_costs` are non-positive.
+fn check_override_type_index_costs(
+  override_type_index_costs: &HashMap<usize, f32>,
+) -> Result<(), FindPathError> {
+  for (type_index, cost) in override_type_index_costs.iter() {
+    if *cost <= 0.0 {
+      return Err(FindPathError::NonPositiveTypeIndexCost(*type_index, *cost));
+    }
+  }
+  Ok(())
+}
+
+/// Finds every node that can be reached from `start_point` with a path cost of
+/// at most `max_cost`.
+pub(crate) fn find_reachable_nodes<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  start_point: &SampledPoint<'a, CS>,
+  max_cost: f32,
+  override_type_index_costs: &HashMap<usize, f32>,
+  permitted_animation_links: PermittedAnimationLinks,
+  permitted_type_indices: PermittedTypeIndices,
+) -> Result<Vec<ReachableNode<'a, CS>>, FindPathError> {
+  // See `find_path` for why this assert is fine.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+  check_override_type_index_costs(override_type_index_costs)?;
+
+  let mut node_costs = pathfinding::find_reachable_nodes(
+    &archipelago.nav_data,
+    start_point.node_ref,
+    CS::to_landmass(&start_point.point),
+    max_cost,
+    override_type_index_costs,
+    permitted_animation_links,
+    permitted_type_indices,
+    /* agent_radius= */ 0.0,
+  )
+  .into_iter()
+  .collect::<Vec<_>>();
+  // Sort the nodes so the result is deterministic.
+  node_costs.sort_by(|(left_node, left_cost), (right_node, right_cost)| {
+    left_cost.total_cmp(right_cost).then(left_node.cmp(right_node))
+  });
+
+  Ok(
+    node_costs
+      .into_iter()
+      .map(|(node_ref, cost)| {
+        let island =
+          archipelago.nav_data.get_island(node_ref.island_id).unwrap();
+        let center = island
+          .transform
+          .apply(island.nav_mesh.polygons[node_ref.polygon_index].center);
+        ReachableNode {
+          center: sampled_point_on_node(archipelago, center, node_ref),
+          cost,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Finds the cost of the cheapest path from `start_point` to each of
+/// `end_points` using a single search.
+pub(crate) fn find_path_costs<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  start_point: &SampledPoint<'a, CS>,
+  end_points: &[SampledPoint<'a, CS>],
+  override_type_index_costs: &HashMap<usize, f32>,
+  permitted_animation_links: PermittedAnimationLinks,
+  permitted_type_indices: PermittedTypeIndices,
+) -> Result<Vec<Option<f32>>, FindPathError> {
+  // See `find_path` for why this assert is fine.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+  check_override_type_index_costs(override_type_index_costs)?;
+
+  let ends = end_points
+    .iter()
+    .map(|end_point| (end_point.node_ref, CS::to_landmass(&end_point.point)))
+    .collect::<Vec<_>>();
+  Ok(pathfinding::find_path_costs(
+    &archipelago.nav_data,
+    start_point.node_ref,
+    CS::to_landmass(&start_point.point),
+    &ends,
+    override_type_index_costs,
+    permitted_animation_links,
+    permitted_type_indices,
+    /* agent_radius= */ 0.0,
+  ))
+}
+
+/// The result of casting a ray across the navigation meshes. See
+/// [`crate::Archipelago::raycast`].
+pub enum RaycastResult<'archipelago, CS: CoordinateSystem> {
+  /// The ray reached the end point without leaving the navigation meshes.
+  Reached,
+  /// The ray left the navigation meshes before reaching the end point.
+  Hit {
+    /// The point where the ray left the navigation meshes. This is on the last
+    /// node the ray crossed.
+    point: SampledPoint<'archipelago, CS>,
+    /// The normal of the edge that was hit, pointing away from the navigation
+    /// meshes.
+    normal: CS::Coordinate,
+  },
+}
+
+// Manual Clone impl for `RaycastResult` to avoid the Clone bound on CS.
+impl<CS: CoordinateSystem> Clone for RaycastResult<'_, CS> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Reached => Self::Reached,
+      Self::Hit { point, normal } => {
+        Self::Hit { point: point.clone(), normal: normal.clone() }
+      }
+    }
+  }
+}
+
+/// Casts a ray from `start_point` towards `end_point` along the navigation
+/// meshes, stopping at the first edge of the navigation meshes that the ray
+/// crosses.
+pub(crate) fn raycast<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  start_point: &SampledPoint<'a, CS>,
+  end_point: CS::Coordinate,
+) -> RaycastResult<'a, CS> {
+  // See `find_path` for why this assert is fine.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  let Some(hit) = archipelago.nav_data.raycast(
+    start_point.node_ref,
+    CS::to_landmass(&start_point.point),
+    CS::to_landmass(&end_point),
+  ) else {
+    return RaycastResult::Reached;
+  };
+
+  RaycastResult::Hit {
+    point: sampled_point_on_node(archipelago, hit.point, hit.node),
+    normal: CS::from_landmass(&hit.normal),
+  }
+}
+
+/// The nearest point on the boundary of the navigation meshes. See
+/// [`crate::Archipelago::find_distance_to_wall`].
+pub struct NearestWall<CS: CoordinateSystem> {
+  /// The nearest point on the boundary of the navigation meshes.
+  pub point: CS::Coordinate,
+  /// The horizontal distance to [`Self::point`].
+  pub distance: f32,
+  /// The normal of the boundary edge, pointing away from the navigation
+  /// meshes.
+  pub normal: CS::Coordinate,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for NearestWall<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("NearestWall")
+      .field("point", &self.point)
+      .field("distance", &self.distance)
+      .field("normal", &self.normal)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: Clone>> Clone for NearestWall<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      point: self.point.clone(),
+      distance: self.distance,
+      normal: self.normal.clone(),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for NearestWall<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.point == other.point
+      && self.distance == other.distance
+      && self.normal == other.normal
+  }
+}
+
+/// Finds the nearest point on the boundary of the navigation meshes to `point`
+/// within `max_radius`. Returns None if there is no boundary within
+/// `max_radius`.
+pub(crate) fn find_distance_to_wall<CS: CoordinateSystem>(
+  archipelago: &Archipelago<CS>,
+  point: &SampledPoint<'_, CS>,
+  max_radius: f32,
+) -> Option<NearestWall<CS>> {
+  // See `find_path` for why this assert is fine.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  let hit = archipelago.nav_data.find_nearest_wall(
+    point.node_ref,
+    CS::to_landmass(&point.point),
+    max_radius,
+  )?;
+  Some(NearestWall {
+    point: CS::from_landmass(&hit.point),
+    distance: hit.distance,
+    normal: CS::from_landmass(&hit.normal),
+  })
 }
 
 #[cfg(test)]
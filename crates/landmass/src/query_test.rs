@@ -1,17 +1,26 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
 
 use glam::Vec2;
 use googletest::{expect_that, matchers::*};
+use rand::{SeedableRng, rngs::SmallRng};
 
 use crate::{
-  Archipelago, ArchipelagoOptions, FindPathError, FromAgentRadius, Island,
-  NavigationMesh, PathStep, SamplePointError, Transform,
-  agent::PermittedAnimationLinks,
+  AddCostVolumeError, Archipelago, ArchipelagoOptions, CostVolume,
+  CostVolumeEffect, FindPathError, FromAgentRadius, Island, NavigationMesh,
+  NearestWall, Obstacle, ObstacleShape, PartialPath, PathStep,
+  RandomPointError, RaycastResult, SamplePointError, Transform,
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
   coords::{CorePointSampleDistance, XY},
   link::AnimationLink,
 };
 
-use super::{find_path, sample_point};
+use super::{
+  find_distance_to_wall, find_path, find_path_costs, find_reachable_nodes,
+  random_point, random_point_in_radius, raycast, sample_point,
+};
 
 #[test]
 fn error_on_dirty_nav_mesh() {
@@ -39,7 +48,8 @@ fn error_on_dirty_nav_mesh() {
     sample_point(
       &archipelago,
       /* point= */ Vec2::new(0.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&1.0)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&1.0),
+      &PermittedTypeIndices::All,
     )
     .map(|p| p.point()),
     Err(SamplePointError::NavDataDirty)
@@ -74,7 +84,8 @@ fn error_on_out_of_range() {
     sample_point(
       &archipelago,
       /* point= */ Vec2::new(-0.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
     )
     .map(|p| p.point()),
     Err(SamplePointError::OutOfRange)
@@ -104,7 +115,7 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
 
   let offset = Vec2::new(10.0, 10.0);
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -113,7 +124,8 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(-0.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.6)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.6),
+      &PermittedTypeIndices::All,
     )
     .map(|p| (p.island(), p.point())),
     Ok((island_id, offset + Vec2::new(0.0, 0.5)))
@@ -122,7 +134,8 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(0.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.6)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.6),
+      &PermittedTypeIndices::All,
     )
     .map(|p| (p.island(), p.point())),
     Ok((island_id, offset + Vec2::new(0.5, 0.5)))
@@ -131,7 +144,8 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(1.2, 1.2),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.6)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.6),
+      &PermittedTypeIndices::All,
     )
     .map(|p| (p.island(), p.point())),
     Ok((island_id, offset + Vec2::new(1.0, 1.0)))
@@ -176,7 +190,7 @@ fn samples_type_indices() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: 0.0, scale: 1.0 },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -185,7 +199,8 @@ fn samples_type_indices() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(0.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
     )
     .map(|p| p.type_index()),
     Ok(0)
@@ -194,7 +209,8 @@ fn samples_type_indices() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(1.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
     )
     .map(|p| p.type_index()),
     Ok(1)
@@ -203,7 +219,8 @@ fn samples_type_indices() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(2.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
     )
     .map(|p| p.type_index()),
     Ok(2)
@@ -212,7 +229,8 @@ fn samples_type_indices() {
     sample_point(
       &archipelago,
       /* point= */ offset + Vec2::new(3.5, 0.5),
-      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1)
+      /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
     )
     .map(|p| p.type_index()),
     Ok(3)
@@ -242,20 +260,32 @@ fn no_path() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: 0.0, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(2.0, 0.0),
+      rotation: 0.0,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   archipelago.update(1.0);
 
   let start_point = archipelago
-    .sample_point(offset + Vec2::new(0.5, 0.5), &1e-5)
+    .sample_point(
+      offset + Vec2::new(0.5, 0.5),
+      &1e-5,
+      PermittedTypeIndices::All,
+    )
     .expect("point is on nav mesh.");
   let end_point = archipelago
-    .sample_point(offset + Vec2::new(2.5, 0.5), &1e-5)
+    .sample_point(
+      offset + Vec2::new(2.5, 0.5),
+      &1e-5,
+      PermittedTypeIndices::All,
+    )
     .expect("point is on nav mesh.");
   assert_eq!(
     find_path(
@@ -263,7 +293,9 @@ fn no_path() {
       &start_point,
       &end_point,
       &HashMap::new(),
-      PermittedAnimationLinks::All
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ false,
     ),
     Err(FindPathError::NoPathFound)
   );
@@ -292,24 +324,40 @@ fn finds_path() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: 0.0, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(1.0, 0.0),
+      rotation: 0.0,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(2.0, 0.5), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(2.0, 0.5),
+      rotation: 0.0,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   archipelago.update(1.0);
 
   let start_point = archipelago
-    .sample_point(offset + Vec2::new(0.5, 0.5), &1e-5)
+    .sample_point(
+      offset + Vec2::new(0.5, 0.5),
+      &1e-5,
+      PermittedTypeIndices::All,
+    )
     .expect("point is on nav mesh.");
   let end_point = archipelago
-    .sample_point(offset + Vec2::new(2.5, 1.25), &1e-5)
+    .sample_point(
+      offset + Vec2::new(2.5, 1.25),
+      &1e-5,
+      PermittedTypeIndices::All,
+    )
     .expect("point is on nav mesh.");
   assert_eq!(
     find_path(
@@ -317,8 +365,11 @@ fn finds_path() {
       &start_point,
       &end_point,
       &HashMap::new(),
-      PermittedAnimationLinks::All
-    ),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ false,
+    )
+    .map(|path| path.steps),
     Ok(vec![
       PathStep::Waypoint(offset + Vec2::new(0.5, 0.5)),
       PathStep::Waypoint(offset + Vec2::new(2.0, 1.0)),
@@ -386,12 +437,14 @@ fn finds_path_with_override_type_index_costs() {
     &archipelago,
     Vec2::new(0.5, 0.5),
     /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
   )
   .unwrap();
   let end_point = sample_point(
     &archipelago,
     Vec2::new(0.5, 11.5),
     /* distance_to_node= */ &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
   )
   .unwrap();
 
@@ -401,11 +454,13 @@ fn finds_path_with_override_type_index_costs() {
     &end_point,
     &HashMap::from([(1, 10.0)]),
     PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* allow_partial_path= */ false,
   )
   .expect("Path found");
 
   assert_eq!(
-    path,
+    path.steps,
     [
       PathStep::Waypoint(Vec2::new(0.5, 0.5)),
       PathStep::Waypoint(Vec2::new(2.0, 1.0)),
@@ -441,10 +496,18 @@ fn find_path_returns_error_on_invalid_node_cost() {
   archipelago.update(1.0);
 
   let start_point = archipelago
-    .sample_point(Vec2::new(0.25, 0.25), /* distance_to_node= */ &0.1)
+    .sample_point(
+      Vec2::new(0.25, 0.25),
+      /* distance_to_node= */ &0.1,
+      PermittedTypeIndices::All,
+    )
     .unwrap();
   let end_point = archipelago
-    .sample_point(Vec2::new(0.25, 0.25), /* distance_to_node= */ &0.1)
+    .sample_point(
+      Vec2::new(0.25, 0.25),
+      /* distance_to_node= */ &0.1,
+      PermittedTypeIndices::All,
+    )
     .unwrap();
 
   assert_eq!(
@@ -454,6 +517,8 @@ fn find_path_returns_error_on_invalid_node_cost() {
       &end_point,
       &HashMap::from([(0, 0.0)]),
       PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ false,
     ),
     Err(FindPathError::NonPositiveTypeIndexCost(0, 0.0))
   );
@@ -464,6 +529,8 @@ fn find_path_returns_error_on_invalid_node_cost() {
       &end_point,
       &HashMap::from([(0, -0.5)]),
       PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ false,
     ),
     Err(FindPathError::NonPositiveTypeIndexCost(0, -0.5))
   );
@@ -497,15 +564,19 @@ fn start_and_end_in_same_node() {
   let start_point = Vec2::new(0.25, 0.25);
   let end_point = Vec2::new(0.75, 0.75);
 
-  let start_sampled_point =
-    archipelago.sample_point(start_point, &0.1).unwrap();
-  let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  let start_sampled_point = archipelago
+    .sample_point(start_point, &0.1, PermittedTypeIndices::All)
+    .unwrap();
+  let end_sampled_point = archipelago
+    .sample_point(end_point, &0.1, PermittedTypeIndices::All)
+    .unwrap();
   let path = archipelago
     .find_path(
       &start_sampled_point,
       &end_sampled_point,
       &HashMap::default(),
       PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
     )
     .unwrap();
 
@@ -556,6 +627,8 @@ fn one_animation_link_path() {
     cost: 1.0,
     kind: 0,
     bidirectional: false,
+    capacity: None,
+    busy_cost: None,
   });
 
   archipelago.update(1.0);
@@ -563,15 +636,19 @@ fn one_animation_link_path() {
   let start_point = Vec2::new(0.25, 0.25);
   let end_point = Vec2::new(0.75, 2.75);
 
-  let start_sampled_point =
-    archipelago.sample_point(start_point, &0.1).unwrap();
-  let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  let start_sampled_point = archipelago
+    .sample_point(start_point, &0.1, PermittedTypeIndices::All)
+    .unwrap();
+  let end_sampled_point = archipelago
+    .sample_point(end_point, &0.1, PermittedTypeIndices::All)
+    .unwrap();
   let path = archipelago
     .find_path(
       &start_sampled_point,
       &end_sampled_point,
       &HashMap::default(),
       PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
     )
     .unwrap();
 
@@ -588,3 +665,1179 @@ fn one_animation_link_path() {
     )
   );
 }
+
+#[test]
+fn finds_partial_path_to_closest_point() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  archipelago.update(1.0);
+
+  let start_point = archipelago
+    .sample_point(Vec2::new(0.5, 0.5), &1e-5, PermittedTypeIndices::All)
+    .expect("point is on nav mesh.");
+  let reachable_point = archipelago
+    .sample_point(Vec2::new(0.75, 0.25), &1e-5, PermittedTypeIndices::All)
+    .expect("point is on nav mesh.");
+  let unreachable_point = archipelago
+    .sample_point(Vec2::new(2.5, 0.25), &1e-5, PermittedTypeIndices::All)
+    .expect("point is on nav mesh.");
+
+  assert_eq!(
+    find_path(
+      &archipelago,
+      &start_point,
+      &reachable_point,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ true,
+    ),
+    Ok(PartialPath {
+      steps: vec![
+        PathStep::Waypoint(Vec2::new(0.5, 0.5)),
+        PathStep::Waypoint(Vec2::new(0.75, 0.25)),
+      ],
+      is_partial: false,
+    })
+  );
+  assert_eq!(
+    find_path(
+      &archipelago,
+      &start_point,
+      &unreachable_point,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ true,
+    ),
+    Ok(PartialPath {
+      steps: vec![
+        PathStep::Waypoint(Vec2::new(0.5, 0.5)),
+        PathStep::Waypoint(Vec2::new(1.0, 0.25)),
+      ],
+      is_partial: true,
+    })
+  );
+}
+
+/// Creates an archipelago with a row of four connected unit squares, and a
+/// disconnected unit square.
+fn row_archipelago() -> Archipelago<XY> {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(4.0, 1.0),
+        //
+        Vec2::new(5.0, 0.0),
+        Vec2::new(6.0, 0.0),
+        Vec2::new(6.0, 1.0),
+        Vec2::new(5.0, 1.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 6, 5],
+        vec![1, 2, 7, 6],
+        vec![2, 3, 8, 7],
+        vec![3, 4, 9, 8],
+        vec![10, 11, 12, 13],
+      ],
+      polygon_type_indices: vec![0, 0, 1, 0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+  archipelago
+}
+
+#[test]
+fn finds_reachable_nodes_within_max_cost() {
+  let archipelago = row_archipelago();
+
+  let start_point = sample_point(
+    &archipelago,
+    Vec2::new(0.5, 0.5),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .unwrap();
+
+  let reachable_nodes = |max_cost, override_type_index_costs| {
+    find_reachable_nodes(
+      &archipelago,
+      &start_point,
+      max_cost,
+      &override_type_index_costs,
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    )
+    .expect("costs are valid")
+    .into_iter()
+    .map(|node| (node.center.point(), node.cost))
+    .collect::<Vec<_>>()
+  };
+
+  assert_eq!(
+    reachable_nodes(2.0, HashMap::new()),
+    [
+      (Vec2::new(0.5, 0.5), 0.0),
+      (Vec2::new(1.5, 0.5), 0.5),
+      (Vec2::new(2.5, 0.5), 1.5),
+    ]
+  );
+  assert_eq!(
+    reachable_nodes(10.0, HashMap::new()),
+    [
+      (Vec2::new(0.5, 0.5), 0.0),
+      (Vec2::new(1.5, 0.5), 0.5),
+      (Vec2::new(2.5, 0.5), 1.5),
+      (Vec2::new(3.5, 0.5), 2.5),
+    ]
+  );
+  // Crossing the third node is now much more expensive.
+  assert_eq!(
+    reachable_nodes(10.0, HashMap::from([(1, 8.0)])),
+    [
+      (Vec2::new(0.5, 0.5), 0.0),
+      (Vec2::new(1.5, 0.5), 0.5),
+      (Vec2::new(2.5, 0.5), 1.5),
+      (Vec2::new(3.5, 0.5), 9.5),
+    ]
+  );
+
+  assert_eq!(
+    find_reachable_nodes(
+      &archipelago,
+      &start_point,
+      10.0,
+      &HashMap::from([(1, 0.0)]),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    )
+    .map(|nodes| nodes.len()),
+    Err(FindPathError::NonPositiveTypeIndexCost(1, 0.0))
+  );
+}
+
+#[test]
+fn finds_path_costs_to_each_end_point() {
+  let archipelago = row_archipelago();
+
+  let sample = |point| {
+    sample_point(
+      &archipelago,
+      point,
+      &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
+    )
+    .unwrap()
+  };
+  let start_point = sample(Vec2::new(0.5, 0.5));
+  let end_points = [
+    sample(Vec2::new(3.5, 0.5)),
+    // This end point is disconnected.
+    sample(Vec2::new(5.5, 0.5)),
+    sample(Vec2::new(1.5, 0.5)),
+    sample(Vec2::new(0.5, 0.25)),
+  ];
+
+  assert_eq!(
+    find_path_costs(
+      &archipelago,
+      &start_point,
+      &end_points,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    ),
+    Ok(vec![Some(3.0), None, Some(1.0), Some(0.25)])
+  );
+  assert_eq!(
+    find_path_costs(
+      &archipelago,
+      &start_point,
+      &end_points,
+      &HashMap::from([(1, 2.0)]),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    ),
+    Ok(vec![Some(4.0), None, Some(1.0), Some(0.25)])
+  );
+  assert_eq!(
+    find_path_costs(
+      &archipelago,
+      &start_point,
+      &[],
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    ),
+    Ok(vec![])
+  );
+}
+
+#[test]
+fn raycast_reaches_end_point_or_hits_edge() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(1.0, 2.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4], vec![5, 4, 7, 6]],
+      polygon_type_indices: vec![0, 1, 2],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+
+  let sample = |point| {
+    sample_point(
+      &archipelago,
+      point,
+      &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
+    )
+    .expect("point is on nav mesh")
+  };
+
+  // Stays within a single node.
+  assert!(matches!(
+    raycast(&archipelago, &sample(Vec2::new(0.25, 0.25)), Vec2::new(0.75, 0.5)),
+    RaycastResult::Reached
+  ));
+  // Crosses through all three nodes.
+  assert!(matches!(
+    raycast(&archipelago, &sample(Vec2::new(1.5, 0.5)), Vec2::new(0.25, 1.5)),
+    RaycastResult::Reached
+  ));
+
+  let RaycastResult::Hit { point, normal } =
+    raycast(&archipelago, &sample(Vec2::new(0.5, 1.5)), Vec2::new(1.5, 1.5))
+  else {
+    panic!("Expected the ray to hit an edge.");
+  };
+  assert_eq!(point.point(), Vec2::new(1.0, 1.5));
+  assert_eq!(point.island(), island_id);
+  assert_eq!(point.type_index(), 2);
+  assert_eq!(normal, Vec2::new(1.0, 0.0));
+
+  let RaycastResult::Hit { point, normal } =
+    raycast(&archipelago, &sample(Vec2::new(0.5, 0.5)), Vec2::new(2.5, 0.5))
+  else {
+    panic!("Expected the ray to hit an edge.");
+  };
+  // The ray passes through the first node and hits the far edge of the second
+  // node.
+  assert_eq!(point.point(), Vec2::new(2.0, 0.5));
+  assert_eq!(point.type_index(), 1);
+  assert_eq!(normal, Vec2::new(1.0, 0.0));
+}
+
+#[test]
+fn raycast_crosses_boundary_links() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let island_2 = archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  archipelago.update(1.0);
+
+  let start_point = sample_point(
+    &archipelago,
+    Vec2::new(0.5, 0.5),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+
+  assert!(matches!(
+    raycast(&archipelago, &start_point, Vec2::new(1.5, 0.75)),
+    RaycastResult::Reached
+  ));
+
+  let RaycastResult::Hit { point, normal } =
+    raycast(&archipelago, &start_point, Vec2::new(2.5, 0.5))
+  else {
+    panic!("Expected the ray to hit an edge.");
+  };
+  assert_eq!(point.point(), Vec2::new(2.0, 0.5));
+  assert_eq!(point.island(), island_2);
+  assert_eq!(normal, Vec2::new(1.0, 0.0));
+}
+
+#[test]
+fn finds_distance_to_wall() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 4.0),
+        Vec2::new(2.0, 4.0),
+        Vec2::new(0.0, 4.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+
+  let point = sample_point(
+    &archipelago,
+    Vec2::new(3.0, 1.5),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+
+  assert_eq!(
+    find_distance_to_wall(&archipelago, &point, 2.0),
+    Some(NearestWall {
+      point: Vec2::new(4.0, 1.5),
+      distance: 1.0,
+      normal: Vec2::new(1.0, 0.0),
+    })
+  );
+  assert_eq!(find_distance_to_wall(&archipelago, &point, 0.5), None);
+
+  let point = sample_point(
+    &archipelago,
+    Vec2::new(2.5, 3.5),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+  // The internal edge between the two polygons is not a wall.
+  assert_eq!(
+    find_distance_to_wall(&archipelago, &point, 2.0),
+    Some(NearestWall {
+      point: Vec2::new(2.5, 4.0),
+      distance: 0.5,
+      normal: Vec2::new(0.0, 1.0),
+    })
+  );
+}
+
+#[googletest::test]
+fn distance_to_wall_ignores_boundary_links() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(0.0, 2.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0, scale: 1.0 },
+    nav_mesh,
+  ));
+  archipelago.update(1.0);
+
+  let point = sample_point(
+    &archipelago,
+    Vec2::new(1.75, 0.5),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+
+  let nearest_wall =
+    find_distance_to_wall(&archipelago, &point, 1.0).expect("wall is in range");
+  expect_that!(nearest_wall.point.x, approx_eq(1.75));
+  expect_that!(nearest_wall.point.y, approx_eq(0.0));
+  expect_that!(nearest_wall.distance, approx_eq(0.5));
+  expect_that!(nearest_wall.normal.x, approx_eq(0.0));
+  expect_that!(nearest_wall.normal.y, approx_eq(-1.0));
+}
+
+#[googletest::test]
+fn distance_to_wall_includes_obstacle_holes() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 2.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(0.0, 2.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // The obstacle is beside the query point, in the next node.
+  let obstacle_id = archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(2.5, 0.5),
+      Vec2::new(3.5, 0.5),
+      Vec2::new(3.5, 1.5),
+      Vec2::new(2.5, 1.5),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  let point = sample_point(
+    &archipelago,
+    Vec2::new(2.25, 1.0),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+  let nearest_wall =
+    find_distance_to_wall(&archipelago, &point, 2.0).expect("wall is in range");
+  expect_that!(nearest_wall.point.x, approx_eq(2.5));
+  expect_that!(nearest_wall.point.y, approx_eq(1.0));
+  expect_that!(nearest_wall.distance, approx_eq(0.25));
+  expect_that!(nearest_wall.normal.x, approx_eq(1.0));
+  expect_that!(nearest_wall.normal.y, approx_eq(0.0));
+
+  // The obstacle now covers the entire second node, so the edge between the
+  // nodes is a wall, and nothing beyond it is considered.
+  archipelago.remove_obstacle(obstacle_id);
+  archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(1.75, -1.0),
+      Vec2::new(5.0, -1.0),
+      Vec2::new(5.0, 3.0),
+      Vec2::new(1.75, 3.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  let point = sample_point(
+    &archipelago,
+    Vec2::new(1.25, 1.0),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+  let nearest_wall =
+    find_distance_to_wall(&archipelago, &point, 2.0).expect("wall is in range");
+  expect_that!(nearest_wall.point.x, approx_eq(1.75));
+  expect_that!(nearest_wall.point.y, approx_eq(1.0));
+  expect_that!(nearest_wall.distance, approx_eq(0.5));
+  expect_that!(nearest_wall.normal.x, approx_eq(1.0));
+  expect_that!(nearest_wall.normal.y, approx_eq(0.0));
+}
+
+#[test]
+fn random_point_is_area_weighted_and_filtered_by_type_index() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 1],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  assert_eq!(
+    random_point(
+      &archipelago,
+      &PermittedTypeIndices::All,
+      &mut SmallRng::seed_from_u64(0)
+    )
+    .map(|p| p.point()),
+    Err(RandomPointError::NavDataDirty)
+  );
+  archipelago.update(1.0);
+
+  let mut rng = SmallRng::seed_from_u64(1234);
+  let mut type_index_counts = [0; 2];
+  for _ in 0..1000 {
+    let point =
+      random_point(&archipelago, &PermittedTypeIndices::All, &mut rng)
+        .expect("nav mesh has nodes");
+    let point_value = point.point();
+    assert!(
+      (0.0..=4.0).contains(&point_value.x)
+        && (0.0..=1.0).contains(&point_value.y),
+      "{point_value:?}"
+    );
+    type_index_counts[point.type_index()] += 1;
+  }
+  // The second node is three times larger than the first.
+  assert!((200..300).contains(&type_index_counts[0]), "{type_index_counts:?}");
+
+  let type_indices =
+    PermittedTypeIndices::Include(Arc::new(HashSet::from([0])));
+  for _ in 0..100 {
+    let point = random_point(&archipelago, &type_indices, &mut rng)
+      .expect("nav mesh has nodes");
+    assert_eq!(point.type_index(), 0);
+    assert!(point.point().x <= 1.0, "{:?}", point.point());
+  }
+
+  assert_eq!(
+    random_point(
+      &archipelago,
+      &PermittedTypeIndices::Include(Arc::new(HashSet::from([2]))),
+      &mut rng
+    )
+    .map(|p| p.point()),
+    Err(RandomPointError::NoNodes)
+  );
+}
+
+#[test]
+fn random_point_avoids_obstacles() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // The obstacle covers most of the nav mesh, leaving equal areas at each
+  // end.
+  let obstacle_id = archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(0.5, -1.0),
+      Vec2::new(3.5, -1.0),
+      Vec2::new(3.5, 2.0),
+      Vec2::new(0.5, 2.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  let mut rng = SmallRng::seed_from_u64(1234);
+  let mut left_count = 0;
+  for _ in 0..1000 {
+    let point =
+      random_point(&archipelago, &PermittedTypeIndices::All, &mut rng)
+        .expect("nav mesh has walkable parts")
+        .point();
+    assert!(point.x <= 0.5 || point.x >= 3.5, "{point:?}");
+    if point.x <= 0.5 {
+      left_count += 1;
+    }
+  }
+  // The walkable parts are picked by their area, not the area of their nodes.
+  assert!((450..550).contains(&left_count), "{left_count}");
+
+  // The obstacle now covers the entire second node.
+  archipelago.remove_obstacle(obstacle_id);
+  archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(0.5, -1.0),
+      Vec2::new(5.0, -1.0),
+      Vec2::new(5.0, 2.0),
+      Vec2::new(0.5, 2.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  for _ in 0..100 {
+    let point =
+      random_point(&archipelago, &PermittedTypeIndices::All, &mut rng)
+        .expect("nav mesh has walkable parts")
+        .point();
+    assert!(
+      (0.0..=0.5).contains(&point.x) && (0.0..=1.0).contains(&point.y),
+      "{point:?}"
+    );
+  }
+}
+#[test]
+fn random_point_in_radius_is_reachable_and_in_radius() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 4.0),
+        Vec2::new(0.0, 4.0),
+        Vec2::new(5.0, 0.0),
+        Vec2::new(9.0, 0.0),
+        Vec2::new(9.0, 4.0),
+        Vec2::new(5.0, 4.0),
+      ],
+      // The two polygons are not connected.
+      polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
+      polygon_type_indices: vec![0, 0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+
+  let start_point = sample_point(
+    &archipelago,
+    Vec2::new(3.5, 2.0),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+
+  let mut rng = SmallRng::seed_from_u64(1234);
+  for _ in 0..100 {
+    let point = random_point_in_radius(
+      &archipelago,
+      &start_point,
+      /* radius= */ 2.0,
+      &PermittedTypeIndices::All,
+      PermittedAnimationLinks::All,
+      &mut rng,
+    )
+    .expect("start node is in range")
+    .point();
+    assert!(point.x <= 4.0, "{point:?}");
+    assert!(point.distance(Vec2::new(3.5, 2.0)) <= 2.0, "{point:?}");
+  }
+
+  assert_eq!(
+    random_point_in_radius(
+      &archipelago,
+      &start_point,
+      /* radius= */ 2.0,
+      &PermittedTypeIndices::Include(Arc::new(HashSet::from([1]))),
+      PermittedAnimationLinks::All,
+      &mut rng,
+    )
+    .map(|p| p.point()),
+    Err(RandomPointError::NoNodes)
+  );
+}
+
+#[test]
+fn random_point_in_radius_is_uniform_in_large_node() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(100.0, 0.0),
+        Vec2::new(100.0, 100.0),
+        Vec2::new(0.0, 100.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // The obstacle covers the left half of the circle.
+  archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(40.0, 40.0),
+      Vec2::new(50.0, 40.0),
+      Vec2::new(50.0, 60.0),
+      Vec2::new(40.0, 60.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  let start_point = sample_point(
+    &archipelago,
+    Vec2::new(50.0, 50.0),
+    &CorePointSampleDistance::new(&0.1),
+    &PermittedTypeIndices::All,
+  )
+  .expect("point is on nav mesh");
+
+  let mut rng = SmallRng::seed_from_u64(1234);
+  let mut above_count = 0;
+  for _ in 0..1000 {
+    let point = random_point_in_radius(
+      &archipelago,
+      &start_point,
+      /* radius= */ 2.0,
+      &PermittedTypeIndices::All,
+      PermittedAnimationLinks::All,
+      &mut rng,
+    )
+    .expect("start node is in range")
+    .point();
+    assert!(point.x >= 50.0, "{point:?}");
+    assert!(point.distance(Vec2::new(50.0, 50.0)) <= 2.0, "{point:?}");
+    if point.y > 50.0 {
+      above_count += 1;
+    }
+  }
+  // The remaining half circle is split evenly.
+  assert!((450..550).contains(&above_count), "{above_count}");
+}
+
+#[test]
+fn obstacles_block_paths_until_moved_or_removed() {
+  let mut archipelago = row_archipelago();
+  let obstacle_id = archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(1.25, -1.0),
+      Vec2::new(1.75, -1.0),
+      Vec2::new(1.75, 2.0),
+      Vec2::new(1.25, 2.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  let find_path_steps = |archipelago: &Archipelago<XY>| {
+    let start_point = archipelago
+      .sample_point(Vec2::new(0.5, 0.5), &1e-5, PermittedTypeIndices::All)
+      .unwrap();
+    let end_point = archipelago
+      .sample_point(Vec2::new(3.5, 0.5), &1e-5, PermittedTypeIndices::All)
+      .unwrap();
+    find_path(
+      archipelago,
+      &start_point,
+      &end_point,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ false,
+    )
+    .map(|path| path.steps.len())
+  };
+
+  // The obstacle cuts the row in half.
+  assert_eq!(find_path_steps(&archipelago), Err(FindPathError::NoPathFound));
+
+  // Leave a gap at the bottom of the row.
+  archipelago.get_obstacle_mut(obstacle_id).unwrap().transform.translation =
+    Vec2::new(0.0, 1.5);
+  archipelago.update(1.0);
+  assert!(find_path_steps(&archipelago).is_ok());
+
+  // Moving the obstacle back blocks the path again, and removing it restores
+  // the path.
+  archipelago.get_obstacle_mut(obstacle_id).unwrap().transform.translation =
+    Vec2::ZERO;
+  archipelago.update(1.0);
+  assert_eq!(find_path_steps(&archipelago), Err(FindPathError::NoPathFound));
+
+  archipelago.remove_obstacle(obstacle_id);
+  archipelago.update(1.0);
+  assert!(find_path_steps(&archipelago).is_ok());
+}
+
+#[test]
+fn obstacles_cut_holes_in_partially_covered_nodes() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(0.0, 10.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.add_obstacle(Obstacle {
+    shape: ObstacleShape::Polygon(vec![
+      Vec2::new(4.0, 3.0),
+      Vec2::new(6.0, 3.0),
+      Vec2::new(6.0, 7.0),
+      Vec2::new(4.0, 7.0),
+    ]),
+    transform: Transform::default(),
+    height: 1.0,
+  });
+  archipelago.update(1.0);
+
+  let sample = |point| {
+    sample_point(
+      &archipelago,
+      point,
+      &CorePointSampleDistance::new(&0.1),
+      &PermittedTypeIndices::All,
+    )
+  };
+  let find_path_steps = |start_point, end_point| {
+    find_path(
+      &archipelago,
+      &sample(start_point).unwrap(),
+      &sample(end_point).unwrap(),
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+      /* allow_partial_path= */ false,
+    )
+    .map(|path| path.steps)
+  };
+
+  // The path walks around the obstacle instead of through it.
+  assert_eq!(
+    find_path_steps(Vec2::new(1.0, 6.0), Vec2::new(9.0, 6.0)),
+    Ok(vec![
+      PathStep::Waypoint(Vec2::new(1.0, 6.0)),
+      PathStep::Waypoint(Vec2::new(4.0, 7.0)),
+      PathStep::Waypoint(Vec2::new(6.0, 7.0)),
+      PathStep::Waypoint(Vec2::new(9.0, 6.0)),
+    ])
+  );
+  assert_eq!(
+    find_path_steps(Vec2::new(1.0, 5.0), Vec2::new(9.0, 5.0))
+      .map(|steps| steps.len()),
+    Ok(4)
+  );
+  // Paths that don't cross the obstacle are still straight.
+  assert_eq!(
+    find_path_steps(Vec2::new(1.0, 1.0), Vec2::new(9.0, 2.0)),
+    Ok(vec![
+      PathStep::Waypoint(Vec2::new(1.0, 1.0)),
+      PathStep::Waypoint(Vec2::new(9.0, 2.0)),
+    ])
+  );
+
+  // Points in the hole are not on the nav mesh, unless they are close enough
+  // to the edge of the hole.
+  assert_eq!(
+    sample(Vec2::new(5.0, 5.0)).map(|point| point.point()),
+    Err(SamplePointError::OutOfRange)
+  );
+  assert_eq!(
+    sample_point(
+      &archipelago,
+      Vec2::new(4.25, 5.0),
+      &CorePointSampleDistance::new(&0.5),
+      &PermittedTypeIndices::All,
+    )
+    .map(|point| point.point()),
+    Ok(Vec2::new(4.0, 5.0))
+  );
+
+  let RaycastResult::Hit { point, normal } = raycast(
+    &archipelago,
+    &sample(Vec2::new(1.0, 5.0)).unwrap(),
+    Vec2::new(9.0, 5.0),
+  ) else {
+    panic!("Expected the ray to hit the obstacle.");
+  };
+  assert_eq!(point.point(), Vec2::new(4.0, 5.0));
+  assert_eq!(normal, Vec2::new(1.0, 0.0));
+  assert!(matches!(
+    raycast(
+      &archipelago,
+      &sample(Vec2::new(1.0, 1.0)).unwrap(),
+      Vec2::new(9.0, 2.0)
+    ),
+    RaycastResult::Reached
+  ));
+
+  assert_eq!(
+    find_distance_to_wall(
+      &archipelago,
+      &sample(Vec2::new(3.0, 5.0)).unwrap(),
+      2.0
+    ),
+    Some(NearestWall {
+      point: Vec2::new(4.0, 5.0),
+      distance: 1.0,
+      normal: Vec2::new(1.0, 0.0),
+    })
+  );
+}
+
+#[test]
+fn cost_volumes_change_path_costs() {
+  let mut archipelago = row_archipelago();
+  let square = |min: Vec2, max: Vec2| {
+    ObstacleShape::Polygon(vec![
+      min,
+      Vec2::new(max.x, min.y),
+      max,
+      Vec2::new(min.x, max.y),
+    ])
+  };
+  let multiplier_volume = archipelago
+    .add_cost_volume(CostVolume {
+      shape: square(Vec2::new(1.25, 0.25), Vec2::new(1.75, 0.75)),
+      transform: Transform::default(),
+      height: 1.0,
+      effect: CostVolumeEffect::CostMultiplier(2.0),
+    })
+    .unwrap();
+  archipelago
+    .add_cost_volume(CostVolume {
+      shape: square(Vec2::new(3.25, 0.25), Vec2::new(3.75, 0.75)),
+      transform: Transform::default(),
+      height: 1.0,
+      effect: CostVolumeEffect::TypeIndex(5),
+    })
+    .unwrap();
+  archipelago.set_type_index_cost(5, 3.0).unwrap();
+  archipelago.update(1.0);
+
+  let path_costs = |archipelago: &Archipelago<XY>| {
+    let start_point = archipelago
+      .sample_point(Vec2::new(0.5, 0.5), &1e-5, PermittedTypeIndices::All)
+      .unwrap();
+    let end_points = [
+      archipelago
+        .sample_point(Vec2::new(2.5, 0.5), &1e-5, PermittedTypeIndices::All)
+        .unwrap(),
+      archipelago
+        .sample_point(Vec2::new(3.5, 0.5), &1e-5, PermittedTypeIndices::All)
+        .unwrap(),
+    ];
+    find_path_costs(
+      archipelago,
+      &start_point,
+      &end_points,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    )
+  };
+
+  // The whole node overlapping a volume is affected, not just the part
+  // covered by the volume.
+  assert_eq!(path_costs(&archipelago), Ok(vec![Some(3.0), Some(5.0)]));
+  assert_eq!(
+    archipelago
+      .sample_point(Vec2::new(3.9, 0.9), &1e-5, PermittedTypeIndices::All)
+      .map(|point| point.type_index()),
+    Ok(5)
+  );
+
+  archipelago.remove_cost_volume(multiplier_volume);
+  archipelago.update(1.0);
+  assert_eq!(path_costs(&archipelago), Ok(vec![Some(2.0), Some(4.0)]));
+
+  assert_eq!(
+    archipelago.add_cost_volume(CostVolume {
+      shape: ObstacleShape::Circle { radius: 1.0 },
+      transform: Transform::default(),
+      height: 1.0,
+      effect: CostVolumeEffect::CostMultiplier(0.0),
+    }),
+    Err(AddCostVolumeError::NonPositiveCostMultiplier(0.0))
+  );
+  assert_eq!(
+    archipelago.add_cost_volume(CostVolume {
+      shape: ObstacleShape::Circle { radius: 1.0 },
+      transform: Transform {
+        translation: Vec2::ZERO,
+        rotation: 0.0,
+        scale: -1.0,
+      },
+      height: 1.0,
+      effect: CostVolumeEffect::CostMultiplier(2.0),
+    }),
+    Err(AddCostVolumeError::NonPositiveScale(-1.0))
+  );
+}
+
+#[test]
+fn permitted_type_indices_filter_paths_and_sampling() {
+  let archipelago = row_archipelago();
+  let no_water = PermittedTypeIndices::Exclude(Arc::new(HashSet::from([1])));
+
+  let start_point = archipelago
+    .sample_point(Vec2::new(0.5, 0.5), &1e-5, no_water.clone())
+    .unwrap();
+  let end_point = archipelago
+    .sample_point(Vec2::new(3.5, 0.5), &1e-5, no_water.clone())
+    .unwrap();
+
+  // The only way to the end point is through the node with type index 1.
+  assert_eq!(
+    find_path(
+      &archipelago,
+      &start_point,
+      &end_point,
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      no_water.clone(),
+      /* allow_partial_path= */ false,
+    ),
+    Err(FindPathError::NoPathFound)
+  );
+  assert_eq!(
+    find_path_costs(
+      &archipelago,
+      &start_point,
+      std::slice::from_ref(&end_point),
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::Include(Arc::new(HashSet::from([0]))),
+    ),
+    Ok(vec![None])
+  );
+  assert_eq!(
+    find_path_costs(
+      &archipelago,
+      &start_point,
+      std::slice::from_ref(&end_point),
+      &HashMap::new(),
+      PermittedAnimationLinks::All,
+      PermittedTypeIndices::All,
+    ),
+    Ok(vec![Some(3.0)])
+  );
+
+  // Sampling skips the forbidden node, and picks the nearest permitted node
+  // instead.
+  let sampled_point = archipelago
+    .sample_point(Vec2::new(2.25, 0.5), &0.5, no_water.clone())
+    .unwrap();
+  assert_eq!(sampled_point.point(), Vec2::new(2.0, 0.5));
+  assert_eq!(sampled_point.type_index(), 0);
+  assert_eq!(
+    archipelago
+      .sample_point(Vec2::new(2.5, 0.5), &0.1, no_water)
+      .map(|point| point.point()),
+    Err(SamplePointError::OutOfRange)
+  );
+}
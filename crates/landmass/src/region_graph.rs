@@ -0,0 +1,330 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+
+use crate::{
+  CoordinateSystem, IslandId,
+  agent::PermittedAnimationLinks,
+  astar::{self, AStarProblem, SearchStatus},
+  nav_data::{KindedOffMeshLink, NavigationData, NodeRef, OffMeshLinkId},
+};
+
+/// A cluster of the region graph: a region of an island (a connected group of
+/// nodes in that island's nav mesh).
+pub(crate) type ClusterId = (IslandId, usize);
+
+/// A coarse graph of the navigation data for hierarchical pathfinding. Each
+/// cluster is a region of an island, and clusters are connected by the off
+/// mesh links (boundary links and animation links) between them. Searching
+/// this graph first selects a corridor of clusters, so the fine-grained search
+/// only needs to explore the nodes in that corridor.
+pub(crate) struct RegionGraph {
+  /// The off mesh links that leave each cluster.
+  cluster_to_links: HashMap<ClusterId, Vec<OffMeshLinkId>>,
+  /// The region graph data of each off mesh link.
+  links: HashMap<OffMeshLinkId, RegionLink>,
+}
+
+/// An off mesh link in the region graph.
+struct RegionLink {
+  /// The midpoint of the link's portal on the source node.
+  portal_point: Vec3,
+  /// The midpoint of the link's portal on the destination node.
+  destination_point: Vec3,
+  /// The cluster that the link leads to.
+  destination_cluster: ClusterId,
+}
+
+impl RegionGraph {
+  /// Creates the region graph for `nav_data`. Disabled links are still
+  /// included, since they are skipped when searching instead.
+  pub(crate) fn new<CS: CoordinateSystem>(
+    nav_data: &NavigationData<CS>,
+  ) -> Self {
+    let mut cluster_to_links = HashMap::<ClusterId, Vec<OffMeshLinkId>>::new();
+    let mut links = HashMap::new();
+    for (&node_ref, link_ids) in nav_data.node_to_off_mesh_link_ids.iter() {
+      let cluster = nav_data.node_to_region_id(node_ref);
+      for &link_id in link_ids {
+        let link = nav_data.off_mesh_links.get(link_id).unwrap();
+        let destination_portal = match &link.kinded {
+          KindedOffMeshLink::BoundaryLink { .. } => link.portal,
+          KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
+            *destination_portal
+          }
+        };
+        cluster_to_links.entry(cluster).or_default().push(link_id);
+        links.insert(
+          link_id,
+          RegionLink {
+            portal_point: link.portal.0.midpoint(link.portal.1),
+            destination_point: destination_portal
+              .0
+              .midpoint(destination_portal.1),
+            destination_cluster: nav_data
+              .node_to_region_id(link.destination_node),
+          },
+        );
+      }
+    }
+    // Sort the links so the search is deterministic.
+    for link_ids in cluster_to_links.values_mut() {
+      link_ids.sort();
+    }
+    Self { cluster_to_links, links }
+  }
+}
+
+/// A search for the corridor of clusters that a path should go through. The
+/// search may be spread across several calls to [`CorridorSearch::resume`].
+pub(crate) struct CorridorSearch {
+  /// The cluster that the path starts in.
+  start_cluster: ClusterId,
+  /// The point that the path starts at, in world space.
+  start_point: Vec3,
+  /// The cluster that the path ends in.
+  end_cluster: ClusterId,
+  /// The point that the path ends at, in world space.
+  end_point: Vec3,
+  /// The cost per unit of distance within a cluster.
+  cheapest_type_index_cost: f32,
+  /// The A* search through the region graph.
+  search: astar::Search<ClusterId, RegionPathNode>,
+}
+
+/// The status of a [`CorridorSearch`] after resuming it.
+pub(crate) enum CorridorSearchStatus {
+  /// The search ran out of budget before finishing.
+  Incomplete,
+  /// The search finished. Contains the corridor of clusters, or [`None`] if
+  /// the region graph has no path between the nodes.
+  Complete(Option<HashSet<ClusterId>>),
+}
+
+impl CorridorSearch {
+  /// Starts a search through `region_graph` for the corridor of clusters that
+  /// a path from `start_node` to `end_node` should go through. `start_point`
+  /// and `end_point` are the points in those nodes, in world space. Distances
+  /// within a cluster are estimated by the straight-line distance times
+  /// `cheapest_type_index_cost`. No clusters are explored until
+  /// [`Self::resume`] is called.
+  #[expect(clippy::too_many_arguments)]
+  pub(crate) fn new<CS: CoordinateSystem>(
+    nav_data: &NavigationData<CS>,
+    region_graph: &RegionGraph,
+    start_node: NodeRef,
+    start_point: Vec3,
+    end_node: NodeRef,
+    end_point: Vec3,
+    cheapest_type_index_cost: f32,
+    permitted_animation_links: &PermittedAnimationLinks,
+  ) -> Self {
+    let problem = RegionPathProblem {
+      nav_data,
+      region_graph,
+      start_cluster: nav_data.node_to_region_id(start_node),
+      start_point,
+      end_cluster: nav_data.node_to_region_id(end_node),
+      end_point,
+      cheapest_type_index_cost,
+      permitted_animation_links,
+    };
+    Self {
+      start_cluster: problem.start_cluster,
+      start_point,
+      end_cluster: problem.end_cluster,
+      end_point,
+      cheapest_type_index_cost,
+      search: astar::Search::new(&problem),
+    }
+  }
+
+  /// The number of clusters explored by the search so far.
+  pub(crate) fn explored_nodes(&self) -> u32 {
+    self.search.stats().explored_nodes
+  }
+
+  /// Determines whether the search can no longer be resumed, since it reached
+  /// off mesh links that were invalidated.
+  pub(crate) fn is_invalidated(
+    &self,
+    invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
+  ) -> bool {
+    self.search.states().any(|state| match state {
+      RegionPathNode::Start | RegionPathNode::End => false,
+      RegionPathNode::OffMeshLink(link_id) => {
+        invalidated_off_mesh_links.contains(link_id)
+      }
+    })
+  }
+
+  /// Continues the search, exploring at most `max_explored_nodes` clusters.
+  /// If `nav_data` no longer has a region graph, the search completes without
+  /// a corridor.
+  pub(crate) fn resume<CS: CoordinateSystem>(
+    &mut self,
+    nav_data: &NavigationData<CS>,
+    permitted_animation_links: &PermittedAnimationLinks,
+    max_explored_nodes: u32,
+  ) -> CorridorSearchStatus {
+    if self.start_cluster == self.end_cluster {
+      return CorridorSearchStatus::Complete(Some(HashSet::from([
+        self.start_cluster
+      ])));
+    }
+    let Some(region_graph) = nav_data.region_graph.as_ref() else {
+      return CorridorSearchStatus::Complete(None);
+    };
+
+    let problem = RegionPathProblem {
+      nav_data,
+      region_graph,
+      start_cluster: self.start_cluster,
+      start_point: self.start_point,
+      end_cluster: self.end_cluster,
+      end_point: self.end_point,
+      cheapest_type_index_cost: self.cheapest_type_index_cost,
+      permitted_animation_links,
+    };
+    let path_result = match self.search.resume(&problem, max_explored_nodes) {
+      SearchStatus::Incomplete => return CorridorSearchStatus::Incomplete,
+      SearchStatus::Complete(path_result) => path_result,
+    };
+    CorridorSearchStatus::Complete(path_result.path.map(|path| {
+      let mut corridor = HashSet::from([self.start_cluster]);
+      corridor.extend(path);
+      corridor
+    }))
+  }
+}
+
+/// The A* problem for finding a path through a [`RegionGraph`].
+struct RegionPathProblem<'a, CS: CoordinateSystem> {
+  /// The navigation data that the region graph was created from.
+  nav_data: &'a NavigationData<CS>,
+  /// The region graph to search.
+  region_graph: &'a RegionGraph,
+  /// The cluster that the path starts in.
+  start_cluster: ClusterId,
+  /// The point that the path starts at, in world space.
+  start_point: Vec3,
+  /// The cluster that the path ends in.
+  end_cluster: ClusterId,
+  /// The point that the path ends at, in world space.
+  end_point: Vec3,
+  /// The cost per unit of distance within a cluster.
+  cheapest_type_index_cost: f32,
+  /// The set of permitted animation links for the agent.
+  permitted_animation_links: &'a PermittedAnimationLinks,
+}
+
+/// A state in the path through a [`RegionGraph`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum RegionPathNode {
+  /// The start of the path.
+  Start,
+  /// The end of the path.
+  End,
+  /// The destination of an off mesh link.
+  OffMeshLink(OffMeshLinkId),
+}
+
+impl<CS: CoordinateSystem> AStarProblem for RegionPathProblem<'_, CS> {
+  /// Each action enters a cluster.
+  type ActionType = ClusterId;
+
+  type StateType = RegionPathNode;
+
+  fn initial_state(&self) -> Self::StateType {
+    RegionPathNode::Start
+  }
+
+  fn successors(
+    &self,
+    state: &Self::StateType,
+  ) -> Vec<(f32, Self::ActionType, Self::StateType)> {
+    let (cluster, point) = match state {
+      RegionPathNode::Start => (self.start_cluster, self.start_point),
+      RegionPathNode::OffMeshLink(link_id) => {
+        let link = self.region_graph.links.get(link_id).unwrap();
+        (link.destination_cluster, link.destination_point)
+      }
+      RegionPathNode::End => {
+        unreachable!("we never need the successors of the goal node")
+      }
+    };
+
+    let end_successor = (cluster == self.end_cluster).then(|| {
+      (
+        point.distance(self.end_point) * self.cheapest_type_index_cost,
+        self.end_cluster,
+        RegionPathNode::End,
+      )
+    });
+
+    let link_ids = self
+      .region_graph
+      .cluster_to_links
+      .get(&cluster)
+      .map_or(&[][..], Vec::as_slice);
+    end_successor
+      .into_iter()
+      .chain(link_ids.iter().filter_map(|link_id| {
+        // Links covered by obstacles or disabled can't be taken.
+        if self.nav_data.blocked_off_mesh_links.contains(link_id)
+          || self.nav_data.disabled_off_mesh_links.contains(link_id)
+        {
+          return None;
+        }
+        let link_cost =
+          match self.nav_data.off_mesh_links.get(*link_id).unwrap().kinded {
+            KindedOffMeshLink::BoundaryLink { .. } => 0.0,
+            KindedOffMeshLink::AnimationLink {
+              cost,
+              kind,
+              animation_link,
+              ..
+            } => {
+              if !self.permitted_animation_links.is_permitted(kind) {
+                return None;
+              }
+              self
+                .nav_data
+                .busy_animation_link_costs
+                .get(&animation_link)
+                .copied()
+                .unwrap_or(cost)
+            }
+          };
+        let region_link = self.region_graph.links.get(link_id).unwrap();
+        let cost = point.distance(region_link.portal_point)
+          * self.cheapest_type_index_cost
+          + link_cost;
+        Some((
+          cost,
+          region_link.destination_cluster,
+          RegionPathNode::OffMeshLink(*link_id),
+        ))
+      }))
+      .collect()
+  }
+
+  fn heuristic(&self, state: &Self::StateType) -> f32 {
+    let point = match state {
+      RegionPathNode::Start => self.start_point,
+      RegionPathNode::End => return 0.0,
+      RegionPathNode::OffMeshLink(link_id) => {
+        self.region_graph.links.get(link_id).unwrap().destination_point
+      }
+    };
+    point.distance(self.end_point) * self.cheapest_type_index_cost
+  }
+
+  fn is_goal_state(&self, state: &Self::StateType) -> bool {
+    matches!(state, RegionPathNode::End)
+  }
+}
+
+#[cfg(test)]
+#[path = "region_graph_test.rs"]
+mod test;
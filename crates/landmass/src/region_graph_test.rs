@@ -0,0 +1,424 @@
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+
+use glam::{Vec2, Vec3};
+use googletest::{expect_eq, expect_that, expect_true, matchers::*};
+
+use crate::{
+  Archipelago, ArchipelagoOptions, FromAgentRadius, Island, IslandId,
+  Transform,
+  agent::{PermittedAnimationLinks, PermittedTypeIndices},
+  coords::{CorePointSampleDistance, XY},
+  nav_mesh::NavigationMesh,
+  pathfinding::{PathResult, PathSearch, PathSearchStatus, find_path},
+  region_graph::{CorridorSearch, CorridorSearchStatus},
+};
+
+/// Creates an island with a grid of `columns` by `rows` cells, each of size
+/// `cell_size`, with its bottom-left corner at `min`.
+fn grid_island(
+  min: Vec2,
+  columns: usize,
+  rows: usize,
+  cell_size: Vec2,
+  type_index: usize,
+) -> Island<XY> {
+  let mut vertices = vec![];
+  for y in 0..=rows {
+    for x in 0..=columns {
+      vertices.push(Vec2::new(x as f32, y as f32) * cell_size);
+    }
+  }
+  let vertex = |x: usize, y: usize| y * (columns + 1) + x;
+  let mut polygons = vec![];
+  for y in 0..rows {
+    for x in 0..columns {
+      polygons.push(vec![
+        vertex(x, y),
+        vertex(x + 1, y),
+        vertex(x + 1, y + 1),
+        vertex(x, y + 1),
+      ]);
+    }
+  }
+  let polygon_type_indices = vec![type_index; polygons.len()];
+  let nav_mesh = NavigationMesh {
+    vertices,
+    polygons,
+    polygon_type_indices,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("The grid is valid.");
+  Island::new(
+    Transform { translation: min, rotation: 0.0, scale: 1.0 },
+    Arc::new(nav_mesh),
+  )
+}
+
+/// Finds a path from the start island to the end island, starting a little
+/// closer to the top corridor.
+fn find_path_across(
+  archipelago: &Archipelago<XY>,
+  permitted_type_indices: PermittedTypeIndices,
+) -> PathResult {
+  let node_at = |point: Vec3| {
+    archipelago
+      .nav_data
+      .sample_point(
+        point,
+        &CorePointSampleDistance {
+          horizontal_distance: 0.1,
+          distance_above: 0.1,
+          distance_below: 0.1,
+          vertical_preference_ratio: 1.0,
+        },
+        &PermittedTypeIndices::All,
+      )
+      .unwrap()
+  };
+  let (start_point, start_node) = node_at(Vec3::new(1.5, 3.5, 0.0));
+  let (end_point, end_node) = node_at(Vec3::new(13.5, 2.5, 0.0));
+  find_path(
+    &archipelago.nav_data,
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    &HashMap::new(),
+    PermittedAnimationLinks::All,
+    permitted_type_indices,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
+  )
+}
+
+/// The islands that `path_result` goes through.
+fn path_islands(path_result: &PathResult) -> Vec<IslandId> {
+  path_result
+    .path
+    .as_ref()
+    .unwrap()
+    .island_segments
+    .iter()
+    .map(|segment| segment.island_id)
+    .collect()
+}
+
+#[googletest::test]
+fn region_graph_skips_dead_ends() {
+  // A start island on the left and an end island on the right, connected by
+  // a corridor along the top and a corridor along the bottom. A dead end
+  // sticks out of the start island between the corridors, right towards the
+  // end island. The top corridor has type index 1.
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: false,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let cell = Vec2::ONE;
+  let corridor_cell = Vec2::new(1.0, 0.5);
+  archipelago.add_island(grid_island(Vec2::ZERO, 5, 5, cell, 0));
+  let top = archipelago.add_island(grid_island(
+    Vec2::new(5.0, 4.5),
+    5,
+    1,
+    corridor_cell,
+    1,
+  ));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 0.0),
+    5,
+    1,
+    corridor_cell,
+    0,
+  ));
+  archipelago.add_island(grid_island(Vec2::new(5.0, 1.0), 4, 3, cell, 0));
+  archipelago.add_island(grid_island(Vec2::new(10.0, 0.0), 5, 5, cell, 0));
+  archipelago.update(1.0);
+  expect_true!(archipelago.nav_data.region_graph.is_none());
+  let without_hierarchy =
+    find_path_across(&archipelago, PermittedTypeIndices::All);
+
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: true,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let cell = Vec2::ONE;
+  let corridor_cell = Vec2::new(1.0, 0.5);
+  archipelago.add_island(grid_island(Vec2::ZERO, 5, 5, cell, 0));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 4.5),
+    5,
+    1,
+    corridor_cell,
+    1,
+  ));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 0.0),
+    5,
+    1,
+    corridor_cell,
+    0,
+  ));
+  archipelago.add_island(grid_island(Vec2::new(5.0, 1.0), 4, 3, cell, 0));
+  archipelago.add_island(grid_island(Vec2::new(10.0, 0.0), 5, 5, cell, 0));
+  archipelago.update(1.0);
+  expect_true!(archipelago.nav_data.region_graph.is_some());
+  let with_hierarchy =
+    find_path_across(&archipelago, PermittedTypeIndices::All);
+
+  // The dead end doesn't lead to the end island, so it is not in the corridor
+  // and is never explored.
+  expect_that!(without_hierarchy.path, some(anything()));
+  expect_eq!(path_islands(&with_hierarchy), path_islands(&without_hierarchy));
+  expect_eq!(path_islands(&with_hierarchy)[1], top);
+  expect_that!(
+    with_hierarchy.stats.explored_nodes,
+    lt(without_hierarchy.stats.explored_nodes)
+  );
+}
+
+#[googletest::test]
+fn falls_back_to_full_search_if_corridor_is_unusable() {
+  // A start island on the left and an end island on the right, connected by
+  // a corridor along the top and a corridor along the bottom. A dead end
+  // sticks out of the start island between the corridors, right towards the
+  // end island. The top corridor has type index 1.
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: true,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let cell = Vec2::ONE;
+  let corridor_cell = Vec2::new(1.0, 0.5);
+  archipelago.add_island(grid_island(Vec2::ZERO, 5, 5, cell, 0));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 4.5),
+    5,
+    1,
+    corridor_cell,
+    1,
+  ));
+  let bottom = archipelago.add_island(grid_island(
+    Vec2::new(5.0, 0.0),
+    5,
+    1,
+    corridor_cell,
+    0,
+  ));
+  archipelago.add_island(grid_island(Vec2::new(5.0, 1.0), 4, 3, cell, 0));
+  archipelago.add_island(grid_island(Vec2::new(10.0, 0.0), 5, 5, cell, 0));
+  archipelago.update(1.0);
+
+  // The region graph ignores type indices, so the corridor goes through the
+  // top corridor, which the agent can't walk on.
+  let path_result = find_path_across(
+    &archipelago,
+    PermittedTypeIndices::Exclude(Arc::new(HashSet::from([1]))),
+  );
+
+  expect_that!(path_result.path, some(anything()));
+  expect_eq!(path_islands(&path_result)[1], bottom);
+}
+
+#[googletest::test]
+fn region_graph_is_removed_when_disabled() {
+  // A start island on the left and an end island on the right, connected by
+  // a corridor along the top and a corridor along the bottom. A dead end
+  // sticks out of the start island between the corridors, right towards the
+  // end island. The top corridor has type index 1.
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: true,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let cell = Vec2::ONE;
+  let corridor_cell = Vec2::new(1.0, 0.5);
+  archipelago.add_island(grid_island(Vec2::ZERO, 5, 5, cell, 0));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 4.5),
+    5,
+    1,
+    corridor_cell,
+    1,
+  ));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 0.0),
+    5,
+    1,
+    corridor_cell,
+    0,
+  ));
+  archipelago.add_island(grid_island(Vec2::new(5.0, 1.0), 4, 3, cell, 0));
+  archipelago.add_island(grid_island(Vec2::new(10.0, 0.0), 5, 5, cell, 0));
+  archipelago.update(1.0);
+  expect_true!(archipelago.nav_data.region_graph.is_some());
+
+  archipelago.archipelago_options.hierarchical_pathfinding = false;
+  archipelago.update(1.0);
+  expect_true!(archipelago.nav_data.region_graph.is_none());
+}
+
+#[googletest::test]
+fn corridor_search_is_spread_across_resumes() {
+  // A start island on the left and an end island on the right, connected by
+  // a corridor along the top and a corridor along the bottom. A dead end
+  // sticks out of the start island between the corridors, right towards the
+  // end island. The top corridor has type index 1.
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: true,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let cell = Vec2::ONE;
+  let corridor_cell = Vec2::new(1.0, 0.5);
+  archipelago.add_island(grid_island(Vec2::ZERO, 5, 5, cell, 0));
+  let top = archipelago.add_island(grid_island(
+    Vec2::new(5.0, 4.5),
+    5,
+    1,
+    corridor_cell,
+    1,
+  ));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 0.0),
+    5,
+    1,
+    corridor_cell,
+    0,
+  ));
+  archipelago.add_island(grid_island(Vec2::new(5.0, 1.0), 4, 3, cell, 0));
+  archipelago.add_island(grid_island(Vec2::new(10.0, 0.0), 5, 5, cell, 0));
+  archipelago.update(1.0);
+
+  let nav_data = &archipelago.nav_data;
+  let node_at = |point: Vec3| {
+    nav_data
+      .sample_point(
+        point,
+        &CorePointSampleDistance {
+          horizontal_distance: 0.1,
+          distance_above: 0.1,
+          distance_below: 0.1,
+          vertical_preference_ratio: 1.0,
+        },
+        &PermittedTypeIndices::All,
+      )
+      .unwrap()
+  };
+  let (start_point, start_node) = node_at(Vec3::new(1.5, 3.5, 0.0));
+  let (end_point, end_node) = node_at(Vec3::new(13.5, 2.5, 0.0));
+  let mut corridor_search = CorridorSearch::new(
+    nav_data,
+    nav_data.region_graph.as_ref().unwrap(),
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    /* cheapest_type_index_cost= */ 1.0,
+    &PermittedAnimationLinks::All,
+  );
+
+  // Each resume only explores a single cluster, so the search needs several
+  // resumes.
+  let mut resumes = 0;
+  let corridor = loop {
+    resumes += 1;
+    match corridor_search.resume(nav_data, &PermittedAnimationLinks::All, 1) {
+      CorridorSearchStatus::Incomplete => {
+        expect_eq!(corridor_search.explored_nodes(), resumes);
+      }
+      CorridorSearchStatus::Complete(corridor) => break corridor,
+    }
+  };
+  expect_that!(resumes, gt(1));
+  expect_eq!(
+    corridor,
+    Some(HashSet::from([
+      nav_data.node_to_region_id(start_node),
+      (top, 0),
+      nav_data.node_to_region_id(end_node),
+    ]))
+  );
+}
+
+#[googletest::test]
+fn path_search_counts_corridor_search_against_budget() {
+  // A start island on the left and an end island on the right, connected by
+  // a corridor along the top and a corridor along the bottom. A dead end
+  // sticks out of the start island between the corridors, right towards the
+  // end island. The top corridor has type index 1.
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: true,
+    ..ArchipelagoOptions::from_agent_radius(0.25)
+  });
+  let cell = Vec2::ONE;
+  let corridor_cell = Vec2::new(1.0, 0.5);
+  archipelago.add_island(grid_island(Vec2::ZERO, 5, 5, cell, 0));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 4.5),
+    5,
+    1,
+    corridor_cell,
+    1,
+  ));
+  archipelago.add_island(grid_island(
+    Vec2::new(5.0, 0.0),
+    5,
+    1,
+    corridor_cell,
+    0,
+  ));
+  archipelago.add_island(grid_island(Vec2::new(5.0, 1.0), 4, 3, cell, 0));
+  archipelago.add_island(grid_island(Vec2::new(10.0, 0.0), 5, 5, cell, 0));
+  archipelago.update(1.0);
+
+  let nav_data = &archipelago.nav_data;
+  let node_at = |point: Vec3| {
+    nav_data
+      .sample_point(
+        point,
+        &CorePointSampleDistance {
+          horizontal_distance: 0.1,
+          distance_above: 0.1,
+          distance_below: 0.1,
+          vertical_preference_ratio: 1.0,
+        },
+        &PermittedTypeIndices::All,
+      )
+      .unwrap()
+  };
+  let (start_point, start_node) = node_at(Vec3::new(1.5, 3.5, 0.0));
+  let (end_point, end_node) = node_at(Vec3::new(13.5, 2.5, 0.0));
+  let mut path_search = PathSearch::new(
+    nav_data,
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    &HashMap::new(),
+    PermittedAnimationLinks::All,
+    PermittedTypeIndices::All,
+    /* agent_radius= */ 0.0,
+    /* allow_partial_path= */ false,
+  );
+
+  // Every explored node (including the clusters explored to find the
+  // corridor) uses up one resume.
+  let mut resumes = 0;
+  let path_result = loop {
+    resumes += 1;
+    match path_search.resume(nav_data, 1) {
+      PathSearchStatus::Incomplete(next_path_search) => {
+        expect_eq!(next_path_search.explored_nodes(), resumes);
+        path_search = *next_path_search;
+      }
+      PathSearchStatus::Complete(path_result) => break path_result,
+    }
+  };
+  expect_that!(path_result.path, some(anything()));
+  expect_eq!(path_result.stats.explored_nodes, resumes);
+
+  let unbudgeted_result =
+    find_path_across(&archipelago, PermittedTypeIndices::All);
+  expect_eq!(unbudgeted_result.stats.explored_nodes, resumes);
+}
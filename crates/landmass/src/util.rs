@@ -1,9 +1,74 @@
 use std::{cmp::Ordering, mem::swap, ops::Deref};
 
-use glam::{Quat, Vec3};
+use glam::Vec3;
+use rand::Rng;
 
 use crate::CoordinateSystem;
 
+/// Calls `f` on each item in `items`. If the `rayon` feature is enabled, items
+/// are processed in parallel.
+#[cfg(feature = "rayon")]
+pub(crate) fn for_each_maybe_parallel<T: Send>(
+  items: &mut [T],
+  f: impl Fn(&mut T) + Send + Sync,
+) {
+  use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+  items.par_iter_mut().for_each(f);
+}
+
+/// Calls `f` on each item in `items`. If the `rayon` feature is enabled, items
+/// are processed in parallel.
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn for_each_maybe_parallel<T>(items: &mut [T], f: impl Fn(&mut T)) {
+  items.iter_mut().for_each(f);
+}
+
+/// Maps each item in `items` with `f`, returning the results in the same order
+/// as `items`. If the `rayon` feature is enabled, items are processed in
+/// parallel.
+#[cfg(feature = "rayon")]
+pub(crate) fn map_maybe_parallel<T: Send, R: Send>(
+  items: &mut [T],
+  f: impl Fn(&mut T) -> R + Send + Sync,
+) -> Vec<R> {
+  use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+  items.par_iter_mut().map(f).collect()
+}
+
+/// Maps each item in `items` with `f`, returning the results in the same order
+/// as `items`. If the `rayon` feature is enabled, items are processed in
+/// parallel.
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn map_maybe_parallel<T, R>(
+  items: &mut [T],
+  f: impl Fn(&mut T) -> R,
+) -> Vec<R> {
+  items.iter_mut().map(f).collect()
+}
+
+/// Picks a random index into `weights`, where each index is picked with
+/// probability proportional to its weight. Returns None if the total weight is
+/// not positive.
+pub(crate) fn random_weighted_index(
+  weights: &[f32],
+  rng: &mut impl Rng,
+) -> Option<usize> {
+  let total_weight: f32 = weights.iter().sum();
+  if total_weight <= 0.0 {
+    return None;
+  }
+  let mut remaining_weight = rng.random::<f32>() * total_weight;
+  for (index, &weight) in weights.iter().enumerate() {
+    if remaining_weight < weight {
+      return Some(index);
+    }
+    remaining_weight -= weight;
+  }
+  // Floating point error may leave some weight remaining, so pick the last
+  // index with any weight.
+  weights.iter().rposition(|&weight| weight > 0.0)
+}
+
 /// An f32 that supports `Ord` operations.
 pub(crate) struct FloatOrd(pub(crate) f32);
 
@@ -56,6 +121,7 @@ impl Deref for FloatOrd {
 
 /// A bounding box.
 #[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum BoundingBox {
   /// The bounding box has no points in it.
   Empty,
@@ -271,99 +337,113 @@ impl BoundingBox {
       BoundingBox::Empty => return BoundingBox::Empty,
       BoundingBox::Box { min, max } => (min, max),
     };
-    let flat_max = Vec3::new(max.x, max.y, min.z);
-
-    let points = [
-      transform.apply(*min),
-      transform.apply(flat_max),
-      transform.apply(Vec3::new(min.x, max.y, min.z)),
-      transform.apply(Vec3::new(max.x, min.y, min.z)),
-    ];
-
-    BoundingBox::Box {
-      min: Vec3::new(
-        points
-          .iter()
-          .map(|p| p.x)
-          .min_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points
-          .iter()
-          .map(|p| p.y)
-          .min_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points[0].z,
-      ),
-      max: Vec3::new(
-        points
-          .iter()
-          .map(|p| p.x)
-          .max_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points
-          .iter()
-          .map(|p| p.y)
-          .max_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points[0].z + (max.z - min.z),
-      ),
-    }
+
+    // The transform may tilt the box, so every corner needs to be included.
+    (0..8).fold(BoundingBox::Empty, |bounds, corner| {
+      bounds.expand_to_point(transform.apply(Vec3::new(
+        if corner & 1 == 0 { min.x } else { max.x },
+        if corner & 2 == 0 { min.y } else { max.y },
+        if corner & 4 == 0 { min.z } else { max.z },
+      )))
+    })
   }
 }
 
 /// A transform that can be applied to Vec3's.
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(bound(
+    serialize = "CS::Coordinate: serde::Serialize, CS::Rotation: serde::Serialize",
+    deserialize = "CS::Coordinate: serde::Deserialize<'de>, CS::Rotation: serde::Deserialize<'de>"
+  ))
+)]
 pub struct Transform<CS: CoordinateSystem> {
   /// The translation to apply.
   pub translation: CS::Coordinate,
-  /// The rotation to apply around the "up" direction. Specifically, the up
-  /// direction is perpendicular to the plane of movement.
-  pub rotation: f32,
+  /// The rotation to apply. For 2D coordinate systems, this is just the
+  /// rotation around the "up" direction (perpendicular to the plane of
+  /// movement). For 3D coordinate systems, this can also tilt (e.g., a rocking
+  /// ship).
+  pub rotation: CS::Rotation,
+  /// The uniform scale to apply. Must be positive. Islands and obstacles clamp
+  /// smaller scales to a small positive scale.
+  pub scale: f32,
 }
 
 // Manual Clone impl to avoid `CS` having a Clone bound itself.
 impl<CS: CoordinateSystem> Clone for Transform<CS> {
   fn clone(&self) -> Self {
-    Self { translation: self.translation.clone(), rotation: self.rotation }
+    Self {
+      translation: self.translation.clone(),
+      rotation: self.rotation.clone(),
+      scale: self.scale,
+    }
   }
 }
 
 // Manual Debug impl to avoid `CS` having a Debug bound itself.
-impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
-  for Transform<CS>
+impl<
+  CS: CoordinateSystem<Coordinate: std::fmt::Debug, Rotation: std::fmt::Debug>,
+> std::fmt::Debug for Transform<CS>
 {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("Transform")
       .field("translation", &self.translation)
       .field("rotation", &self.rotation)
+      .field("scale", &self.scale)
       .finish()
   }
 }
 
 // Manual Default impl to avoid `CS` having a Default bound itself.
-impl<CS: CoordinateSystem<Coordinate: Default>> Default for Transform<CS> {
+impl<CS: CoordinateSystem<Coordinate: Default, Rotation: Default>> Default
+  for Transform<CS>
+{
   fn default() -> Self {
-    Self { translation: Default::default(), rotation: Default::default() }
+    Self {
+      translation: Default::default(),
+      rotation: Default::default(),
+      scale: 1.0,
+    }
   }
 }
 
 // Manual PartialEq impl to avoid `CS` having a PartialEq bound itself.
-impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for Transform<CS> {
+impl<CS: CoordinateSystem<Coordinate: PartialEq, Rotation: PartialEq>> PartialEq
+  for Transform<CS>
+{
   fn eq(&self, other: &Self) -> bool {
-    self.translation == other.translation && self.rotation == other.rotation
+    self.translation == other.translation
+      && self.rotation == other.rotation
+      && self.scale == other.scale
   }
 }
 
+/// The smallest scale used for islands and obstacles. Smaller scales (including
+/// zero, negative, and NaN scales) are clamped to this, since they would
+/// collapse or mirror the geometry.
+pub(crate) const MIN_SCALE: f32 = 1e-3;
+
 impl<CS: CoordinateSystem> Transform<CS> {
+  /// Returns the transform with its scale clamped to [`MIN_SCALE`].
+  pub(crate) fn with_clamped_scale(mut self) -> Self {
+    // `f32::max` ignores NaN, so NaN scales are clamped as well.
+    self.scale = self.scale.max(MIN_SCALE);
+    self
+  }
+
   /// Applies the transformation.
   pub(crate) fn apply(&self, point: Vec3) -> Vec3 {
-    Quat::from_rotation_z(self.rotation) * point
+    CS::to_landmass_rotation(&self.rotation) * (point * self.scale)
       + CS::to_landmass(&self.translation)
   }
 
   /// Inverses the transformation.
   pub(crate) fn apply_inverse(&self, point: Vec3) -> Vec3 {
-    Quat::from_rotation_z(-self.rotation)
+    CS::to_landmass_rotation(&self.rotation).inverse()
       * (point - CS::to_landmass(&self.translation))
+      / self.scale
   }
 }
 
@@ -1,6 +1,6 @@
 use std::f32::consts::PI;
 
-use glam::Vec3;
+use glam::{Quat, Vec3};
 
 use crate::{
   Transform, XYZ,
@@ -241,7 +241,8 @@ fn transform_empty_does_nothing() {
   assert_eq!(
     BoundingBox::Empty.transform(&Transform::<XYZ> {
       translation: Vec3::new(1.0, 2.0, 3.0),
-      rotation: 0.75
+      rotation: Quat::from_rotation_z(0.75),
+      scale: 1.0,
     }),
     BoundingBox::Empty
   );
@@ -254,7 +255,8 @@ fn transforms_bounds() {
     BoundingBox::new_box(Vec3::new(1.0, 3.0, 2.0), Vec3::new(6.0, 4.0, 5.0))
       .transform(&Transform::<XYZ> {
         translation: Vec3::new(-4.0, 1.0, -3.0),
-        rotation: PI * -0.75,
+        rotation: Quat::from_rotation_z(PI * -0.75),
+        scale: 1.0,
       })
       .as_box();
   let expected_min = Vec3::new(-3.0 / root_2 - 4.0, -10.0 / root_2 + 1.0, -1.0);
@@ -269,6 +271,28 @@ fn transforms_bounds() {
   );
 }
 
+#[test]
+fn transforms_bounds_with_scale_and_tilt() {
+  let (actual_min, actual_max) =
+    BoundingBox::new_box(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0))
+      .transform(&Transform::<XYZ> {
+        translation: Vec3::new(1.0, 1.0, 1.0),
+        rotation: Quat::from_rotation_x(PI * 0.5),
+        scale: 2.0,
+      })
+      .as_box();
+  let expected_min = Vec3::new(1.0, -5.0, 1.0);
+  assert!(
+    actual_min.abs_diff_eq(expected_min, 1e-5),
+    "actual_min={actual_min} expected_min={expected_min}"
+  );
+  let expected_max = Vec3::new(3.0, 1.0, 5.0);
+  assert!(
+    actual_max.abs_diff_eq(expected_max, 1e-5),
+    "actual_max={actual_max} expected_max={expected_max}"
+  );
+}
+
 #[test]
 fn octant_bounding_box_hierarchy() {
   let mut values = vec![
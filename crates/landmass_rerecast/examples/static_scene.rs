@@ -174,7 +174,7 @@ fn snap_to_ground(
         origin: transform.translation + Vec3::Y * 0.5,
         direction: Dir3::NEG_Y,
       },
-      &settings,
+      settings,
     );
     let Some((_, hit)) = result.first() else {
       continue;
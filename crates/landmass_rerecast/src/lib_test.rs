@@ -178,7 +178,8 @@ fn landmass_mesh_created_once_rerecast_mesh_is_added_and_updated() {
   );
 
   // We now mutate the rerecast nav mesh. This should result in the nav mesh
-  // being modified.
+  // being modified. The new nav mesh is wide enough for the agent to cross
+  // between its two nodes.
   let rerecast_mesh = bevy_rerecast::Navmesh {
     polygon: PolygonNavmesh {
       aabb: Aabb3d { min: Vec3::ZERO, max: Vec3::ONE * 100.0 },
@@ -188,10 +189,10 @@ fn landmass_mesh_created_once_rerecast_mesh_is_added_and_updated() {
       vertices: vec![
         U16Vec3::new(0, 0, 0),
         U16Vec3::new(1, 0, 0),
-        U16Vec3::new(1, 0, 1),
-        U16Vec3::new(0, 0, 1),
+        U16Vec3::new(1, 0, 3),
+        U16Vec3::new(0, 0, 3),
         U16Vec3::new(2, 0, 0),
-        U16Vec3::new(2, 0, 1),
+        U16Vec3::new(2, 0, 3),
       ],
       polygons: vec![3, 2, 1, 0, 5, 4, 1, 2],
       areas: vec![AreaType(0), AreaType(0)],
@@ -205,12 +206,12 @@ fn landmass_mesh_created_once_rerecast_mesh_is_added_and_updated() {
       vertices: vec![
         Vec3::new(0.0, 0.0, 0.0),
         Vec3::new(1.0, 0.0, 0.0),
-        Vec3::new(1.0, 0.0, 1.0),
-        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(1.0, 0.0, 3.0),
+        Vec3::new(0.0, 0.0, 3.0),
         Vec3::new(1.0, 0.0, 0.0),
         Vec3::new(2.0, 0.0, 0.0),
-        Vec3::new(2.0, 0.0, 1.0),
-        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(2.0, 0.0, 3.0),
+        Vec3::new(1.0, 0.0, 3.0),
       ],
       triangles: vec![[0, 2, 1], [2, 0, 3], [0, 2, 1], [2, 0, 3]],
       meshes: vec![